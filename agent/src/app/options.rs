@@ -3,9 +3,11 @@ use std::time::Duration;
 
 // internal crates
 use crate::deploy::fsm;
+use crate::http::breaker::CircuitBreakerOptions;
+use crate::network::{failover::FailoverOptions, options::NetworkOptions};
 use crate::server::serve::ServerOptions;
 use crate::storage::{caches::CacheCapacities, layout::StorageLayout};
-use crate::workers::{mqtt, poller, token_refresh::TokenRefreshWorkerOptions};
+use crate::workers::{bridge, mqtt, poller, token_refresh::TokenRefreshWorkerOptions};
 
 #[derive(Debug, Clone, Copy)]
 pub struct LifecycleOptions {
@@ -43,6 +45,10 @@ pub struct AppOptions {
     pub fsm_settings: fsm::Settings,
 
     pub backend_base_url: String,
+    pub backend_fallback_urls: Vec<String>,
+    pub backend_failover: FailoverOptions,
+    pub network: NetworkOptions,
+    pub circuit_breaker: CircuitBreakerOptions,
 
     pub enable_socket_server: bool,
     pub server: ServerOptions,
@@ -52,6 +58,9 @@ pub struct AppOptions {
 
     pub enable_poller: bool,
     pub poller: poller::Options,
+
+    pub enable_local_bridge: bool,
+    pub local_bridge: bridge::Options,
 }
 
 impl Default for AppOptions {
@@ -64,6 +73,10 @@ impl Default for AppOptions {
             fsm_settings: fsm::Settings::default(),
 
             backend_base_url: "https://api.mirurobotics.com/agent/v1".to_string(),
+            backend_fallback_urls: Vec::new(),
+            backend_failover: FailoverOptions::default(),
+            network: NetworkOptions::default(),
+            circuit_breaker: CircuitBreakerOptions::default(),
 
             enable_socket_server: true,
             server: ServerOptions::default(),
@@ -73,6 +86,9 @@ impl Default for AppOptions {
 
             enable_poller: true,
             poller: poller::Options::default(),
+
+            enable_local_bridge: false,
+            local_bridge: bridge::Options::default(),
         }
     }
 }
@@ -11,30 +11,44 @@ use crate::app::{
     state::AppState,
 };
 use crate::authn::token_mngr::{TokenManager, TokenManagerExt};
-use crate::http::client::HTTPClient;
+use crate::diagnostics::collect as diagnostics;
+use crate::http::{breaker::CircuitBreaker, client::HTTPClient, conditional::ConditionalCache};
+use crate::network::{failover::Endpoints, tls::TLSOptions};
 use crate::server::{errors::*, serve::serve, state::ServerState};
+use crate::storage::layout::StorageLayout;
 use crate::trace;
 use crate::workers::{
+    bridge,
+    commands::{Commands, CommandsArgs},
     mqtt, poller,
     token_refresh::{run_token_refresh_worker, TokenRefreshWorkerOptions},
 };
 
 // external
-use tokio::sync::broadcast;
+use tokio::sync::{broadcast, Notify};
 use tokio::task::JoinHandle;
 use tracing::{error, info};
 
+// why the agent stopped running
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExitReason {
+    Shutdown,
+    // the backend requested a restart (via the mqtt restart command)
+    Restart,
+}
+
 pub async fn run(
     agent_version: String,
     options: AppOptions,
     shutdown_signal: impl Future<Output = ()> + Send + 'static,
-) -> Result<(), ServerErr> {
+) -> Result<ExitReason, ServerErr> {
     info!("Initializing miru agent...");
 
     // Create a single shutdown channel that all components will listen to
     let (shutdown_tx, _shutdown_rx): (tokio::sync::broadcast::Sender<()>, _) =
         tokio::sync::broadcast::channel(1);
     let mut shutdown_manager = ShutdownManager::new(shutdown_tx.clone(), options.lifecycle);
+    let restart = Arc::new(Notify::new());
 
     // initialize the app (and shutdown if failures occur)
     let app_state = match init(
@@ -42,6 +56,7 @@ pub async fn run(
         &options,
         shutdown_tx.clone(),
         &mut shutdown_manager,
+        restart.clone(),
     )
     .await
     {
@@ -52,6 +67,7 @@ pub async fn run(
             return Err(e);
         }
     };
+    let mut exit_reason = ExitReason::Shutdown;
 
     // if the app is not persistent, wait for ctrl-c, an idle timeout, or max runtime
     // reached to trigger a shutdown
@@ -71,6 +87,10 @@ pub async fn run(
             _ = await_max_runtime(options.lifecycle.max_runtime) => {
                 info!("Max runtime ({:?}) reached, shutting down...", options.lifecycle.max_runtime);
             }
+            _ = restart.notified() => {
+                info!("Restart requested, shutting down...");
+                exit_reason = ExitReason::Restart;
+            }
         }
     }
     // if the app is persistent, wait for ctrl-c to trigger a shutdown
@@ -79,12 +99,17 @@ pub async fn run(
             _ = shutdown_signal => {
                 info!("Shutdown signal received, shutting down...");
             }
+            _ = restart.notified() => {
+                info!("Restart requested, shutting down...");
+                exit_reason = ExitReason::Restart;
+            }
         }
     }
 
     // shutdown the server
     drop(shutdown_tx);
-    shutdown_manager.shutdown().await
+    shutdown_manager.shutdown().await?;
+    Ok(exit_reason)
 }
 
 async fn await_idle_timeout(
@@ -120,6 +145,7 @@ async fn init(
     options: &AppOptions,
     shutdown_tx: broadcast::Sender<()>,
     shutdown_manager: &mut ShutdownManager,
+    restart: Arc<Notify>,
 ) -> Result<Arc<AppState>, ServerErr> {
     let app_state = init_app_state(agent_version, options, shutdown_manager).await?;
 
@@ -154,6 +180,20 @@ async fn init(
     if options.enable_mqtt_worker {
         init_mqtt_worker(
             options.mqtt_worker.clone(),
+            &options.network.tls,
+            &options.storage.layout,
+            app_state.clone(),
+            restart,
+            shutdown_manager,
+            shutdown_tx.subscribe(),
+        )
+        .await?;
+    }
+
+    if options.enable_local_bridge {
+        init_local_bridge_worker(
+            options.local_bridge.clone(),
+            &options.storage.layout,
             app_state.clone(),
             shutdown_manager,
             shutdown_tx.subscribe(),
@@ -169,11 +209,28 @@ async fn init_app_state(
     options: &AppOptions,
     shutdown_manager: &mut ShutdownManager,
 ) -> Result<Arc<AppState>, ServerErr> {
+    let http_client = HTTPClient::new_with_network(&options.backend_base_url, &options.network)
+        .await
+        .map_err(|e| {
+            ServerErr::NetworkErr(Box::new(ServerNetworkErr {
+                source: e,
+                trace: trace!(),
+            }))
+        })?
+        .with_conditional_cache(ConditionalCache::new(
+            options.storage.layout.http_cache_dir(),
+        ))
+        .with_circuit_breaker(Arc::new(CircuitBreaker::new(options.circuit_breaker)))
+        .with_endpoints(Arc::new(Endpoints::new(
+            &options.backend_base_url,
+            &options.backend_fallback_urls,
+            options.backend_failover,
+        )));
     let (app_state, app_state_handle) = AppState::init(
         agent_version,
         &options.storage.layout,
         options.storage.cache_capacities,
-        Arc::new(HTTPClient::new(&options.backend_base_url).await),
+        Arc::new(http_client),
         options.fsm_settings,
     )
     .await?;
@@ -258,23 +315,55 @@ async fn init_poller_worker(
 }
 
 async fn init_mqtt_worker(
-    options: mqtt::Options,
+    mut options: mqtt::Options,
+    tls: &TLSOptions,
+    layout: &StorageLayout,
     app_state: Arc<AppState>,
+    restart: Arc<Notify>,
     shutdown_manager: &mut ShutdownManager,
     mut shutdown_rx: broadcast::Receiver<()>,
 ) -> Result<(), ServerErr> {
     info!("Initializing mqtt worker...");
 
+    // the broker connection trusts the same certificate authorities (and presents the
+    // same client certificate) as the http client
+    if options.tls_config.is_none() && !tls.is_default() {
+        let tls_config = tls.rustls_client_config().map_err(|e| {
+            ServerErr::NetworkErr(Box::new(ServerNetworkErr {
+                source: e,
+                trace: trace!(),
+            }))
+        })?;
+        options.tls_config = Some(tls_config);
+    }
+
+    // messages published while disconnected from the broker are queued in the outbox
+    options.outbox = Some(app_state.outbox.clone());
+
     let token_mngr = app_state.token_mngr.clone();
     let syncer = app_state.syncer.clone();
     let device_file = app_state.device_file.clone();
+    let event_queue = app_state.event_queue.clone();
+    let commands = Commands::new(
+        options.commands.clone(),
+        CommandsArgs {
+            cfg_inst_cache: app_state.caches.cfg_inst.clone(),
+            cfg_inst_content_cache: app_state.caches.cfg_inst_content.clone(),
+            deployment_dir: layout.config_instance_deployment_dir(),
+            restart,
+        },
+    );
 
     let mqtt_handle = tokio::spawn(async move {
         mqtt::run(
             &options,
-            token_mngr.as_ref(),
-            syncer.as_ref(),
-            device_file.as_ref(),
+            mqtt::Args {
+                token_mngr: token_mngr.as_ref(),
+                syncer: syncer.as_ref(),
+                device_file: device_file.as_ref(),
+                commands: &commands,
+                event_queue: event_queue.as_ref(),
+            },
             tokio::time::sleep,
             Box::pin(async move {
                 let _ = shutdown_rx.recv().await;
@@ -286,6 +375,48 @@ async fn init_mqtt_worker(
     Ok(())
 }
 
+async fn init_local_bridge_worker(
+    options: bridge::Options,
+    layout: &StorageLayout,
+    app_state: Arc<AppState>,
+    shutdown_manager: &mut ShutdownManager,
+    mut shutdown_rx: broadcast::Receiver<()>,
+) -> Result<(), ServerErr> {
+    info!("Initializing local bridge worker...");
+
+    let syncer = app_state.syncer.clone();
+    let caches = app_state.caches.clone();
+    let published_topics = layout.local_bridge_topics();
+
+    let local_bridge_handle = tokio::spawn(async move {
+        bridge::run(
+            &options,
+            bridge::Args {
+                syncer: syncer.as_ref(),
+                cfg_inst_cache: caches.cfg_inst.as_ref(),
+                cfg_inst_content_cache: caches.cfg_inst_content.as_ref(),
+                cfg_schema_cache: caches.cfg_schema.as_ref(),
+                published_topics: &published_topics,
+            },
+            tokio::time::sleep,
+            Box::pin(async move {
+                let _ = shutdown_rx.recv().await;
+            }),
+        )
+        .await;
+    });
+    shutdown_manager.with_local_bridge_worker_handle(local_bridge_handle)?;
+    Ok(())
+}
+
+// the mqtt worker's brokers, which the socket server reports the active one of
+fn mqtt_brokers(options: &mqtt::Options) -> Arc<Endpoints> {
+    options
+        .brokers
+        .clone()
+        .unwrap_or_else(|| Arc::new(Endpoints::single(&options.broker_address.broker)))
+}
+
 async fn init_socket_server(
     options: &AppOptions,
     app_state: Arc<AppState>,
@@ -302,7 +433,12 @@ async fn init_socket_server(
         app_state.caches.clone(),
         app_state.token_mngr.clone(),
         app_state.activity_tracker.clone(),
-    );
+        mqtt_brokers(&options.mqtt_worker),
+    )
+    .with_diagnostics(diagnostics::Options {
+        layout: options.storage.layout.clone(),
+        ..Default::default()
+    });
     let server_handle = serve(&options.server, Arc::new(server_state), async move {
         let _ = shutdown_rx.recv().await;
     })
@@ -328,6 +464,7 @@ struct ShutdownManager {
     socket_server_handle: Option<JoinHandle<Result<(), ServerErr>>>,
     poller_worker_handle: Option<JoinHandle<()>>,
     mqtt_worker_handle: Option<JoinHandle<()>>,
+    local_bridge_worker_handle: Option<JoinHandle<()>>,
     token_refresh_worker_handle: Option<JoinHandle<()>>,
 }
 
@@ -340,6 +477,7 @@ impl ShutdownManager {
             socket_server_handle: None,
             poller_worker_handle: None,
             mqtt_worker_handle: None,
+            local_bridge_worker_handle: None,
             token_refresh_worker_handle: None,
         }
     }
@@ -412,6 +550,22 @@ impl ShutdownManager {
         Ok(())
     }
 
+    pub fn with_local_bridge_worker_handle(
+        &mut self,
+        local_bridge_handle: JoinHandle<()>,
+    ) -> Result<(), ServerErr> {
+        if self.local_bridge_worker_handle.is_some() {
+            return Err(ServerErr::ShutdownMngrDuplicateArgErr(Box::new(
+                ShutdownMngrDuplicateArgErr {
+                    arg_name: "local_bridge_handle".to_string(),
+                    trace: trace!(),
+                },
+            )));
+        }
+        self.local_bridge_worker_handle = Some(local_bridge_handle);
+        Ok(())
+    }
+
     pub fn with_socket_server_handle(
         &mut self,
         socket_server_handle: JoinHandle<Result<(), ServerErr>>,
@@ -492,7 +646,19 @@ impl ShutdownManager {
             info!("MQTT worker handle not found, skipping MQTT worker shutdown...");
         }
 
-        // 4. server
+        // 4. local bridge
+        if let Some(local_bridge_worker_handle) = self.local_bridge_worker_handle.take() {
+            local_bridge_worker_handle.await.map_err(|e| {
+                ServerErr::JoinHandleErr(Box::new(JoinHandleErr {
+                    source: Box::new(e),
+                    trace: trace!(),
+                }))
+            })?;
+        } else {
+            info!("Local bridge worker handle not found, skipping local bridge worker shutdown...");
+        }
+
+        // 5. server
         if let Some(socket_server_handle) = self.socket_server_handle.take() {
             socket_server_handle.await.map_err(|e| {
                 ServerErr::JoinHandleErr(Box::new(JoinHandleErr {
@@ -504,7 +670,7 @@ impl ShutdownManager {
             info!("Socket server handle not found, skipping socket server shutdown...");
         }
 
-        // 5. app state
+        // 6. app state
         if let Some(app_state) = self.app_state.take() {
             app_state.state.shutdown().await?;
             app_state.state_handle.await;
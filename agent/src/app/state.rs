@@ -1,6 +1,7 @@
 // standard library
 use std::future::Future;
 use std::sync::Arc;
+use std::time::Duration;
 
 // internal crates
 use crate::activity::ActivityTracker;
@@ -20,7 +21,9 @@ use crate::server::errors::*;
 use crate::storage::{
     caches::{CacheCapacities, Caches},
     device::DeviceFile,
+    events::EventQueue,
     layout::StorageLayout,
+    outbox::Outbox,
 };
 use crate::sync::syncer::{Syncer, SyncerArgs, SyncerExt};
 use crate::trace;
@@ -32,12 +35,22 @@ use tracing::info;
 
 pub type DeviceID = String;
 
+// the maximum number of config instance events kept while the broker is unreachable
+const EVENT_QUEUE_CAPACITY: usize = 1000;
+
+// the maximum number of mqtt messages kept (and for how long) while the broker is
+// unreachable
+const OUTBOX_CAPACITY: usize = 500;
+const OUTBOX_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
 #[derive(Clone, Debug)]
 pub struct AppState {
     pub device_file: Arc<DeviceFile>,
     pub http_client: Arc<HTTPClient>,
     pub syncer: Arc<Syncer>,
     pub caches: Arc<Caches>,
+    pub event_queue: Arc<EventQueue>,
+    pub outbox: Arc<Outbox>,
     pub token_mngr: Arc<TokenManager>,
     pub activity_tracker: Arc<ActivityTracker>,
 }
@@ -86,6 +99,33 @@ impl AppState {
             })?;
         let caches = Arc::new(caches);
 
+        // initialize the queue of events for the mqtt worker to publish
+        let (event_queue, event_queue_handle) = EventQueue::spawn(
+            64,
+            layout.config_instance_event_queue(),
+            EVENT_QUEUE_CAPACITY,
+        )
+        .await
+        .map_err(|e| {
+            ServerErr::FileSysErr(Box::new(ServerFileSysErr {
+                source: e,
+                trace: trace!(),
+            }))
+        })?;
+        let event_queue = Arc::new(event_queue);
+
+        // initialize the outbox of mqtt messages published while disconnected
+        let (outbox, outbox_handle) =
+            Outbox::spawn(64, layout.mqtt_outbox(), OUTBOX_CAPACITY, OUTBOX_TTL)
+                .await
+                .map_err(|e| {
+                    ServerErr::FileSysErr(Box::new(ServerFileSysErr {
+                        source: e,
+                        trace: trace!(),
+                    }))
+                })?;
+        let outbox = Arc::new(outbox);
+
         // initialize the token manager
         let (token_mngr, token_mngr_handle) = TokenManager::spawn(
             64,
@@ -112,8 +152,10 @@ impl AppState {
                 token_mngr: token_mngr.clone(),
                 cfg_inst_cache: caches.cfg_inst.clone(),
                 cfg_inst_content_cache: caches.cfg_inst_content.clone(),
+                cfg_schema_content_cache: Some(caches.cfg_schema_content.clone()),
                 deployment_dir: layout.config_instance_deployment_dir(),
                 fsm_settings,
+                event_queue: Some(event_queue.clone()),
                 agent_version,
                 cooldown_options: CooldownOptions {
                     base_secs: 1,
@@ -134,7 +176,13 @@ impl AppState {
         let activity_tracker = Arc::new(ActivityTracker::new());
 
         let shutdown_handle = async move {
-            let handles = vec![token_mngr_handle, syncer_handle, device_file_handle];
+            let handles = vec![
+                token_mngr_handle,
+                syncer_handle,
+                device_file_handle,
+                event_queue_handle,
+                outbox_handle,
+            ];
 
             futures::future::join(futures::future::join_all(handles), caches_shutdown_handle).await;
         };
@@ -145,6 +193,8 @@ impl AppState {
                 http_client,
                 syncer,
                 caches,
+                event_queue,
+                outbox,
                 token_mngr,
                 activity_tracker,
             },
@@ -239,6 +289,22 @@ impl AppState {
             }))
         })?;
 
+        // shutdown the event queue
+        self.event_queue.shutdown().await.map_err(|e| {
+            ServerErr::FileSysErr(Box::new(ServerFileSysErr {
+                source: e,
+                trace: trace!(),
+            }))
+        })?;
+
+        // shutdown the outbox
+        self.outbox.shutdown().await.map_err(|e| {
+            ServerErr::FileSysErr(Box::new(ServerFileSysErr {
+                source: e,
+                trace: trace!(),
+            }))
+        })?;
+
         // shutdown the token manager
         self.token_mngr.shutdown().await.map_err(|e| {
             ServerErr::AuthnErr(Box::new(ServerAuthnErr {
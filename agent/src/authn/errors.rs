@@ -1,5 +1,6 @@
 // standard library
 use std::fmt;
+use std::time::Duration;
 
 // internal crates
 use crate::crypt::errors::CryptErr;
@@ -285,6 +286,16 @@ macro_rules! forward_error_method {
     };
 }
 
+impl AuthnErr {
+    // how long the backend asked us to wait before retrying (if at all)
+    pub fn retry_after(&self) -> Option<Duration> {
+        match self {
+            AuthnErr::HTTPErr(e) => e.source.retry_after(),
+            _ => None,
+        }
+    }
+}
+
 impl fmt::Display for AuthnErr {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         forward_error_method!(self, fmt, f)
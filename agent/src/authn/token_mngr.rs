@@ -13,7 +13,7 @@ use openapi_client::models::{IssueDeviceClaims, IssueDeviceTokenRequest};
 use chrono::{DateTime, Duration, Utc};
 use serde::Serialize;
 use tokio::sync::mpsc::{self, Receiver, Sender};
-use tokio::sync::oneshot;
+use tokio::sync::{oneshot, watch};
 use tokio::task::JoinHandle;
 use tracing::{error, info};
 use uuid::Uuid;
@@ -33,6 +33,8 @@ pub trait TokenManagerExt {
     async fn shutdown(&self) -> Result<(), AuthnErr>;
     async fn get_token(&self) -> Result<Arc<Token>, AuthnErr>;
     async fn refresh_token(&self) -> Result<(), AuthnErr>;
+    // receives each token issued after subscribing
+    async fn subscribe(&self) -> Result<watch::Receiver<Arc<Token>>, AuthnErr>;
 }
 
 // ======================== SINGLE THREADED IMPLEMENTATION ========================= //
@@ -41,6 +43,7 @@ pub struct SingleThreadTokenManager<HTTPClientT: DevicesExt> {
     http_client: Arc<HTTPClientT>,
     token_file: TokenFile,
     private_key_file: File,
+    refreshed_tx: watch::Sender<Arc<Token>>,
 }
 
 impl<HTTPClientT: DevicesExt> SingleThreadTokenManager<HTTPClientT> {
@@ -67,6 +70,7 @@ impl<HTTPClientT: DevicesExt> SingleThreadTokenManager<HTTPClientT> {
             http_client,
             token_file,
             private_key_file,
+            refreshed_tx: watch::Sender::new(Arc::new(Token::default())),
         })
     }
 
@@ -87,9 +91,22 @@ impl<HTTPClientT: DevicesExt> SingleThreadTokenManager<HTTPClientT> {
             }))
         })?;
 
+        // notify the subscribers of the new token
+        self.refreshed_tx.send_replace(self.token_file.read().await);
+
         Ok(())
     }
 
+    async fn subscribe(&self) -> watch::Receiver<Arc<Token>> {
+        // start subscribers from the current token without notifying existing ones
+        let token = self.token_file.read().await;
+        self.refreshed_tx.send_if_modified(|current| {
+            *current = token;
+            false
+        });
+        self.refreshed_tx.subscribe()
+    }
+
     async fn issue_token(&self) -> Result<Token, AuthnErr> {
         // prepare the token request
         let payload = self.prepare_issue_token_request().await?;
@@ -173,6 +190,9 @@ pub enum WorkerCommand {
     RefreshToken {
         respond_to: oneshot::Sender<Result<(), AuthnErr>>,
     },
+    Subscribe {
+        respond_to: oneshot::Sender<Result<watch::Receiver<Arc<Token>>, AuthnErr>>,
+    },
     Shutdown {
         respond_to: oneshot::Sender<Result<(), AuthnErr>>,
     },
@@ -217,6 +237,12 @@ impl<HTTPClientT: DevicesExt> Worker<HTTPClientT> {
                         error!("Actor failed to refresh token");
                     }
                 }
+                WorkerCommand::Subscribe { respond_to } => {
+                    let subscriber = self.token_mngr.subscribe().await;
+                    if respond_to.send(Ok(subscriber)).is_err() {
+                        error!("Actor failed to send token subscriber");
+                    }
+                }
             }
         }
     }
@@ -314,6 +340,25 @@ impl TokenManagerExt for TokenManager {
             }))
         })?
     }
+
+    async fn subscribe(&self) -> Result<watch::Receiver<Arc<Token>>, AuthnErr> {
+        let (send, recv) = oneshot::channel();
+        self.sender
+            .send(WorkerCommand::Subscribe { respond_to: send })
+            .await
+            .map_err(|e| {
+                AuthnErr::SendActorMessageErr(Box::new(SendActorMessageErr {
+                    source: Box::new(e),
+                    trace: trace!(),
+                }))
+            })?;
+        recv.await.map_err(|e| {
+            AuthnErr::ReceiveActorMessageErr(Box::new(ReceiveActorMessageErr {
+                source: Box::new(e),
+                trace: trace!(),
+            }))
+        })?
+    }
 }
 
 impl TokenManagerExt for Arc<TokenManager> {
@@ -328,4 +373,7 @@ impl TokenManagerExt for Arc<TokenManager> {
     async fn refresh_token(&self) -> Result<(), AuthnErr> {
         self.as_ref().refresh_token().await
     }
+    async fn subscribe(&self) -> Result<watch::Receiver<Arc<Token>>, AuthnErr> {
+        self.as_ref().subscribe().await
+    }
 }
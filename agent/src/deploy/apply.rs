@@ -14,17 +14,22 @@ use crate::deploy::{
     observer::{on_update, Observer},
 };
 use crate::filesys::dir::Dir;
+use crate::metrics::registry::{self, DeployMetrics, DeployOperation};
 use crate::models::config_instance::{
-    ActivityStatus, ConfigInstance, ConfigInstanceID, TargetStatus,
+    ActivityStatus, ConfigInstance, ConfigInstanceID, ErrorStatus, TargetStatus,
 };
+use crate::models::config_schema::ConfigSchemaID;
+use crate::mqtt::events::ConfigInstanceEvent;
 use crate::storage::config_instances::{
     ConfigInstanceCache, ConfigInstanceCacheEntry, ConfigInstanceContentCache,
 };
+use crate::storage::config_schemas::ConfigSchemaContentCache;
+use crate::storage::events::EventQueue;
 use crate::trace;
 
 // external crates
 use async_trait::async_trait;
-use tracing::{debug, error, info};
+use tracing::{debug, error, info, warn};
 
 pub fn is_dirty(old: Option<&ConfigInstanceCacheEntry>, new: &ConfigInstance) -> bool {
     let old = match old {
@@ -56,18 +61,151 @@ impl<'a> Observer for StorageObserver<'a> {
     }
 }
 
+// records deploy, remove and archive outcomes by comparing each update against the
+// config instance's cached state. It must run before the storage observer so that the
+// cache still holds the previous state.
+pub struct MetricsObserver<'a> {
+    pub cfg_inst_cache: &'a ConfigInstanceCache,
+    pub metrics: &'a DeployMetrics,
+}
+
+#[async_trait]
+impl<'a> Observer for MetricsObserver<'a> {
+    async fn on_update(&mut self, cfg_inst: &ConfigInstance) -> Result<(), DeployErr> {
+        let prev = self
+            .cfg_inst_cache
+            .read_optional(cfg_inst.id.clone())
+            .await
+            .map_err(|e| {
+                DeployErr::CrudErr(Box::new(DeployCrudErr {
+                    source: e,
+                    trace: trace!(),
+                }))
+            })?;
+
+        if let Some((operation, success)) = classify_update(prev.as_ref(), cfg_inst) {
+            self.metrics.record(operation, success);
+        }
+        Ok(())
+    }
+}
+
+// queues an event for the mqtt worker to publish whenever a config instance's activity
+// or error status changes. Like the metrics observer, it must run before the storage
+// observer. Failing to queue an event doesn't fail the deployment since the backend
+// still learns of the change on the next sync.
+pub struct EventObserver<'a> {
+    pub cfg_inst_cache: &'a ConfigInstanceCache,
+    pub event_queue: &'a EventQueue,
+}
+
+#[async_trait]
+impl<'a> Observer for EventObserver<'a> {
+    async fn on_update(&mut self, cfg_inst: &ConfigInstance) -> Result<(), DeployErr> {
+        let prev = self
+            .cfg_inst_cache
+            .read_optional(cfg_inst.id.clone())
+            .await
+            .map_err(|e| {
+                DeployErr::CrudErr(Box::new(DeployCrudErr {
+                    source: e,
+                    trace: trace!(),
+                }))
+            })?;
+
+        if !is_transition(prev.as_ref(), cfg_inst) {
+            return Ok(());
+        }
+        if let Err(e) = self
+            .event_queue
+            .push(ConfigInstanceEvent::new(cfg_inst))
+            .await
+        {
+            warn!(
+                "unable to queue an event for config instance {}: {e}",
+                cfg_inst.id
+            );
+        }
+        Ok(())
+    }
+}
+
+pub fn is_transition(prev: Option<&ConfigInstance>, new: &ConfigInstance) -> bool {
+    match prev {
+        Some(prev) => {
+            prev.activity_status != new.activity_status || prev.error_status != new.error_status
+        }
+        None => true,
+    }
+}
+
+pub fn classify_update(
+    prev: Option<&ConfigInstance>,
+    new: &ConfigInstance,
+) -> Option<(DeployOperation, bool)> {
+    let prev = match prev {
+        Some(prev) => prev,
+        // config instances unknown to the agent have never been deployed so removing
+        // them is an archive
+        None => {
+            return match new.activity_status {
+                ActivityStatus::Deployed => Some((DeployOperation::Deploy, true)),
+                ActivityStatus::Removed => Some((DeployOperation::Archive, true)),
+                _ => None,
+            }
+        }
+    };
+
+    let operation = match fsm::next_action(prev, false) {
+        fsm::NextAction::Deploy => DeployOperation::Deploy,
+        fsm::NextAction::Remove => DeployOperation::Remove,
+        fsm::NextAction::Archive => DeployOperation::Archive,
+        fsm::NextAction::None | fsm::NextAction::Wait(_) => return None,
+    };
+
+    // successful transitions change the activity status
+    if prev.activity_status != new.activity_status {
+        return Some((operation, true));
+    }
+
+    // error transitions leave the activity status as is but set an error status and
+    // restart the cooldown
+    let is_error = new.error_status != ErrorStatus::None
+        && (prev.attempts != new.attempts || prev.cooldown_ends_at != new.cooldown_ends_at);
+    if is_error {
+        return Some((operation, false));
+    }
+    None
+}
+
 pub async fn apply(
     mut cfg_insts_to_apply: HashMap<ConfigInstanceID, ConfigInstance>,
     cfg_inst_cache: &ConfigInstanceCache,
     cfg_inst_content_cache: &ConfigInstanceContentCache,
+    // the content of each config instance is validated against its cached config
+    // schema before being deployed (if set)
+    cfg_schema_content_cache: Option<&ConfigSchemaContentCache>,
     deployment_dir: &Dir,
     fsm_settings: &fsm::Settings,
+    event_queue: Option<&EventQueue>,
 ) -> Result<HashMap<ConfigInstanceID, ConfigInstance>, DeployErr> {
     let num_cfg_insts_to_apply = cfg_insts_to_apply.len();
     debug!("Applying {num_cfg_insts_to_apply} config instances {cfg_insts_to_apply:?}");
 
     // observers
     let mut observers: Vec<&mut dyn Observer> = Vec::new();
+    let mut metrics_observer = MetricsObserver {
+        cfg_inst_cache,
+        metrics: &registry::global().deploy,
+    };
+    observers.push(&mut metrics_observer);
+    let mut event_observer = event_queue.map(|event_queue| EventObserver {
+        cfg_inst_cache,
+        event_queue,
+    });
+    if let Some(event_observer) = event_observer.as_mut() {
+        observers.push(event_observer);
+    }
     let mut storage_observer = StorageObserver { cfg_inst_cache };
     observers.push(&mut storage_observer);
 
@@ -97,6 +235,7 @@ pub async fn apply(
             cfg_inst,
             cfg_inst_cache,
             cfg_inst_content_cache,
+            cfg_schema_content_cache,
             deployment_dir,
             fsm_settings,
             &mut observers,
@@ -128,10 +267,11 @@ pub async fn apply(
     Ok(applied_cfg_insts)
 }
 
-async fn apply_one<R1, R2>(
+async fn apply_one<R1, R2, R3>(
     cfg_inst: ConfigInstance,
     all_cfg_insts: &R1,
     all_cfg_inst_contents: &R2,
+    all_cfg_schema_contents: Option<&R3>,
     deployment_dir: &Dir,
     fsm_settings: &fsm::Settings,
     observers: &mut [&mut dyn Observer],
@@ -139,6 +279,7 @@ async fn apply_one<R1, R2>(
 where
     R1: Find<ConfigInstanceID, ConfigInstance>,
     R2: Read<ConfigInstanceID, serde_json::Value>,
+    R3: Read<ConfigSchemaID, serde_json::Value>,
 {
     match fsm::next_action(&cfg_inst, true) {
         fsm::NextAction::None => (DeployResults::empty(), Ok(())),
@@ -147,6 +288,7 @@ where
                 cfg_inst,
                 all_cfg_insts,
                 all_cfg_inst_contents,
+                all_cfg_schema_contents,
                 deployment_dir,
                 fsm_settings,
                 observers,
@@ -158,6 +300,7 @@ where
                 cfg_inst,
                 all_cfg_insts,
                 all_cfg_inst_contents,
+                all_cfg_schema_contents,
                 deployment_dir,
                 fsm_settings,
                 observers,
@@ -170,10 +313,11 @@ where
 }
 
 // =================================== DEPLOY ====================================== //
-async fn deploy<R1, R2>(
+async fn deploy<R1, R2, R3>(
     cfg_inst: ConfigInstance,
     all_cfg_insts: &R1,
     all_cfg_inst_contents: &R2,
+    all_cfg_schema_contents: Option<&R3>,
     deployment_dir: &Dir,
     fsm_settings: &fsm::Settings,
     observers: &mut [&mut dyn Observer],
@@ -181,6 +325,7 @@ async fn deploy<R1, R2>(
 where
     R1: Find<ConfigInstanceID, ConfigInstance>,
     R2: Read<ConfigInstanceID, serde_json::Value>,
+    R3: Read<ConfigSchemaID, serde_json::Value>,
 {
     if fsm::next_action(&cfg_inst, true) != fsm::NextAction::Deploy {
         let next_action = fsm::next_action(&cfg_inst, true);
@@ -213,6 +358,7 @@ where
         conflicts,
         vec![cfg_inst],
         all_cfg_inst_contents,
+        all_cfg_schema_contents,
         deployment_dir,
         fsm_settings,
         observers,
@@ -221,10 +367,11 @@ where
 }
 
 // =================================== REMOVE ====================================== //
-async fn remove<R1, R2>(
+async fn remove<R1, R2, R3>(
     mut cfg_inst: ConfigInstance,
     all_cfg_insts: &R1,
     all_cfg_inst_contents: &R2,
+    all_cfg_schema_contents: Option<&R3>,
     deployment_dir: &Dir,
     fsm_settings: &fsm::Settings,
     observers: &mut [&mut dyn Observer],
@@ -232,6 +379,7 @@ async fn remove<R1, R2>(
 where
     R1: Find<ConfigInstanceID, ConfigInstance>,
     R2: Read<ConfigInstanceID, serde_json::Value>,
+    R3: Read<ConfigSchemaID, serde_json::Value>,
 {
     if fsm::next_action(&cfg_inst, true) != fsm::NextAction::Remove {
         let next_action = fsm::next_action(&cfg_inst, true);
@@ -282,6 +430,7 @@ where
         vec![cfg_inst],
         replacements,
         all_cfg_inst_contents,
+        all_cfg_schema_contents,
         deployment_dir,
         fsm_settings,
         observers,
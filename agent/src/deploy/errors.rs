@@ -5,6 +5,7 @@ use std::fmt;
 use crate::cache::errors::CacheErr;
 use crate::crud::errors::CrudErr;
 use crate::deploy::fsm;
+use crate::deploy::validate::SchemaViolation;
 use crate::errors::{Code, HTTPCode, MiruError, Trace};
 use crate::filesys::errors::FileSysErr;
 use crate::models::config_instance::ConfigInstance;
@@ -149,6 +150,97 @@ impl fmt::Display for ConflictingDeploymentsErr {
     }
 }
 
+#[derive(Debug)]
+pub struct SchemaValidationErr {
+    pub cfg_inst_id: String,
+    pub config_schema_id: String,
+    pub violations: Vec<SchemaViolation>,
+    pub trace: Box<Trace>,
+}
+
+impl SchemaValidationErr {
+    // the json pointers of the config content values which failed validation
+    pub fn json_pointers(&self) -> Vec<String> {
+        let mut pointers = Vec::new();
+        for violation in &self.violations {
+            if !pointers.contains(&violation.instance_path) {
+                pointers.push(violation.instance_path.clone());
+            }
+        }
+        pointers
+    }
+}
+
+impl MiruError for SchemaValidationErr {
+    fn code(&self) -> Code {
+        Code::SchemaValidationFailed
+    }
+
+    fn http_status(&self) -> HTTPCode {
+        HTTPCode::UNPROCESSABLE_ENTITY
+    }
+
+    fn is_network_connection_error(&self) -> bool {
+        false
+    }
+
+    fn params(&self) -> Option<serde_json::Value> {
+        Some(serde_json::json!({
+            "config_instance_id": self.cfg_inst_id,
+            "config_schema_id": self.config_schema_id,
+            "json_pointers": self.json_pointers(),
+            "violations": self.violations,
+        }))
+    }
+}
+
+impl fmt::Display for SchemaValidationErr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "the content of config instance '{}' does not match config schema '{}' at {:?}",
+            self.cfg_inst_id,
+            self.config_schema_id,
+            self.json_pointers()
+        )
+    }
+}
+
+#[derive(Debug)]
+pub struct InvalidConfigSchemaErr {
+    pub config_schema_id: String,
+    pub message: String,
+    pub trace: Box<Trace>,
+}
+
+impl MiruError for InvalidConfigSchemaErr {
+    fn code(&self) -> Code {
+        Code::InternalServerError
+    }
+
+    fn http_status(&self) -> HTTPCode {
+        HTTPCode::INTERNAL_SERVER_ERROR
+    }
+
+    fn is_network_connection_error(&self) -> bool {
+        false
+    }
+
+    fn params(&self) -> Option<serde_json::Value> {
+        None
+    }
+}
+
+impl fmt::Display for InvalidConfigSchemaErr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "config schema '{}' is not a valid json schema: {}",
+            self.config_schema_id, self.message
+        )
+    }
+}
+
 #[derive(Debug)]
 pub struct DeployFileSysErr {
     pub source: FileSysErr,
@@ -275,6 +367,8 @@ pub enum DeployErr {
     ConfigInstanceNotDeployableErr(Box<ConfigInstanceNotDeployableErr>),
     ConfigInstanceNotRemoveableErr(Box<ConfigInstanceNotRemoveableErr>),
     ConfigInstanceNotArchiveableErr(Box<ConfigInstanceNotArchiveableErr>),
+    SchemaValidationErr(Box<SchemaValidationErr>),
+    InvalidConfigSchemaErr(Box<InvalidConfigSchemaErr>),
 
     CacheErr(Box<DeployCacheErr>),
     CrudErr(Box<DeployCrudErr>),
@@ -289,6 +383,8 @@ macro_rules! forward_error_method {
             DeployErr::ConfigInstanceNotDeployableErr(e) => e.$method($($arg)?),
             DeployErr::ConfigInstanceNotRemoveableErr(e) => e.$method($($arg)?),
             DeployErr::ConfigInstanceNotArchiveableErr(e) => e.$method($($arg)?),
+            DeployErr::SchemaValidationErr(e) => e.$method($($arg)?),
+            DeployErr::InvalidConfigSchemaErr(e) => e.$method($($arg)?),
 
             DeployErr::CacheErr(e) => e.$method($($arg)?),
             DeployErr::CrudErr(e) => e.$method($($arg)?),
@@ -1,14 +1,16 @@
 // internal crates
 use crate::crud::prelude::Read;
-use crate::deploy::errors::{DeployCrudErr, DeployErr, DeployFileSysErr};
+use crate::deploy::errors::{DeployCrudErr, DeployErr, DeployFileSysErr, SchemaValidationErr};
 use crate::deploy::fsm;
 use crate::deploy::observer::{on_update, Observer};
+use crate::deploy::validate::validate;
 use crate::filesys::dir::Dir;
 use crate::models::config_instance::{ConfigInstance, ConfigInstanceID, TargetStatus};
+use crate::models::config_schema::ConfigSchemaID;
 use crate::trace;
 
 // external crates
-use tracing::{error, info};
+use tracing::{error, info, warn};
 
 #[derive(Debug)]
 pub struct DeployResults {
@@ -25,21 +27,24 @@ impl DeployResults {
     }
 }
 
-pub async fn deploy_with_rollback<R>(
+pub async fn deploy_with_rollback<R, S>(
     to_remove: Vec<ConfigInstance>,
     to_deploy: Vec<ConfigInstance>,
     cfg_inst_content_reader: &R,
+    cfg_schema_content_reader: Option<&S>,
     deployment_dir: &Dir,
     settings: &fsm::Settings,
     observers: &mut [&mut dyn Observer],
 ) -> (DeployResults, Result<(), DeployErr>)
 where
     R: Read<ConfigInstanceID, serde_json::Value>,
+    S: Read<ConfigSchemaID, serde_json::Value>,
 {
     let result = deploy_with_rollback_impl(
         to_remove,
         to_deploy,
         cfg_inst_content_reader,
+        cfg_schema_content_reader,
         deployment_dir,
         settings,
         observers,
@@ -51,16 +56,18 @@ where
     result
 }
 
-async fn deploy_with_rollback_impl<R>(
+async fn deploy_with_rollback_impl<R, S>(
     to_remove: Vec<ConfigInstance>,
     to_deploy: Vec<ConfigInstance>,
     cfg_inst_content_reader: &R,
+    cfg_schema_content_reader: Option<&S>,
     deployment_dir: &Dir,
     settings: &fsm::Settings,
     observers: &mut [&mut dyn Observer],
 ) -> (DeployResults, Result<(), DeployErr>)
 where
     R: Read<ConfigInstanceID, serde_json::Value>,
+    S: Read<ConfigSchemaID, serde_json::Value>,
 {
     // remove the previous config instance. Don't worry whether it failed or not as we want
     // to attempt to deploy the next config instance regardless
@@ -73,6 +80,7 @@ where
     let (to_deploy, result) = deploy_many(
         to_deploy,
         cfg_inst_content_reader,
+        cfg_schema_content_reader,
         deployment_dir,
         settings,
         observers,
@@ -105,6 +113,7 @@ where
     let (to_remove, result) = deploy_many(
         to_remove,
         cfg_inst_content_reader,
+        cfg_schema_content_reader,
         deployment_dir,
         settings,
         observers,
@@ -124,15 +133,17 @@ where
 }
 
 // =================================== DEPLOY ====================================== //
-async fn deploy_many<R>(
+async fn deploy_many<R, S>(
     cfg_insts: Vec<ConfigInstance>,
     content_fetcher: &R,
+    schema_fetcher: Option<&S>,
     deployment_dir: &Dir,
     settings: &fsm::Settings,
     observers: &mut [&mut dyn Observer],
 ) -> (Vec<ConfigInstance>, Result<(), DeployErr>)
 where
     R: Read<ConfigInstanceID, serde_json::Value>,
+    S: Read<ConfigSchemaID, serde_json::Value>,
 {
     let mut post_deploy_cfg_insts = Vec::new();
     let mut cfg_insts_iter = cfg_insts.into_iter();
@@ -140,6 +151,7 @@ where
         let (post_deploy_cfg_inst, result) = deploy(
             cfg_inst,
             content_fetcher,
+            schema_fetcher,
             deployment_dir,
             settings,
             observers,
@@ -159,17 +171,25 @@ where
     (post_deploy_cfg_insts, Ok(()))
 }
 
-async fn deploy<R>(
+async fn deploy<R, S>(
     mut cfg_inst: ConfigInstance,
     content_fetcher: &R,
+    schema_fetcher: Option<&S>,
     deployment_dir: &Dir,
     settings: &fsm::Settings,
     observers: &mut [&mut dyn Observer],
 ) -> (ConfigInstance, Result<(), DeployErr>)
 where
     R: Read<ConfigInstanceID, serde_json::Value>,
+    S: Read<ConfigSchemaID, serde_json::Value>,
 {
-    let result = write_cfg_inst_to_deployment_dir(&cfg_inst, content_fetcher, deployment_dir).await;
+    let result = write_cfg_inst_to_deployment_dir(
+        &cfg_inst,
+        content_fetcher,
+        schema_fetcher,
+        deployment_dir,
+    )
+    .await;
 
     match result {
         Ok(_) => {
@@ -191,13 +211,15 @@ where
     }
 }
 
-async fn write_cfg_inst_to_deployment_dir<R>(
+async fn write_cfg_inst_to_deployment_dir<R, S>(
     cfg_inst: &ConfigInstance,
     content_fetcher: &R,
+    schema_fetcher: Option<&S>,
     deployment_dir: &Dir,
 ) -> Result<(), DeployErr>
 where
     R: Read<ConfigInstanceID, serde_json::Value>,
+    S: Read<ConfigSchemaID, serde_json::Value>,
 {
     let cfg_inst_content = content_fetcher
         .read(cfg_inst.id.clone())
@@ -209,6 +231,11 @@ where
             }))
         })?;
 
+    // never write content which doesn't match its config schema to the deployment dir
+    if let Some(schema_fetcher) = schema_fetcher {
+        validate_cfg_inst_content(cfg_inst, &cfg_inst_content, schema_fetcher).await?;
+    }
+
     let dest_file = deployment_dir.file(&cfg_inst.relative_filepath);
     dest_file
         .write_json(&cfg_inst_content, true, true)
@@ -223,6 +250,56 @@ where
     Ok(())
 }
 
+// validates the content against its cached config schema. This deliberately fails
+// open: if the schema isn't cached the content is deployed without being validated
+// rather than blocking the deploy until the schema can be fetched. Only content which
+// violates its schema is rejected.
+async fn validate_cfg_inst_content<S>(
+    cfg_inst: &ConfigInstance,
+    cfg_inst_content: &serde_json::Value,
+    schema_fetcher: &S,
+) -> Result<(), DeployErr>
+where
+    S: Read<ConfigSchemaID, serde_json::Value>,
+{
+    let schema = schema_fetcher
+        .read_optional(cfg_inst.config_schema_id.clone())
+        .await
+        .map_err(|e| {
+            DeployErr::CrudErr(Box::new(DeployCrudErr {
+                source: e,
+                trace: trace!(),
+            }))
+        })?;
+
+    // the schema is fetched alongside the config instance but may be missing if the
+    // backend couldn't be reached for it (or doesn't have a json schema for it), in
+    // which case the content is deployed unvalidated
+    let schema = match schema {
+        Some(schema) => schema,
+        None => {
+            warn!(
+                "config schema '{}' is not cached, deploying config instance '{}' without validating it",
+                cfg_inst.config_schema_id, cfg_inst.id
+            );
+            return Ok(());
+        }
+    };
+
+    let violations = validate(&cfg_inst.config_schema_id, &schema, cfg_inst_content)?;
+    if !violations.is_empty() {
+        return Err(DeployErr::SchemaValidationErr(Box::new(
+            SchemaValidationErr {
+                cfg_inst_id: cfg_inst.id.clone(),
+                config_schema_id: cfg_inst.config_schema_id.clone(),
+                violations,
+                trace: trace!(),
+            },
+        )));
+    }
+    Ok(())
+}
+
 // =================================== REMOVE ====================================== //
 async fn remove_many(
     cfg_insts: Vec<ConfigInstance>,
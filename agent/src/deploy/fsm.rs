@@ -1,6 +1,8 @@
 // internal crates
 use crate::errors::MiruError;
-use crate::models::config_instance::{ActivityStatus, ConfigInstance, ErrorStatus, TargetStatus};
+use crate::models::config_instance::{
+    ActivityStatus, ConfigInstance, DeployError, ErrorStatus, TargetStatus,
+};
 use crate::utils::calc_exp_backoff;
 
 // external crates
@@ -90,6 +92,8 @@ struct TransitionOptions {
     error_status: Option<ErrorStatus>,
     attempts: Option<u32>,
     cooldown: Option<TimeDelta>,
+    // None leaves the last deploy error as is, Some(None) clears it
+    last_deploy_error: Option<Option<DeployError>>,
 }
 
 fn transition(mut cfg_inst: ConfigInstance, options: TransitionOptions) -> ConfigInstance {
@@ -109,6 +113,10 @@ fn transition(mut cfg_inst: ConfigInstance, options: TransitionOptions) -> Confi
         cfg_inst.set_cooldown(cooldown);
     }
 
+    if let Some(last_deploy_error) = options.last_deploy_error {
+        cfg_inst.last_deploy_error = last_deploy_error;
+    }
+
     cfg_inst
 }
 
@@ -143,6 +151,13 @@ fn get_success_options(
             None
         },
         cooldown: None,
+        // keep the last deploy error until the target status is reached (e.g. a
+        // rollback removing a config instance which failed to deploy keeps the error)
+        last_deploy_error: if has_recovered(cfg_inst, new_activity_status) {
+            Some(None)
+        } else {
+            None
+        },
     }
 }
 
@@ -194,11 +209,12 @@ pub fn error(
     e: &impl MiruError,
     increment_attempts: bool,
 ) -> ConfigInstance {
-    let options = get_error_options(
+    let mut options = get_error_options(
         &cfg_inst,
         increment_attempts && should_increment_attempts(e),
         settings,
     );
+    options.last_deploy_error = Some(Some(DeployError::new(e)));
     transition(cfg_inst, options)
 }
 
@@ -237,5 +253,6 @@ fn get_error_options(
         error_status: new_error_status,
         attempts: Some(attempts),
         cooldown: Some(TimeDelta::seconds(cooldown)),
+        last_deploy_error: None,
     }
 }
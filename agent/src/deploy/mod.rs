@@ -3,3 +3,4 @@ pub mod errors;
 pub mod filesys;
 pub mod fsm;
 pub mod observer;
+pub mod validate;
@@ -0,0 +1,71 @@
+// standard library
+use std::sync::{Arc, LazyLock};
+
+// internal crates
+use crate::deploy::errors::{DeployErr, InvalidConfigSchemaErr};
+use crate::trace;
+
+// external crates
+use jsonschema::Validator;
+use moka::sync::Cache;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SchemaViolation {
+    // json pointer to the offending value in the config content ("" is the root)
+    pub instance_path: String,
+    // json pointer to the schema keyword which failed
+    pub schema_path: String,
+    pub message: String,
+}
+
+// the number of compiled validators kept (the least recently used are evicted)
+const MAX_VALIDATORS: u64 = 64;
+
+// the compiled validators of the config schemas by id (with the schema they were
+// compiled from). They're process wide (like the metrics) so that deploys and the
+// socket server's validations share them without a handle threaded through to each.
+static VALIDATORS: LazyLock<Cache<String, Arc<(Value, Validator)>>> =
+    LazyLock::new(|| Cache::new(MAX_VALIDATORS));
+
+/// Validate the config content against the config schema's JSON Schema document,
+/// returning every violation found (empty if the content is valid). An error is only
+/// returned if the schema itself can't be compiled. Compiled schemas are cached by
+/// config schema id.
+pub fn validate(
+    config_schema_id: &str,
+    schema: &Value,
+    content: &Value,
+) -> Result<Vec<SchemaViolation>, DeployErr> {
+    let validator = validator(config_schema_id, schema)?;
+    let (_, validator) = validator.as_ref();
+    Ok(validator
+        .iter_errors(content)
+        .map(|e| SchemaViolation {
+            instance_path: e.instance_path.to_string(),
+            schema_path: e.schema_path.to_string(),
+            message: e.to_string(),
+        })
+        .collect())
+}
+
+fn validator(config_schema_id: &str, schema: &Value) -> Result<Arc<(Value, Validator)>, DeployErr> {
+    // the schema is compared in case the cached one is stale
+    if let Some(cached) = VALIDATORS.get(config_schema_id) {
+        if &cached.0 == schema {
+            return Ok(cached);
+        }
+    }
+
+    let validator = jsonschema::validator_for(schema).map_err(|e| {
+        DeployErr::InvalidConfigSchemaErr(Box::new(InvalidConfigSchemaErr {
+            config_schema_id: config_schema_id.to_string(),
+            message: e.to_string(),
+            trace: trace!(),
+        }))
+    })?;
+    let validator = Arc::new((schema.clone(), validator));
+    VALIDATORS.insert(config_schema_id.to_string(), validator.clone());
+    Ok(validator)
+}
@@ -0,0 +1,111 @@
+// external crates
+use chrono::{DateTime, Utc};
+
+const BLOCK_SIZE: usize = 512;
+const NAME_LEN: usize = 100;
+const PREFIX_LEN: usize = 155;
+
+// the entry types used
+const REGULAR_FILE: u8 = b'0';
+// a gnu extension holding the path of the next entry (supported by gnu tar, bsdtar,
+// python's tarfile, ...)
+const GNU_LONG_NAME: u8 = b'L';
+
+/// A minimal (uncompressed) ustar archive writer. Only regular files are supported,
+/// which is all the diagnostics bundle needs, and the archive can be extracted with
+/// any tar implementation (e.g. `tar -xf diagnostics.tar`).
+#[derive(Debug, Default)]
+pub struct Archive {
+    buf: Vec<u8>,
+}
+
+impl Archive {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append a file to the archive. Paths longer than 100 bytes are split across the
+    /// ustar prefix and name fields or, if they don't fit, preceded by a gnu long name
+    /// entry.
+    pub fn append(&mut self, path: &str, data: &[u8], modified_at: DateTime<Utc>) {
+        let (prefix, name) = match split_path(path) {
+            Some(split) => split,
+            None => {
+                let mut long_name = path.as_bytes().to_vec();
+                long_name.push(0);
+                self.append_entry("././@LongLink", "", GNU_LONG_NAME, &long_name, modified_at);
+                ("", truncate(path, NAME_LEN))
+            }
+        };
+        self.append_entry(name, prefix, REGULAR_FILE, data, modified_at);
+    }
+
+    fn append_entry(
+        &mut self,
+        name: &str,
+        prefix: &str,
+        kind: u8,
+        data: &[u8],
+        modified_at: DateTime<Utc>,
+    ) {
+        let mut header = [0u8; BLOCK_SIZE];
+        header[..name.len()].copy_from_slice(name.as_bytes());
+        write_octal(&mut header[100..108], 0o644);
+        write_octal(&mut header[108..116], 0);
+        write_octal(&mut header[116..124], 0);
+        write_octal(&mut header[124..136], data.len() as u64);
+        write_octal(&mut header[136..148], modified_at.timestamp().max(0) as u64);
+        header[156] = kind;
+        header[257..263].copy_from_slice(b"ustar\0");
+        header[263..265].copy_from_slice(b"00");
+        header[345..345 + prefix.len()].copy_from_slice(prefix.as_bytes());
+
+        // the checksum is computed with the checksum field itself set to spaces
+        header[148..156].copy_from_slice(b"        ");
+        let checksum: u32 = header.iter().map(|b| *b as u32).sum();
+        let checksum = format!("{checksum:06o}\0 ");
+        header[148..156].copy_from_slice(checksum.as_bytes());
+
+        self.buf.extend_from_slice(&header);
+        self.buf.extend_from_slice(data);
+        let padding = (BLOCK_SIZE - data.len() % BLOCK_SIZE) % BLOCK_SIZE;
+        self.buf.extend(std::iter::repeat_n(0u8, padding));
+    }
+
+    /// Terminate the archive with the two empty blocks required by the format.
+    pub fn finish(mut self) -> Vec<u8> {
+        self.buf.extend(std::iter::repeat_n(0u8, 2 * BLOCK_SIZE));
+        self.buf
+    }
+}
+
+// writes the value as zero padded octal digits followed by a nul terminator
+fn write_octal(field: &mut [u8], value: u64) {
+    let digits = field.len() - 1;
+    let octal = format!("{value:0digits$o}");
+    let octal = &octal.as_bytes()[octal.len().saturating_sub(digits)..];
+    field[..digits].copy_from_slice(octal);
+    field[digits] = 0;
+}
+
+// splits the path into the ustar prefix and name fields (joined by a '/'), if it fits
+fn split_path(path: &str) -> Option<(&str, &str)> {
+    if path.len() <= NAME_LEN {
+        return Some(("", path));
+    }
+    path.char_indices()
+        .filter(|(i, c)| *c == '/' && *i <= PREFIX_LEN)
+        .map(|(i, _)| (&path[..i], &path[i + 1..]))
+        .find(|(_, name)| !name.is_empty() && name.len() <= NAME_LEN)
+}
+
+fn truncate(s: &str, max_len: usize) -> &str {
+    if s.len() <= max_len {
+        return s;
+    }
+    let mut end = max_len;
+    while !s.is_char_boundary(end) {
+        end -= 1;
+    }
+    &s[..end]
+}
@@ -12,6 +12,11 @@ pub type HTTPCode = StatusCode;
 pub enum Code {
     InternalServerError,
     ResourceNotFound,
+    Unauthorized,
+    Forbidden,
+    SchemaValidationFailed,
+    TLSPinMismatch,
+    UnsupportedBundleVersion,
     BackendError(String),
 }
 
@@ -20,6 +25,11 @@ impl Code {
         match self {
             Self::InternalServerError => "internal_server_error",
             Self::ResourceNotFound => "resource_not_found",
+            Self::Unauthorized => "unauthorized",
+            Self::Forbidden => "forbidden",
+            Self::SchemaValidationFailed => "schema_validation_failed",
+            Self::TLSPinMismatch => "tls_pin_mismatch",
+            Self::UnsupportedBundleVersion => "unsupported_bundle_version",
             Self::BackendError(code) => code,
         }
     }
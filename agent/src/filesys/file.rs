@@ -20,7 +20,7 @@ use atomicwrites::{AllowOverwrite, AtomicFile};
 use secrecy::{ExposeSecretMut, SecretBox};
 use serde::de::DeserializeOwned;
 use tokio::fs::File as TokioFile;
-use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
 #[allow(unused_imports)]
 use tracing::{debug, error, info, warn};
 
@@ -117,6 +117,37 @@ impl File {
         Ok(buf)
     }
 
+    /// Read at most the last `max_bytes` bytes of a file
+    pub async fn read_tail(&self, max_bytes: u64) -> Result<Vec<u8>, FileSysErr> {
+        self.assert_exists()?;
+
+        let mut file = TokioFile::open(self.to_string()).await.map_err(|e| {
+            FileSysErr::OpenFileErr(Box::new(OpenFileErr {
+                source: Box::new(e),
+                file: self.clone(),
+                trace: trace!(),
+            }))
+        })?;
+        let to_read_err = |e| {
+            FileSysErr::ReadFileErr(Box::new(ReadFileErr {
+                source: Box::new(e),
+                file: self.clone(),
+                trace: trace!(),
+            }))
+        };
+        let size = self.size().await?;
+        file.seek(std::io::SeekFrom::Start(size.saturating_sub(max_bytes)))
+            .await
+            .map_err(to_read_err)?;
+        // the file may grow while it's read (e.g. logs) so the read is bounded too
+        let mut buf = Vec::new();
+        file.take(max_bytes)
+            .read_to_end(&mut buf)
+            .await
+            .map_err(to_read_err)?;
+        Ok(buf)
+    }
+
     pub async fn read_secret_bytes(&self) -> Result<SecretBox<Vec<u8>>, FileSysErr> {
         self.assert_exists()?;
 
@@ -258,6 +289,33 @@ impl File {
         self.write_bytes(&json_bytes, overwrite, atomic).await
     }
 
+    /// Write json to a file (overwriting it if it exists) which only the owner may read
+    /// and write (600). The file is written atomically and its permissions are set
+    /// before the contents are written so that it's never readable by others.
+    pub async fn write_private_json<T: serde::Serialize>(&self, obj: &T) -> Result<(), FileSysErr> {
+        let json_bytes = serde_json::to_vec_pretty(obj).map_err(|e| {
+            FileSysErr::ParseJSONErr(Box::new(ParseJSONErr {
+                source: Box::new(e),
+                file: self.clone(),
+                trace: trace!(),
+            }))
+        })?;
+        self.parent()?.create_if_absent().await?;
+
+        let af = AtomicFile::new(self.to_string(), AllowOverwrite);
+        af.write(|f| {
+            f.set_permissions(std::fs::Permissions::from_mode(0o600))?;
+            f.write_all(&json_bytes)
+        })
+        .map_err(|e| {
+            FileSysErr::AtomicWriteFileErr(Box::new(AtomicWriteFileErr {
+                source: Box::new(e.into()),
+                file: self.clone(),
+                trace: trace!(),
+            }))
+        })
+    }
+
     /// Delete a file
     pub async fn delete(&self) -> Result<(), FileSysErr> {
         if !self.exists() {
@@ -0,0 +1,203 @@
+// standard library
+use std::sync::Mutex;
+
+// external crates
+use tokio::time::{Duration, Instant};
+#[allow(unused_imports)]
+use tracing::{debug, error, info, warn};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CircuitBreakerOptions {
+    // the number of consecutive network failures which opens the circuit
+    pub failure_threshold: u32,
+    // how long the circuit stays open before a probe request is let through
+    pub cooldown: Duration,
+}
+
+impl Default for CircuitBreakerOptions {
+    fn default() -> Self {
+        Self {
+            failure_threshold: 5,
+            cooldown: Duration::from_secs(30),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CircuitState {
+    // requests flow normally
+    Closed,
+    // requests fail fast without touching the network
+    Open,
+    // a single probe request is in flight to test whether the backend is back
+    HalfOpen,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CircuitBreakerStatus {
+    pub state: CircuitState,
+    pub consecutive_failures: u32,
+    // how long until a probe request is allowed (only while open)
+    pub retry_in: Option<Duration>,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Inner {
+    Closed {
+        failures: u32,
+    },
+    Open {
+        failures: u32,
+        until: Instant,
+    },
+    // a probe which never reports back (e.g. its future was dropped) is given up on
+    // after the cooldown so that the circuit can't get stuck half-open
+    HalfOpen {
+        failures: u32,
+        probe_started: Instant,
+    },
+}
+
+// implemented by clients whose backend requests go through a circuit breaker so that
+// callers can skip work which is bound to fail while the backend is unreachable
+pub trait CircuitBreakerExt {
+    fn is_circuit_open(&self) -> bool;
+}
+
+// Tracks the health of the connection to the backend across every user of the
+// HTTP client. Network failures (connection errors and timeouts) count against the
+// backend while any response, successful or not, means it is reachable.
+#[derive(Debug)]
+pub struct CircuitBreaker {
+    options: CircuitBreakerOptions,
+    inner: Mutex<Inner>,
+}
+
+impl Default for CircuitBreaker {
+    fn default() -> Self {
+        Self::new(CircuitBreakerOptions::default())
+    }
+}
+
+impl CircuitBreaker {
+    pub fn new(options: CircuitBreakerOptions) -> Self {
+        Self {
+            options,
+            inner: Mutex::new(Inner::Closed { failures: 0 }),
+        }
+    }
+
+    pub fn options(&self) -> CircuitBreakerOptions {
+        self.options
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Inner> {
+        // the state is always valid so a poisoned lock is safe to reuse
+        self.inner.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    // whether a request may be sent, which transitions an open circuit whose cooldown
+    // has elapsed to half-open (letting this request through as the probe)
+    pub fn allow(&self) -> bool {
+        let mut inner = self.lock();
+        let now = Instant::now();
+        match *inner {
+            Inner::Closed { .. } => true,
+            Inner::Open { failures, until } => {
+                if now < until {
+                    return false;
+                }
+                info!("backend circuit breaker is half-open, sending a probe request");
+                *inner = Inner::HalfOpen {
+                    failures,
+                    probe_started: now,
+                };
+                true
+            }
+            Inner::HalfOpen {
+                failures,
+                probe_started,
+            } => {
+                if now.duration_since(probe_started) < self.options.cooldown {
+                    return false;
+                }
+                *inner = Inner::HalfOpen {
+                    failures,
+                    probe_started: now,
+                };
+                true
+            }
+        }
+    }
+
+    pub fn record_success(&self) {
+        let mut inner = self.lock();
+        if !matches!(*inner, Inner::Closed { .. }) {
+            info!("backend is reachable again, closing the circuit breaker");
+        }
+        *inner = Inner::Closed { failures: 0 };
+    }
+
+    pub fn record_failure(&self) {
+        let mut inner = self.lock();
+        let now = Instant::now();
+        let until = now + self.options.cooldown;
+        *inner = match *inner {
+            Inner::Closed { failures } => {
+                let failures = failures.saturating_add(1);
+                if failures >= self.options.failure_threshold {
+                    warn!(
+                        "opening the backend circuit breaker for {}s after {failures} consecutive network failures",
+                        self.options.cooldown.as_secs()
+                    );
+                    Inner::Open { failures, until }
+                } else {
+                    Inner::Closed { failures }
+                }
+            }
+            // failures from requests sent before the circuit opened
+            Inner::Open { failures, until } => Inner::Open {
+                failures: failures.saturating_add(1),
+                until,
+            },
+            Inner::HalfOpen { failures, .. } => {
+                warn!("backend circuit breaker probe failed, reopening the circuit");
+                Inner::Open {
+                    failures: failures.saturating_add(1),
+                    until,
+                }
+            }
+        };
+    }
+
+    pub fn state(&self) -> CircuitState {
+        self.status().state
+    }
+
+    // whether requests are being short-circuited, i.e. the circuit is open or a probe
+    // is in flight
+    pub fn is_open(&self) -> bool {
+        self.state() != CircuitState::Closed
+    }
+
+    pub fn status(&self) -> CircuitBreakerStatus {
+        let inner = *self.lock();
+        match inner {
+            Inner::Closed { failures } => CircuitBreakerStatus {
+                state: CircuitState::Closed,
+                consecutive_failures: failures,
+                retry_in: None,
+            },
+            Inner::Open { failures, until } => CircuitBreakerStatus {
+                state: CircuitState::Open,
+                consecutive_failures: failures,
+                retry_in: Some(until.saturating_duration_since(Instant::now())),
+            },
+            Inner::HalfOpen { failures, .. } => CircuitBreakerStatus {
+                state: CircuitState::HalfOpen,
+                consecutive_failures: failures,
+                retry_in: None,
+            },
+        }
+    }
+}
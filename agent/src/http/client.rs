@@ -1,15 +1,26 @@
 // standard library
 use std::fmt;
 use std::sync::Arc;
+use std::time::Instant;
 
 // internal crates
 use crate::errors::MiruError;
 use crate::http::{
+    breaker::{CircuitBreaker, CircuitBreakerExt},
+    conditional::{ConditionalCache, ConditionalEntry},
     errors::{reqwest_err_to_http_client_err, HTTPErr},
     errors::{
-        BuildReqwestErr, CacheErr, InvalidHeaderValueErr, MarshalJSONErr, RequestFailed,
-        TimeoutErr, UnmarshalJSONErr,
+        BuildReqwestErr, CacheErr, CircuitOpenErr, InvalidHeaderValueErr, MarshalJSONErr,
+        RequestFailed, TimeoutErr, UnmarshalJSONErr,
     },
+    fixtures::{FixtureMode, FixtureRequest, Fixtures},
+    retry::{self, RetryPolicy, IDEMPOTENCY_KEY_HEADER},
+};
+use crate::metrics::registry;
+use crate::network::{
+    errors::{BuildClientErr, NetworkErr},
+    failover::Endpoints,
+    options::NetworkOptions,
 };
 use crate::telemetry::SystemInfo;
 use crate::trace;
@@ -17,11 +28,13 @@ use crate::utils::version_info;
 use openapi_client::models::ErrorResponse;
 
 // external crates
+use chrono::{DateTime, Utc};
 use moka::future::Cache;
-use reqwest::header::{HeaderMap, HeaderValue, AUTHORIZATION};
+use reqwest::header::{HeaderMap, HeaderValue, AUTHORIZATION, RETRY_AFTER};
 use serde::{de::DeserializeOwned, Serialize};
 use tokio::sync::OnceCell;
 use tokio::time::{sleep, timeout, Duration};
+use tracing::{debug, warn};
 use uuid::Uuid;
 
 // type aliases
@@ -35,6 +48,10 @@ pub struct RequestContext {
     pub url: String,
     pub method: reqwest::Method,
     pub timeout: Duration,
+    // the attempt (starting at 1) the request is on, which is incremented on each retry
+    pub attempts: u32,
+    // the backend endpoint (base url) the request is sent to, if the url belongs to one
+    pub endpoint: Option<String>,
 }
 
 // RequestContext is safe to send between threads since all fields are Send + Sync
@@ -42,11 +59,16 @@ impl fmt::Display for RequestContext {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(
             f,
-            "{} {} (timeout: {}ms)",
+            "{} {} (timeout: {}ms, attempt: {}",
             self.method,
             self.url,
-            self.timeout.as_millis()
-        )
+            self.timeout.as_millis(),
+            self.attempts
+        )?;
+        if let Some(endpoint) = &self.endpoint {
+            write!(f, ", endpoint: {endpoint}")?;
+        }
+        write!(f, ")")
     }
 }
 
@@ -104,10 +126,14 @@ impl Headers {
 pub struct HTTPClient {
     // allow crate access since this struct is defined throughout the crate
     pub(crate) client: reqwest::Client,
-    pub(crate) base_url: String,
+    endpoints: Arc<Endpoints>,
     pub(crate) default_timeout: Duration,
     headers: Headers,
     cache: Cache<RequestKey, (Response, RequestID)>,
+    retry_policy: RetryPolicy,
+    fixtures: Option<Fixtures>,
+    conditional_cache: Option<ConditionalCache>,
+    breaker: Arc<CircuitBreaker>,
 }
 
 // Use Lazy to implement the Singleton(ish) Pattern for the reqwest client (see the
@@ -135,21 +161,99 @@ async fn init_client() -> reqwest::Client {
     }
 }
 
+// builds a client which routes requests through the configured proxy and trusts the
+// configured certificate authorities (and presents the client certificate, if any)
+pub fn build_client(network: &NetworkOptions) -> Result<reqwest::Client, NetworkErr> {
+    let mut builder = reqwest::Client::builder();
+    if let Some(proxy) = &network.proxy {
+        builder = builder.proxy(proxy.to_reqwest()?);
+    }
+    builder = network.tls.apply_to_reqwest(builder)?;
+    builder.build().map_err(|e| {
+        NetworkErr::BuildClientErr(Box::new(BuildClientErr {
+            source: e,
+            trace: trace!(),
+        }))
+    })
+}
+
 impl HTTPClient {
     pub async fn new(base_url: &str) -> Self {
         let client = CLIENT.get_or_init(init_client).await;
+        Self::with_client(client.clone(), base_url)
+    }
+
+    // the shared client is used unless the network options differ from the defaults,
+    // in which case a client is built from them
+    pub async fn new_with_network(
+        base_url: &str,
+        network: &NetworkOptions,
+    ) -> Result<Self, NetworkErr> {
+        if network.is_default() {
+            return Ok(Self::new(base_url).await);
+        }
+        Ok(Self::with_client(build_client(network)?, base_url))
+    }
 
+    fn with_client(client: reqwest::Client, base_url: &str) -> Self {
         HTTPClient {
-            client: client.clone(),
-            base_url: base_url.to_string(),
+            client,
+            endpoints: Arc::new(Endpoints::single(base_url)),
             default_timeout: Duration::from_secs(10),
             headers: Headers::default(),
             cache: Cache::builder()
                 .time_to_live(Duration::from_secs(2))
                 .build(),
+            retry_policy: RetryPolicy::default(),
+            fixtures: None,
+            conditional_cache: None,
+            breaker: Arc::new(CircuitBreaker::default()),
         }
     }
 
+    // records requests to (or replays them from) fixture files for deterministic
+    // integration tests
+    pub fn with_fixtures(mut self, fixtures: Fixtures) -> Self {
+        self.fixtures = Some(fixtures);
+        self
+    }
+
+    // stores the validators (ETag, Last-Modified) and body of GET responses so that
+    // subsequent requests are conditional and a 304 reuses the stored body
+    pub fn with_conditional_cache(mut self, conditional_cache: ConditionalCache) -> Self {
+        self.conditional_cache = Some(conditional_cache);
+        self
+    }
+
+    pub fn with_circuit_breaker(mut self, breaker: Arc<CircuitBreaker>) -> Self {
+        self.breaker = breaker;
+        self
+    }
+
+    pub fn circuit_breaker(&self) -> &Arc<CircuitBreaker> {
+        &self.breaker
+    }
+
+    // the backend urls to fail over between, replacing the single base url
+    pub fn with_endpoints(mut self, endpoints: Arc<Endpoints>) -> Self {
+        self.endpoints = endpoints;
+        self
+    }
+
+    pub fn endpoints(&self) -> &Arc<Endpoints> {
+        &self.endpoints
+    }
+
+    // the base url of the backend endpoint currently in use
+    pub fn base_url(&self) -> String {
+        self.endpoints.active()
+    }
+
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
     fn add_token_to_headers(&self, headers: &mut HeaderMap, token: &str) -> Result<(), HTTPErr> {
         headers.insert(
             AUTHORIZATION,
@@ -180,6 +284,12 @@ impl HTTPClient {
         if let Some(token) = token {
             self.add_token_to_headers(&mut headers, token)?;
         }
+        // lets the backend deduplicate non-idempotent requests which are retried
+        if retry::needs_idempotency_key(&method) {
+            if let Ok(value) = HeaderValue::from_str(&Uuid::new_v4().to_string()) {
+                headers.insert(IDEMPOTENCY_KEY_HEADER, value);
+            }
+        }
         request = request.headers(headers);
 
         // body
@@ -203,6 +313,8 @@ impl HTTPClient {
                 url: url.to_string(),
                 method,
                 timeout,
+                attempts: 1,
+                endpoint: self.endpoints.endpoint_of(url).map(str::to_string),
             },
         ))
     }
@@ -236,18 +348,137 @@ impl HTTPClient {
         self.build_request(reqwest::Method::PATCH, url, Some(body), timeout, token)
     }
 
+    // sends the request, retrying network connection errors per the client's retry
+    // policy if the request is safe to retry. Requests fail fast with a CircuitOpenErr
+    // while the client's circuit breaker is open. The context's attempt count is updated
+    // to the attempt which produced the returned response or error.
     pub async fn send(
+        &self,
+        request: reqwest::Request,
+        context: &mut RequestContext,
+    ) -> Result<reqwest::Response, HTTPErr> {
+        let retryable = self.retry_policy.max_attempts > 1 && retry::is_retryable(&request);
+        let started_at = Instant::now();
+        let mut request = request;
+
+        // fail fast while the backend is believed to be unreachable
+        if !self.breaker.allow() {
+            return Err(HTTPErr::CircuitOpenErr(Box::new(CircuitOpenErr {
+                request: context.clone(),
+                retry_in: self.breaker.status().retry_in,
+                trace: trace!(),
+            })));
+        }
+
+        loop {
+            // requests with streaming bodies can't be cloned and thus aren't retried
+            let next = if retryable { request.try_clone() } else { None };
+            let error = match self.send_once(request, context).await {
+                Ok(response) => {
+                    self.breaker.record_success();
+                    if let Some(endpoint) = &context.endpoint {
+                        self.endpoints.record_success(endpoint);
+                    }
+                    return Ok(response);
+                }
+                Err(e) => e,
+            };
+            let failover = match &context.endpoint {
+                Some(endpoint) if error.is_network_connection_error() => {
+                    let active = self.endpoints.record_failure(endpoint);
+                    (active != *endpoint).then(|| (endpoint.clone(), active))
+                }
+                Some(endpoint) => {
+                    self.endpoints.record_success(endpoint);
+                    None
+                }
+                None => None,
+            };
+            // a pin mismatch means the backend can't be reached safely so it counts
+            // against the breaker, keeping the agent from hammering the interceptor
+            if error.is_network_connection_error() || matches!(error, HTTPErr::PinMismatchErr(_)) {
+                self.breaker.record_failure();
+            } else {
+                self.breaker.record_success();
+            }
+            let Some(next) = next else {
+                return Err(error);
+            };
+            // don't retry once the circuit has opened (possibly due to this request)
+            if !error.is_network_connection_error() || self.breaker.is_open() {
+                return Err(error);
+            }
+            let delay = self.retry_policy.backoff(context.attempts);
+            if !self
+                .retry_policy
+                .allows_retry(context.attempts, started_at.elapsed(), delay)
+            {
+                return Err(error);
+            }
+            warn!(
+                "request {context} failed with a network connection error, retrying in {}ms: {error}",
+                delay.as_millis()
+            );
+            sleep(delay).await;
+            request = next;
+            if let Some((from, to)) = failover {
+                Self::redirect(&mut request, context, &from, &to);
+            }
+            context.attempts += 1;
+        }
+    }
+
+    // points a request to a different backend endpoint
+    fn redirect(
+        request: &mut reqwest::Request,
+        context: &mut RequestContext,
+        from: &str,
+        to: &str,
+    ) {
+        let Some(path) = context.url.strip_prefix(from) else {
+            return;
+        };
+        let url = format!("{to}{path}");
+        match reqwest::Url::parse(&url) {
+            Ok(parsed) => {
+                debug!("retrying request {context} against {to}");
+                *request.url_mut() = parsed;
+                context.url = url;
+                context.endpoint = Some(to.to_string());
+            }
+            Err(e) => warn!("unable to retry request {context} against {to}: {e}"),
+        }
+    }
+
+    async fn send_once(
         &self,
         request: reqwest::Request,
         context: &RequestContext,
     ) -> Result<reqwest::Response, HTTPErr> {
+        let recording = match &self.fixtures {
+            Some(fixtures) => match fixtures.mode() {
+                FixtureMode::Replay => return fixtures.replay(&request, context).await,
+                FixtureMode::Record => Some((fixtures, FixtureRequest::from_request(&request))),
+            },
+            None => None,
+        };
+
         let time_limit = match request.timeout() {
             Some(time_limit) => *time_limit,
             None => self.default_timeout,
         };
         // request server
-        let response = timeout(time_limit, self.client.execute(request))
-            .await
+        let started_at = Instant::now();
+        let result = timeout(time_limit, self.client.execute(request)).await;
+        registry::global().http.observe(
+            context.method.as_str(),
+            &registry::endpoint_label(
+                context.endpoint.as_deref().unwrap_or_default(),
+                &context.url,
+            ),
+            started_at.elapsed(),
+        );
+        let response = result
             .map_err(|e| {
                 HTTPErr::TimeoutErr(Box::new(TimeoutErr {
                     msg: e.to_string(),
@@ -256,22 +487,26 @@ impl HTTPClient {
                 }))
             })?
             .map_err(|e| reqwest_err_to_http_client_err(e, context, trace!()))?;
-        Ok(response)
+        match recording {
+            Some((fixtures, request)) => fixtures.record(request, response, context).await,
+            None => Ok(response),
+        }
     }
 
+    // the context's attempt count is only updated if the request is actually sent (i.e.
+    // the response wasn't served from the cache)
     pub async fn send_cached(
         &self,
         key: RequestKey,
         request: reqwest::Request,
-        context: &RequestContext,
+        context: &mut RequestContext,
     ) -> Result<(String, IsCacheHit), HTTPErr> {
         let id = Uuid::new_v4();
 
         let result = self
             .cache
-            .try_get_with(key, async move {
-                let response = self.send(request, context).await?;
-                Ok((self.handle_response(response, context).await?, id))
+            .try_get_with(key.clone(), async move {
+                Ok((self.send_conditional(&key, request, context).await?, id))
             })
             .await
             .map_err(|e: Arc<HTTPErr>| {
@@ -280,6 +515,7 @@ impl HTTPClient {
                     http_status: e.http_status(),
                     is_network_connection_error: e.is_network_connection_error(),
                     params: e.params(),
+                    retry_after: e.retry_after(),
                     msg: e.to_string(),
                     trace: trace!(),
                 }))
@@ -288,6 +524,46 @@ impl HTTPClient {
         Ok((result.0, is_cache_hit))
     }
 
+    // sends the request and returns the body of its (successful) response. GET requests
+    // are made conditional if the client has a conditional cache, in which case a 304
+    // reuses the body stored from the last successful response to the same key.
+    pub async fn send_conditional(
+        &self,
+        key: &str,
+        mut request: reqwest::Request,
+        context: &mut RequestContext,
+    ) -> Result<String, HTTPErr> {
+        let cache = match &self.conditional_cache {
+            Some(cache) if request.method() == reqwest::Method::GET => cache,
+            _ => {
+                let response = self.send(request, context).await?;
+                return self.handle_response(response, context).await;
+            }
+        };
+
+        let entry = cache.read(key).await;
+        if let Some(entry) = &entry {
+            entry.apply_to(&mut request);
+        }
+        let response = self.send(request, context).await?;
+        if response.status() == reqwest::StatusCode::NOT_MODIFIED {
+            if let Some(entry) = entry {
+                debug!("request {context} was not modified, reusing the cached body");
+                return Ok(entry.body);
+            }
+        }
+
+        let headers = response.headers().clone();
+        let body = self.handle_response(response, context).await?;
+        match ConditionalEntry::from_headers(key.to_string(), &headers, body.clone()) {
+            Some(entry) => cache.write(&entry).await,
+            // the stored validators no longer apply
+            None if entry.is_some() => cache.remove(key).await,
+            None => {}
+        }
+        Ok(body)
+    }
+
     pub fn marshal_json_payload<T>(&self, payload: &T) -> Result<String, HTTPErr>
     where
         T: Serialize,
@@ -309,6 +585,15 @@ impl HTTPClient {
 
         // check for an error response
         if !status.is_success() {
+            let retry_after = match status {
+                reqwest::StatusCode::TOO_MANY_REQUESTS
+                | reqwest::StatusCode::SERVICE_UNAVAILABLE => response
+                    .headers()
+                    .get(RETRY_AFTER)
+                    .and_then(|value| value.to_str().ok())
+                    .and_then(|value| parse_retry_after(value, Utc::now())),
+                _ => None,
+            };
             let error_response = match response.text().await {
                 Ok(text) => self
                     .parse_json_response_text::<ErrorResponse>(text, context)
@@ -320,6 +605,7 @@ impl HTTPClient {
                 request: context.clone(),
                 status,
                 error: error_response,
+                retry_after,
                 trace: trace!(),
             })));
         }
@@ -355,10 +641,39 @@ impl HTTPClient {
     ) -> Self {
         HTTPClient {
             client: reqwest::Client::new(),
-            base_url: base_url.to_string(),
+            endpoints: Arc::new(Endpoints::single(base_url)),
             default_timeout,
             headers: Headers::default(),
             cache,
+            retry_policy: RetryPolicy::default(),
+            fixtures: None,
+            conditional_cache: None,
+            breaker: Arc::new(CircuitBreaker::default()),
         }
     }
 }
+
+impl CircuitBreakerExt for HTTPClient {
+    fn is_circuit_open(&self) -> bool {
+        self.breaker.is_open()
+    }
+}
+
+impl CircuitBreakerExt for Arc<HTTPClient> {
+    fn is_circuit_open(&self) -> bool {
+        self.as_ref().is_circuit_open()
+    }
+}
+
+// parses the value of a Retry-After header, which is either a number of seconds or an
+// HTTP date (https://httpwg.org/specs/rfc9110.html#field.retry-after)
+pub fn parse_retry_after(value: &str, now: DateTime<Utc>) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(secs) = value.parse::<u64>() {
+        return Some(Duration::from_secs(secs));
+    }
+    let date = DateTime::parse_from_rfc2822(value).ok()?;
+    let delta = date.with_timezone(&Utc) - now;
+    // dates in the past mean the request may be retried immediately
+    Some(delta.to_std().unwrap_or(Duration::ZERO))
+}
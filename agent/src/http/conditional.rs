@@ -0,0 +1,171 @@
+// standard library
+use std::time::SystemTime;
+
+// internal crates
+use crate::crypt::sha256;
+use crate::filesys::{dir::Dir, file::File, path::PathExt};
+
+// external crates
+use reqwest::header::{
+    HeaderMap, HeaderValue, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED,
+};
+use serde::{Deserialize, Serialize};
+#[allow(unused_imports)]
+use tracing::{debug, error, info, warn};
+
+// the validators and body of the last successful response to a request
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConditionalEntry {
+    pub key: String,
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+    pub body: String,
+}
+
+impl ConditionalEntry {
+    // returns None if the response carries no validators since there's then no way to
+    // revalidate the body
+    pub fn from_headers(key: String, headers: &HeaderMap, body: String) -> Option<Self> {
+        let header = |name| {
+            headers
+                .get(name)
+                .and_then(|value: &HeaderValue| value.to_str().ok())
+                .map(|value| value.to_string())
+        };
+        let etag = header(ETAG);
+        let last_modified = header(LAST_MODIFIED);
+        if etag.is_none() && last_modified.is_none() {
+            return None;
+        }
+        Some(Self {
+            key,
+            etag,
+            last_modified,
+            body,
+        })
+    }
+
+    // adds the If-None-Match / If-Modified-Since headers so the backend can respond
+    // with a 304 (and no body) when nothing has changed
+    pub fn apply_to(&self, request: &mut reqwest::Request) {
+        let headers = request.headers_mut();
+        if let Some(value) = self
+            .etag
+            .as_ref()
+            .and_then(|etag| HeaderValue::from_str(etag).ok())
+        {
+            headers.insert(IF_NONE_MATCH, value);
+        }
+        if let Some(value) = self
+            .last_modified
+            .as_ref()
+            .and_then(|last_modified| HeaderValue::from_str(last_modified).ok())
+        {
+            headers.insert(IF_MODIFIED_SINCE, value);
+        }
+    }
+}
+
+pub const DEFAULT_MAX_ENTRIES: usize = 64;
+
+// Persists conditional entries on disk (one file per request key) so they survive
+// restarts. The cache is best effort: any failure to read or write an entry is logged
+// and simply results in an unconditional request. Since every distinct url (e.g. each
+// page or filter of a list) is its own entry, the least recently written entries are
+// evicted once there are more than the maximum.
+#[derive(Debug, Clone)]
+pub struct ConditionalCache {
+    dir: Dir,
+    max_entries: usize,
+}
+
+impl ConditionalCache {
+    pub fn new(dir: Dir) -> Self {
+        Self {
+            dir,
+            max_entries: DEFAULT_MAX_ENTRIES,
+        }
+    }
+
+    pub fn with_max_entries(mut self, max_entries: usize) -> Self {
+        self.max_entries = max_entries;
+        self
+    }
+
+    pub fn dir(&self) -> &Dir {
+        &self.dir
+    }
+
+    fn file(&self, key: &str) -> File {
+        self.dir.file(&format!("{}.json", sha256::hash_str(key)))
+    }
+
+    pub async fn read(&self, key: &str) -> Option<ConditionalEntry> {
+        let file = self.file(key);
+        if !file.exists() {
+            return None;
+        }
+        match file.read_json::<ConditionalEntry>().await {
+            // guard against hash collisions
+            Ok(entry) if entry.key == key => Some(entry),
+            Ok(_) => None,
+            Err(e) => {
+                warn!("failed to read conditional cache entry for '{key}': {e}");
+                None
+            }
+        }
+    }
+
+    pub async fn write(&self, entry: &ConditionalEntry) {
+        let file = self.file(&entry.key);
+        if let Err(e) = file.write_json(entry, true, true).await {
+            warn!(
+                "failed to write conditional cache entry for '{}': {e}",
+                entry.key
+            );
+            return;
+        }
+        self.evict(&file).await;
+    }
+
+    // evicts the least recently written entries (other than the one just written)
+    // beyond the maximum
+    async fn evict(&self, written: &File) {
+        let files = match self.dir.files().await {
+            Ok(files) => files,
+            Err(e) => {
+                warn!("failed to list the conditional cache entries: {e}");
+                return;
+            }
+        };
+        if files.len() <= self.max_entries {
+            return;
+        }
+
+        let mut entries = Vec::with_capacity(files.len());
+        for file in files {
+            if file.path() == written.path() {
+                continue;
+            }
+            let modified = file.last_modified().await.unwrap_or(SystemTime::UNIX_EPOCH);
+            entries.push((modified, file));
+        }
+        entries.sort_by_key(|(modified, _)| *modified);
+        let num_evicted = (entries.len() + 1).saturating_sub(self.max_entries.max(1));
+        for (_, file) in entries.into_iter().take(num_evicted) {
+            if let Err(e) = file.delete().await {
+                warn!("failed to evict conditional cache entry {file}: {e}");
+            }
+        }
+    }
+
+    pub async fn remove(&self, key: &str) {
+        let file = self.file(key);
+        if !file.exists() {
+            return;
+        }
+        if let Err(e) = file.delete().await {
+            warn!("failed to remove conditional cache entry for '{key}': {e}");
+        }
+    }
+}
@@ -12,10 +12,14 @@ use crate::http::search::{
     format_search_clause, format_search_group, LogicalOperator, SearchOperator,
 };
 use openapi_client::models::{
-    ConfigInstance, ConfigInstanceActivityStatus, ConfigInstanceErrorStatus, ConfigInstanceList,
+    BulkUpdateConfigInstancesRequest, BulkUpdateConfigInstancesResponse, ConfigInstance,
+    ConfigInstanceActivityStatus, ConfigInstanceErrorStatus, ConfigInstanceList,
     ConfigInstanceSearch, ConfigInstanceTargetStatus, UpdateConfigInstanceRequest,
 };
 
+// the maximum number of config instances which may be updated in a single bulk request
+pub const MAX_BULK_UPDATE_SIZE: usize = 100;
+
 #[allow(async_fn_in_trait)]
 pub trait ConfigInstancesExt: Send + Sync {
     async fn list_config_instances(
@@ -40,11 +44,17 @@ pub trait ConfigInstancesExt: Send + Sync {
         updates: &UpdateConfigInstanceRequest,
         token: &str,
     ) -> Result<ConfigInstance, HTTPErr>;
+
+    async fn bulk_update_config_instances(
+        &self,
+        updates: &BulkUpdateConfigInstancesRequest,
+        token: &str,
+    ) -> Result<BulkUpdateConfigInstancesResponse, HTTPErr>;
 }
 
 impl HTTPClient {
     fn config_instances_url(&self) -> String {
-        format!("{}/config_instances", self.base_url)
+        format!("{}/config_instances", self.base_url())
     }
 
     fn config_instance_url(&self, config_instance_id: &str) -> String {
@@ -60,10 +70,11 @@ impl ConfigInstancesExt for HTTPClient {
     ) -> Result<ConfigInstanceList, HTTPErr> {
         // build the request
         let url = format!("{}{}", self.config_instances_url(), query_params,);
-        let (request, context) = self.build_get_request(&url, self.default_timeout, Some(token))?;
+        let (request, mut context) =
+            self.build_get_request(&url, self.default_timeout, Some(token))?;
 
         // send the request (with caching)
-        let response = self.send_cached(url, request, &context).await?.0;
+        let response = self.send_cached(url, request, &mut context).await?.0;
 
         // parse the response
         self.parse_json_response_text::<ConfigInstanceList>(response, &context)
@@ -111,7 +122,7 @@ impl ConfigInstancesExt for HTTPClient {
         token: &str,
     ) -> Result<ConfigInstance, HTTPErr> {
         // build the request
-        let (request, context) = self.build_patch_request(
+        let (request, mut context) = self.build_patch_request(
             &self.config_instance_url(config_instance_id),
             self.marshal_json_payload(updates)?,
             self.default_timeout,
@@ -119,13 +130,35 @@ impl ConfigInstancesExt for HTTPClient {
         )?;
 
         // send the request (no caching)
-        let http_resp = self.send(request, &context).await?;
+        let http_resp = self.send(request, &mut context).await?;
         let text_resp = self.handle_response(http_resp, &context).await?;
 
         // parse the response
         self.parse_json_response_text::<ConfigInstance>(text_resp, &context)
             .await
     }
+
+    async fn bulk_update_config_instances(
+        &self,
+        updates: &BulkUpdateConfigInstancesRequest,
+        token: &str,
+    ) -> Result<BulkUpdateConfigInstancesResponse, HTTPErr> {
+        // build the request
+        let (request, mut context) = self.build_patch_request(
+            &self.config_instances_url(),
+            self.marshal_json_payload(updates)?,
+            self.default_timeout,
+            Some(token),
+        )?;
+
+        // send the request (no caching)
+        let http_resp = self.send(request, &mut context).await?;
+        let text_resp = self.handle_response(http_resp, &context).await?;
+
+        // parse the response
+        self.parse_json_response_text::<BulkUpdateConfigInstancesResponse>(text_resp, &context)
+            .await
+    }
 }
 
 impl ConfigInstancesExt for Arc<HTTPClient> {
@@ -164,6 +197,16 @@ impl ConfigInstancesExt for Arc<HTTPClient> {
             .update_config_instance(config_instance_id, updates, token)
             .await
     }
+
+    async fn bulk_update_config_instances(
+        &self,
+        updates: &BulkUpdateConfigInstancesRequest,
+        token: &str,
+    ) -> Result<BulkUpdateConfigInstancesResponse, HTTPErr> {
+        self.as_ref()
+            .bulk_update_config_instances(updates, token)
+            .await
+    }
 }
 
 // ================================ SEARCH FILTERS ================================ //
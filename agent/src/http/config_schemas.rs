@@ -1,4 +1,5 @@
 // standard library
+use std::fmt;
 use std::sync::Arc;
 
 // internal crates
@@ -6,7 +7,8 @@ use crate::crypt::sha256;
 use crate::http::client::HTTPClient;
 use crate::http::errors::HTTPErr;
 use crate::http::errors::{ConfigSchemaNotFound, TooManyConfigSchemas};
-use crate::http::pagination::Pagination;
+use crate::http::expand::format_expand_query;
+use crate::http::pagination::{Pagination, MAX_PAGINATE_LIMIT};
 use crate::http::query::build_query_params;
 use crate::http::search::{
     format_search_clause, format_search_group, LogicalOperator, SearchOperator,
@@ -31,6 +33,16 @@ pub trait ConfigSchemasExt: Send + Sync {
         token: &str,
     ) -> Result<ConfigSchemaList, HTTPErr>;
 
+    async fn list_all_config_schemas<I>(
+        &self,
+        filters: ConfigSchemaFilters,
+        expansions: I,
+        token: &str,
+    ) -> Result<Vec<ConfigSchema>, HTTPErr>
+    where
+        I: IntoIterator + Send,
+        I::Item: fmt::Display;
+
     async fn find_one_config_schema(
         &self,
         filters: ConfigSchemaFilters,
@@ -40,7 +52,7 @@ pub trait ConfigSchemasExt: Send + Sync {
 
 impl HTTPClient {
     fn config_schemas_url(&self) -> String {
-        format!("{}/config_schemas", self.base_url)
+        format!("{}/config_schemas", self.base_url())
     }
 }
 
@@ -51,8 +63,8 @@ impl ConfigSchemasExt for HTTPClient {
         token: &str,
     ) -> Result<SchemaDigestResponse, HTTPErr> {
         // build the request
-        let url = format!("{}/config_schemas/hash/serialized", self.base_url);
-        let (request, context) = self.build_post_request(
+        let url = format!("{}/config_schemas/hash/serialized", self.base_url());
+        let (request, mut context) = self.build_post_request(
             &url,
             self.marshal_json_payload(payload)?,
             self.default_timeout,
@@ -61,13 +73,47 @@ impl ConfigSchemasExt for HTTPClient {
 
         // send the request (with caching)
         let key = format!("{}:{}", url, sha256::hash_bytes(&payload.schema));
-        let response = self.send_cached(key, request, &context).await?.0;
+        let response = self.send_cached(key, request, &mut context).await?.0;
 
         // parse the response
         self.parse_json_response_text::<SchemaDigestResponse>(response, &context)
             .await
     }
 
+    async fn list_all_config_schemas<I>(
+        &self,
+        filters: ConfigSchemaFilters,
+        expansions: I,
+        token: &str,
+    ) -> Result<Vec<ConfigSchema>, HTTPErr>
+    where
+        I: IntoIterator + Send,
+        I::Item: fmt::Display,
+    {
+        let search_query = build_search_query(filters);
+        let expand_query = format_expand_query(expansions);
+        let mut pagination = Pagination {
+            limit: MAX_PAGINATE_LIMIT,
+            offset: 0,
+        };
+        let mut config_schemas = Vec::new();
+
+        loop {
+            let query_params = build_query_params(
+                search_query.as_deref(),
+                expand_query.as_deref(),
+                &pagination,
+            );
+            let resp = self.list_config_schemas(&query_params, token).await?;
+            config_schemas.extend(resp.data);
+            if !resp.has_more {
+                break;
+            }
+            pagination.offset += pagination.limit;
+        }
+        Ok(config_schemas)
+    }
+
     async fn find_one_config_schema(
         &self,
         filters: ConfigSchemaFilters,
@@ -113,10 +159,11 @@ impl ConfigSchemasExt for HTTPClient {
     ) -> Result<ConfigSchemaList, HTTPErr> {
         // build the request
         let url = format!("{}{}", self.config_schemas_url(), query_params);
-        let (request, context) = self.build_get_request(&url, self.default_timeout, Some(token))?;
+        let (request, mut context) =
+            self.build_get_request(&url, self.default_timeout, Some(token))?;
 
         // send the request (with caching)
-        let response = self.send_cached(url, request, &context).await?.0;
+        let response = self.send_cached(url, request, &mut context).await?.0;
 
         // parse the response
         self.parse_json_response_text::<ConfigSchemaList>(response, &context)
@@ -141,6 +188,21 @@ impl ConfigSchemasExt for Arc<HTTPClient> {
         self.as_ref().list_config_schemas(query_params, token).await
     }
 
+    async fn list_all_config_schemas<I>(
+        &self,
+        filters: ConfigSchemaFilters,
+        expansions: I,
+        token: &str,
+    ) -> Result<Vec<ConfigSchema>, HTTPErr>
+    where
+        I: IntoIterator + Send,
+        I::Item: fmt::Display,
+    {
+        self.as_ref()
+            .list_all_config_schemas(filters, expansions, token)
+            .await
+    }
+
     async fn find_one_config_schema(
         &self,
         filters: ConfigSchemaFilters,
@@ -153,10 +215,18 @@ impl ConfigSchemasExt for Arc<HTTPClient> {
 // ================================ SEARCH FILTERS ================================ //
 #[derive(Debug, Clone)]
 pub struct ConfigSchemaFilters {
+    pub ids: Option<IDFilter>,
     pub digests: Option<DigestFilter>,
     pub config_type_slugs: Option<ConfigTypeSlugFilter>,
 }
 
+#[derive(Debug, Clone)]
+pub struct IDFilter {
+    pub not: bool,
+    pub op: SearchOperator,
+    pub val: Vec<String>,
+}
+
 #[derive(Debug, Clone)]
 pub struct DigestFilter {
     pub not: bool,
@@ -173,6 +243,14 @@ pub struct ConfigTypeSlugFilter {
 
 pub fn build_search_query(filters: ConfigSchemaFilters) -> Option<String> {
     let mut clauses: Vec<String> = Vec::new();
+    if let Some(ids) = filters.ids {
+        clauses.push(format_search_clause(
+            ConfigSchemaSearch::CONFIG_SCHEMA_SEARCH_ID,
+            SearchOperator::Equals,
+            ids.val,
+            ids.not,
+        ));
+    }
     if let Some(digests) = filters.digests {
         clauses.push(format_search_clause(
             ConfigSchemaSearch::CONFIG_SCHEMA_SEARCH_DIGEST,
@@ -31,7 +31,7 @@ pub trait DevicesExt: Send + Sync {
 
 impl HTTPClient {
     fn devices_url(&self) -> String {
-        format!("{}/devices", self.base_url)
+        format!("{}/devices", self.base_url())
     }
 
     fn device_url(&self, device_id: &str) -> String {
@@ -48,7 +48,7 @@ impl DevicesExt for HTTPClient {
     ) -> Result<Device, HTTPErr> {
         // build the request
         let url = format!("{}/activate", self.device_url(device_id));
-        let (request, context) = self.build_post_request(
+        let (request, mut context) = self.build_post_request(
             &url,
             self.marshal_json_payload(payload)?,
             self.default_timeout,
@@ -56,7 +56,7 @@ impl DevicesExt for HTTPClient {
         )?;
 
         // send the request (no caching)
-        let http_resp = self.send(request, &context).await?;
+        let http_resp = self.send(request, &mut context).await?;
         let text_resp = self.handle_response(http_resp, &context).await?;
 
         // parse the response
@@ -70,7 +70,7 @@ impl DevicesExt for HTTPClient {
         payload: &IssueDeviceTokenRequest,
     ) -> Result<TokenResponse, HTTPErr> {
         let url = format!("{}/issue_token", self.device_url(device_id));
-        let (request, context) = self.build_post_request(
+        let (request, mut context) = self.build_post_request(
             &url,
             self.marshal_json_payload(payload)?,
             self.default_timeout,
@@ -78,7 +78,7 @@ impl DevicesExt for HTTPClient {
         )?;
 
         // send the request (no caching)
-        let http_resp = self.send(request, &context).await?;
+        let http_resp = self.send(request, &mut context).await?;
         let text_resp = self.handle_response(http_resp, &context).await?;
 
         // parse the response
@@ -93,7 +93,7 @@ impl DevicesExt for HTTPClient {
         token: &str,
     ) -> Result<Device, HTTPErr> {
         let url = self.device_url(device_id);
-        let (request, context) = self.build_patch_request(
+        let (request, mut context) = self.build_patch_request(
             &url,
             self.marshal_json_payload(payload)?,
             self.default_timeout,
@@ -101,7 +101,7 @@ impl DevicesExt for HTTPClient {
         )?;
 
         // send the request (no caching)
-        let http_resp = self.send(request, &context).await?;
+        let http_resp = self.send(request, &mut context).await?;
         let text_resp = self.handle_response(http_resp, &context).await?;
 
         // parse the response
@@ -1,11 +1,14 @@
 // standard library
 use std::fmt;
+use std::time::Duration;
 
 // internal crates
 use crate::errors::Trace;
 use crate::errors::{Code, HTTPCode, MiruError};
+use crate::filesys::errors::FileSysErr;
 use crate::http::backend::BackendErrorCodes;
 use crate::http::client::RequestContext;
+use crate::network::pinning::{find_pin_mismatch, PinMismatch};
 use openapi_client::models::ErrorResponse;
 
 // external crates
@@ -18,6 +21,9 @@ pub struct RequestFailed {
     pub request: RequestContext,
     pub status: reqwest::StatusCode,
     pub error: Option<ErrorResponse>,
+    // how long the backend asked us to wait before retrying (parsed from the
+    // Retry-After header of 429 and 503 responses)
+    pub retry_after: Option<Duration>,
     pub trace: Box<Trace>,
 }
 
@@ -55,7 +61,11 @@ impl fmt::Display for RequestFailed {
             f,
             "Request {} failed with status code {}: {}",
             self.request, self.status, debug_msg
-        )
+        )?;
+        if let Some(retry_after) = self.retry_after {
+            write!(f, " (retry after {}s)", retry_after.as_secs())?;
+        }
+        Ok(())
     }
 }
 
@@ -174,6 +184,7 @@ pub struct CacheErr {
     pub http_status: HTTPCode,
     pub is_network_connection_error: bool,
     pub params: Option<serde_json::Value>,
+    pub retry_after: Option<Duration>,
     pub trace: Box<Trace>,
 }
 
@@ -236,6 +247,50 @@ impl fmt::Display for ConnectionErr {
     }
 }
 
+#[derive(Debug)]
+pub struct PinMismatchErr {
+    pub request: RequestContext,
+    pub mismatch: PinMismatch,
+    pub trace: Box<Trace>,
+}
+
+impl MiruError for PinMismatchErr {
+    fn code(&self) -> Code {
+        Code::TLSPinMismatch
+    }
+
+    fn http_status(&self) -> HTTPCode {
+        HTTPCode::BAD_GATEWAY
+    }
+
+    // the backend may well be reachable, just not without going through whoever is
+    // intercepting the connection, so this must not be retried like a network error
+    fn is_network_connection_error(&self) -> bool {
+        false
+    }
+
+    fn params(&self) -> Option<serde_json::Value> {
+        Some(json!({
+            "presented_pins": self
+                .mismatch
+                .presented
+                .iter()
+                .map(|pin| pin.to_string())
+                .collect::<Vec<_>>(),
+        }))
+    }
+}
+
+impl fmt::Display for PinMismatchErr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Request {} was aborted since the connection may be intercepted: {}",
+            self.request, self.mismatch
+        )
+    }
+}
+
 #[derive(Debug)]
 pub struct DecodeRespBodyErr {
     pub request: RequestContext,
@@ -432,6 +487,124 @@ impl fmt::Display for ReqwestErr {
     }
 }
 
+#[derive(Debug)]
+pub struct CircuitOpenErr {
+    pub request: RequestContext,
+    pub retry_in: Option<Duration>,
+    pub trace: Box<Trace>,
+}
+
+impl MiruError for CircuitOpenErr {
+    fn code(&self) -> Code {
+        Code::InternalServerError
+    }
+
+    fn http_status(&self) -> HTTPCode {
+        HTTPCode::INTERNAL_SERVER_ERROR
+    }
+
+    // the request wasn't sent because the backend is believed to be unreachable
+    fn is_network_connection_error(&self) -> bool {
+        true
+    }
+
+    fn params(&self) -> Option<serde_json::Value> {
+        None
+    }
+}
+
+impl fmt::Display for CircuitOpenErr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Request {} was not sent since the backend circuit breaker is open",
+            self.request
+        )?;
+        if let Some(retry_in) = self.retry_in {
+            write!(f, " (probing again in {}s)", retry_in.as_secs())?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub struct FixtureNotFoundErr {
+    pub request: RequestContext,
+    pub key: String,
+    // the key of the most similar fixture and how the request differs from it
+    pub closest: Option<String>,
+    pub diff: String,
+    pub trace: Box<Trace>,
+}
+
+impl MiruError for FixtureNotFoundErr {
+    fn code(&self) -> Code {
+        Code::InternalServerError
+    }
+
+    fn http_status(&self) -> HTTPCode {
+        HTTPCode::INTERNAL_SERVER_ERROR
+    }
+
+    fn is_network_connection_error(&self) -> bool {
+        false
+    }
+
+    fn params(&self) -> Option<serde_json::Value> {
+        Some(json!({
+            "key": self.key,
+            "closest": self.closest,
+        }))
+    }
+}
+
+impl fmt::Display for FixtureNotFoundErr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.closest {
+            Some(closest) => write!(
+                f,
+                "No fixture recorded for request {} (key '{}'); closest fixture is '{}':\n{}",
+                self.request, self.key, closest, self.diff
+            ),
+            None => write!(
+                f,
+                "No fixture recorded for request {} (key '{}') and no fixtures exist",
+                self.request, self.key
+            ),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct FixtureFileSysErr {
+    pub source: FileSysErr,
+    pub trace: Box<Trace>,
+}
+
+impl MiruError for FixtureFileSysErr {
+    fn code(&self) -> Code {
+        self.source.code()
+    }
+
+    fn http_status(&self) -> HTTPCode {
+        self.source.http_status()
+    }
+
+    fn is_network_connection_error(&self) -> bool {
+        self.source.is_network_connection_error()
+    }
+
+    fn params(&self) -> Option<serde_json::Value> {
+        self.source.params()
+    }
+}
+
+impl fmt::Display for FixtureFileSysErr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Fixture file system error: {}", self.source)
+    }
+}
+
 #[derive(Debug)]
 pub struct MockErr {
     pub is_network_connection_error: bool,
@@ -471,6 +644,7 @@ pub enum HTTPErr {
     RequestFailed(Box<RequestFailed>),
     TimeoutErr(Box<TimeoutErr>),
     CacheErr(Box<CacheErr>),
+    CircuitOpenErr(Box<CircuitOpenErr>),
 
     // config schema errors
     ConfigSchemaNotFound(Box<ConfigSchemaNotFound>),
@@ -478,6 +652,7 @@ pub enum HTTPErr {
 
     // external crate errors
     ConnectionErr(Box<ConnectionErr>),
+    PinMismatchErr(Box<PinMismatchErr>),
     DecodeRespBodyErr(Box<DecodeRespBodyErr>),
     InvalidHeaderValueErr(Box<InvalidHeaderValueErr>),
     MarshalJSONErr(Box<MarshalJSONErr>),
@@ -485,6 +660,10 @@ pub enum HTTPErr {
     ReqwestErr(Box<ReqwestErr>),
     BuildReqwestErr(Box<BuildReqwestErr>),
 
+    // record/replay errors
+    FixtureNotFoundErr(Box<FixtureNotFoundErr>),
+    FixtureFileSysErr(Box<FixtureFileSysErr>),
+
     // mock errors (not for production use)
     MockErr(Box<MockErr>),
 }
@@ -499,6 +678,29 @@ impl HTTPErr {
             _ => false,
         }
     }
+
+    // how long the backend asked us to wait before retrying the request (if at all)
+    pub fn retry_after(&self) -> Option<Duration> {
+        match self {
+            HTTPErr::RequestFailed(e) => e.retry_after,
+            HTTPErr::CacheErr(e) => e.retry_after,
+            _ => None,
+        }
+    }
+
+    // whether the backend does not support the requested endpoint (e.g. an older
+    // backend which predates it)
+    pub fn is_unsupported_endpoint_error(&self) -> bool {
+        match self {
+            HTTPErr::RequestFailed(e) => matches!(
+                e.status,
+                reqwest::StatusCode::NOT_FOUND
+                    | reqwest::StatusCode::METHOD_NOT_ALLOWED
+                    | reqwest::StatusCode::NOT_IMPLEMENTED
+            ),
+            _ => false,
+        }
+    }
 }
 
 macro_rules! forward_error_method {
@@ -507,15 +709,19 @@ macro_rules! forward_error_method {
             Self::RequestFailed(e) => e.$method($($arg)?),
             Self::TimeoutErr(e) => e.$method($($arg)?),
             Self::CacheErr(e) => e.$method($($arg)?),
+            Self::CircuitOpenErr(e) => e.$method($($arg)?),
             Self::ConfigSchemaNotFound(e) => e.$method($($arg)?),
             Self::TooManyConfigSchemas(e) => e.$method($($arg)?),
             Self::ConnectionErr(e) => e.$method($($arg)?),
+            Self::PinMismatchErr(e) => e.$method($($arg)?),
             Self::DecodeRespBodyErr(e) => e.$method($($arg)?),
             Self::InvalidHeaderValueErr(e) => e.$method($($arg)?),
             Self::MarshalJSONErr(e) => e.$method($($arg)?),
             Self::UnmarshalJSONErr(e) => e.$method($($arg)?),
             Self::ReqwestErr(e) => e.$method($($arg)?),
             Self::BuildReqwestErr(e) => e.$method($($arg)?),
+            Self::FixtureNotFoundErr(e) => e.$method($($arg)?),
+            Self::FixtureFileSysErr(e) => e.$method($($arg)?),
             Self::MockErr(e) => e.$method($($arg)?),
         }
     };
@@ -550,7 +756,14 @@ pub fn reqwest_err_to_http_client_err(
     context: &RequestContext,
     trace: Box<Trace>,
 ) -> HTTPErr {
-    if e.is_connect() {
+    // checked first since a failed handshake is also a connection error
+    if let Some(mismatch) = find_pin_mismatch(&e) {
+        HTTPErr::PinMismatchErr(Box::new(PinMismatchErr {
+            request: context.clone(),
+            mismatch: mismatch.clone(),
+            trace,
+        }))
+    } else if e.is_connect() {
         HTTPErr::ConnectionErr(Box::new(ConnectionErr {
             request: context.clone(),
             source: e,
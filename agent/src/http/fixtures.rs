@@ -0,0 +1,327 @@
+// standard library
+use std::collections::BTreeMap;
+
+// internal crates
+use crate::crypt::sha256;
+use crate::filesys::{dir::Dir, errors::FileSysErr, file::sanitize_filename, path::PathExt};
+use crate::http::{
+    client::RequestContext,
+    errors::{reqwest_err_to_http_client_err, FixtureFileSysErr, FixtureNotFoundErr, HTTPErr},
+};
+use crate::trace;
+
+// external crates
+use reqwest::header::{HeaderName, HeaderValue};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+pub const REDACTED: &str = "<redacted>";
+
+// only these response headers are recorded, which keeps credentials (cookies, etc.)
+// out of the fixtures
+const RECORDED_HEADERS: [&str; 5] = [
+    "content-type",
+    "retry-after",
+    "etag",
+    "last-modified",
+    "cache-control",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FixtureMode {
+    // requests are sent to the backend and each request/response pair is written to
+    // the fixtures directory
+    Record,
+    // responses are served from the fixtures directory without touching the network
+    Replay,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FixtureRequest {
+    pub method: String,
+    pub path: String,
+    // sorted by key then value so that parameter order doesn't matter
+    pub query: Vec<(String, String)>,
+    // recorded for reference only, requests are matched on their key
+    pub body: Option<Value>,
+}
+
+impl FixtureRequest {
+    pub fn from_request(request: &reqwest::Request) -> Self {
+        let url = request.url();
+        let mut query: Vec<(String, String)> = url
+            .query_pairs()
+            .map(|(key, value)| {
+                let value = if is_secret_key(&key) {
+                    REDACTED.to_string()
+                } else {
+                    value.to_string()
+                };
+                (key.to_string(), value)
+            })
+            .collect();
+        query.sort();
+        let body = request
+            .body()
+            .and_then(|body| body.as_bytes())
+            .filter(|bytes| !bytes.is_empty())
+            .map(parse_body);
+        Self {
+            method: request.method().to_string(),
+            path: url.path().to_string(),
+            query,
+            body,
+        }
+    }
+
+    // the key a request is matched on: its method, path and normalized query
+    pub fn key(&self) -> String {
+        let mut key = format!("{} {}", self.method, self.path);
+        if !self.query.is_empty() {
+            key.push('?');
+            key.push_str(&normalize_query(&self.query));
+        }
+        key
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FixtureResponse {
+    pub status: u16,
+    pub headers: BTreeMap<String, String>,
+    // JSON bodies are stored as is (with secrets redacted) for readability while any
+    // other body is stored as a string
+    pub body: Value,
+}
+
+impl FixtureResponse {
+    fn into_reqwest(self) -> reqwest::Response {
+        let body = match self.body {
+            Value::String(text) => text,
+            value => value.to_string(),
+        };
+        let mut response = axum::http::Response::new(body);
+        *response.status_mut() = reqwest::StatusCode::from_u16(self.status)
+            .unwrap_or(reqwest::StatusCode::INTERNAL_SERVER_ERROR);
+        for (name, value) in self.headers {
+            if let (Ok(name), Ok(value)) = (
+                HeaderName::from_bytes(name.as_bytes()),
+                HeaderValue::from_str(&value),
+            ) {
+                response.headers_mut().insert(name, value);
+            }
+        }
+        reqwest::Response::from(response)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Fixture {
+    pub request: FixtureRequest,
+    pub response: FixtureResponse,
+}
+
+#[derive(Debug, Clone)]
+pub struct Fixtures {
+    mode: FixtureMode,
+    dir: Dir,
+}
+
+impl Fixtures {
+    pub fn new(mode: FixtureMode, dir: Dir) -> Self {
+        Self { mode, dir }
+    }
+
+    pub fn mode(&self) -> FixtureMode {
+        self.mode
+    }
+
+    pub fn dir(&self) -> &Dir {
+        &self.dir
+    }
+
+    // fixture file names are readable versions of their keys with a hash suffix
+    // since sanitizing the key may map different keys to the same name
+    pub fn file_name(key: &str) -> String {
+        let readable: String = sanitize_filename(key).chars().take(100).collect();
+        let hash = sha256::hash_str(key);
+        format!("{}-{}.json", readable, &hash[..12])
+    }
+
+    pub async fn list(&self) -> Result<Vec<Fixture>, HTTPErr> {
+        if !self.dir.exists() {
+            return Ok(Vec::new());
+        }
+        let files = self.dir.files().await.map_err(fixture_fs_err)?;
+        let mut fixtures = Vec::new();
+        for file in files {
+            if file.path().extension().is_none_or(|ext| ext != "json") {
+                continue;
+            }
+            fixtures.push(file.read_json::<Fixture>().await.map_err(fixture_fs_err)?);
+        }
+        Ok(fixtures)
+    }
+
+    pub async fn replay(
+        &self,
+        request: &reqwest::Request,
+        context: &RequestContext,
+    ) -> Result<reqwest::Response, HTTPErr> {
+        let request = FixtureRequest::from_request(request);
+        let key = request.key();
+        let fixtures = self.list().await?;
+        if let Some(fixture) = fixtures.iter().find(|f| f.request.key() == key) {
+            return Ok(fixture.response.clone().into_reqwest());
+        }
+
+        let closest = fixtures
+            .iter()
+            .min_by_key(|f| edit_distance(&f.request.key(), &key));
+        Err(HTTPErr::FixtureNotFoundErr(Box::new(FixtureNotFoundErr {
+            request: context.clone(),
+            key,
+            closest: closest.map(|f| f.request.key()),
+            diff: closest
+                .map(|f| diff_requests(&f.request, &request))
+                .unwrap_or_default(),
+            trace: trace!(),
+        })))
+    }
+
+    // writes the response to the fixture for the request (replacing any previous
+    // recording) and returns an identical response since reading the body consumes
+    // the original
+    pub async fn record(
+        &self,
+        request: FixtureRequest,
+        response: reqwest::Response,
+        context: &RequestContext,
+    ) -> Result<reqwest::Response, HTTPErr> {
+        let status = response.status();
+        let original_headers = response.headers().clone();
+        let headers = original_headers
+            .iter()
+            .filter(|(name, _)| RECORDED_HEADERS.contains(&name.as_str()))
+            .filter_map(|(name, value)| {
+                Some((name.as_str().to_string(), value.to_str().ok()?.to_string()))
+            })
+            .collect();
+        let bytes = response
+            .bytes()
+            .await
+            .map_err(|e| reqwest_err_to_http_client_err(e, context, trace!()))?;
+        let fixture = Fixture {
+            response: FixtureResponse {
+                status: status.as_u16(),
+                headers,
+                body: parse_body(&bytes),
+            },
+            request,
+        };
+
+        let file = self.dir.file(&Self::file_name(&fixture.request.key()));
+        file.write_json(&fixture, true, true)
+            .await
+            .map_err(fixture_fs_err)?;
+
+        // the caller gets the response as received, secrets included
+        let mut response = axum::http::Response::new(bytes);
+        *response.status_mut() = status;
+        *response.headers_mut() = original_headers;
+        Ok(reqwest::Response::from(response))
+    }
+}
+
+fn fixture_fs_err(e: FileSysErr) -> HTTPErr {
+    HTTPErr::FixtureFileSysErr(Box::new(FixtureFileSysErr {
+        source: e,
+        trace: trace!(),
+    }))
+}
+
+pub fn normalize_query(query: &[(String, String)]) -> String {
+    query
+        .iter()
+        .map(|(key, value)| format!("{key}={value}"))
+        .collect::<Vec<_>>()
+        .join("&")
+}
+
+fn is_secret_key(key: &str) -> bool {
+    let key = key.to_ascii_lowercase();
+    key.contains("token") || key == "authorization" || key == "password"
+}
+
+// parses a body as JSON (redacting any secrets) and falls back to a string otherwise
+fn parse_body(bytes: &[u8]) -> Value {
+    match serde_json::from_slice::<Value>(bytes) {
+        Ok(mut value) => {
+            redact_secrets(&mut value);
+            value
+        }
+        Err(_) => Value::String(String::from_utf8_lossy(bytes).to_string()),
+    }
+}
+
+pub fn redact_secrets(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map.iter_mut() {
+                if is_secret_key(key) && !value.is_null() {
+                    *value = Value::String(REDACTED.to_string());
+                } else {
+                    redact_secrets(value);
+                }
+            }
+        }
+        Value::Array(values) => values.iter_mut().for_each(redact_secrets),
+        _ => {}
+    }
+}
+
+// a line diff of the matched parts of two requests where '-' lines belong to the
+// fixture and '+' lines to the request
+pub fn diff_requests(fixture: &FixtureRequest, request: &FixtureRequest) -> String {
+    let mut lines = Vec::new();
+    let mut diff_field = |name: &str, expected: &str, actual: &str| {
+        if expected == actual {
+            lines.push(format!("  {name}: {expected}"));
+        } else {
+            lines.push(format!("- {name}: {expected}"));
+            lines.push(format!("+ {name}: {actual}"));
+        }
+    };
+    diff_field("method", &fixture.method, &request.method);
+    diff_field("path", &fixture.path, &request.path);
+
+    for param in &fixture.query {
+        let line = format!("query: {}={}", param.0, param.1);
+        if request.query.contains(param) {
+            lines.push(format!("  {line}"));
+        } else {
+            lines.push(format!("- {line}"));
+        }
+    }
+    for param in &request.query {
+        if !fixture.query.contains(param) {
+            lines.push(format!("+ query: {}={}", param.0, param.1));
+        }
+    }
+    lines.join("\n")
+}
+
+// the levenshtein distance between two strings
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut cur = vec![i + 1; b.len() + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = prev[j] + usize::from(ca != *cb);
+            cur[j + 1] = substitution.min(prev[j + 1] + 1).min(cur[j] + 1);
+        }
+        prev = cur;
+    }
+    prev[b.len()]
+}
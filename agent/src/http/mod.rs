@@ -1,11 +1,15 @@
 pub mod backend;
+pub mod breaker;
 pub mod client;
+pub mod conditional;
 pub mod config_instances;
 pub mod config_schemas;
 pub mod devices;
 pub mod errors;
 pub mod expand;
+pub mod fixtures;
 pub mod pagination;
 pub mod prelude;
 pub mod query;
+pub mod retry;
 pub mod search;
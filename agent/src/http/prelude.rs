@@ -1,4 +1,5 @@
 // internal crates
+pub use crate::http::breaker::CircuitBreakerExt;
 pub use crate::http::config_instances::ConfigInstancesExt;
 pub use crate::http::config_schemas::ConfigSchemasExt;
 
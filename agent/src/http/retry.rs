@@ -0,0 +1,88 @@
+// standard library
+use std::time::Duration;
+
+// external crates
+use reqwest::Method;
+use uuid::Uuid;
+
+pub const IDEMPOTENCY_KEY_HEADER: &str = "Idempotency-Key";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    // the total number of attempts (including the first one), so 1 disables retries
+    pub max_attempts: u32,
+    // the backoff before the n-th retry is base_delay * 2^(n-1) (capped at max_delay)
+    // with the upper half of it jittered
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+    // a retry is not attempted if it would start after this much time has passed
+    // since the first attempt
+    pub time_budget: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(250),
+            max_delay: Duration::from_secs(5),
+            time_budget: Duration::from_secs(30),
+        }
+    }
+}
+
+impl RetryPolicy {
+    pub fn disabled() -> Self {
+        Self {
+            max_attempts: 1,
+            ..Default::default()
+        }
+    }
+
+    // the (jittered) delay to wait after the given attempt failed
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        self.backoff_with_jitter(attempt, random_fraction())
+    }
+
+    // jitter is a fraction in [0, 1) selecting a delay between half and all of the
+    // exponential backoff so that retries from many devices don't line up
+    pub fn backoff_with_jitter(&self, attempt: u32, jitter: f64) -> Duration {
+        let exp = attempt.saturating_sub(1).min(31);
+        let delay = self.base_delay.saturating_mul(1 << exp).min(self.max_delay);
+        let half = delay / 2;
+        half + half.mul_f64(jitter.clamp(0.0, 1.0))
+    }
+
+    // whether another attempt may be made after the given attempt failed, having
+    // already spent 'elapsed' and needing to wait 'delay' before retrying
+    pub fn allows_retry(&self, attempt: u32, elapsed: Duration, delay: Duration) -> bool {
+        attempt < self.max_attempts && elapsed.saturating_add(delay) < self.time_budget
+    }
+}
+
+// methods which may be sent more than once without changing the outcome
+// (https://httpwg.org/specs/rfc9110.html#idempotent.methods)
+pub fn is_idempotent(method: &Method) -> bool {
+    matches!(
+        *method,
+        Method::GET | Method::HEAD | Method::PUT | Method::DELETE | Method::OPTIONS | Method::TRACE
+    )
+}
+
+// non-idempotent requests are only safe to retry if they carry an idempotency key
+// which lets the backend deduplicate them
+pub fn is_retryable(request: &reqwest::Request) -> bool {
+    is_idempotent(request.method()) || request.headers().contains_key(IDEMPOTENCY_KEY_HEADER)
+}
+
+pub fn needs_idempotency_key(method: &Method) -> bool {
+    matches!(*method, Method::POST | Method::PATCH)
+}
+
+// v4 uuids are random so they double as a source of jitter without pulling in a
+// dedicated random number generator. The low 62 bits are used since the two bits
+// above them hold the uuid's variant.
+fn random_fraction() -> f64 {
+    let bits = (Uuid::new_v4().as_u128() as u64) & ((1 << 62) - 1);
+    bits as f64 / (1u64 << 62) as f64
+}
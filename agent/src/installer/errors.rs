@@ -7,6 +7,7 @@ use crate::crypt::errors::CryptErr;
 use crate::errors::{Code, HTTPCode, MiruError, Trace};
 use crate::filesys::errors::FileSysErr;
 use crate::http::errors::HTTPErr;
+use crate::network::errors::NetworkErr;
 use crate::storage::errors::StorageErr;
 
 #[derive(Debug)]
@@ -129,6 +130,36 @@ impl fmt::Display for InstallHTTPErr {
     }
 }
 
+#[derive(Debug)]
+pub struct InstallNetworkErr {
+    pub source: NetworkErr,
+    pub trace: Box<Trace>,
+}
+
+impl MiruError for InstallNetworkErr {
+    fn code(&self) -> Code {
+        Code::InternalServerError
+    }
+
+    fn http_status(&self) -> HTTPCode {
+        HTTPCode::INTERNAL_SERVER_ERROR
+    }
+
+    fn is_network_connection_error(&self) -> bool {
+        false
+    }
+
+    fn params(&self) -> Option<serde_json::Value> {
+        None
+    }
+}
+
+impl fmt::Display for InstallNetworkErr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.source)
+    }
+}
+
 #[derive(Debug)]
 pub struct InstallStorageErr {
     pub source: StorageErr,
@@ -166,6 +197,7 @@ pub enum InstallErr {
     CryptErr(InstallCryptErr),
     FileSysErr(InstallFileSysErr),
     HTTPErr(InstallHTTPErr),
+    NetworkErr(InstallNetworkErr),
     StorageErr(InstallStorageErr),
 }
 
@@ -177,6 +209,7 @@ macro_rules! forward_error_method {
             Self::CryptErr(e) => e.$method($($arg)?),
             Self::FileSysErr(e) => e.$method($($arg)?),
             Self::HTTPErr(e) => e.$method($($arg)?),
+            Self::NetworkErr(e) => e.$method($($arg)?),
             Self::StorageErr(e) => e.$method($($arg)?),
         }
     };
@@ -1,5 +1,5 @@
 // standard library
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 use std::env;
 
 // internal crates
@@ -9,6 +9,8 @@ use crate::http::{client::HTTPClient, devices::DevicesExt};
 use crate::installer::{display, errors::*};
 use crate::logs::{init, LogOptions};
 use crate::models::device::{Device, DeviceStatus};
+use crate::mqtt::client::Protocol;
+use crate::network::options::NetworkOptions;
 use crate::storage::{layout::StorageLayout, settings, setup::clean_storage_setup};
 use crate::trace;
 use crate::utils::version_info;
@@ -63,9 +65,62 @@ async fn install_helper(
     if let Some(mqtt_broker_host) = cli_args.get("mqtt-broker-host") {
         settings.mqtt_broker.host = mqtt_broker_host.to_string();
     }
+    if let Some(mqtt_broker_protocol) = cli_args.get("mqtt-broker-protocol") {
+        settings.mqtt_broker.protocol = Protocol::parse(mqtt_broker_protocol).ok_or_else(|| {
+            let msg = format!("Invalid mqtt broker protocol: {}", mqtt_broker_protocol);
+            error!("{}", msg);
+            msg
+        })?;
+    }
+    if let Some(mqtt_broker_port) = cli_args.get("mqtt-broker-port") {
+        let port = mqtt_broker_port.parse::<u16>().map_err(|e| {
+            let msg = format!("Invalid mqtt broker port '{}': {}", mqtt_broker_port, e);
+            error!("{}", msg);
+            msg
+        })?;
+        settings.mqtt_broker.port = Some(port);
+    }
+    if let Some(mqtt_broker_path) = cli_args.get("mqtt-broker-path") {
+        settings.mqtt_broker.websocket_path = mqtt_broker_path.to_string();
+    }
+
+    if let Some(proxy_url) = cli_args.get("proxy-url") {
+        settings.proxy.url = Some(proxy_url.to_string());
+    }
+    if let Some(no_proxy) = cli_args.get("no-proxy") {
+        settings.proxy.no_proxy = split_list(no_proxy);
+    }
+    if let Some(ca_cert_files) = cli_args.get("ca-cert-files") {
+        settings.tls.ca_cert_files = split_list(ca_cert_files);
+    }
+    if let Some(client_cert_file) = cli_args.get("client-cert-file") {
+        settings.tls.client_cert_file = Some(client_cert_file.to_string());
+    }
+    if let Some(client_key_file) = cli_args.get("client-key-file") {
+        settings.tls.client_key_file = Some(client_key_file.to_string());
+    }
+    if let Some(backend_pins) = cli_args.get("backend-pins") {
+        settings.tls.backend_pins = split_list(backend_pins);
+    }
+    if let Some(mqtt_broker_pins) = cli_args.get("mqtt-broker-pins") {
+        settings.tls.mqtt_broker_pins = split_list(mqtt_broker_pins);
+    }
 
     // run the installation
-    let http_client = HTTPClient::new(&settings.backend.base_url).await;
+    let network = NetworkOptions::load(&settings).await.map_err(|e| {
+        InstallErr::NetworkErr(InstallNetworkErr {
+            source: e,
+            trace: trace!(),
+        })
+    })?;
+    let http_client = HTTPClient::new_with_network(&settings.backend.base_url, &network)
+        .await
+        .map_err(|e| {
+            InstallErr::NetworkErr(InstallNetworkErr {
+                source: e,
+                trace: trace!(),
+            })
+        })?;
     let layout = StorageLayout::default();
     bootstrap(
         &layout,
@@ -81,6 +136,15 @@ async fn install_helper(
     Ok(())
 }
 
+// splits a comma separated cli argument into its (non-empty) items
+fn split_list(arg: &str) -> Vec<String> {
+    arg.split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
 // walks user through the installation process
 pub async fn bootstrap<HTTPClientT: DevicesExt>(
     layout: &StorageLayout,
@@ -119,6 +183,7 @@ pub async fn bootstrap<HTTPClientT: DevicesExt>(
             last_synced_at: DateTime::<Utc>::UNIX_EPOCH,
             last_connected_at: DateTime::<Utc>::UNIX_EPOCH,
             last_disconnected_at: DateTime::<Utc>::UNIX_EPOCH,
+            connection_history: VecDeque::new(),
         },
         settings,
         &private_key_file,
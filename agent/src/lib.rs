@@ -5,13 +5,16 @@ pub mod cache;
 pub mod crud;
 pub mod crypt;
 pub mod deploy;
+pub mod diagnostics;
 pub mod errors;
 pub mod filesys;
 pub mod http;
 pub mod installer;
 pub mod logs;
+pub mod metrics;
 pub mod models;
 pub mod mqtt;
+pub mod network;
 pub mod server;
 pub mod services;
 pub mod storage;
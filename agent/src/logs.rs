@@ -2,6 +2,9 @@
 use std::fmt::Display;
 use std::path::PathBuf;
 
+// internal crates
+use crate::filesys::{errors::FileSysErr, file::File};
+
 // external crates
 use serde::{Deserialize, Serialize};
 #[allow(unused_imports)]
@@ -124,3 +127,96 @@ pub fn init(options: LogOptions) -> Result<WorkerGuard, Box<dyn std::error::Erro
         Ok(guard)
     }
 }
+
+pub const REDACTED: &str = "<redacted>";
+
+// keys whose values are never shared off the device (matched case insensitively)
+pub const SENSITIVE_KEYS: [&str; 5] = ["password", "secret", "token", "private_key", "api_key"];
+
+/// Read the last lines of a log file which fit in `max_bytes` (without the line the
+/// limit falls in the middle of). The lines aren't redacted.
+pub async fn read_tail(file: &File, max_bytes: usize) -> Result<String, FileSysErr> {
+    // one more byte than the limit is read to tell whether the first line is complete
+    let bytes = file.read_tail(max_bytes as u64 + 1).await?;
+    let bytes = if bytes.len() > max_bytes {
+        let first_line_end = bytes.iter().position(|b| *b == b'\n');
+        &bytes[first_line_end.map_or(bytes.len(), |i| i + 1)..]
+    } else {
+        &bytes[..]
+    };
+    Ok(String::from_utf8_lossy(bytes).into_owned())
+}
+
+/// Replace the secrets which may have been logged with a placeholder: the values of
+/// sensitive keys (e.g. 'password=...' or '"token": "..."'), bearer tokens and the
+/// credentials of urls.
+pub fn redact(log: &str) -> String {
+    // ascii lowercasing preserves the byte offsets
+    let lower = log.to_ascii_lowercase();
+    let mut secrets = Vec::new();
+    for key in SENSITIVE_KEYS {
+        for (start, _) in lower.match_indices(key) {
+            secrets.extend(key_value(&lower, start + key.len()));
+        }
+    }
+    for (start, prefix) in lower.match_indices("bearer") {
+        let value_start = start + prefix.len();
+        let value = lower[value_start..].trim_start_matches([' ', '\t']);
+        let value_start = lower.len() - value.len();
+        if value_start > start + prefix.len() {
+            let value_end = value_start + value.find(is_value_end).unwrap_or(value.len());
+            secrets.push((value_start, value_end));
+        }
+    }
+    for (start, separator) in lower.match_indices("://") {
+        let authority_start = start + separator.len();
+        let authority = &lower[authority_start..];
+        let authority = &authority[..authority
+            .find(|c: char| is_value_end(c) || matches!(c, '/' | '?' | '#'))
+            .unwrap_or(authority.len())];
+        if let Some(at) = authority.rfind('@') {
+            secrets.push((authority_start, authority_start + at));
+        }
+    }
+
+    secrets.sort_unstable();
+    let mut redacted = String::with_capacity(log.len());
+    let mut end = 0;
+    for (start, secret_end) in secrets {
+        if secret_end <= end || start == secret_end {
+            continue;
+        }
+        if start >= end {
+            redacted.push_str(&log[end..start]);
+            redacted.push_str(REDACTED);
+        }
+        end = secret_end;
+    }
+    redacted.push_str(&log[end..]);
+    redacted
+}
+
+// the value assigned to the key ending at the offset (e.g. 'token: "abc"' or
+// 'password=abc'), skipping the rest of the key's name (e.g. 'token_file=...')
+fn key_value(lower: &str, key_end: usize) -> Option<(usize, usize)> {
+    let rest = &lower[key_end..];
+    let rest = rest.trim_start_matches(|c: char| c.is_ascii_alphanumeric() || c == '_');
+    let rest = rest.trim_start_matches(['"', '\'']).trim_start();
+    let rest = rest.strip_prefix([':', '='])?.trim_start();
+    let value_start = lower.len() - rest.len();
+    match rest.chars().next()? {
+        quote @ ('"' | '\'') => {
+            let value = &rest[1..];
+            let value_end = value.find(quote).unwrap_or(value.len());
+            Some((value_start + 1, value_start + 1 + value_end))
+        }
+        _ => Some((
+            value_start,
+            value_start + rest.find(is_value_end).unwrap_or(rest.len()),
+        )),
+    }
+}
+
+fn is_value_end(c: char) -> bool {
+    c.is_whitespace() || matches!(c, ',' | ';' | '&' | ')' | ']' | '}' | '"' | '\'')
+}
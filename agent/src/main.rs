@@ -1,18 +1,30 @@
 // standard library
 use std::collections::HashMap;
 use std::env;
+use std::net::{Ipv4Addr, SocketAddr};
+use std::sync::Arc;
+use std::time::Duration;
 
 // internal
 use miru_agent::app::options::{AppOptions, LifecycleOptions};
-use miru_agent::app::run::run;
+use miru_agent::app::run::{run, ExitReason};
+use miru_agent::diagnostics::collect;
+use miru_agent::filesys::file::File;
 use miru_agent::installer::install::install;
 use miru_agent::logs::{init, LogOptions};
-use miru_agent::mqtt::client::ConnectAddress;
+use miru_agent::mqtt::client::{ConnectAddress, Credentials, OptionsBuilder, Protocol};
+use miru_agent::network::{
+    failover::{Endpoints, FailoverOptions},
+    options::NetworkOptions,
+};
+use miru_agent::server::access::AccessPolicy;
+use miru_agent::server::serve::{ServerOptions, TCPServerOptions};
+use miru_agent::storage::bundle;
 use miru_agent::storage::device::assert_activated;
 use miru_agent::storage::layout::StorageLayout;
 use miru_agent::storage::settings::Settings;
 use miru_agent::utils::version_info;
-use miru_agent::workers::mqtt;
+use miru_agent::workers::{bridge, commands, mqtt};
 
 // external
 use tokio::signal::unix::signal;
@@ -47,6 +59,16 @@ async fn main() {
         return install(&cli_args).await;
     }
 
+    // export the device's config state to a bundle & exit
+    if let Some(path) = cli_args.get("export") {
+        return export(path).await;
+    }
+
+    // write a diagnostics bundle for field support & exit
+    if let Some(path) = cli_args.get("diagnostics") {
+        return diagnostics(path).await;
+    }
+
     // run the agent starting here
 
     // check the agent has been activated
@@ -69,14 +91,56 @@ async fn main() {
 
     // initialize the logging
     let log_options = LogOptions {
-        log_level: settings.log_level,
+        log_level: settings.log_level.clone(),
         ..Default::default()
     };
     let result = init(log_options);
-    if let Err(e) = result {
+    if let Err(e) = &result {
         println!("Failed to initialize logging: {e}");
     }
 
+    // load the proxy and tls settings (refuse to run with an explicitly configured
+    // certificate or proxy which can't be used rather than silently ignoring it)
+    let network = match NetworkOptions::load(&settings).await {
+        Ok(network) => network,
+        Err(e) => {
+            error!("Unable to load the proxy and tls settings: {}", e);
+            return;
+        }
+    };
+
+    // the metrics tcp listener is only ever bound to localhost
+    let metrics_address = if settings.metrics.enable_tcp_listener {
+        Some(SocketAddr::from((
+            Ipv4Addr::LOCALHOST,
+            settings.metrics.tcp_port,
+        )))
+    } else {
+        None
+    };
+
+    // the local bridge's broker runs on the device so it's connected to without TLS
+    let local_bridge_defaults = bridge::Options::default();
+    let local_bridge_credentials = match (
+        settings.local_bridge.username,
+        settings.local_bridge.password,
+    ) {
+        (Some(username), password) => Credentials::new(username, password.unwrap_or_default()),
+        _ => local_bridge_defaults.client.credentials.clone(),
+    };
+    let local_bridge = bridge::Options {
+        client: OptionsBuilder::new(local_bridge_credentials)
+            .with_connect_address(ConnectAddress::new(
+                Protocol::TCP,
+                settings.local_bridge.host,
+                settings.local_bridge.port,
+            ))
+            .with_client_id(local_bridge_defaults.client.client_id.clone())
+            .build(),
+        topic_prefix: settings.local_bridge.topic_prefix,
+        ..local_bridge_defaults
+    };
+
     // run the server
     let options = AppOptions {
         lifecycle: LifecycleOptions {
@@ -84,23 +148,98 @@ async fn main() {
             ..Default::default()
         },
         backend_base_url: settings.backend.base_url,
+        backend_fallback_urls: settings.backend.fallback_urls,
+        backend_failover: FailoverOptions {
+            fail_back_after: Duration::from_secs(settings.backend.fail_back_secs),
+            ..Default::default()
+        },
+        network,
         enable_socket_server: settings.enable_socket_server,
         enable_mqtt_worker: settings.enable_mqtt_worker,
         enable_poller: settings.enable_poller,
+        enable_local_bridge: settings.local_bridge.enabled,
+        local_bridge,
         mqtt_worker: mqtt::Options {
-            broker_address: ConnectAddress {
-                broker: settings.mqtt_broker.host,
-                ..Default::default()
-            },
+            brokers: Some(Arc::new(Endpoints::new(
+                &settings.mqtt_broker.host,
+                &settings.mqtt_broker.fallback_hosts,
+                FailoverOptions {
+                    fail_back_after: Duration::from_secs(settings.mqtt_broker.fail_back_secs),
+                    ..Default::default()
+                },
+            ))),
+            broker_address: ConnectAddress::new(
+                settings.mqtt_broker.protocol,
+                settings.mqtt_broker.host.clone(),
+                settings.mqtt_broker.port(),
+            )
+            .with_path(settings.mqtt_broker.websocket_path.clone()),
+            protocol_version: settings.mqtt_broker.protocol_version,
+            session_expiry: Duration::from_secs(settings.mqtt_broker.session_expiry_secs),
+            keep_alive: settings.mqtt_broker.keep_alive(),
+            timeouts: settings.mqtt_broker.timeouts(),
+            capacity: settings.mqtt_broker.capacity,
+            qos: settings.mqtt_broker.qos(),
+            commands: commands::Options::default()
+                .with_authorized(&settings.remote_commands.authorized),
+            ..Default::default()
+        },
+        server: ServerOptions {
+            metrics_address,
+            access_policy: AccessPolicy::new(settings.socket_access.rules.clone()),
+            tcp: settings.tcp_server.enabled.then(|| TCPServerOptions {
+                address: settings.tcp_server.socket_addr(),
+                tokens_file: layout.auth_dir().api_tokens_file(),
+            }),
             ..Default::default()
         },
         ..Default::default()
     };
     info!("Running the server with options: {:?}", options);
-    let result = run(version_info.version, options, await_shutdown_signal()).await;
-    if let Err(e) = result {
-        error!("Failed to run the server: {e}");
+    match run(version_info.version, options, await_shutdown_signal()).await {
+        Ok(ExitReason::Shutdown) => {}
+        // systemd restarts the agent when it exits with this status
+        Ok(ExitReason::Restart) => {
+            info!("Exiting with status {RESTART_EXIT_STATUS} to restart the agent");
+            // flush the logs before exiting since exit skips destructors
+            drop(result);
+            std::process::exit(RESTART_EXIT_STATUS);
+        }
+        Err(e) => {
+            error!("Failed to run the server: {e}");
+        }
+    }
+}
+
+// must match RestartForceExitStatus in the systemd service
+const RESTART_EXIT_STATUS: i32 = 75;
+
+async fn export(path: &str) {
+    let layout = StorageLayout::default();
+    let bundle = match bundle::export_layout(&layout).await {
+        Ok(bundle) => bundle,
+        Err(e) => {
+            println!("Failed to export device: {e}");
+            return;
+        }
+    };
+    let file = File::new(path);
+    if let Err(e) = bundle::write(&bundle, &file).await {
+        println!("Failed to write bundle to {file}: {e}");
+        return;
+    }
+    println!("Exported device to {file}");
+}
+
+async fn diagnostics(path: &str) {
+    // the agent may not be running so the live sync state isn't included
+    let archive = collect::collect(&collect::Options::default(), None).await;
+    let file = File::new(path);
+    if let Err(e) = file.write_bytes(&archive, true, true).await {
+        println!("Failed to write diagnostics to {file}: {e}");
+        return;
     }
+    println!("Wrote diagnostics to {file}");
 }
 
 async fn await_shutdown_signal() {
@@ -0,0 +1,240 @@
+// standard library
+use std::fmt::Write;
+
+// internal crates
+use crate::metrics::registry::{HistogramSnapshot, Metrics};
+
+pub const CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+pub const NAMESPACE: &str = "miru_agent";
+
+// encodes metrics in the prometheus text exposition format
+// (https://prometheus.io/docs/instrumenting/exposition_formats/)
+#[derive(Debug, Default)]
+pub struct Encoder {
+    buf: String,
+}
+
+impl Encoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn counter(&mut self, name: &str, help: &str, value: u64) {
+        let name = full_name(name);
+        self.header(&name, help, "counter");
+        let _ = writeln!(self.buf, "{name} {value}");
+    }
+
+    pub fn gauge(&mut self, name: &str, help: &str, value: f64) {
+        let name = full_name(name);
+        self.header(&name, help, "gauge");
+        let _ = writeln!(self.buf, "{name} {}", format_float(value));
+    }
+
+    pub fn labeled_gauges(&mut self, name: &str, help: &str, series: &[(&[(&str, &str)], f64)]) {
+        let name = full_name(name);
+        self.header(&name, help, "gauge");
+        for (labels, value) in series {
+            let _ = writeln!(
+                self.buf,
+                "{name}{} {}",
+                format_labels(labels),
+                format_float(*value)
+            );
+        }
+    }
+
+    pub fn histogram(&mut self, name: &str, help: &str, snapshot: &HistogramSnapshot) {
+        let name = full_name(name);
+        self.header(&name, help, "histogram");
+        self.histogram_series(&name, &[], snapshot);
+    }
+
+    pub fn labeled_histograms(
+        &mut self,
+        name: &str,
+        help: &str,
+        series: &[(Vec<(&str, &str)>, HistogramSnapshot)],
+    ) {
+        let name = full_name(name);
+        self.header(&name, help, "histogram");
+        for (labels, snapshot) in series {
+            self.histogram_series(&name, labels, snapshot);
+        }
+    }
+
+    pub fn finish(self) -> String {
+        self.buf
+    }
+
+    fn header(&mut self, name: &str, help: &str, kind: &str) {
+        let _ = writeln!(self.buf, "# HELP {name} {}", escape_help(help));
+        let _ = writeln!(self.buf, "# TYPE {name} {kind}");
+    }
+
+    fn histogram_series(
+        &mut self,
+        name: &str,
+        labels: &[(&str, &str)],
+        snapshot: &HistogramSnapshot,
+    ) {
+        for (bound, count) in snapshot.buckets.iter() {
+            let le = format_float(*bound);
+            let mut bucket_labels = labels.to_vec();
+            bucket_labels.push(("le", &le));
+            let _ = writeln!(
+                self.buf,
+                "{name}_bucket{} {count}",
+                format_labels(&bucket_labels)
+            );
+        }
+        let labels = format_labels(labels);
+        let _ = writeln!(
+            self.buf,
+            "{name}_sum{labels} {}",
+            format_float(snapshot.sum_secs)
+        );
+        let _ = writeln!(self.buf, "{name}_count{labels} {}", snapshot.count);
+    }
+}
+
+// encodes the metrics which are recorded as events occur. Metrics which are read from
+// the agent's state at scrape time (caches, sync state, etc.) are encoded by the caller.
+pub fn encode_registry(encoder: &mut Encoder, metrics: &Metrics) {
+    // sync
+    encoder.counter(
+        "sync_attempts_total",
+        "Total number of syncs attempted with the backend",
+        metrics.sync.attempts.get(),
+    );
+    encoder.counter(
+        "sync_successes_total",
+        "Total number of syncs which succeeded",
+        metrics.sync.successes.get(),
+    );
+    encoder.counter(
+        "sync_failures_total",
+        "Total number of syncs which failed",
+        metrics.sync.failures.get(),
+    );
+    encoder.histogram(
+        "sync_duration_seconds",
+        "Duration of syncs with the backend",
+        &metrics.sync.duration.snapshot(),
+    );
+
+    // deploy
+    encoder.counter(
+        "deploys_total",
+        "Total number of config instances deployed",
+        metrics.deploy.deploys.get(),
+    );
+    encoder.counter(
+        "deploy_failures_total",
+        "Total number of failed config instance deployments",
+        metrics.deploy.deploy_failures.get(),
+    );
+    encoder.counter(
+        "removes_total",
+        "Total number of config instances removed",
+        metrics.deploy.removes.get(),
+    );
+    encoder.counter(
+        "remove_failures_total",
+        "Total number of failed config instance removals",
+        metrics.deploy.remove_failures.get(),
+    );
+    encoder.counter(
+        "archives_total",
+        "Total number of config instances archived",
+        metrics.deploy.archives.get(),
+    );
+    encoder.counter(
+        "archive_failures_total",
+        "Total number of failed config instance archivals",
+        metrics.deploy.archive_failures.get(),
+    );
+
+    // mqtt
+    encoder.gauge(
+        "mqtt_connected",
+        "Whether the agent is connected to the MQTT broker (1) or not (0)",
+        metrics.mqtt.connected.get() as f64,
+    );
+    encoder.counter(
+        "mqtt_reconnects_total",
+        "Total number of times the agent reconnected to the MQTT broker",
+        metrics.mqtt.reconnects.get(),
+    );
+    encoder.gauge(
+        "mqtt_outbox_messages",
+        "Number of MQTT messages queued for publishing once the broker is reachable",
+        metrics.mqtt.outbox_messages.get() as f64,
+    );
+    encoder.counter(
+        "mqtt_outbox_dropped_total",
+        "Total number of queued MQTT messages dropped because the outbox was full or they expired",
+        metrics.mqtt.outbox_dropped.get(),
+    );
+
+    // http
+    let http = metrics.http.snapshot();
+    let series: Vec<(Vec<(&str, &str)>, HistogramSnapshot)> = http
+        .iter()
+        .map(|(key, snapshot)| {
+            (
+                vec![
+                    ("method", key.method.as_str()),
+                    ("endpoint", key.endpoint.as_str()),
+                ],
+                snapshot.clone(),
+            )
+        })
+        .collect();
+    encoder.labeled_histograms(
+        "http_request_duration_seconds",
+        "Latency of HTTP requests to the backend by endpoint",
+        &series,
+    );
+}
+
+fn full_name(name: &str) -> String {
+    format!("{NAMESPACE}_{name}")
+}
+
+fn format_labels(labels: &[(&str, &str)]) -> String {
+    if labels.is_empty() {
+        return String::new();
+    }
+    let labels: Vec<String> = labels
+        .iter()
+        .map(|(key, value)| format!("{key}=\"{}\"", escape_label_value(value)))
+        .collect();
+    format!("{{{}}}", labels.join(","))
+}
+
+fn format_float(value: f64) -> String {
+    if value.is_infinite() {
+        if value.is_sign_positive() {
+            "+Inf".to_string()
+        } else {
+            "-Inf".to_string()
+        }
+    } else if value.is_nan() {
+        "NaN".to_string()
+    } else {
+        value.to_string()
+    }
+}
+
+fn escape_help(help: &str) -> String {
+    help.replace('\\', "\\\\").replace('\n', "\\n")
+}
+
+fn escape_label_value(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
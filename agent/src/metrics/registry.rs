@@ -0,0 +1,262 @@
+// standard library
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicI64, AtomicU64, Ordering};
+use std::sync::{Arc, LazyLock, Mutex};
+use std::time::Duration;
+
+// ================================ PRIMITIVES ===================================== //
+#[derive(Debug, Default)]
+pub struct Counter {
+    value: AtomicU64,
+}
+
+impl Counter {
+    pub fn inc(&self) {
+        self.inc_by(1);
+    }
+
+    pub fn inc_by(&self, n: u64) {
+        self.value.fetch_add(n, Ordering::Relaxed);
+    }
+
+    pub fn get(&self) -> u64 {
+        self.value.load(Ordering::Relaxed)
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Gauge {
+    value: AtomicI64,
+}
+
+impl Gauge {
+    pub fn set(&self, value: i64) {
+        self.value.store(value, Ordering::Relaxed);
+    }
+
+    pub fn get(&self) -> i64 {
+        self.value.load(Ordering::Relaxed)
+    }
+}
+
+// upper bounds (in seconds) of the histogram buckets. The +Inf bucket is implicit.
+pub const DURATION_BUCKETS: [f64; 13] = [
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0,
+];
+
+#[derive(Debug)]
+pub struct Histogram {
+    // non-cumulative counts per bucket; the final element is the +Inf bucket
+    buckets: [AtomicU64; DURATION_BUCKETS.len() + 1],
+    sum_micros: AtomicU64,
+    count: AtomicU64,
+}
+
+impl Default for Histogram {
+    fn default() -> Self {
+        Self {
+            buckets: std::array::from_fn(|_| AtomicU64::new(0)),
+            sum_micros: AtomicU64::new(0),
+            count: AtomicU64::new(0),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct HistogramSnapshot {
+    // cumulative counts for each bound in DURATION_BUCKETS followed by +Inf
+    pub buckets: Vec<(f64, u64)>,
+    pub sum_secs: f64,
+    pub count: u64,
+}
+
+impl Histogram {
+    pub fn observe(&self, duration: Duration) {
+        let secs = duration.as_secs_f64();
+        let idx = DURATION_BUCKETS
+            .iter()
+            .position(|bound| secs <= *bound)
+            .unwrap_or(DURATION_BUCKETS.len());
+        self.buckets[idx].fetch_add(1, Ordering::Relaxed);
+        self.sum_micros
+            .fetch_add(duration.as_micros() as u64, Ordering::Relaxed);
+        self.count.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn snapshot(&self) -> HistogramSnapshot {
+        let mut cumulative = 0;
+        let mut buckets = Vec::with_capacity(self.buckets.len());
+        for (i, bucket) in self.buckets.iter().enumerate() {
+            cumulative += bucket.load(Ordering::Relaxed);
+            let bound = DURATION_BUCKETS.get(i).copied().unwrap_or(f64::INFINITY);
+            buckets.push((bound, cumulative));
+        }
+        HistogramSnapshot {
+            buckets,
+            sum_secs: self.sum_micros.load(Ordering::Relaxed) as f64 / 1_000_000.0,
+            count: self.count.load(Ordering::Relaxed),
+        }
+    }
+}
+
+// ================================== SYNC ======================================== //
+#[derive(Debug, Default)]
+pub struct SyncMetrics {
+    pub attempts: Counter,
+    pub successes: Counter,
+    pub failures: Counter,
+    pub duration: Histogram,
+}
+
+impl SyncMetrics {
+    pub fn record(&self, success: bool, duration: Duration) {
+        self.attempts.inc();
+        if success {
+            self.successes.inc();
+        } else {
+            self.failures.inc();
+        }
+        self.duration.observe(duration);
+    }
+}
+
+// ================================= DEPLOY ======================================= //
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DeployOperation {
+    Deploy,
+    Remove,
+    Archive,
+}
+
+#[derive(Debug, Default)]
+pub struct DeployMetrics {
+    pub deploys: Counter,
+    pub deploy_failures: Counter,
+    pub removes: Counter,
+    pub remove_failures: Counter,
+    pub archives: Counter,
+    pub archive_failures: Counter,
+}
+
+impl DeployMetrics {
+    pub fn record(&self, operation: DeployOperation, success: bool) {
+        let counter = match (operation, success) {
+            (DeployOperation::Deploy, true) => &self.deploys,
+            (DeployOperation::Deploy, false) => &self.deploy_failures,
+            (DeployOperation::Remove, true) => &self.removes,
+            (DeployOperation::Remove, false) => &self.remove_failures,
+            (DeployOperation::Archive, true) => &self.archives,
+            (DeployOperation::Archive, false) => &self.archive_failures,
+        };
+        counter.inc();
+    }
+}
+
+// ================================== MQTT ======================================== //
+#[derive(Debug, Default)]
+pub struct MQTTMetrics {
+    pub connected: Gauge,
+    pub reconnects: Counter,
+    pub outbox_messages: Gauge,
+    // dropped because the outbox was full or they expired before being published
+    pub outbox_dropped: Counter,
+    // whether the client has connected at least once (so the first connection is
+    // not counted as a reconnect)
+    has_connected: Gauge,
+}
+
+impl MQTTMetrics {
+    pub fn on_connect(&self) {
+        if self.has_connected.get() > 0 {
+            self.reconnects.inc();
+        }
+        self.has_connected.set(1);
+        self.connected.set(1);
+    }
+
+    pub fn on_disconnect(&self) {
+        self.connected.set(0);
+    }
+}
+
+// ================================== HTTP ======================================== //
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct EndpointKey {
+    pub method: String,
+    pub endpoint: String,
+}
+
+#[derive(Debug, Default)]
+pub struct HTTPMetrics {
+    latencies: Mutex<BTreeMap<EndpointKey, Arc<Histogram>>>,
+}
+
+impl HTTPMetrics {
+    pub fn observe(&self, method: &str, endpoint: &str, duration: Duration) {
+        let key = EndpointKey {
+            method: method.to_string(),
+            endpoint: endpoint.to_string(),
+        };
+        let histogram = match self.latencies.lock() {
+            Ok(mut latencies) => latencies.entry(key).or_default().clone(),
+            Err(_) => return,
+        };
+        histogram.observe(duration);
+    }
+
+    pub fn snapshot(&self) -> Vec<(EndpointKey, HistogramSnapshot)> {
+        let latencies = match self.latencies.lock() {
+            Ok(latencies) => latencies,
+            Err(_) => return Vec::new(),
+        };
+        latencies
+            .iter()
+            .map(|(key, histogram)| (key.clone(), histogram.snapshot()))
+            .collect()
+    }
+}
+
+// converts a request url into a low cardinality endpoint label by dropping the base
+// url and query string and replacing resource ids (any path segment containing a digit)
+// with a placeholder
+pub fn endpoint_label(base_url: &str, url: &str) -> String {
+    let path = match url.strip_prefix(base_url) {
+        Some(path) => path,
+        // fall back to the url's path if it isn't relative to the base url
+        None => match url.split_once("://") {
+            Some((_, rest)) => rest.find('/').map(|i| &rest[i..]).unwrap_or_default(),
+            None => url,
+        },
+    };
+    let path = path.split(['?', '#']).next().unwrap_or_default();
+    let segments: Vec<&str> = path
+        .split('/')
+        .filter(|segment| !segment.is_empty())
+        .map(|segment| {
+            if segment.chars().any(|c| c.is_ascii_digit()) {
+                "{id}"
+            } else {
+                segment
+            }
+        })
+        .collect();
+    format!("/{}", segments.join("/"))
+}
+
+// ================================ REGISTRY ====================================== //
+#[derive(Debug, Default)]
+pub struct Metrics {
+    pub sync: SyncMetrics,
+    pub deploy: DeployMetrics,
+    pub mqtt: MQTTMetrics,
+    pub http: HTTPMetrics,
+}
+
+// the metrics are process wide (like the reqwest client) so that the syncer, deploy
+// observers, http client and mqtt worker can record to them without each needing a
+// handle threaded through to it
+static METRICS: LazyLock<Metrics> = LazyLock::new(Metrics::default);
+
+pub fn global() -> &'static Metrics {
+    &METRICS
+}
@@ -1,5 +1,6 @@
 // internal crates
 use crate::deserialize_error;
+use crate::errors::MiruError;
 
 // external crates
 use chrono::{DateTime, TimeDelta, Utc};
@@ -464,6 +465,37 @@ impl Status {
     }
 }
 
+// =============================== DEPLOY ERROR ==================================== //
+// the most recent error the agent encountered while trying to reach a config
+// instance's target status (cleared once the agent succeeds)
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DeployError {
+    pub code: String,
+    pub message: String,
+    pub params: Option<serde_json::Value>,
+    pub occurred_at: DateTime<Utc>,
+}
+
+impl DeployError {
+    pub fn new(e: &impl MiruError) -> Self {
+        Self {
+            code: e.code().as_str().to_string(),
+            message: e.to_string(),
+            params: e.params(),
+            occurred_at: Utc::now(),
+        }
+    }
+
+    pub fn to_sdk(deploy_error: DeployError) -> openapi_server::models::DeployError {
+        openapi_server::models::DeployError {
+            code: deploy_error.code,
+            message: deploy_error.message,
+            params: deploy_error.params,
+            occurred_at: deploy_error.occurred_at.to_rfc3339(),
+        }
+    }
+}
+
 // =============================== CONFIG INSTANCE ================================= //
 pub type ConfigInstanceID = String;
 
@@ -484,6 +516,7 @@ pub struct ConfigInstance {
     // fsm fields
     pub attempts: u32,
     pub cooldown_ends_at: DateTime<Utc>,
+    pub last_deploy_error: Option<DeployError>,
 }
 
 impl Default for ConfigInstance {
@@ -502,6 +535,7 @@ impl Default for ConfigInstance {
             config_type_id: format!("unknown-{}", Uuid::new_v4()),
             attempts: 0,
             cooldown_ends_at: DateTime::<Utc>::UNIX_EPOCH,
+            last_deploy_error: None,
         }
     }
 }
@@ -538,6 +572,7 @@ impl ConfigInstance {
             // fsm fields
             attempts: 0,
             cooldown_ends_at: DateTime::<Utc>::UNIX_EPOCH,
+            last_deploy_error: None,
         }
     }
 
@@ -563,6 +598,35 @@ impl ConfigInstance {
         }
     }
 
+    // the config instance as stored on the device (including the deployment fsm's
+    // attempts and cooldown)
+    pub fn to_local_sdk(
+        cfg_inst: ConfigInstance,
+        content: Option<serde_json::Value>,
+    ) -> openapi_server::models::LocalConfigInstance {
+        let status = Status::to_sdk(&cfg_inst.status());
+        openapi_server::models::LocalConfigInstance {
+            object: openapi_server::models::local_config_instance::Object::ConfigInstance,
+            id: cfg_inst.id,
+            target_status: TargetStatus::to_sdk(&cfg_inst.target_status),
+            status,
+            activity_status: ActivityStatus::to_sdk(&cfg_inst.activity_status),
+            error_status: ErrorStatus::to_sdk(&cfg_inst.error_status),
+            relative_filepath: cfg_inst.relative_filepath,
+            created_at: cfg_inst.created_at.to_rfc3339(),
+            updated_at: cfg_inst.updated_at.to_rfc3339(),
+            device_id: cfg_inst.device_id,
+            config_schema_id: cfg_inst.config_schema_id,
+            config_type_id: cfg_inst.config_type_id,
+            attempts: i32::try_from(cfg_inst.attempts).unwrap_or(i32::MAX),
+            cooldown_ends_at: cfg_inst.cooldown_ends_at.to_rfc3339(),
+            last_deploy_error: cfg_inst
+                .last_deploy_error
+                .map(|e| Box::new(DeployError::to_sdk(e))),
+            content,
+        }
+    }
+
     pub fn status(&self) -> Status {
         match self.error_status {
             ErrorStatus::None => match self.activity_status {
@@ -620,6 +684,7 @@ impl<'de> Deserialize<'de> for ConfigInstance {
 
             // optional fields
             patch_id: Option<String>,
+            last_deploy_error: Option<DeployError>,
         }
 
         let result = match DeserializeConfigInstance::deserialize(deserializer) {
@@ -663,6 +728,7 @@ impl<'de> Deserialize<'de> for ConfigInstance {
             config_type_id: result.config_type_id,
             attempts,
             cooldown_ends_at,
+            last_deploy_error: result.last_deploy_error,
         })
     }
 }
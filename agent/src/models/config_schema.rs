@@ -38,6 +38,22 @@ impl Default for ConfigSchema {
     }
 }
 
+impl ConfigSchema {
+    pub fn from_backend(backend_schema: openapi_client::models::ConfigSchema) -> ConfigSchema {
+        ConfigSchema {
+            id: backend_schema.id,
+            version: backend_schema.version,
+            digest: backend_schema.digest,
+            created_at: backend_schema.created_at,
+            created_by_id: Some(backend_schema.created_by_id),
+            config_type_id: backend_schema.config_type_id,
+            config_type_slug: backend_schema
+                .config_type
+                .map(|config_type| config_type.slug),
+        }
+    }
+}
+
 impl<'de> Deserialize<'de> for ConfigSchema {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
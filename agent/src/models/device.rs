@@ -2,6 +2,9 @@
 use crate::deserialize_error;
 use crate::utils::Mergeable;
 
+// standard crates
+use std::collections::VecDeque;
+
 // external crates
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
@@ -54,6 +57,17 @@ impl<'de> Deserialize<'de> for DeviceStatus {
     }
 }
 
+// the number of connection intervals kept in the device's connection history
+pub const CONNECTION_HISTORY_CAPACITY: usize = 100;
+
+// a connection to the mqtt broker. The disconnect time is missing while connected and
+// if the connection ended without it being recorded (e.g. the agent crashed).
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
+pub struct ConnectionInterval {
+    pub connected_at: DateTime<Utc>,
+    pub disconnected_at: Option<DateTime<Utc>>,
+}
+
 #[derive(Debug, Serialize, PartialEq, Eq, Clone)]
 pub struct Device {
     #[serde(rename = "device_id")]
@@ -66,6 +80,8 @@ pub struct Device {
     pub last_synced_at: DateTime<Utc>,
     pub last_connected_at: DateTime<Utc>,
     pub last_disconnected_at: DateTime<Utc>,
+    // oldest first
+    pub connection_history: VecDeque<ConnectionInterval>,
 }
 
 impl Default for Device {
@@ -80,6 +96,7 @@ impl Default for Device {
             last_synced_at: DateTime::<Utc>::UNIX_EPOCH,
             last_connected_at: DateTime::<Utc>::UNIX_EPOCH,
             last_disconnected_at: DateTime::<Utc>::UNIX_EPOCH,
+            connection_history: VecDeque::new(),
         }
     }
 }
@@ -102,6 +119,7 @@ impl<'de> Deserialize<'de> for Device {
             last_synced_at: Option<DateTime<Utc>>,
             last_connected_at: Option<DateTime<Utc>>,
             last_disconnected_at: Option<DateTime<Utc>>,
+            connection_history: Option<VecDeque<ConnectionInterval>>,
         }
 
         let result = match DeserializeAgent::deserialize(deserializer) {
@@ -142,6 +160,8 @@ impl<'de> Deserialize<'de> for Device {
                     default.last_disconnected_at
                 )
             }),
+            // device files written before the connection history was kept don't have it
+            connection_history: result.connection_history.unwrap_or_default(),
         })
     }
 }
@@ -168,9 +188,24 @@ impl Mergeable<Updates> for Device {
         }
         if let Some(last_connected_at) = updates.last_connected_at {
             self.last_connected_at = last_connected_at;
+            // a connection replacing one which never recorded its disconnect (e.g. a
+            // reconnect with a refreshed token) ends the previous interval
+            if let Some(interval) = self.connection_history.back_mut() {
+                interval.disconnected_at.get_or_insert(last_connected_at);
+            }
+            self.connection_history.push_back(ConnectionInterval {
+                connected_at: last_connected_at,
+                disconnected_at: None,
+            });
+            while self.connection_history.len() > CONNECTION_HISTORY_CAPACITY {
+                self.connection_history.pop_front();
+            }
         }
         if let Some(last_disconnected_at) = updates.last_disconnected_at {
             self.last_disconnected_at = last_disconnected_at;
+            if let Some(interval) = self.connection_history.back_mut() {
+                interval.disconnected_at.get_or_insert(last_disconnected_at);
+            }
         }
     }
 }
@@ -0,0 +1,86 @@
+// internal crates
+use crate::models::config_instance::ConfigInstance;
+use crate::mqtt::{client::MQTTClient, errors::*};
+use crate::trace;
+
+// external crates
+use chrono::{DateTime, Utc};
+use rumqttc::QoS;
+use serde::{Deserialize, Serialize};
+
+// ================================= MESSAGES ====================================== //
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LocalConfigMetadata {
+    pub config_instance_id: String,
+    pub config_schema_id: String,
+    pub config_type_id: String,
+    // unknown until the config schema has been cached
+    pub config_type_slug: Option<String>,
+    pub relative_filepath: String,
+    pub patch_id: Option<String>,
+    pub updated_at: DateTime<Utc>,
+}
+
+// the effective content of a config instance deployed to the device, published to the
+// local broker for the apps running on the device
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LocalConfig {
+    pub metadata: LocalConfigMetadata,
+    pub content: serde_json::Value,
+}
+
+impl LocalConfig {
+    pub fn new(
+        cfg_inst: &ConfigInstance,
+        config_type_slug: Option<String>,
+        content: serde_json::Value,
+    ) -> Self {
+        Self {
+            metadata: LocalConfigMetadata {
+                config_instance_id: cfg_inst.id.clone(),
+                config_schema_id: cfg_inst.config_schema_id.clone(),
+                config_type_id: cfg_inst.config_type_id.clone(),
+                config_type_slug,
+                relative_filepath: cfg_inst.relative_filepath.clone(),
+                patch_id: cfg_inst.patch_id.clone(),
+                updated_at: cfg_inst.updated_at,
+            },
+            content,
+        }
+    }
+}
+
+// =================================== TRAIT ======================================= //
+#[allow(async_fn_in_trait)]
+pub trait LocalBridgeExt {
+    // retained so that apps receive the config as soon as they subscribe
+    async fn publish_local_config(
+        &self,
+        topic: &str,
+        config: &LocalConfig,
+    ) -> Result<(), MQTTError>;
+    // clears the topic's retained config
+    async fn clear_local_config(&self, topic: &str) -> Result<(), MQTTError>;
+}
+
+impl LocalBridgeExt for MQTTClient {
+    async fn publish_local_config(
+        &self,
+        topic: &str,
+        config: &LocalConfig,
+    ) -> Result<(), MQTTError> {
+        let payload_bytes = serde_json::to_vec(config).map_err(|e| {
+            MQTTError::SerdeErr(Box::new(SerdeErr {
+                source: e,
+                trace: trace!(),
+            }))
+        })?;
+        self.publish(topic, QoS::AtLeastOnce, true, &payload_bytes)
+            .await
+    }
+
+    async fn clear_local_config(&self, topic: &str) -> Result<(), MQTTError> {
+        // an empty retained message removes the broker's retained message
+        self.publish(topic, QoS::AtLeastOnce, true, &[]).await
+    }
+}
@@ -1,27 +1,133 @@
 // standard crates
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
 use std::time::Duration;
 
 // internal crates
 use crate::mqtt::errors::*;
+use crate::network::pinning::find_pin_mismatch;
+use crate::storage::outbox::{OutboundMessage, Outbox};
 use crate::trace;
+use crate::utils::version_info;
 
 // external crates
+use bytes::Bytes;
 use chrono::{DateTime, Utc};
-use rumqttc::{AsyncClient, Event, EventLoop, MqttOptions, QoS, Transport};
+use rumqttc::{
+    tokio_rustls::rustls::ClientConfig, v5, AsyncClient, MqttOptions, QoS, TlsConfiguration,
+    Transport,
+};
+use serde::{Deserialize, Serialize};
 use tokio::time::timeout;
+use tracing::{debug, error, warn};
+
+// the user property carrying the agent's version on mqtt v5 connections and publishes
+pub const AGENT_VERSION_PROPERTY: &str = "agent_version";
 
 // ================================== OPTIONS ====================================== //
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Copy, Default, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ProtocolVersion {
+    #[default]
+    V3,
+    V5,
+}
+
+impl<'de> Deserialize<'de> for ProtocolVersion {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let default = ProtocolVersion::default();
+
+        let s = match String::deserialize(deserializer) {
+            Ok(s) => s,
+            Err(e) => {
+                error!("Error deserializing mqtt protocol version: {:?}", e);
+                return Ok(default);
+            }
+        };
+        match s.to_lowercase().as_str() {
+            "v3" | "3" | "3.1.1" => Ok(ProtocolVersion::V3),
+            "v5" | "5" => Ok(ProtocolVersion::V5),
+            _ => {
+                error!(
+                    "Invalid mqtt protocol version: {}. Setting to default: '{:?}'",
+                    s, default
+                );
+                Ok(default)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
 pub enum Protocol {
     TCP,
+    #[default]
     SSL,
+    // mqtt over secure websockets for networks which only allow https traffic
+    WSS,
 }
 
+impl Protocol {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "tcp" | "mqtt" => Some(Protocol::TCP),
+            "ssl" | "tls" | "mqtts" => Some(Protocol::SSL),
+            "wss" => Some(Protocol::WSS),
+            _ => None,
+        }
+    }
+
+    pub fn default_port(&self) -> u16 {
+        match self {
+            Protocol::TCP => 1883,
+            Protocol::SSL => 8883,
+            Protocol::WSS => 443,
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Protocol {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let default = Protocol::default();
+
+        let s = match String::deserialize(deserializer) {
+            Ok(s) => s,
+            Err(e) => {
+                error!("Error deserializing mqtt protocol: {:?}", e);
+                return Ok(default);
+            }
+        };
+        match Protocol::parse(&s) {
+            Some(protocol) => Ok(protocol),
+            None => {
+                error!(
+                    "Invalid mqtt protocol: {}. Setting to default: '{:?}'",
+                    s, default
+                );
+                Ok(default)
+            }
+        }
+    }
+}
+
+pub const DEFAULT_WEBSOCKET_PATH: &str = "/mqtt";
+
 #[derive(Debug, Clone)]
 pub struct ConnectAddress {
     pub protocol: Protocol,
     pub broker: String,
     pub port: u16,
+    // the websocket endpoint's path (websocket protocols only)
+    pub path: String,
 }
 
 impl ConnectAddress {
@@ -30,26 +136,51 @@ impl ConnectAddress {
             protocol,
             broker,
             port,
+            path: DEFAULT_WEBSOCKET_PATH.to_string(),
+        }
+    }
+
+    pub fn with_path(mut self, path: String) -> Self {
+        self.path = path;
+        self
+    }
+
+    // websocket connections are made to a url rather than a host
+    pub fn broker_addr(&self) -> String {
+        match self.protocol {
+            Protocol::TCP | Protocol::SSL => self.broker.clone(),
+            Protocol::WSS => format!(
+                "wss://{}:{}/{}",
+                self.broker,
+                self.port,
+                self.path.trim_start_matches('/')
+            ),
         }
     }
 }
 
 impl Default for ConnectAddress {
     fn default() -> Self {
-        Self {
-            protocol: Protocol::SSL,
-            broker: "mqtt.mirurobotics.com".to_string(),
-            port: 8883,
-        }
+        Self::new(Protocol::SSL, "mqtt.mirurobotics.com".to_string(), 8883)
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Clone)]
 pub struct Credentials {
     pub username: String,
     pub password: String,
 }
 
+// the password (the device's token) is never logged
+impl fmt::Debug for Credentials {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Credentials")
+            .field("username", &self.username)
+            .field("password", &"<redacted>")
+            .finish()
+    }
+}
+
 impl Credentials {
     pub fn new(username: String, password: String) -> Self {
         Self { username, password }
@@ -67,6 +198,8 @@ impl Default for Credentials {
 
 #[derive(Debug, Clone, Copy)]
 pub struct Timeouts {
+    // establishing the network connection with the broker
+    pub connect: Duration,
     pub publish: Duration,
     pub subscribe: Duration,
     pub unsubscribe: Duration,
@@ -76,6 +209,7 @@ pub struct Timeouts {
 impl Default for Timeouts {
     fn default() -> Self {
         Self {
+            connect: Duration::from_secs(5),
             publish: Duration::from_secs(3),
             subscribe: Duration::from_secs(3),
             unsubscribe: Duration::from_secs(3),
@@ -84,6 +218,20 @@ impl Default for Timeouts {
     }
 }
 
+// published by the broker on the client's behalf once the client disconnects without
+// sending a disconnect packet (e.g. the connection dropped)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LastWill {
+    pub topic: String,
+    pub payload: Vec<u8>,
+    pub qos: QoS,
+    pub retain: bool,
+}
+
+// a prebuilt TLS configuration (see network::tls::TLSOptions) used in place of the
+// system's root certificates for SSL connections
+pub type TLSConfig = Arc<ClientConfig>;
+
 #[derive(Debug, Clone)]
 pub struct Options {
     pub connect_address: ConnectAddress,
@@ -92,6 +240,22 @@ pub struct Options {
     pub keep_alive: Duration,
     pub timeouts: Timeouts,
     pub capacity: usize,
+    // the qos of the device's publishes and subscriptions
+    pub qos: QoS,
+    pub tls_config: Option<TLSConfig>,
+    pub version: ProtocolVersion,
+    // how long the broker keeps the session (and its subscriptions) after a disconnect
+    // (mqtt v5 only)
+    pub session_expiry: Duration,
+    // sent with the connect packet and every publish (mqtt v5 only)
+    pub user_properties: Vec<(String, String)>,
+    pub last_will: Option<LastWill>,
+    // where messages are queued while disconnected from the broker
+    pub outbox: Option<Arc<Outbox>>,
+}
+
+fn default_user_properties() -> Vec<(String, String)> {
+    vec![(AGENT_VERSION_PROPERTY.to_string(), version_info().version)]
 }
 
 impl Options {
@@ -110,6 +274,13 @@ impl Options {
             keep_alive,
             timeouts,
             capacity,
+            qos: QoS::AtLeastOnce,
+            tls_config: None,
+            version: ProtocolVersion::default(),
+            session_expiry: Duration::from_secs(5 * 60),
+            user_properties: default_user_properties(),
+            last_will: None,
+            outbox: None,
         }
     }
 
@@ -145,6 +316,13 @@ impl OptionsBuilder {
                 keep_alive: Duration::from_secs(20),
                 timeouts: Timeouts::default(),
                 capacity: 64,
+                qos: QoS::AtLeastOnce,
+                tls_config: None,
+                version: ProtocolVersion::default(),
+                session_expiry: Duration::from_secs(5 * 60),
+                user_properties: default_user_properties(),
+                last_will: None,
+                outbox: None,
             },
         }
     }
@@ -164,86 +342,662 @@ impl OptionsBuilder {
         self
     }
 
+    pub fn with_keep_alive(mut self, keep_alive: Duration) -> Self {
+        self.options.keep_alive = keep_alive;
+        self
+    }
+
     pub fn with_timeouts(mut self, timeouts: Timeouts) -> Self {
         self.options.timeouts = timeouts;
         self
     }
 
+    pub fn with_capacity(mut self, capacity: usize) -> Self {
+        self.options.capacity = capacity;
+        self
+    }
+
+    pub fn with_qos(mut self, qos: QoS) -> Self {
+        self.options.qos = qos;
+        self
+    }
+
+    pub fn with_tls_config(mut self, tls_config: Option<TLSConfig>) -> Self {
+        self.options.tls_config = tls_config;
+        self
+    }
+
+    pub fn with_version(mut self, version: ProtocolVersion) -> Self {
+        self.options.version = version;
+        self
+    }
+
+    pub fn with_session_expiry(mut self, session_expiry: Duration) -> Self {
+        self.options.session_expiry = session_expiry;
+        self
+    }
+
+    pub fn with_last_will(mut self, last_will: Option<LastWill>) -> Self {
+        self.options.last_will = last_will;
+        self
+    }
+
+    pub fn with_outbox(mut self, outbox: Option<Arc<Outbox>>) -> Self {
+        self.options.outbox = outbox;
+        self
+    }
+
     pub fn build(self) -> Options {
         self.options
     }
 }
 
+// =================================== EVENTS ====================================== //
+// the mqtt v5 properties of a publish, which are dropped on mqtt v3 connections
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Properties {
+    pub response_topic: Option<String>,
+    pub correlation_data: Option<Vec<u8>>,
+    pub user_properties: Vec<(String, String)>,
+}
+
+// where the reply to a request is published (the request's response topic and
+// correlation data)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReplyTo {
+    pub topic: String,
+    pub correlation_data: Option<Vec<u8>>,
+}
+
+impl ReplyTo {
+    pub fn properties(&self) -> Properties {
+        Properties {
+            correlation_data: self.correlation_data.clone(),
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Message {
+    pub topic: String,
+    pub payload: Vec<u8>,
+    pub properties: Properties,
+}
+
+impl Message {
+    pub fn reply_to(&self) -> Option<ReplyTo> {
+        let topic = self.properties.response_topic.clone()?;
+        Some(ReplyTo {
+            topic,
+            correlation_data: self.properties.correlation_data.clone(),
+        })
+    }
+}
+
+// the events (of either protocol version) the agent acts on
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Event {
+    // the broker accepted the connection
+    Connected { session_present: bool },
+    // the broker closed the connection
+    Disconnected,
+    Message(Message),
+    // the event loop wrote a publish to the broker (the packet id is 0 for qos 0)
+    Published { pkid: u16 },
+    // the broker acknowledged the publish with the packet id
+    Acked { pkid: u16 },
+    Other,
+}
+
+impl From<rumqttc::Event> for Event {
+    fn from(event: rumqttc::Event) -> Self {
+        let incoming = match event {
+            rumqttc::Event::Incoming(incoming) => incoming,
+            rumqttc::Event::Outgoing(rumqttc::Outgoing::Publish(pkid)) => {
+                return Event::Published { pkid };
+            }
+            rumqttc::Event::Outgoing(_) => return Event::Other,
+        };
+        match incoming {
+            rumqttc::Incoming::ConnAck(connack)
+                if connack.code == rumqttc::ConnectReturnCode::Success =>
+            {
+                Event::Connected {
+                    session_present: connack.session_present,
+                }
+            }
+            rumqttc::Incoming::Disconnect => Event::Disconnected,
+            rumqttc::Incoming::PubAck(puback) => Event::Acked { pkid: puback.pkid },
+            rumqttc::Incoming::PubComp(pubcomp) => Event::Acked { pkid: pubcomp.pkid },
+            rumqttc::Incoming::Publish(publish) => Event::Message(Message {
+                topic: publish.topic,
+                payload: publish.payload.to_vec(),
+                properties: Properties::default(),
+            }),
+            _ => Event::Other,
+        }
+    }
+}
+
+impl From<v5::Event> for Event {
+    fn from(event: v5::Event) -> Self {
+        let incoming = match event {
+            v5::Event::Incoming(incoming) => incoming,
+            v5::Event::Outgoing(rumqttc::Outgoing::Publish(pkid)) => {
+                return Event::Published { pkid };
+            }
+            v5::Event::Outgoing(_) => return Event::Other,
+        };
+        match incoming {
+            v5::Incoming::ConnAck(connack)
+                if connack.code == v5::mqttbytes::v5::ConnectReturnCode::Success =>
+            {
+                Event::Connected {
+                    session_present: connack.session_present,
+                }
+            }
+            v5::Incoming::Disconnect(_) => Event::Disconnected,
+            v5::Incoming::PubAck(puback) => Event::Acked { pkid: puback.pkid },
+            v5::Incoming::PubComp(pubcomp) => Event::Acked { pkid: pubcomp.pkid },
+            v5::Incoming::Publish(publish) => {
+                let properties = publish.properties.unwrap_or_default();
+                Event::Message(Message {
+                    topic: String::from_utf8_lossy(&publish.topic).to_string(),
+                    payload: publish.payload.to_vec(),
+                    properties: Properties {
+                        response_topic: properties.response_topic,
+                        correlation_data: properties.correlation_data.map(|data| data.to_vec()),
+                        user_properties: properties.user_properties,
+                    },
+                })
+            }
+            _ => Event::Other,
+        }
+    }
+}
+
+// ================================= DELIVERIES ===================================== //
+// the publishes which must reach the broker (e.g. queued events) are tracked by id
+// until the broker acknowledges them so that they're only removed from their queue
+// once delivered
+pub trait DeliveryExt {
+    // whether the tracked publish was handed to the client but not yet acknowledged
+    fn is_in_flight(&self, id: &str) -> bool;
+    // whether the broker acknowledged the tracked publish, forgetting it if so
+    fn take_acked(&self, id: &str) -> bool;
+}
+
+// matches the publishes handed to the event loop with the packet ids it assigns them.
+// The event loop writes publishes in the order they're requested, reporting each
+// write, so the oldest unwritten publish is always the one just written.
+#[derive(Debug, Default)]
+struct Deliveries {
+    // publishes handed to the event loop but not yet written, oldest first (None for
+    // untracked publishes)
+    unwritten: VecDeque<Option<String>>,
+    // written publishes awaiting an ack. The event loop may reuse a packet id before
+    // the ack of its previous publish is reported, so the publishes are queued.
+    unacked: HashMap<u16, VecDeque<Option<String>>>,
+    // packet ids the event loop resends after reconnecting
+    resending: HashSet<u16>,
+    // acknowledged publishes which haven't been taken yet
+    acked: HashSet<String>,
+}
+
+impl Deliveries {
+    // returns whether a tracked publish was acknowledged
+    fn record(&mut self, event: &Event) -> bool {
+        match event {
+            // unacknowledged publishes are resent with their packet ids
+            Event::Connected { .. } => {
+                self.resending = self.unacked.keys().copied().collect();
+                false
+            }
+            Event::Published { pkid } => {
+                if self.resending.remove(pkid) {
+                    return false;
+                }
+                let Some(id) = self.unwritten.pop_front() else {
+                    return false;
+                };
+                // qos 0 publishes aren't acknowledged
+                if *pkid == 0 {
+                    return id.map(|id| self.acked.insert(id)).unwrap_or(false);
+                }
+                self.unacked.entry(*pkid).or_default().push_back(id);
+                false
+            }
+            Event::Acked { pkid } => {
+                let Some(ids) = self.unacked.get_mut(pkid) else {
+                    return false;
+                };
+                let id = ids.pop_front().flatten();
+                if ids.is_empty() {
+                    self.unacked.remove(pkid);
+                }
+                id.map(|id| self.acked.insert(id)).unwrap_or(false)
+            }
+            _ => false,
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.unwritten.is_empty() && self.unacked.is_empty()
+    }
+
+    fn is_in_flight(&self, id: &str) -> bool {
+        let matches = |tracked: &Option<String>| tracked.as_deref() == Some(id);
+        self.unwritten.iter().any(matches) || self.unacked.values().flatten().any(matches)
+    }
+}
+
 // =================================== CLIENT ======================================= //
+// the maximum number of messages (or events) published at once. Publishing more than
+// the client's request capacity before the event loop is polled again would block so
+// the capacity must be at least the batch size.
+pub const PUBLISH_BATCH_SIZE: usize = 32;
+
+pub(crate) enum AnyClient {
+    V3(AsyncClient),
+    V5(v5::AsyncClient),
+}
+
+pub enum EventLoop {
+    V3(Box<rumqttc::EventLoop>),
+    V5(Box<v5::EventLoop>),
+}
+
 pub struct MQTTClient {
     pub created_at: DateTime<Utc>,
-    pub(crate) client: AsyncClient,
+    pub(crate) client: AnyClient,
     pub(crate) timeouts: Timeouts,
+    qos: QoS,
+    user_properties: Vec<(String, String)>,
+    outbox: Option<Arc<Outbox>>,
+    // whether the client's event loop is connected to the broker (set by whoever polls
+    // the event loop)
+    connected: AtomicBool,
+    deliveries: Mutex<Deliveries>,
+    // held while handing a publish to the event loop so the publishes are tracked in
+    // the order they're requested
+    publish_lock: tokio::sync::Mutex<()>,
+}
+
+fn transport(options: &Options) -> Transport {
+    let tls_config = || match &options.tls_config {
+        Some(tls_config) => TlsConfiguration::Rustls(tls_config.clone()),
+        None => TlsConfiguration::default(),
+    };
+    match options.connect_address.protocol {
+        Protocol::TCP => Transport::Tcp,
+        Protocol::SSL => Transport::Tls(tls_config()),
+        Protocol::WSS => Transport::Wss(tls_config()),
+    }
+}
+
+fn to_v5_qos(qos: QoS) -> v5::mqttbytes::QoS {
+    match qos {
+        QoS::AtMostOnce => v5::mqttbytes::QoS::AtMostOnce,
+        QoS::AtLeastOnce => v5::mqttbytes::QoS::AtLeastOnce,
+        QoS::ExactlyOnce => v5::mqttbytes::QoS::ExactlyOnce,
+    }
 }
 
 impl MQTTClient {
     pub async fn new(options: &Options) -> (Self, EventLoop) {
+        let (client, eventloop) = match options.version {
+            ProtocolVersion::V3 => {
+                let (client, eventloop) = Self::new_v3(options);
+                (AnyClient::V3(client), EventLoop::V3(Box::new(eventloop)))
+            }
+            ProtocolVersion::V5 => {
+                let (client, eventloop) = Self::new_v5(options);
+                (AnyClient::V5(client), EventLoop::V5(Box::new(eventloop)))
+            }
+        };
+
+        (
+            Self {
+                created_at: Utc::now(),
+                client,
+                timeouts: options.timeouts,
+                qos: options.qos,
+                user_properties: options.user_properties.clone(),
+                outbox: options.outbox.clone(),
+                connected: AtomicBool::new(false),
+                deliveries: Mutex::new(Deliveries::default()),
+                publish_lock: tokio::sync::Mutex::new(()),
+            },
+            eventloop,
+        )
+    }
+
+    fn new_v3(options: &Options) -> (AsyncClient, rumqttc::EventLoop) {
         let mut mqtt_options = MqttOptions::new(
             &options.client_id,
-            &options.connect_address.broker,
+            options.connect_address.broker_addr(),
             options.connect_address.port,
         );
 
         mqtt_options.set_keep_alive(options.keep_alive);
         mqtt_options.set_credentials(&options.credentials.username, &options.credentials.password);
+        mqtt_options.set_transport(transport(options));
+        if let Some(last_will) = &options.last_will {
+            mqtt_options.set_last_will(rumqttc::LastWill::new(
+                &last_will.topic,
+                last_will.payload.clone(),
+                last_will.qos,
+                last_will.retain,
+            ));
+        }
+
+        let (client, mut eventloop) = AsyncClient::new(mqtt_options, options.capacity);
+        let mut network_options = eventloop.network_options();
+        network_options.set_connection_timeout(options.timeouts.connect.as_secs().max(1));
+        eventloop.set_network_options(network_options);
+        (client, eventloop)
+    }
+
+    fn new_v5(options: &Options) -> (v5::AsyncClient, v5::EventLoop) {
+        let mut mqtt_options = v5::MqttOptions::new(
+            &options.client_id,
+            options.connect_address.broker_addr(),
+            options.connect_address.port,
+        );
+
+        mqtt_options.set_keep_alive(options.keep_alive);
+        mqtt_options.set_credentials(&options.credentials.username, &options.credentials.password);
+        mqtt_options.set_transport(transport(options));
+        if let Some(last_will) = &options.last_will {
+            mqtt_options.set_last_will(v5::mqttbytes::v5::LastWill::new(
+                &last_will.topic,
+                last_will.payload.clone(),
+                to_v5_qos(last_will.qos),
+                last_will.retain,
+                None,
+            ));
+        }
+
+        // resume the previous session (and its subscriptions) if it hasn't expired
+        let mut connect_properties = v5::mqttbytes::v5::ConnectProperties::new();
+        connect_properties.session_expiry_interval =
+            Some(u32::try_from(options.session_expiry.as_secs()).unwrap_or(u32::MAX));
+        connect_properties.user_properties = options.user_properties.clone();
+        mqtt_options.set_clean_start(false);
+        mqtt_options.set_connect_properties(connect_properties);
+        mqtt_options.set_connection_timeout(options.timeouts.connect.as_secs().max(1));
+
+        v5::AsyncClient::new(mqtt_options, options.capacity)
+    }
+
+    pub async fn publish(
+        &self,
+        topic: &str,
+        qos: QoS,
+        retained: bool,
+        payload: &[u8],
+    ) -> Result<(), MQTTError> {
+        self.publish_with_properties(topic, qos, retained, payload, Properties::default())
+            .await
+    }
+
+    // the properties are dropped on mqtt v3 connections. If the client has an outbox,
+    // messages published while disconnected (or which fail to publish) are queued to be
+    // published once reconnected instead of being lost.
+    pub async fn publish_with_properties(
+        &self,
+        topic: &str,
+        qos: QoS,
+        retained: bool,
+        payload: &[u8],
+        properties: Properties,
+    ) -> Result<(), MQTTError> {
+        let Some(outbox) = &self.outbox else {
+            return self
+                .send_publish(None, topic, qos, retained, payload, properties)
+                .await;
+        };
+        let message = OutboundMessage::new(topic, qos, retained, payload, properties, outbox.ttl());
 
-        match options.connect_address.protocol {
-            Protocol::TCP => {
-                mqtt_options.set_transport(Transport::Tcp);
+        // queued messages are published first to preserve the order of the messages
+        let publish_err = if self.is_connected() && outbox.is_empty().await.unwrap_or(true) {
+            match self
+                .send_publish(
+                    None,
+                    topic,
+                    qos,
+                    retained,
+                    payload,
+                    message.properties.clone(),
+                )
+                .await
+            {
+                Ok(_) => return Ok(()),
+                Err(e) => Some(e),
             }
-            Protocol::SSL => {
-                mqtt_options.set_transport(Transport::Tls(Default::default()));
+        } else {
+            None
+        };
+
+        match outbox.push(message.clone()).await {
+            Ok(_) => {
+                debug!("queued message to {topic} until reconnected: {publish_err:?}");
+                Ok(())
+            }
+            Err(e) => {
+                error!("error queueing message to {topic}: {e:?}");
+                match publish_err {
+                    Some(publish_err) => Err(publish_err),
+                    None => {
+                        self.send_publish(None, topic, qos, retained, payload, message.properties)
+                            .await
+                    }
+                }
             }
         }
+    }
 
-        let (client, eventloop) = AsyncClient::new(mqtt_options, options.capacity);
+    // publishes the oldest queued messages in order, removing them from the outbox
+    // once the broker acknowledged them (see record_delivery). Messages awaiting an ack
+    // aren't published again while this client is connected. Returns whether acked
+    // messages were removed, in which case more messages may be ready to publish.
+    pub async fn publish_queued(&self) -> bool {
+        let Some(outbox) = &self.outbox else {
+            return false;
+        };
+        let messages = match outbox.peek(PUBLISH_BATCH_SIZE).await {
+            Ok(messages) => messages,
+            Err(e) => {
+                error!("error reading queued messages: {e:?}");
+                return false;
+            }
+        };
 
-        (
-            Self {
-                created_at: Utc::now(),
-                client,
-                timeouts: options.timeouts,
-            },
-            eventloop,
-        )
+        let (acked, unacked): (Vec<_>, Vec<_>) = messages
+            .into_iter()
+            .partition(|message| self.take_acked(&message.message_id));
+        let acked: Vec<String> = acked.into_iter().map(|m| m.message_id).collect();
+        if !acked.is_empty() {
+            debug!("delivered {} queued messages", acked.len());
+            if let Err(e) = outbox.remove(acked.clone()).await {
+                error!("error removing delivered messages from the outbox: {e:?}");
+                return false;
+            }
+        }
+
+        for message in unacked {
+            if self.is_in_flight(&message.message_id) {
+                continue;
+            }
+            if let Err(e) = self
+                .send_publish(
+                    Some(&message.message_id),
+                    &message.topic,
+                    message.qos(),
+                    message.retain,
+                    &message.payload,
+                    message.properties.clone(),
+                )
+                .await
+            {
+                // leave the rest of the messages queued for the next connection
+                warn!(
+                    "error publishing queued message to {}: {e:?}",
+                    message.topic
+                );
+                break;
+            }
+        }
+
+        !acked.is_empty()
     }
 
-    pub async fn publish(
+    // publishes the message without queueing it in the outbox, tracking its delivery
+    // under the id (see DeliveryExt)
+    pub async fn publish_tracked(
         &self,
+        id: &str,
         topic: &str,
         qos: QoS,
         retained: bool,
         payload: &[u8],
+        properties: Properties,
     ) -> Result<(), MQTTError> {
-        timeout(
-            self.timeouts.publish,
-            self.client.publish(topic, qos, retained, payload),
-        )
-        .await
-        .map_err(|_| {
-            MQTTError::TimeoutErr(Box::new(TimeoutErr {
-                msg: "Publish timeout".to_string(),
-                trace: trace!(),
-            }))
-        })?
-        .map_err(|e| {
-            MQTTError::PublishErr(Box::new(PublishErr {
-                source: e,
-                trace: trace!(),
-            }))
-        })?;
+        self.send_publish(Some(id), topic, qos, retained, payload, properties)
+            .await
+    }
+
+    // records the deliveries reported by the event loop, returning whether the broker
+    // acknowledged a tracked publish. Must be called with every polled event.
+    pub fn record_delivery(&self, event: &Event) -> bool {
+        self.deliveries().record(event)
+    }
+
+    // whether any publishes (tracked or not) are awaiting a write or an ack
+    pub fn has_in_flight(&self) -> bool {
+        !self.deliveries().is_empty()
+    }
+
+    // takes over the acknowledged publishes of the client this client replaces so they
+    // aren't published again (its in-flight publishes are republished by this client)
+    pub fn inherit_acked(&self, previous: &MQTTClient) {
+        let acked = std::mem::take(&mut previous.deliveries().acked);
+        self.deliveries().acked.extend(acked);
+    }
+
+    fn deliveries(&self) -> MutexGuard<'_, Deliveries> {
+        self.deliveries.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub fn outbox(&self) -> Option<&Arc<Outbox>> {
+        self.outbox.as_ref()
+    }
+
+    pub fn set_connected(&self, connected: bool) {
+        self.connected.store(connected, Ordering::Relaxed);
+    }
+
+    pub fn is_connected(&self) -> bool {
+        self.connected.load(Ordering::Relaxed)
+    }
+
+    // the qos of the device's publishes and subscriptions
+    pub fn qos(&self) -> QoS {
+        self.qos
+    }
+
+    async fn send_publish(
+        &self,
+        id: Option<&str>,
+        topic: &str,
+        qos: QoS,
+        retained: bool,
+        payload: &[u8],
+        properties: Properties,
+    ) -> Result<(), MQTTError> {
+        let _publishing = self.publish_lock.lock().await;
+        self.deliveries()
+            .unwritten
+            .push_back(id.map(str::to_string));
+        let result = self
+            .request_publish(topic, qos, retained, payload, properties)
+            .await;
+        // the event loop never received the publish
+        if result.is_err() {
+            self.deliveries().unwritten.pop_back();
+        }
+        result
+    }
+
+    async fn request_publish(
+        &self,
+        topic: &str,
+        qos: QoS,
+        retained: bool,
+        payload: &[u8],
+        properties: Properties,
+    ) -> Result<(), MQTTError> {
+        let result = match &self.client {
+            AnyClient::V3(client) => timeout(
+                self.timeouts.publish,
+                client.publish(topic, qos, retained, payload),
+            )
+            .await
+            .map(|result| result.map_err(|e| ClientError::V3(Box::new(e)))),
+            AnyClient::V5(client) => {
+                let mut user_properties = self.user_properties.clone();
+                user_properties.extend(properties.user_properties);
+                let properties = v5::mqttbytes::v5::PublishProperties {
+                    response_topic: properties.response_topic,
+                    correlation_data: properties.correlation_data.map(Bytes::from),
+                    user_properties,
+                    ..Default::default()
+                };
+                timeout(
+                    self.timeouts.publish,
+                    client.publish_with_properties(
+                        topic,
+                        to_v5_qos(qos),
+                        retained,
+                        payload.to_vec(),
+                        properties,
+                    ),
+                )
+                .await
+                .map(|result| result.map_err(|e| ClientError::V5(Box::new(e))))
+            }
+        };
+        result
+            .map_err(|_| {
+                MQTTError::TimeoutErr(Box::new(TimeoutErr {
+                    msg: "Publish timeout".to_string(),
+                    trace: trace!(),
+                }))
+            })?
+            .map_err(|e| {
+                MQTTError::PublishErr(Box::new(PublishErr {
+                    source: e,
+                    trace: trace!(),
+                }))
+            })?;
 
         Ok(())
     }
 
     pub async fn subscribe(&self, topic: &str, qos: QoS) -> Result<(), MQTTError> {
-        timeout(self.timeouts.subscribe, self.client.subscribe(topic, qos))
+        let result = match &self.client {
+            AnyClient::V3(client) => timeout(self.timeouts.subscribe, client.subscribe(topic, qos))
+                .await
+                .map(|result| result.map_err(|e| ClientError::V3(Box::new(e)))),
+            AnyClient::V5(client) => timeout(
+                self.timeouts.subscribe,
+                client.subscribe(topic, to_v5_qos(qos)),
+            )
             .await
+            .map(|result| result.map_err(|e| ClientError::V5(Box::new(e)))),
+        };
+        result
             .map_err(|_| {
                 MQTTError::TimeoutErr(Box::new(TimeoutErr {
                     msg: "Subscribe timeout".to_string(),
@@ -261,8 +1015,15 @@ impl MQTTClient {
     }
 
     pub async fn unsubscribe(&self, topic: &str) -> Result<(), MQTTError> {
-        timeout(self.timeouts.unsubscribe, self.client.unsubscribe(topic))
-            .await
+        let result = match &self.client {
+            AnyClient::V3(client) => timeout(self.timeouts.unsubscribe, client.unsubscribe(topic))
+                .await
+                .map(|result| result.map_err(|e| ClientError::V3(Box::new(e)))),
+            AnyClient::V5(client) => timeout(self.timeouts.unsubscribe, client.unsubscribe(topic))
+                .await
+                .map(|result| result.map_err(|e| ClientError::V5(Box::new(e)))),
+        };
+        result
             .map_err(|_| {
                 MQTTError::TimeoutErr(Box::new(TimeoutErr {
                     msg: "Unsubscribe timeout".to_string(),
@@ -280,8 +1041,15 @@ impl MQTTClient {
     }
 
     pub async fn disconnect(&self) -> Result<(), MQTTError> {
-        timeout(self.timeouts.disconnect, self.client.disconnect())
-            .await
+        let result = match &self.client {
+            AnyClient::V3(client) => timeout(self.timeouts.disconnect, client.disconnect())
+                .await
+                .map(|result| result.map_err(|e| ClientError::V3(Box::new(e)))),
+            AnyClient::V5(client) => timeout(self.timeouts.disconnect, client.disconnect())
+                .await
+                .map(|result| result.map_err(|e| ClientError::V5(Box::new(e)))),
+        };
+        result
             .map_err(|_| {
                 MQTTError::TimeoutErr(Box::new(TimeoutErr {
                     msg: "Disconnect timeout".to_string(),
@@ -299,34 +1067,113 @@ impl MQTTClient {
     }
 }
 
+impl DeliveryExt for MQTTClient {
+    fn is_in_flight(&self, id: &str) -> bool {
+        self.deliveries().is_in_flight(id)
+    }
+
+    fn take_acked(&self, id: &str) -> bool {
+        self.deliveries().acked.remove(id)
+    }
+}
+
 pub async fn poll(eventloop: &mut EventLoop) -> Result<Event, MQTTError> {
-    eventloop.poll().await.map_err(|e| {
-        match e {
-            // poor network connection errors
+    match eventloop {
+        EventLoop::V3(eventloop) => eventloop
+            .poll()
+            .await
+            .map(Event::from)
+            .map_err(|e| to_mqtt_error(ConnectionError::V3(e))),
+        EventLoop::V5(eventloop) => eventloop
+            .poll()
+            .await
+            .map(Event::from)
+            .map_err(|e| to_mqtt_error(ConnectionError::V5(e))),
+    }
+}
+
+fn to_mqtt_error(e: ConnectionError) -> MQTTError {
+    // checked first since a failed handshake is also an io error
+    if let Some(mismatch) = find_pin_mismatch(&e) {
+        return MQTTError::PinMismatchErr(Box::new(PinMismatchErr {
+            mismatch: mismatch.clone(),
+            source: e,
+            trace: trace!(),
+        }));
+    }
+    if is_network_connection_error(&e) {
+        return MQTTError::NetworkConnectionErr(Box::new(NetworkConnectionErr {
+            source: e,
+            trace: trace!(),
+        }));
+    }
+    if is_authentication_error(&e) {
+        return MQTTError::AuthenticationErr(Box::new(AuthenticationErr {
+            source: e,
+            trace: trace!(),
+        }));
+    }
+    MQTTError::PollErr(Box::new(PollErr {
+        source: e,
+        trace: trace!(),
+    }))
+}
+
+// poor network connections and brokers which are (temporarily) unavailable
+fn is_network_connection_error(e: &ConnectionError) -> bool {
+    use v5::mqttbytes::v5::{ConnectReturnCode, DisconnectReasonCode};
+
+    match e {
+        ConnectionError::V3(e) => matches!(
+            e,
             rumqttc::ConnectionError::NetworkTimeout
-            | rumqttc::ConnectionError::Io(_)
-            | rumqttc::ConnectionError::MqttState(rumqttc::StateError::AwaitPingResp)
-            | rumqttc::ConnectionError::FlushTimeout
-            | rumqttc::ConnectionError::NotConnAck(_) => {
-                MQTTError::NetworkConnectionErr(Box::new(NetworkConnectionErr {
-                    source: e,
-                    trace: trace!(),
-                }))
-            }
+                | rumqttc::ConnectionError::Io(_)
+                | rumqttc::ConnectionError::MqttState(rumqttc::StateError::AwaitPingResp)
+                | rumqttc::ConnectionError::FlushTimeout
+                | rumqttc::ConnectionError::NotConnAck(_)
+        ),
+        ConnectionError::V5(e) => matches!(
+            e,
+            v5::ConnectionError::Timeout(_)
+                | v5::ConnectionError::Io(_)
+                | v5::ConnectionError::MqttState(v5::StateError::AwaitPingResp)
+                | v5::ConnectionError::MqttState(v5::StateError::Io(_))
+                | v5::ConnectionError::NotConnAck(_)
+                | v5::ConnectionError::ConnectionRefused(
+                    ConnectReturnCode::ServerUnavailable
+                        | ConnectReturnCode::ServerBusy
+                        | ConnectReturnCode::UseAnotherServer
+                        | ConnectReturnCode::ServerMoved
+                )
+                | v5::ConnectionError::MqttState(v5::StateError::ServerDisconnect {
+                    reason_code: DisconnectReasonCode::ServerBusy
+                        | DisconnectReasonCode::ServerShuttingDown
+                        | DisconnectReasonCode::KeepAliveTimeout
+                        | DisconnectReasonCode::UseAnotherServer
+                        | DisconnectReasonCode::ServerMoved,
+                    ..
+                })
+        ),
+    }
+}
 
-            // mqtt broker rejected the authentication
-            rumqttc::ConnectionError::ConnectionRefused(_) => {
-                MQTTError::AuthenticationErr(Box::new(AuthenticationErr {
-                    source: e,
-                    trace: trace!(),
-                }))
-            }
+// the broker rejected the credentials (all mqtt v3 refusals are treated as such since
+// v3 return codes are too coarse to tell them apart)
+fn is_authentication_error(e: &ConnectionError) -> bool {
+    use v5::mqttbytes::v5::{ConnectReturnCode, DisconnectReasonCode};
 
-            // all other errors
-            _ => MQTTError::PollErr(Box::new(PollErr {
-                source: e,
-                trace: trace!(),
-            })),
-        }
-    })
+    match e {
+        ConnectionError::V3(e) => matches!(e, rumqttc::ConnectionError::ConnectionRefused(_)),
+        ConnectionError::V5(e) => matches!(
+            e,
+            v5::ConnectionError::ConnectionRefused(
+                ConnectReturnCode::BadUserNamePassword
+                    | ConnectReturnCode::NotAuthorized
+                    | ConnectReturnCode::BadAuthenticationMethod
+            ) | v5::ConnectionError::MqttState(v5::StateError::ServerDisconnect {
+                reason_code: DisconnectReasonCode::NotAuthorized,
+                ..
+            })
+        ),
+    }
 }
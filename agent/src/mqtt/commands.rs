@@ -0,0 +1,203 @@
+// standard library
+use std::fmt;
+
+// external crates
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+// ================================== COMMANDS ===================================== //
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CommandName {
+    SyncState,
+    DeployedInstances,
+    Logs,
+    DriftCheck,
+    Restart,
+}
+
+impl CommandName {
+    pub fn variants() -> Vec<CommandName> {
+        vec![
+            CommandName::SyncState,
+            CommandName::DeployedInstances,
+            CommandName::Logs,
+            CommandName::DriftCheck,
+            CommandName::Restart,
+        ]
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CommandName::SyncState => "sync_state",
+            CommandName::DeployedInstances => "deployed_instances",
+            CommandName::Logs => "logs",
+            CommandName::DriftCheck => "drift_check",
+            CommandName::Restart => "restart",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<CommandName> {
+        CommandName::variants()
+            .into_iter()
+            .find(|command| command.as_str() == name)
+    }
+}
+
+impl fmt::Display for CommandName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+// ================================== ENVELOPES ==================================== //
+// every command request is a json object of the form
+// { "message_id": "...", "params": { ... } } where the params are command specific
+// (and may be omitted for commands without any)
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CommandRequest {
+    pub message_id: String,
+    #[serde(default)]
+    pub params: serde_json::Value,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CommandErrorCode {
+    InvalidRequest,
+    Unauthorized,
+    RateLimited,
+    Failed,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CommandError {
+    pub code: CommandErrorCode,
+    pub message: String,
+}
+
+impl CommandError {
+    pub fn new(code: CommandErrorCode, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+
+    pub fn failed(e: impl fmt::Display) -> Self {
+        Self::new(CommandErrorCode::Failed, e.to_string())
+    }
+}
+
+// exactly one of the result and the error is set
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CommandResponse {
+    pub message_id: String,
+    pub command: CommandName,
+    pub timestamp: String,
+    pub result: Option<serde_json::Value>,
+    pub error: Option<CommandError>,
+}
+
+impl CommandResponse {
+    pub fn new(
+        message_id: String,
+        command: CommandName,
+        result: Result<serde_json::Value, CommandError>,
+    ) -> Self {
+        let (result, error) = match result {
+            Ok(result) => (Some(result), None),
+            Err(error) => (None, Some(error)),
+        };
+        Self {
+            message_id,
+            command,
+            timestamp: Utc::now().to_rfc3339(),
+            result,
+            error,
+        }
+    }
+}
+
+// ================================ SYNC STATE ===================================== //
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SyncStateParams {}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SyncStateResult {
+    pub last_attempted_sync_at: DateTime<Utc>,
+    pub last_synced_at: DateTime<Utc>,
+    pub cooldown_ends_at: DateTime<Utc>,
+    pub err_streak: u32,
+    pub in_cooldown: bool,
+}
+
+// ============================ DEPLOYED INSTANCES ================================= //
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeployedInstancesParams {}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeployedInstance {
+    pub id: String,
+    pub config_schema_id: String,
+    pub config_type_id: String,
+    pub relative_filepath: String,
+    pub patch_id: Option<String>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeployedInstancesResult {
+    pub config_instances: Vec<DeployedInstance>,
+}
+
+// =================================== LOGS ======================================== //
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LogsParams {
+    // defaults to 100 and is capped by the agent's maximum
+    pub lines: Option<usize>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LogsResult {
+    // the log file the lines were read from
+    pub file: String,
+    pub lines: Vec<String>,
+}
+
+// ================================ DRIFT CHECK ==================================== //
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DriftCheckParams {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DriftReason {
+    // the deployed file no longer exists
+    Missing,
+    // the deployed file's content differs from the config instance's content
+    Modified,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Drift {
+    pub config_instance_id: String,
+    pub relative_filepath: String,
+    pub reason: DriftReason,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DriftCheckResult {
+    // the number of deployed config instances checked
+    pub checked: usize,
+    pub drifted: Vec<Drift>,
+}
+
+// ================================== RESTART ====================================== //
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RestartParams {}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RestartResult {
+    // how long after the response the agent restarts
+    pub delay_ms: u64,
+}
@@ -1,37 +1,109 @@
 // internal crates
-use crate::mqtt::client::MQTTClient;
+use crate::mqtt::client::{LastWill, MQTTClient, Properties, ReplyTo};
 // use crate::mqtt::device::
 use crate::mqtt::{
+    commands::CommandResponse,
     errors::*,
-    topics::{device_ping, device_pong, device_sync},
+    events::ConfigInstanceEvent,
+    topics::{
+        device_command_response, device_commands, device_config_instance_events, device_ping,
+        device_pong, device_presence, device_sync,
+    },
 };
 use crate::trace;
+use crate::utils::version_info;
 
 // external crates
 use chrono::Utc;
 use rumqttc::QoS;
+use serde::{Deserialize, Serialize};
 
 pub type SyncDevice = openapi_client::models::SyncDevice;
 pub type Ping = openapi_client::models::Ping;
 pub type Pong = openapi_client::models::Pong;
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PresenceStatus {
+    Online,
+    Offline,
+}
+
+// retained on the device's presence topic: 'online' once connected and 'offline' (the
+// connection's last will) once the broker notices the connection dropped
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Presence {
+    pub status: PresenceStatus,
+    pub agent_version: String,
+    pub session_id: String,
+}
+
+impl Presence {
+    pub fn new(status: PresenceStatus, session_id: &str) -> Self {
+        Self {
+            status,
+            agent_version: version_info().version,
+            session_id: session_id.to_string(),
+        }
+    }
+
+    pub fn last_will(device_id: &str, session_id: &str) -> Result<LastWill, MQTTError> {
+        let payload = Self::new(PresenceStatus::Offline, session_id);
+        let payload_bytes = serde_json::to_vec(&payload).map_err(|e| {
+            MQTTError::SerdeErr(Box::new(SerdeErr {
+                source: e,
+                trace: trace!(),
+            }))
+        })?;
+        Ok(LastWill {
+            topic: device_presence(device_id),
+            payload: payload_bytes,
+            qos: QoS::AtLeastOnce,
+            retain: true,
+        })
+    }
+}
+
 // =================================== TRAIT ======================================= //
 #[allow(async_fn_in_trait)]
 pub trait DeviceExt {
     async fn subscribe_device_sync(&self, device_id: &str) -> Result<(), MQTTError>;
     async fn publish_device_sync(&self, device_id: &str) -> Result<(), MQTTError>;
     async fn subscribe_device_ping(&self, device_id: &str) -> Result<(), MQTTError>;
+    // published on the ping's response topic if it has one, otherwise on the device's
+    // pong topic
     async fn publish_device_pong(
         &self,
         device_id: &str,
         message_id: String,
+        reply_to: Option<&ReplyTo>,
+    ) -> Result<(), MQTTError>;
+    async fn subscribe_device_commands(&self, device_id: &str) -> Result<(), MQTTError>;
+    // published on the request's response topic if it has one, otherwise on the
+    // command's response topic
+    async fn publish_command_response(
+        &self,
+        device_id: &str,
+        response: &CommandResponse,
+        reply_to: Option<&ReplyTo>,
+    ) -> Result<(), MQTTError>;
+    async fn publish_config_instance_event(
+        &self,
+        device_id: &str,
+        event: &ConfigInstanceEvent,
+    ) -> Result<(), MQTTError>;
+    // retained so that it replaces the previous connection's last will
+    async fn publish_device_presence(
+        &self,
+        device_id: &str,
+        presence: &Presence,
     ) -> Result<(), MQTTError>;
 }
 
 impl DeviceExt for MQTTClient {
     async fn subscribe_device_sync(&self, device_id: &str) -> Result<(), MQTTError> {
         let topic = device_sync(device_id);
-        self.subscribe(&topic, QoS::AtLeastOnce).await
+        self.subscribe(&topic, self.qos()).await
     }
 
     async fn publish_device_sync(&self, device_id: &str) -> Result<(), MQTTError> {
@@ -43,21 +115,20 @@ impl DeviceExt for MQTTClient {
                 trace: trace!(),
             }))
         })?;
-        self.publish(&topic, QoS::AtLeastOnce, true, &payload_bytes)
-            .await
+        self.publish(&topic, self.qos(), true, &payload_bytes).await
     }
 
     async fn subscribe_device_ping(&self, device_id: &str) -> Result<(), MQTTError> {
         let topic = device_ping(device_id);
-        self.subscribe(&topic, QoS::AtLeastOnce).await
+        self.subscribe(&topic, self.qos()).await
     }
 
     async fn publish_device_pong(
         &self,
         device_id: &str,
         ping_message_id: String,
+        reply_to: Option<&ReplyTo>,
     ) -> Result<(), MQTTError> {
-        let topic = device_pong(device_id);
         let payload = Pong {
             message_id: ping_message_id,
             timestamp: Utc::now().to_rfc3339(),
@@ -68,7 +139,96 @@ impl DeviceExt for MQTTClient {
                 trace: trace!(),
             }))
         })?;
-        self.publish(&topic, QoS::AtLeastOnce, false, &payload_bytes)
-            .await
+        match reply_to {
+            Some(reply_to) => {
+                self.publish_with_properties(
+                    &reply_to.topic,
+                    self.qos(),
+                    false,
+                    &payload_bytes,
+                    reply_to.properties(),
+                )
+                .await
+            }
+            None => {
+                let topic = device_pong(device_id);
+                self.publish(&topic, self.qos(), false, &payload_bytes)
+                    .await
+            }
+        }
+    }
+
+    async fn subscribe_device_commands(&self, device_id: &str) -> Result<(), MQTTError> {
+        let topic = device_commands(device_id);
+        self.subscribe(&topic, self.qos()).await
+    }
+
+    async fn publish_command_response(
+        &self,
+        device_id: &str,
+        response: &CommandResponse,
+        reply_to: Option<&ReplyTo>,
+    ) -> Result<(), MQTTError> {
+        let payload_bytes = serde_json::to_vec(response).map_err(|e| {
+            MQTTError::SerdeErr(Box::new(SerdeErr {
+                source: e,
+                trace: trace!(),
+            }))
+        })?;
+        match reply_to {
+            Some(reply_to) => {
+                self.publish_with_properties(
+                    &reply_to.topic,
+                    self.qos(),
+                    false,
+                    &payload_bytes,
+                    reply_to.properties(),
+                )
+                .await
+            }
+            None => {
+                let topic = device_command_response(device_id, response.command);
+                self.publish(&topic, self.qos(), false, &payload_bytes)
+                    .await
+            }
+        }
+    }
+
+    async fn publish_config_instance_event(
+        &self,
+        device_id: &str,
+        event: &ConfigInstanceEvent,
+    ) -> Result<(), MQTTError> {
+        let topic = device_config_instance_events(device_id);
+        let payload_bytes = serde_json::to_vec(event).map_err(|e| {
+            MQTTError::SerdeErr(Box::new(SerdeErr {
+                source: e,
+                trace: trace!(),
+            }))
+        })?;
+        self.publish_tracked(
+            &event.event_id,
+            &topic,
+            self.qos(),
+            false,
+            &payload_bytes,
+            Properties::default(),
+        )
+        .await
+    }
+
+    async fn publish_device_presence(
+        &self,
+        device_id: &str,
+        presence: &Presence,
+    ) -> Result<(), MQTTError> {
+        let topic = device_presence(device_id);
+        let payload_bytes = serde_json::to_vec(presence).map_err(|e| {
+            MQTTError::SerdeErr(Box::new(SerdeErr {
+                source: e,
+                trace: trace!(),
+            }))
+        })?;
+        self.publish(&topic, self.qos(), true, &payload_bytes).await
     }
 }
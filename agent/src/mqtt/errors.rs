@@ -1,17 +1,143 @@
 // standard library
+use std::error::Error as StdError;
 use std::fmt;
 
 // internal crates
 use crate::errors::Trace;
 use crate::errors::{Code, HTTPCode, MiruError};
+use crate::network::pinning::PinMismatch;
 
 // external crates
+use rumqttc::v5;
+use serde_json::json;
 #[allow(unused_imports)]
 use tracing::{debug, error, info, trace, warn};
 
+// ================================ REASON CODES =================================== //
+// the reason code (mqtt v5 only) the broker rejected a request or closed the
+// connection with
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReasonCode {
+    // the packet which carried the reason code (e.g. "connack" or "disconnect")
+    pub packet: &'static str,
+    pub code: String,
+    // the broker's human readable reason, if given
+    pub reason: Option<String>,
+}
+
+impl ReasonCode {
+    fn new(packet: &'static str, code: impl fmt::Debug, reason: Option<String>) -> Self {
+        Self {
+            packet,
+            code: format!("{code:?}"),
+            reason,
+        }
+    }
+
+    fn params(&self) -> serde_json::Value {
+        json!({
+            "packet": self.packet,
+            "reason_code": self.code,
+            "reason": self.reason,
+        })
+    }
+}
+
+impl fmt::Display for ReasonCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} reason code {}", self.packet, self.code)?;
+        if let Some(reason) = &self.reason {
+            write!(f, " ({reason})")?;
+        }
+        Ok(())
+    }
+}
+
+// ============================ PROTOCOL VERSION ERRORS ============================ //
+// the connection errors of the mqtt v3 and v5 event loops
+#[derive(Debug)]
+pub enum ConnectionError {
+    V3(rumqttc::ConnectionError),
+    V5(v5::ConnectionError),
+}
+
+impl ConnectionError {
+    pub fn reason_code(&self) -> Option<ReasonCode> {
+        let ConnectionError::V5(e) = self else {
+            return None;
+        };
+        match e {
+            v5::ConnectionError::ConnectionRefused(code) => {
+                Some(ReasonCode::new("connack", code, None))
+            }
+            v5::ConnectionError::MqttState(state) => match state {
+                v5::StateError::ServerDisconnect {
+                    reason_code,
+                    reason_string,
+                } => Some(ReasonCode::new(
+                    "disconnect",
+                    reason_code,
+                    reason_string.clone(),
+                )),
+                v5::StateError::SubFail { reason } => Some(ReasonCode::new("suback", reason, None)),
+                v5::StateError::UnsubFail { reason } => {
+                    Some(ReasonCode::new("unsuback", reason, None))
+                }
+                v5::StateError::PubAckFail { reason } => {
+                    Some(ReasonCode::new("puback", reason, None))
+                }
+                v5::StateError::PubRecFail { reason } => {
+                    Some(ReasonCode::new("pubrec", reason, None))
+                }
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    fn params(&self) -> Option<serde_json::Value> {
+        self.reason_code().map(|reason_code| reason_code.params())
+    }
+}
+
+impl fmt::Display for ConnectionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConnectionError::V3(e) => write!(f, "{e}"),
+            ConnectionError::V5(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+impl StdError for ConnectionError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        match self {
+            ConnectionError::V3(e) => Some(e),
+            ConnectionError::V5(e) => Some(e),
+        }
+    }
+}
+
+// the errors of the mqtt v3 and v5 clients when queueing a request for the event loop
+#[derive(Debug)]
+pub enum ClientError {
+    V3(Box<rumqttc::ClientError>),
+    V5(Box<v5::ClientError>),
+}
+
+impl fmt::Display for ClientError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ClientError::V3(e) => write!(f, "{e}"),
+            ClientError::V5(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+// =================================== ERRORS ====================================== //
 #[derive(Debug)]
 pub struct AuthenticationErr {
-    pub source: rumqttc::ConnectionError,
+    pub source: ConnectionError,
     pub trace: Box<Trace>,
 }
 
@@ -29,7 +155,7 @@ impl MiruError for AuthenticationErr {
     }
 
     fn params(&self) -> Option<serde_json::Value> {
-        None
+        self.source.params()
     }
 }
 
@@ -41,7 +167,7 @@ impl fmt::Display for AuthenticationErr {
 
 #[derive(Debug)]
 pub struct NetworkConnectionErr {
-    pub source: rumqttc::ConnectionError,
+    pub source: ConnectionError,
     pub trace: Box<Trace>,
 }
 
@@ -59,7 +185,7 @@ impl MiruError for NetworkConnectionErr {
     }
 
     fn params(&self) -> Option<serde_json::Value> {
-        None
+        self.source.params()
     }
 }
 
@@ -69,9 +195,45 @@ impl fmt::Display for NetworkConnectionErr {
     }
 }
 
+#[derive(Debug)]
+pub struct PinMismatchErr {
+    pub mismatch: PinMismatch,
+    pub source: ConnectionError,
+    pub trace: Box<Trace>,
+}
+
+impl MiruError for PinMismatchErr {
+    fn code(&self) -> Code {
+        Code::TLSPinMismatch
+    }
+
+    fn http_status(&self) -> HTTPCode {
+        HTTPCode::BAD_GATEWAY
+    }
+
+    // not a network connection error so that reconnect attempts back off
+    fn is_network_connection_error(&self) -> bool {
+        false
+    }
+
+    fn params(&self) -> Option<serde_json::Value> {
+        None
+    }
+}
+
+impl fmt::Display for PinMismatchErr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Aborted connecting to MQTT broker since the connection may be intercepted: {}",
+            self.mismatch
+        )
+    }
+}
+
 #[derive(Debug)]
 pub struct PollErr {
-    pub source: rumqttc::ConnectionError,
+    pub source: ConnectionError,
     pub trace: Box<Trace>,
 }
 
@@ -89,7 +251,7 @@ impl MiruError for PollErr {
     }
 
     fn params(&self) -> Option<serde_json::Value> {
-        None
+        self.source.params()
     }
 }
 
@@ -131,7 +293,7 @@ impl fmt::Display for TimeoutErr {
 
 #[derive(Debug)]
 pub struct PublishErr {
-    pub source: rumqttc::ClientError,
+    pub source: ClientError,
     pub trace: Box<Trace>,
 }
 
@@ -227,6 +389,7 @@ impl fmt::Display for MockErr {
 pub enum MQTTError {
     AuthenticationErr(Box<AuthenticationErr>),
     NetworkConnectionErr(Box<NetworkConnectionErr>),
+    PinMismatchErr(Box<PinMismatchErr>),
     TimeoutErr(Box<TimeoutErr>),
     PollErr(Box<PollErr>),
     PublishErr(Box<PublishErr>),
@@ -240,6 +403,7 @@ macro_rules! forward_error_method {
         match $self {
             MQTTError::AuthenticationErr(e) => e.$method($($arg)?),
             MQTTError::NetworkConnectionErr(e) => e.$method($($arg)?),
+            MQTTError::PinMismatchErr(e) => e.$method($($arg)?),
             MQTTError::TimeoutErr(e) => e.$method($($arg)?),
             MQTTError::PollErr(e) => e.$method($($arg)?),
             MQTTError::PublishErr(e) => e.$method($($arg)?),
@@ -274,6 +438,15 @@ impl MiruError for MQTTError {
 }
 
 impl MQTTError {
+    pub fn reason_code(&self) -> Option<ReasonCode> {
+        match self {
+            MQTTError::AuthenticationErr(e) => e.source.reason_code(),
+            MQTTError::NetworkConnectionErr(e) => e.source.reason_code(),
+            MQTTError::PollErr(e) => e.source.reason_code(),
+            _ => None,
+        }
+    }
+
     pub fn is_authentication_error(&self) -> bool {
         match self {
             MQTTError::AuthenticationErr(_) => true,
@@ -0,0 +1,40 @@
+// internal crates
+use crate::models::config_instance::{ActivityStatus, ConfigInstance, ErrorStatus, TargetStatus};
+
+// external crates
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+// ============================= CONFIG INSTANCES ================================== //
+// published whenever the agent changes a config instance's activity or error status
+// so the backend doesn't have to wait for the next sync to learn of it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConfigInstanceEvent {
+    // unique per event so the backend can drop duplicate (qos 1) deliveries
+    pub event_id: String,
+    pub config_instance_id: String,
+    pub relative_filepath: String,
+    pub target_status: TargetStatus,
+    pub activity_status: ActivityStatus,
+    pub error_status: ErrorStatus,
+    pub attempts: u32,
+    pub cooldown_ends_at: DateTime<Utc>,
+    pub occurred_at: DateTime<Utc>,
+}
+
+impl ConfigInstanceEvent {
+    pub fn new(cfg_inst: &ConfigInstance) -> Self {
+        Self {
+            event_id: Uuid::new_v4().to_string(),
+            config_instance_id: cfg_inst.id.clone(),
+            relative_filepath: cfg_inst.relative_filepath.clone(),
+            target_status: cfg_inst.target_status,
+            activity_status: cfg_inst.activity_status,
+            error_status: cfg_inst.error_status,
+            attempts: cfg_inst.attempts,
+            cooldown_ends_at: cfg_inst.cooldown_ends_at,
+            occurred_at: Utc::now(),
+        }
+    }
+}
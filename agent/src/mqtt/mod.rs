@@ -1,4 +1,7 @@
+pub mod bridge;
 pub mod client;
+pub mod commands;
 pub mod device;
 pub mod errors;
+pub mod events;
 pub mod topics;
@@ -1,3 +1,6 @@
+// internal crates
+use crate::mqtt::commands::CommandName;
+
 const VERSION: &str = "v1";
 
 // device sync was the first topic we supported and we didn't use the /v1 prefix :(
@@ -12,10 +15,36 @@ pub fn device_pong(device_id: &str) -> String {
     format!("{VERSION}/resp/devices/{device_id}/pong")
 }
 
+// subscribes to every command (the last topic level being the command's name)
+pub fn device_commands(device_id: &str) -> String {
+    format!("{VERSION}/cmd/devices/{device_id}/commands/+")
+}
+pub fn device_command(device_id: &str, command: CommandName) -> String {
+    format!("{VERSION}/cmd/devices/{device_id}/commands/{command}")
+}
+pub fn device_command_response(device_id: &str, command: CommandName) -> String {
+    format!("{VERSION}/resp/devices/{device_id}/commands/{command}")
+}
+
+pub fn device_config_instance_events(device_id: &str) -> String {
+    format!("{VERSION}/evt/devices/{device_id}/config_instances")
+}
+
+// retained so the device's last known presence is always available
+pub fn device_presence(device_id: &str) -> String {
+    format!("{VERSION}/evt/devices/{device_id}/presence")
+}
+
+// the local broker's topic for a config type's deployed config
+pub fn local_config(topic_prefix: &str, config_type: &str) -> String {
+    format!("{}/{config_type}", topic_prefix.trim_end_matches('/'))
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum SubscriptionTopics {
     Sync,
     Ping,
+    Command(CommandName),
     Unknown,
 }
 
@@ -24,7 +53,14 @@ pub fn parse_subscription(device_id: &str, topic: &str) -> SubscriptionTopics {
         SubscriptionTopics::Sync
     } else if topic == device_ping(device_id) {
         SubscriptionTopics::Ping
+    } else if let Some(command) = parse_command(device_id, topic) {
+        SubscriptionTopics::Command(command)
     } else {
         SubscriptionTopics::Unknown
     }
 }
+
+fn parse_command(device_id: &str, topic: &str) -> Option<CommandName> {
+    let prefix = format!("{VERSION}/cmd/devices/{device_id}/commands/");
+    CommandName::parse(topic.strip_prefix(&prefix)?)
+}
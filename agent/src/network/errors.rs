@@ -0,0 +1,314 @@
+// standard library
+use std::fmt;
+
+// internal crates
+use crate::errors::{Code, HTTPCode, MiruError, Trace};
+use crate::filesys::errors::FileSysErr;
+
+// external crates
+#[allow(unused_imports)]
+use tracing::{debug, error, info, trace, warn};
+
+#[derive(Debug)]
+pub struct InvalidCertErr {
+    pub path: String,
+    pub msg: String,
+    pub trace: Box<Trace>,
+}
+
+impl MiruError for InvalidCertErr {
+    fn code(&self) -> Code {
+        Code::InternalServerError
+    }
+
+    fn http_status(&self) -> HTTPCode {
+        HTTPCode::INTERNAL_SERVER_ERROR
+    }
+
+    fn is_network_connection_error(&self) -> bool {
+        false
+    }
+
+    fn params(&self) -> Option<serde_json::Value> {
+        None
+    }
+}
+
+impl fmt::Display for InvalidCertErr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Invalid certificate file '{}': {}", self.path, self.msg)
+    }
+}
+
+#[derive(Debug)]
+pub struct IncompleteClientCertErr {
+    pub msg: String,
+    pub trace: Box<Trace>,
+}
+
+impl MiruError for IncompleteClientCertErr {
+    fn code(&self) -> Code {
+        Code::InternalServerError
+    }
+
+    fn http_status(&self) -> HTTPCode {
+        HTTPCode::INTERNAL_SERVER_ERROR
+    }
+
+    fn is_network_connection_error(&self) -> bool {
+        false
+    }
+
+    fn params(&self) -> Option<serde_json::Value> {
+        None
+    }
+}
+
+impl fmt::Display for IncompleteClientCertErr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Incomplete client certificate settings: {}", self.msg)
+    }
+}
+
+#[derive(Debug)]
+pub struct InvalidPinsErr {
+    pub endpoint: String,
+    pub msg: String,
+    pub trace: Box<Trace>,
+}
+
+impl MiruError for InvalidPinsErr {
+    fn code(&self) -> Code {
+        Code::InternalServerError
+    }
+
+    fn http_status(&self) -> HTTPCode {
+        HTTPCode::INTERNAL_SERVER_ERROR
+    }
+
+    fn is_network_connection_error(&self) -> bool {
+        false
+    }
+
+    fn params(&self) -> Option<serde_json::Value> {
+        None
+    }
+}
+
+impl fmt::Display for InvalidPinsErr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Invalid {} pins: {}", self.endpoint, self.msg)
+    }
+}
+
+#[derive(Debug)]
+pub struct NetworkFileSysErr {
+    pub source: FileSysErr,
+    pub trace: Box<Trace>,
+}
+
+impl MiruError for NetworkFileSysErr {
+    fn code(&self) -> Code {
+        self.source.code()
+    }
+
+    fn http_status(&self) -> HTTPCode {
+        self.source.http_status()
+    }
+
+    fn is_network_connection_error(&self) -> bool {
+        self.source.is_network_connection_error()
+    }
+
+    fn params(&self) -> Option<serde_json::Value> {
+        self.source.params()
+    }
+}
+
+impl fmt::Display for NetworkFileSysErr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "File system error: {}", self.source)
+    }
+}
+
+#[derive(Debug)]
+pub struct InvalidProxyErr {
+    pub source: reqwest::Error,
+    pub trace: Box<Trace>,
+}
+
+impl MiruError for InvalidProxyErr {
+    fn code(&self) -> Code {
+        Code::InternalServerError
+    }
+
+    fn http_status(&self) -> HTTPCode {
+        HTTPCode::INTERNAL_SERVER_ERROR
+    }
+
+    fn is_network_connection_error(&self) -> bool {
+        false
+    }
+
+    fn params(&self) -> Option<serde_json::Value> {
+        None
+    }
+}
+
+impl fmt::Display for InvalidProxyErr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Invalid proxy settings: {}", self.source)
+    }
+}
+
+#[derive(Debug)]
+pub struct BuildClientErr {
+    pub source: reqwest::Error,
+    pub trace: Box<Trace>,
+}
+
+impl MiruError for BuildClientErr {
+    fn code(&self) -> Code {
+        Code::InternalServerError
+    }
+
+    fn http_status(&self) -> HTTPCode {
+        HTTPCode::INTERNAL_SERVER_ERROR
+    }
+
+    fn is_network_connection_error(&self) -> bool {
+        false
+    }
+
+    fn params(&self) -> Option<serde_json::Value> {
+        None
+    }
+}
+
+impl fmt::Display for BuildClientErr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Failed to build http client: {}", self.source)
+    }
+}
+
+#[derive(Debug)]
+pub struct RustlsErr {
+    // the http client and the mqtt client use different versions of rustls
+    pub msg: String,
+    pub trace: Box<Trace>,
+}
+
+impl MiruError for RustlsErr {
+    fn code(&self) -> Code {
+        Code::InternalServerError
+    }
+
+    fn http_status(&self) -> HTTPCode {
+        HTTPCode::INTERNAL_SERVER_ERROR
+    }
+
+    fn is_network_connection_error(&self) -> bool {
+        false
+    }
+
+    fn params(&self) -> Option<serde_json::Value> {
+        None
+    }
+}
+
+impl fmt::Display for RustlsErr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Failed to build TLS configuration: {}", self.msg)
+    }
+}
+
+#[derive(Debug)]
+pub struct ConvertKeyErr {
+    pub path: String,
+    pub source: openssl::error::ErrorStack,
+    pub trace: Box<Trace>,
+}
+
+impl MiruError for ConvertKeyErr {
+    fn code(&self) -> Code {
+        Code::InternalServerError
+    }
+
+    fn http_status(&self) -> HTTPCode {
+        HTTPCode::INTERNAL_SERVER_ERROR
+    }
+
+    fn is_network_connection_error(&self) -> bool {
+        false
+    }
+
+    fn params(&self) -> Option<serde_json::Value> {
+        None
+    }
+}
+
+impl fmt::Display for ConvertKeyErr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Failed to read client key '{}' as a PEM private key: {}",
+            self.path, self.source
+        )
+    }
+}
+
+#[derive(Debug)]
+pub enum NetworkErr {
+    // crate errors
+    InvalidCertErr(Box<InvalidCertErr>),
+    IncompleteClientCertErr(Box<IncompleteClientCertErr>),
+    InvalidPinsErr(Box<InvalidPinsErr>),
+
+    // internal crate errors
+    FileSysErr(Box<NetworkFileSysErr>),
+
+    // external crate errors
+    InvalidProxyErr(Box<InvalidProxyErr>),
+    BuildClientErr(Box<BuildClientErr>),
+    RustlsErr(Box<RustlsErr>),
+    ConvertKeyErr(Box<ConvertKeyErr>),
+}
+
+macro_rules! forward_error_method {
+    ($self:ident, $method:ident $(, $arg:expr)?) => {
+        match $self {
+            Self::InvalidCertErr(e) => e.$method($($arg)?),
+            Self::IncompleteClientCertErr(e) => e.$method($($arg)?),
+            Self::InvalidPinsErr(e) => e.$method($($arg)?),
+            Self::FileSysErr(e) => e.$method($($arg)?),
+            Self::InvalidProxyErr(e) => e.$method($($arg)?),
+            Self::BuildClientErr(e) => e.$method($($arg)?),
+            Self::RustlsErr(e) => e.$method($($arg)?),
+            Self::ConvertKeyErr(e) => e.$method($($arg)?),
+        }
+    };
+}
+
+impl fmt::Display for NetworkErr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        forward_error_method!(self, fmt, f)
+    }
+}
+
+impl MiruError for NetworkErr {
+    fn code(&self) -> Code {
+        forward_error_method!(self, code)
+    }
+
+    fn http_status(&self) -> HTTPCode {
+        forward_error_method!(self, http_status)
+    }
+
+    fn is_network_connection_error(&self) -> bool {
+        forward_error_method!(self, is_network_connection_error)
+    }
+
+    fn params(&self) -> Option<serde_json::Value> {
+        forward_error_method!(self, params)
+    }
+}
@@ -0,0 +1,164 @@
+// standard library
+use std::sync::Mutex;
+
+// external crates
+use tokio::time::{Duration, Instant};
+#[allow(unused_imports)]
+use tracing::{debug, error, info, warn};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FailoverOptions {
+    // the number of consecutive failures of the active endpoint before failing over
+    pub failure_threshold: u32,
+    // how long to stick with a fallback endpoint before trying the primary again
+    pub fail_back_after: Duration,
+}
+
+impl Default for FailoverOptions {
+    fn default() -> Self {
+        Self {
+            failure_threshold: 2,
+            fail_back_after: Duration::from_secs(10 * 60),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EndpointStatus {
+    pub active: String,
+    pub primary: String,
+    pub endpoints: Vec<String>,
+    // how long until the primary is tried again (only while failed over)
+    pub fail_back_in: Option<Duration>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Active {
+    index: usize,
+    failures: u32,
+    since: Instant,
+}
+
+// An ordered list of interchangeable endpoints (backend urls or mqtt broker hosts),
+// the first of which is the primary. The active endpoint is sticky: it's only replaced
+// by the next endpoint in the list once it fails repeatedly, except that the primary is
+// tried again once the fail back period has elapsed.
+#[derive(Debug)]
+pub struct Endpoints {
+    endpoints: Vec<String>,
+    options: FailoverOptions,
+    active: Mutex<Active>,
+}
+
+impl Endpoints {
+    pub fn new(primary: &str, fallbacks: &[String], options: FailoverOptions) -> Self {
+        let mut endpoints = vec![primary.to_string()];
+        for fallback in fallbacks.iter() {
+            if !endpoints.contains(fallback) {
+                endpoints.push(fallback.to_string());
+            }
+        }
+        Self {
+            endpoints,
+            options,
+            active: Mutex::new(Active {
+                index: 0,
+                failures: 0,
+                since: Instant::now(),
+            }),
+        }
+    }
+
+    pub fn single(endpoint: &str) -> Self {
+        Self::new(endpoint, &[], FailoverOptions::default())
+    }
+
+    pub fn endpoints(&self) -> &[String] {
+        &self.endpoints
+    }
+
+    pub fn primary(&self) -> &str {
+        &self.endpoints[0]
+    }
+
+    pub fn options(&self) -> FailoverOptions {
+        self.options
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Active> {
+        // the state is always valid so a poisoned lock is safe to reuse
+        self.active.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    // the endpoint requests should be sent to, which fails back to the primary once the
+    // fail back period has elapsed
+    pub fn active(&self) -> String {
+        let mut active = self.lock();
+        if active.index != 0 && active.since.elapsed() >= self.options.fail_back_after {
+            info!(
+                "failing back from {} to the primary endpoint {}",
+                self.endpoints[active.index],
+                self.primary()
+            );
+            *active = Active {
+                index: 0,
+                failures: 0,
+                since: Instant::now(),
+            };
+        }
+        self.endpoints[active.index].clone()
+    }
+
+    // the configured endpoint which the url belongs to, if any
+    pub fn endpoint_of(&self, url: &str) -> Option<&str> {
+        self.endpoints
+            .iter()
+            .filter(|endpoint| url.starts_with(endpoint.as_str()))
+            .max_by_key(|endpoint| endpoint.len())
+            .map(|endpoint| endpoint.as_str())
+    }
+
+    pub fn record_success(&self, endpoint: &str) {
+        let mut active = self.lock();
+        if self.endpoints[active.index] == endpoint {
+            active.failures = 0;
+        }
+    }
+
+    // returns the (possibly new) active endpoint. Failures of an endpoint which is no
+    // longer active (e.g. from requests sent before failing over) are ignored.
+    pub fn record_failure(&self, endpoint: &str) -> String {
+        let mut active = self.lock();
+        if self.endpoints[active.index] != endpoint {
+            return self.endpoints[active.index].clone();
+        }
+        active.failures = active.failures.saturating_add(1);
+        if active.failures >= self.options.failure_threshold && self.endpoints.len() > 1 {
+            let next = (active.index + 1) % self.endpoints.len();
+            warn!(
+                "failing over from {endpoint} to {} after {} consecutive failures",
+                self.endpoints[next], active.failures
+            );
+            *active = Active {
+                index: next,
+                failures: 0,
+                since: Instant::now(),
+            };
+        }
+        self.endpoints[active.index].clone()
+    }
+
+    pub fn status(&self) -> EndpointStatus {
+        let active = *self.lock();
+        EndpointStatus {
+            active: self.endpoints[active.index].clone(),
+            primary: self.primary().to_string(),
+            endpoints: self.endpoints.clone(),
+            fail_back_in: (active.index != 0).then(|| {
+                self.options
+                    .fail_back_after
+                    .saturating_sub(active.since.elapsed())
+            }),
+        }
+    }
+}
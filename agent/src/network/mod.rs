@@ -0,0 +1,6 @@
+pub mod errors;
+pub mod failover;
+pub mod options;
+pub mod pinning;
+pub mod proxy;
+pub mod tls;
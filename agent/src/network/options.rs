@@ -0,0 +1,24 @@
+// internal crates
+use crate::network::{errors::NetworkErr, proxy::ProxyOptions, tls::TLSOptions};
+use crate::storage::settings::Settings;
+
+// outbound connection options for the backend (http) and the mqtt broker. The proxy is
+// only used by the http client.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct NetworkOptions {
+    pub proxy: Option<ProxyOptions>,
+    pub tls: TLSOptions,
+}
+
+impl NetworkOptions {
+    pub async fn load(settings: &Settings) -> Result<Self, NetworkErr> {
+        Ok(Self {
+            proxy: ProxyOptions::from_settings(&settings.proxy),
+            tls: TLSOptions::load(&settings.tls).await?,
+        })
+    }
+
+    pub fn is_default(&self) -> bool {
+        self.proxy.is_none() && self.tls.is_default()
+    }
+}
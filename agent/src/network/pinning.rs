@@ -0,0 +1,254 @@
+// standard library
+use std::cell::RefCell;
+use std::error::Error as StdError;
+use std::fmt;
+use std::io;
+
+// internal crates
+use crate::crypt::base64;
+use crate::network::errors::{InvalidPinsErr, NetworkErr};
+use crate::trace;
+
+// external crates
+use openssl::x509::X509;
+use rustls::pki_types::{CertificateDer, TrustAnchor, UnixTime};
+use sha2::{Digest, Sha256};
+
+pub const PIN_PREFIX: &str = "sha256/";
+
+// the sha256 digest of a certificate's DER encoded SubjectPublicKeyInfo, written as
+// "sha256/<base64>" (the same format as curl's --pinnedpubkey and HPKP)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Pin([u8; 32]);
+
+impl Pin {
+    pub fn parse(pin: &str) -> Option<Self> {
+        let encoded = pin.trim().strip_prefix(PIN_PREFIX)?;
+        let digest = base64::decode_bytes_standard(encoded).ok()?;
+        Some(Self(digest.try_into().ok()?))
+    }
+
+    // returns None if the certificate or its public key can't be parsed
+    pub fn from_cert_der(cert: &[u8]) -> Option<Self> {
+        let spki = X509::from_der(cert)
+            .and_then(|cert| cert.public_key())
+            .and_then(|key| key.public_key_to_der())
+            .ok()?;
+        Some(Self::from_spki_der(&spki))
+    }
+
+    pub fn from_spki_der(spki: &[u8]) -> Self {
+        Self(Sha256::digest(spki).into())
+    }
+
+    // trust anchors only hold the contents of the SubjectPublicKeyInfo SEQUENCE
+    fn from_anchor(anchor: &TrustAnchor<'_>) -> Self {
+        let contents = anchor.subject_public_key_info.as_ref();
+        let mut spki = vec![0x30];
+        match contents.len() {
+            len if len < 0x80 => spki.push(len as u8),
+            len => {
+                let len = (len as u32).to_be_bytes();
+                let len = &len[len.iter().take_while(|b| **b == 0).count()..];
+                spki.push(0x80 | len.len() as u8);
+                spki.extend_from_slice(len);
+            }
+        }
+        spki.extend_from_slice(contents);
+        Self::from_spki_der(&spki)
+    }
+}
+
+impl fmt::Display for Pin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{PIN_PREFIX}{}", base64::encode_bytes_standard(&self.0))
+    }
+}
+
+// The pins one endpoint's certificate chain is checked against. A chain matches if
+// the public key of any certificate on a verified path from the server's certificate
+// to a trusted root is pinned, so either the server's key or the key of an issuing
+// certificate authority may be pinned. Certificates the server presents which aren't
+// part of the path are ignored since anyone can attach a copy of a pinned certificate.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PinSet {
+    endpoint: String,
+    pins: Vec<Pin>,
+}
+
+impl PinSet {
+    // returns None when no pins are configured, i.e. pinning is disabled for the
+    // endpoint. At least two distinct pins are required since a single pin leaves no
+    // way to rotate the pinned key without locking the agent out.
+    pub fn new(endpoint: &str, pins: &[String]) -> Result<Option<Self>, NetworkErr> {
+        if pins.is_empty() {
+            return Ok(None);
+        }
+        let to_err = |msg: String| {
+            NetworkErr::InvalidPinsErr(Box::new(InvalidPinsErr {
+                endpoint: endpoint.to_string(),
+                msg,
+                trace: trace!(),
+            }))
+        };
+
+        let mut parsed: Vec<Pin> = Vec::with_capacity(pins.len());
+        for pin in pins.iter() {
+            let pin = Pin::parse(pin).ok_or_else(|| {
+                to_err(format!(
+                    "'{pin}' is not a base64 encoded sha256 digest prefixed with '{PIN_PREFIX}'"
+                ))
+            })?;
+            if !parsed.contains(&pin) {
+                parsed.push(pin);
+            }
+        }
+        if parsed.len() < 2 {
+            return Err(to_err(
+                "a backup pin is required so that the pinned key can be rotated".to_string(),
+            ));
+        }
+
+        Ok(Some(Self {
+            endpoint: endpoint.to_string(),
+            pins: parsed,
+        }))
+    }
+
+    pub fn endpoint(&self) -> &str {
+        &self.endpoint
+    }
+
+    pub fn pins(&self) -> &[Pin] {
+        &self.pins
+    }
+
+    // Searches the paths from the server's certificate to the trusted roots for one
+    // which contains a pinned key. The chain is expected to have been verified already
+    // (including the server's name) so any other verification failure is reported as a
+    // mismatch of the keys on the paths found so far.
+    pub fn check(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        intermediates: &[CertificateDer<'_>],
+        roots: &[TrustAnchor<'_>],
+        now: UnixTime,
+    ) -> Result<(), PinMismatch> {
+        let presented = RefCell::new(Vec::new());
+        let verify_path = |path: &webpki::VerifiedPath<'_>| {
+            let mut keys = vec![Pin::from_spki_der(
+                &path.end_entity().subject_public_key_info(),
+            )];
+            keys.extend(
+                path.intermediate_certificates()
+                    .map(|cert| Pin::from_spki_der(&cert.subject_public_key_info())),
+            );
+            keys.push(Pin::from_anchor(path.anchor()));
+            if keys.iter().any(|key| self.pins.contains(key)) {
+                return Ok(());
+            }
+            let mut presented = presented.borrow_mut();
+            for key in keys {
+                if !presented.contains(&key) {
+                    presented.push(key);
+                }
+            }
+            // not fatal so the remaining paths are still searched
+            Err(webpki::Error::UnknownIssuer)
+        };
+
+        let verified = webpki::EndEntityCert::try_from(end_entity).and_then(|cert| {
+            cert.verify_for_usage(
+                webpki::ALL_VERIFICATION_ALGS,
+                roots,
+                intermediates,
+                now,
+                webpki::KeyUsage::server_auth(),
+                None,
+                Some(&verify_path),
+            )
+            .map(|_| ())
+        });
+        if verified.is_ok() {
+            return Ok(());
+        }
+
+        let mut presented = presented.into_inner();
+        if presented.is_empty() {
+            presented.extend(Pin::from_cert_der(end_entity));
+        }
+        Err(PinMismatch {
+            endpoint: self.endpoint.clone(),
+            presented,
+        })
+    }
+}
+
+// returned by the TLS certificate verifier when a certificate chain doesn't match the
+// pins, which is then found again in the connection error (see find_pin_mismatch)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PinMismatch {
+    pub endpoint: String,
+    pub presented: Vec<Pin>,
+}
+
+impl fmt::Display for PinMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let presented = self
+            .presented
+            .iter()
+            .map(|pin| pin.to_string())
+            .collect::<Vec<_>>()
+            .join(", ");
+        write!(
+            f,
+            "none of the public keys presented by the {} match its pins (presented: [{presented}])",
+            self.endpoint
+        )
+    }
+}
+
+impl StdError for PinMismatch {}
+
+// Searches an error and its sources for a pin mismatch. The mismatch is wrapped in a
+// rustls error (which doesn't expose it as a source) inside an io error (whose source
+// skips the wrapped error) so both are unwrapped explicitly.
+pub fn find_pin_mismatch<'a>(error: &'a (dyn StdError + 'static)) -> Option<&'a PinMismatch> {
+    let mut next = Some(error);
+    while let Some(error) = next {
+        if let Some(mismatch) = error.downcast_ref::<PinMismatch>() {
+            return Some(mismatch);
+        }
+        if let Some(inner) = unwrap_rustls_other(error) {
+            return find_pin_mismatch(inner);
+        }
+        if let Some(inner) = error.downcast_ref::<io::Error>().and_then(|e| e.get_ref()) {
+            if let Some(mismatch) = find_pin_mismatch(inner) {
+                return Some(mismatch);
+            }
+        }
+        next = error.source();
+    }
+    None
+}
+
+// the http client (rustls) and the mqtt client (rumqttc's rustls) use different
+// versions of rustls
+fn unwrap_rustls_other<'a>(
+    error: &'a (dyn StdError + 'static),
+) -> Option<&'a (dyn StdError + 'static)> {
+    use rumqttc::tokio_rustls::rustls as mqtt_rustls;
+
+    if let Some(rustls::Error::InvalidCertificate(rustls::CertificateError::Other(other))) =
+        error.downcast_ref::<rustls::Error>()
+    {
+        return Some(other.0.as_ref());
+    }
+    if let Some(mqtt_rustls::Error::InvalidCertificate(mqtt_rustls::CertificateError::Other(
+        other,
+    ))) = error.downcast_ref::<mqtt_rustls::Error>()
+    {
+        return Some(other.0.as_ref());
+    }
+    None
+}
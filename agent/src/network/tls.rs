@@ -0,0 +1,337 @@
+// standard library
+use std::fmt;
+use std::sync::Arc;
+
+// internal crates
+use crate::filesys::file::File;
+use crate::network::errors::{
+    BuildClientErr, ConvertKeyErr, IncompleteClientCertErr, InvalidCertErr, NetworkErr,
+    NetworkFileSysErr, RustlsErr,
+};
+use crate::network::pinning::PinSet;
+use crate::storage::settings;
+use crate::trace;
+
+// external crates
+use openssl::pkey::PKey;
+use rumqttc::tokio_rustls::rustls::{
+    pki_types::{pem::PemObject, CertificateDer},
+    ClientConfig,
+};
+use tracing::{debug, error};
+
+#[derive(Clone, PartialEq, Eq)]
+pub struct ClientIdentity {
+    pub cert_pem: Vec<u8>,
+    // always a pkcs#8 pem since that is the only format native-tls accepts
+    pub key_pem: Vec<u8>,
+}
+
+// never print the private key
+impl fmt::Debug for ClientIdentity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ClientIdentity")
+            .field("cert_pem", &format!("{} bytes", self.cert_pem.len()))
+            .field("key_pem", &"<redacted>")
+            .finish()
+    }
+}
+
+// TLS options shared by the http client and the mqtt client. The certificate
+// authorities are trusted in addition to the system's root certificates while the pins
+// (if any) restrict which of the trusted certificates each endpoint may present.
+#[derive(Clone, Default, PartialEq, Eq)]
+pub struct TLSOptions {
+    pub ca_certs: Vec<Vec<u8>>,
+    pub client_identity: Option<ClientIdentity>,
+    pub backend_pins: Option<PinSet>,
+    pub mqtt_broker_pins: Option<PinSet>,
+}
+
+impl fmt::Debug for TLSOptions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TLSOptions")
+            .field("ca_certs", &self.ca_certs.len())
+            .field("client_identity", &self.client_identity)
+            .field("backend_pins", &self.backend_pins)
+            .field("mqtt_broker_pins", &self.mqtt_broker_pins)
+            .finish()
+    }
+}
+
+impl TLSOptions {
+    pub async fn load(settings: &settings::TLS) -> Result<Self, NetworkErr> {
+        let mut ca_certs = Vec::with_capacity(settings.ca_cert_files.len());
+        for path in settings.ca_cert_files.iter() {
+            let pem = read_file(path).await?;
+            parse_certs(&pem, path)?;
+            ca_certs.push(pem);
+        }
+
+        let client_identity = match (&settings.client_cert_file, &settings.client_key_file) {
+            (Some(cert_path), Some(key_path)) => {
+                let cert_pem = read_file(cert_path).await?;
+                parse_certs(&cert_pem, cert_path)?;
+                let key_pem = to_pkcs8_pem(&read_file(key_path).await?, key_path)?;
+                Some(ClientIdentity { cert_pem, key_pem })
+            }
+            (None, None) => None,
+            (Some(_), None) => {
+                return Err(NetworkErr::IncompleteClientCertErr(Box::new(
+                    IncompleteClientCertErr {
+                        msg: "a client certificate was given without a client key".to_string(),
+                        trace: trace!(),
+                    },
+                )));
+            }
+            (None, Some(_)) => {
+                return Err(NetworkErr::IncompleteClientCertErr(Box::new(
+                    IncompleteClientCertErr {
+                        msg: "a client key was given without a client certificate".to_string(),
+                        trace: trace!(),
+                    },
+                )));
+            }
+        };
+
+        Ok(Self {
+            ca_certs,
+            client_identity,
+            backend_pins: PinSet::new("backend", &settings.backend_pins)?,
+            mqtt_broker_pins: PinSet::new("mqtt broker", &settings.mqtt_broker_pins)?,
+        })
+    }
+
+    pub fn is_default(&self) -> bool {
+        self.ca_certs.is_empty()
+            && self.client_identity.is_none()
+            && self.backend_pins.is_none()
+            && self.mqtt_broker_pins.is_none()
+    }
+
+    // native-tls has no way to inspect the server's certificate chain during the
+    // handshake so rustls is used instead when the backend is pinned
+    pub fn apply_to_reqwest(
+        &self,
+        mut builder: reqwest::ClientBuilder,
+    ) -> Result<reqwest::ClientBuilder, NetworkErr> {
+        if let Some(pins) = &self.backend_pins {
+            let config = http_rustls::client_config(self, Some(pins))?;
+            return Ok(builder.use_preconfigured_tls(config));
+        }
+        let to_err = |e| {
+            NetworkErr::BuildClientErr(Box::new(BuildClientErr {
+                source: e,
+                trace: trace!(),
+            }))
+        };
+        for pem in self.ca_certs.iter() {
+            for cert in reqwest::Certificate::from_pem_bundle(pem).map_err(to_err)? {
+                builder = builder.add_root_certificate(cert);
+            }
+        }
+        if let Some(identity) = &self.client_identity {
+            let identity = reqwest::Identity::from_pkcs8_pem(&identity.cert_pem, &identity.key_pem)
+                .map_err(to_err)?;
+            builder = builder.identity(identity);
+        }
+        Ok(builder)
+    }
+
+    // builds the rustls configuration used by the mqtt client's TLS transport
+    pub fn rustls_client_config(&self) -> Result<Arc<ClientConfig>, NetworkErr> {
+        mqtt_rustls::client_config(self, self.mqtt_broker_pins.as_ref()).map(Arc::new)
+    }
+}
+
+// The http client and the mqtt client depend on different versions of rustls whose
+// apis are (for our purposes) identical, so the configuration is built by the same
+// code expanded once per version.
+macro_rules! rustls_client_config {
+    () => {
+        use super::*;
+        use rustls::client::danger::{
+            HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier,
+        };
+        use rustls::client::WebPkiServerVerifier;
+        use rustls::pki_types::{CertificateDer, PrivateKeyDer, ServerName, TrustAnchor, UnixTime};
+        use rustls::{DigitallySignedStruct, RootCertStore, SignatureScheme};
+
+        // verifies the chain as usual before checking the pins against the certificates
+        // of a verified path (see PinSet::check)
+        #[derive(Debug)]
+        struct PinnedVerifier {
+            pins: PinSet,
+            roots: Vec<TrustAnchor<'static>>,
+            inner: Arc<WebPkiServerVerifier>,
+        }
+
+        impl ServerCertVerifier for PinnedVerifier {
+            fn verify_server_cert(
+                &self,
+                end_entity: &CertificateDer<'_>,
+                intermediates: &[CertificateDer<'_>],
+                server_name: &ServerName<'_>,
+                ocsp_response: &[u8],
+                now: UnixTime,
+            ) -> Result<ServerCertVerified, rustls::Error> {
+                let verified = self.inner.verify_server_cert(
+                    end_entity,
+                    intermediates,
+                    server_name,
+                    ocsp_response,
+                    now,
+                )?;
+                if let Err(mismatch) = self.pins.check(end_entity, intermediates, &self.roots, now)
+                {
+                    error!("TLS pin mismatch for {server_name:?}: {mismatch}");
+                    return Err(rustls::Error::InvalidCertificate(
+                        rustls::CertificateError::Other(rustls::OtherError(Arc::new(mismatch))),
+                    ));
+                }
+                Ok(verified)
+            }
+
+            fn verify_tls12_signature(
+                &self,
+                message: &[u8],
+                cert: &CertificateDer<'_>,
+                dss: &DigitallySignedStruct,
+            ) -> Result<HandshakeSignatureValid, rustls::Error> {
+                self.inner.verify_tls12_signature(message, cert, dss)
+            }
+
+            fn verify_tls13_signature(
+                &self,
+                message: &[u8],
+                cert: &CertificateDer<'_>,
+                dss: &DigitallySignedStruct,
+            ) -> Result<HandshakeSignatureValid, rustls::Error> {
+                self.inner.verify_tls13_signature(message, cert, dss)
+            }
+
+            fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+                self.inner.supported_verify_schemes()
+            }
+        }
+
+        pub fn client_config(
+            tls: &TLSOptions,
+            pins: Option<&PinSet>,
+        ) -> Result<rustls::ClientConfig, NetworkErr> {
+            let mut root_store = RootCertStore::empty();
+            match rustls_native_certs::load_native_certs() {
+                Ok(certs) => {
+                    let (added, ignored) = root_store.add_parsable_certificates(certs);
+                    debug!("loaded {added} system root certificates ({ignored} ignored)");
+                }
+                Err(e) => {
+                    error!("unable to load the system's root certificates: {e}");
+                }
+            }
+            for pem in tls.ca_certs.iter() {
+                for cert in parse_certs(pem, "ca certificate")? {
+                    root_store.add(cert).map_err(rustls_err)?;
+                }
+            }
+
+            let provider = Arc::new(rustls::crypto::ring::default_provider());
+            let builder = rustls::ClientConfig::builder_with_provider(provider.clone())
+                .with_safe_default_protocol_versions()
+                .map_err(rustls_err)?;
+            let builder = match pins {
+                Some(pins) => {
+                    let roots = root_store.roots.clone();
+                    let inner =
+                        WebPkiServerVerifier::builder_with_provider(Arc::new(root_store), provider)
+                            .build()
+                            .map_err(rustls_err)?;
+                    builder
+                        .dangerous()
+                        .with_custom_certificate_verifier(Arc::new(PinnedVerifier {
+                            pins: pins.clone(),
+                            roots,
+                            inner,
+                        }))
+                }
+                None => builder.with_root_certificates(root_store),
+            };
+
+            match &tls.client_identity {
+                Some(identity) => {
+                    let certs = parse_certs(&identity.cert_pem, "client certificate")?;
+                    let key = PrivateKeyDer::from_pem_slice(&identity.key_pem).map_err(|e| {
+                        NetworkErr::InvalidCertErr(Box::new(InvalidCertErr {
+                            path: "client key".to_string(),
+                            msg: e.to_string(),
+                            trace: trace!(),
+                        }))
+                    })?;
+                    builder
+                        .with_client_auth_cert(certs, key)
+                        .map_err(rustls_err)
+                }
+                None => Ok(builder.with_no_client_auth()),
+            }
+        }
+    };
+}
+
+mod http_rustls {
+    rustls_client_config!();
+}
+
+mod mqtt_rustls {
+    use rumqttc::tokio_rustls::rustls;
+    rustls_client_config!();
+}
+
+fn rustls_err(e: impl fmt::Display) -> NetworkErr {
+    NetworkErr::RustlsErr(Box::new(RustlsErr {
+        msg: e.to_string(),
+        trace: trace!(),
+    }))
+}
+
+async fn read_file(path: &str) -> Result<Vec<u8>, NetworkErr> {
+    File::new(path).read_bytes().await.map_err(|e| {
+        NetworkErr::FileSysErr(Box::new(NetworkFileSysErr {
+            source: e,
+            trace: trace!(),
+        }))
+    })
+}
+
+fn parse_certs(pem: &[u8], path: &str) -> Result<Vec<CertificateDer<'static>>, NetworkErr> {
+    let certs = CertificateDer::pem_slice_iter(pem)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| {
+            NetworkErr::InvalidCertErr(Box::new(InvalidCertErr {
+                path: path.to_string(),
+                msg: e.to_string(),
+                trace: trace!(),
+            }))
+        })?;
+    if certs.is_empty() {
+        return Err(NetworkErr::InvalidCertErr(Box::new(InvalidCertErr {
+            path: path.to_string(),
+            msg: "no PEM encoded certificates found".to_string(),
+            trace: trace!(),
+        })));
+    }
+    Ok(certs)
+}
+
+// accepts pkcs#1, sec1 and pkcs#8 encoded keys
+fn to_pkcs8_pem(pem: &[u8], path: &str) -> Result<Vec<u8>, NetworkErr> {
+    let to_err = |e| {
+        NetworkErr::ConvertKeyErr(Box::new(ConvertKeyErr {
+            path: path.to_string(),
+            source: e,
+            trace: trace!(),
+        }))
+    };
+    PKey::private_key_from_pem(pem)
+        .and_then(|key| key.private_key_to_pem_pkcs8())
+        .map_err(to_err)
+}
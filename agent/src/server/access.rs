@@ -0,0 +1,327 @@
+// standard library
+use std::fmt;
+use std::sync::Arc;
+
+// internal crates
+use crate::errors::MiruError;
+use crate::server::errors::{
+    AccessDeniedErr, InsufficientTokenScopeErr, InvalidAPITokenErr, ServerErr,
+};
+use crate::server::handlers::to_error_response;
+use crate::server::tokens::{APITokens, TokenScope};
+use crate::trace;
+
+// external crates
+use axum::{
+    extract::{connect_info::Connected, ConnectInfo, Query, Request, State},
+    http::{header, Method},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    serve::IncomingStream,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use tokio::net::UnixListener;
+use tracing::{error, warn};
+
+pub const ROOT_UID: u32 = 0;
+
+// ============================== PEER CREDENTIALS ============================== //
+// the identity of the process on the other end of the unix socket, as reported by
+// the kernel (SO_PEERCRED)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PeerCredentials {
+    pub uid: u32,
+    pub gid: u32,
+    pub pid: Option<i32>,
+}
+
+impl PeerCredentials {
+    // used when the kernel fails to report the peer's credentials so that the peer
+    // matches no uid or gid in the access policy
+    pub fn unknown() -> Self {
+        Self {
+            uid: u32::MAX,
+            gid: u32::MAX,
+            pid: None,
+        }
+    }
+
+    pub fn is_root(&self) -> bool {
+        self.uid == ROOT_UID
+    }
+}
+
+impl fmt::Display for PeerCredentials {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.pid {
+            Some(pid) => write!(f, "peer (uid={}, gid={}, pid={})", self.uid, self.gid, pid),
+            None => write!(f, "peer (uid={}, gid={})", self.uid, self.gid),
+        }
+    }
+}
+
+impl Connected<IncomingStream<'_, UnixListener>> for PeerCredentials {
+    fn connect_info(stream: IncomingStream<'_, UnixListener>) -> Self {
+        match stream.io().peer_cred() {
+            Ok(cred) => Self {
+                uid: cred.uid(),
+                gid: cred.gid(),
+                pid: cred.pid(),
+            },
+            Err(e) => {
+                error!("Failed to read the peer credentials of a socket connection: {e}");
+                Self::unknown()
+            }
+        }
+    }
+}
+
+// ================================ ACCESS POLICY =============================== //
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct AccessRule {
+    // the endpoints this rule applies to, e.g. "POST /v1/device/sync", "/v1/device/*"
+    // or "*"; a pattern without a method applies to every method
+    pub endpoints: Vec<String>,
+    // the peers granted access to the endpoints
+    pub uids: Vec<u32>,
+    pub gids: Vec<u32>,
+    // when set, the peers may only request the config types with these slugs (and only
+    // from the endpoints scoped to a config type)
+    pub config_type_slugs: Option<Vec<String>>,
+}
+
+impl AccessRule {
+    pub fn applies_to(&self, method: &str, path: &str) -> bool {
+        self.endpoints
+            .iter()
+            .any(|pattern| endpoint_matches(pattern, method, path))
+    }
+
+    fn grants(&self, peer: &PeerCredentials, config_type_slug: Option<&str>) -> bool {
+        if !self.uids.contains(&peer.uid) && !self.gids.contains(&peer.gid) {
+            return false;
+        }
+        match (&self.config_type_slugs, config_type_slug) {
+            (None, _) => true,
+            (Some(slugs), Some(slug)) => slugs.iter().any(|s| s == slug),
+            (Some(_), None) => false,
+        }
+    }
+}
+
+fn endpoint_matches(pattern: &str, method: &str, path: &str) -> bool {
+    let pattern = pattern.trim();
+    let path_pattern = match pattern.split_once(char::is_whitespace) {
+        Some((pattern_method, path_pattern)) => {
+            if !pattern_method.eq_ignore_ascii_case(method) {
+                return false;
+            }
+            path_pattern.trim()
+        }
+        None => pattern,
+    };
+    if path_pattern == "*" {
+        return true;
+    }
+    match path_pattern.strip_suffix('*') {
+        Some(prefix) => path.starts_with(prefix),
+        None => path_pattern.trim_end_matches('/') == path.trim_end_matches('/'),
+    }
+}
+
+// the only endpoints which are scoped to the config type in their query. Other
+// endpoints (e.g. listing config instances) ignore the config type so rules
+// restricted to config types never grant access to them.
+const CONFIG_TYPE_SCOPED_PATHS: [&str; 2] = [
+    "/v1/config_instances/deployed",
+    "/v1/config_schemas/validate",
+];
+
+fn is_config_type_scoped(path: &str) -> bool {
+    CONFIG_TYPE_SCOPED_PATHS.contains(&path.trim_end_matches('/'))
+}
+
+// endpoints which change the state of the device or expose its settings and logs.
+// Unless configured otherwise, only root may call them.
+const ROOT_ONLY_ENDPOINTS: [&str; 3] = [
+    "POST /v1/device/sync",
+    "GET /v1/device/export",
+    "GET /v1/diagnostics",
+];
+
+// the rules used when the settings don't configure any
+pub fn default_rules() -> Vec<AccessRule> {
+    vec![AccessRule {
+        endpoints: ROOT_ONLY_ENDPOINTS.iter().map(|e| e.to_string()).collect(),
+        // a rule granting no peers limits the endpoints to root
+        ..AccessRule::default()
+    }]
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AccessPolicy {
+    pub rules: Vec<AccessRule>,
+}
+
+impl Default for AccessPolicy {
+    fn default() -> Self {
+        Self::new(default_rules())
+    }
+}
+
+impl AccessPolicy {
+    pub fn new(rules: Vec<AccessRule>) -> Self {
+        Self { rules }
+    }
+
+    // whether any rule restricts peers to config types, in which case the other
+    // endpoints can't be left open without exposing every config type
+    fn is_config_type_restricted(&self) -> bool {
+        self.rules
+            .iter()
+            .any(|rule| rule.config_type_slugs.is_some())
+    }
+
+    // root may call every endpoint and endpoints without a rule are open to every
+    // peer that can connect to the socket (unless the policy restricts peers to
+    // config types, see is_config_type_restricted); otherwise at least one of the
+    // rules for the endpoint must grant the peer access
+    pub fn is_authorized(
+        &self,
+        peer: &PeerCredentials,
+        method: &str,
+        path: &str,
+        config_type_slug: Option<&str>,
+    ) -> bool {
+        if peer.is_root() {
+            return true;
+        }
+        let mut rules = self
+            .rules
+            .iter()
+            .filter(|rule| rule.applies_to(method, path))
+            .peekable();
+        if rules.peek().is_none() {
+            return !self.is_config_type_restricted();
+        }
+        let config_type_slug = config_type_slug.filter(|_| is_config_type_scoped(path));
+        rules.any(|rule| rule.grants(peer, config_type_slug))
+    }
+
+    pub fn authorize(
+        &self,
+        peer: &PeerCredentials,
+        method: &str,
+        path: &str,
+        config_type_slug: Option<&str>,
+    ) -> Result<(), AccessDeniedErr> {
+        if self.is_authorized(peer, method, path, config_type_slug) {
+            return Ok(());
+        }
+        Err(AccessDeniedErr {
+            peer: *peer,
+            method: method.to_string(),
+            path: path.to_string(),
+            trace: trace!(),
+        })
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ConfigTypeSlugQuery {
+    config_type_slug: Option<String>,
+}
+
+// ================================= MIDDLEWARE ================================= //
+pub async fn authorize(
+    State(policy): State<Arc<AccessPolicy>>,
+    req: Request,
+    next: Next,
+) -> Response {
+    let peer = req
+        .extensions()
+        .get::<ConnectInfo<PeerCredentials>>()
+        .map(|info| info.0)
+        .unwrap_or_else(PeerCredentials::unknown);
+    let slug = Query::<ConfigTypeSlugQuery>::try_from_uri(req.uri())
+        .ok()
+        .and_then(|query| query.0.config_type_slug);
+
+    match policy.authorize(
+        &peer,
+        req.method().as_str(),
+        req.uri().path(),
+        slug.as_deref(),
+    ) {
+        Ok(()) => next.run(req).await,
+        Err(e) => {
+            warn!("{e}");
+            (e.http_status(), Json(to_error_response(e))).into_response()
+        }
+    }
+}
+
+// ================================ BEARER TOKENS =============================== //
+// endpoints which use POST but don't change the state of the device
+const READ_ONLY_POST_PATHS: [&str; 2] = [
+    "/v1/config_schemas/hash/serialized",
+    "/v1/config_schemas/validate",
+];
+
+pub fn is_read_only_request(method: &Method, path: &str) -> bool {
+    match *method {
+        Method::GET | Method::HEAD | Method::OPTIONS => true,
+        Method::POST => READ_ONLY_POST_PATHS.contains(&path.trim_end_matches('/')),
+        _ => false,
+    }
+}
+
+pub fn authorize_token(
+    tokens: &APITokens,
+    authorization: Option<&str>,
+    method: &Method,
+    path: &str,
+) -> Result<TokenScope, ServerErr> {
+    let scope = authorization
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .and_then(|token| tokens.scope(token.trim()));
+    match scope {
+        Some(TokenScope::ReadOnly) if !is_read_only_request(method, path) => Err(
+            ServerErr::InsufficientTokenScopeErr(Box::new(InsufficientTokenScopeErr {
+                scope: TokenScope::ReadOnly,
+                method: method.to_string(),
+                path: path.to_string(),
+                trace: trace!(),
+            })),
+        ),
+        Some(scope) => Ok(scope),
+        None => Err(ServerErr::InvalidAPITokenErr(Box::new(
+            InvalidAPITokenErr {
+                method: method.to_string(),
+                path: path.to_string(),
+                trace: trace!(),
+            },
+        ))),
+    }
+}
+
+pub async fn authorize_bearer(
+    State(tokens): State<Arc<APITokens>>,
+    req: Request,
+    next: Next,
+) -> Response {
+    let authorization = req
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok());
+
+    match authorize_token(&tokens, authorization, req.method(), req.uri().path()) {
+        Ok(_) => next.run(req).await,
+        Err(e) => {
+            warn!("Rejected tcp request: {e}");
+            (e.http_status(), Json(to_error_response(e))).into_response()
+        }
+    }
+}
@@ -1,5 +1,6 @@
 // standard library
 use std::fmt;
+use std::net::SocketAddr;
 
 // internal crates
 use crate::authn::errors::AuthnErr;
@@ -10,6 +11,9 @@ use crate::errors::{Code, HTTPCode, Trace};
 use crate::filesys::errors::FileSysErr;
 use crate::filesys::file::File;
 use crate::http::errors::HTTPErr;
+use crate::network::errors::NetworkErr;
+use crate::server::access::PeerCredentials;
+use crate::server::tokens::TokenScope;
 use crate::services::errors::ServiceErr;
 use crate::storage::errors::StorageErr;
 use crate::sync::errors::SyncErr;
@@ -109,6 +113,126 @@ impl fmt::Display for ShutdownMngrDuplicateArgErr {
     }
 }
 
+#[derive(Debug)]
+pub struct AccessDeniedErr {
+    pub peer: PeerCredentials,
+    pub method: String,
+    pub path: String,
+    pub trace: Box<Trace>,
+}
+
+impl MiruError for AccessDeniedErr {
+    fn code(&self) -> Code {
+        Code::Forbidden
+    }
+
+    fn http_status(&self) -> HTTPCode {
+        HTTPCode::FORBIDDEN
+    }
+
+    fn is_network_connection_error(&self) -> bool {
+        false
+    }
+
+    fn params(&self) -> Option<serde_json::Value> {
+        Some(serde_json::json!({
+            "uid": self.peer.uid,
+            "gid": self.peer.gid,
+            "pid": self.peer.pid,
+            "method": self.method,
+            "path": self.path,
+        }))
+    }
+}
+
+impl fmt::Display for AccessDeniedErr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} is not authorized to call '{} {}'",
+            self.peer, self.method, self.path
+        )
+    }
+}
+
+#[derive(Debug)]
+pub struct InvalidAPITokenErr {
+    pub method: String,
+    pub path: String,
+    pub trace: Box<Trace>,
+}
+
+impl MiruError for InvalidAPITokenErr {
+    fn code(&self) -> Code {
+        Code::Unauthorized
+    }
+
+    fn http_status(&self) -> HTTPCode {
+        HTTPCode::UNAUTHORIZED
+    }
+
+    fn is_network_connection_error(&self) -> bool {
+        false
+    }
+
+    fn params(&self) -> Option<serde_json::Value> {
+        Some(serde_json::json!({
+            "method": self.method,
+            "path": self.path,
+        }))
+    }
+}
+
+impl fmt::Display for InvalidAPITokenErr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "missing or invalid bearer token for '{} {}'",
+            self.method, self.path
+        )
+    }
+}
+
+#[derive(Debug)]
+pub struct InsufficientTokenScopeErr {
+    pub scope: TokenScope,
+    pub method: String,
+    pub path: String,
+    pub trace: Box<Trace>,
+}
+
+impl MiruError for InsufficientTokenScopeErr {
+    fn code(&self) -> Code {
+        Code::Forbidden
+    }
+
+    fn http_status(&self) -> HTTPCode {
+        HTTPCode::FORBIDDEN
+    }
+
+    fn is_network_connection_error(&self) -> bool {
+        false
+    }
+
+    fn params(&self) -> Option<serde_json::Value> {
+        Some(serde_json::json!({
+            "scope": self.scope.as_str(),
+            "method": self.method,
+            "path": self.path,
+        }))
+    }
+}
+
+impl fmt::Display for InsufficientTokenScopeErr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "a token with the '{}' scope may not call '{} {}'",
+            self.scope, self.method, self.path
+        )
+    }
+}
+
 #[derive(Debug)]
 pub struct ServerAuthnErr {
     pub source: AuthnErr,
@@ -229,6 +353,36 @@ impl fmt::Display for ServerHTTPErr {
     }
 }
 
+#[derive(Debug)]
+pub struct ServerNetworkErr {
+    pub source: NetworkErr,
+    pub trace: Box<Trace>,
+}
+
+impl MiruError for ServerNetworkErr {
+    fn code(&self) -> Code {
+        self.source.code()
+    }
+
+    fn http_status(&self) -> HTTPCode {
+        self.source.http_status()
+    }
+
+    fn is_network_connection_error(&self) -> bool {
+        self.source.is_network_connection_error()
+    }
+
+    fn params(&self) -> Option<serde_json::Value> {
+        self.source.params()
+    }
+}
+
+impl fmt::Display for ServerNetworkErr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "network error: {}", self.source)
+    }
+}
+
 #[derive(Debug)]
 pub struct ServerStorageErr {
     pub source: StorageErr,
@@ -354,6 +508,41 @@ impl fmt::Display for BindUnixSocketErr {
     }
 }
 
+#[derive(Debug)]
+pub struct BindTcpSocketErr {
+    pub address: SocketAddr,
+    pub source: std::io::Error,
+    pub trace: Box<Trace>,
+}
+
+impl MiruError for BindTcpSocketErr {
+    fn code(&self) -> Code {
+        Code::InternalServerError
+    }
+
+    fn http_status(&self) -> HTTPCode {
+        HTTPCode::INTERNAL_SERVER_ERROR
+    }
+
+    fn is_network_connection_error(&self) -> bool {
+        false
+    }
+
+    fn params(&self) -> Option<serde_json::Value> {
+        None
+    }
+}
+
+impl fmt::Display for BindTcpSocketErr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "failed to bind tcp socket '{}': {}",
+            self.address, self.source
+        )
+    }
+}
+
 #[derive(Debug)]
 pub struct RunAxumServerErr {
     pub source: std::io::Error,
@@ -480,6 +669,9 @@ pub enum ServerErr {
     MissingDeviceIDErr(Box<MissingDeviceIDErr>),
     TimestampConversionErr(Box<TimestampConversionErr>),
     ShutdownMngrDuplicateArgErr(Box<ShutdownMngrDuplicateArgErr>),
+    AccessDeniedErr(Box<AccessDeniedErr>),
+    InvalidAPITokenErr(Box<InvalidAPITokenErr>),
+    InsufficientTokenScopeErr(Box<InsufficientTokenScopeErr>),
 
     // internal crate errors
     AuthnErr(Box<ServerAuthnErr>),
@@ -487,12 +679,14 @@ pub enum ServerErr {
     CryptErr(Box<ServerCryptErr>),
     FileSysErr(Box<ServerFileSysErr>),
     HTTPErr(Box<ServerHTTPErr>),
+    NetworkErr(Box<ServerNetworkErr>),
     ServiceErr(Box<ServerServiceErr>),
     StorageErr(Box<ServerStorageErr>),
     SyncErr(Box<ServerSyncErr>),
 
     // external crate errors
     BindUnixSocketErr(Box<BindUnixSocketErr>),
+    BindTcpSocketErr(Box<BindTcpSocketErr>),
     RunAxumServerErr(Box<RunAxumServerErr>),
     SendShutdownSignalErr(Box<SendShutdownSignalErr>),
     JoinHandleErr(Box<JoinHandleErr>),
@@ -504,14 +698,19 @@ macro_rules! forward_error_method {
             Self::MissingDeviceIDErr(e) => e.$method($($arg)?),
             Self::TimestampConversionErr(e) => e.$method($($arg)?),
             Self::ShutdownMngrDuplicateArgErr(e) => e.$method($($arg)?),
+            Self::AccessDeniedErr(e) => e.$method($($arg)?),
+            Self::InvalidAPITokenErr(e) => e.$method($($arg)?),
+            Self::InsufficientTokenScopeErr(e) => e.$method($($arg)?),
             Self::AuthnErr(e) => e.$method($($arg)?),
             Self::CryptErr(e) => e.$method($($arg)?),
             Self::FileSysErr(e) => e.$method($($arg)?),
             Self::HTTPErr(e) => e.$method($($arg)?),
+            Self::NetworkErr(e) => e.$method($($arg)?),
             Self::ServiceErr(e) => e.$method($($arg)?),
             Self::StorageErr(e) => e.$method($($arg)?),
             Self::SyncErr(e) => e.$method($($arg)?),
             Self::BindUnixSocketErr(e) => e.$method($($arg)?),
+            Self::BindTcpSocketErr(e) => e.$method($($arg)?),
             Self::RunAxumServerErr(e) => e.$method($($arg)?),
             Self::SendShutdownSignalErr(e) => e.$method($($arg)?),
             Self::JoinHandleErr(e) => e.$method($($arg)?),
@@ -3,32 +3,66 @@ use std::sync::Arc;
 // internal crates
 use crate::authn::token_mngr::TokenManagerExt;
 use crate::errors::MiruError;
+use crate::http::breaker::{CircuitBreakerStatus, CircuitState};
+use crate::metrics::{prometheus, registry};
+use crate::models::config_instance::{ActivityStatus, ErrorStatus, TargetStatus};
 use crate::models::device::DeviceStatus;
+use crate::network::failover::EndpointStatus;
 use crate::server::errors::*;
 use crate::server::state::ServerState;
-use crate::services::config_instances::{get_deployed, get_deployed::GetDeployedArgs};
-use crate::services::config_schemas::{hash, hash::HashSchemaArgsI};
-use crate::services::device::{get, sync};
+use crate::services::config_instances::{
+    get as get_cfg_inst, get_deployed, get_deployed::GetDeployedArgs, list as list_cfg_insts,
+    list::ConfigInstanceFilters,
+};
+use crate::services::config_schemas::{
+    hash, hash::HashSchemaArgsI, validate, validate::ValidateArgs,
+};
+use crate::services::device::{export, get, sync};
+use crate::services::diagnostics::get as diagnostics;
+use crate::services::metrics;
 use crate::trace;
 use crate::utils::version_info;
 use openapi_server::models::{
-    Error, ErrorResponse, HashSchemaSerializedRequest, HashSerializedConfigSchemaFormat,
-    HealthResponse, SchemaDigestResponse, VersionResponse,
+    CircuitBreakerState, CircuitBreakerStatus as SDKCircuitBreakerStatus,
+    ConfigInstanceActivityStatus, ConfigInstanceErrorStatus, ConfigInstanceTargetStatus,
+    ConfigInstancesResponse, ConnectionInterval, DeviceConnectionsResponse,
+    EndpointStatus as SDKEndpointStatus, Error, ErrorResponse, HashSchemaSerializedRequest,
+    HashSerializedConfigSchemaFormat, HealthResponse, SchemaDigestResponse, StatusResponse,
+    VersionResponse,
 };
 
 // external
-use axum::{extract::Query, extract::State, http::StatusCode, response::IntoResponse, Json};
-use serde::Deserialize;
+use axum::{
+    extract::Path,
+    extract::Query,
+    extract::State,
+    http::{header, StatusCode},
+    response::IntoResponse,
+    Json,
+};
+use serde::{de::IntoDeserializer, Deserialize, Deserializer};
 use serde_json::json;
 use tracing::error;
 
 // ================================= AGENT INFO ==================================== //
-pub async fn health() -> impl IntoResponse {
+pub async fn health(State(state): State<Arc<ServerState>>) -> impl IntoResponse {
+    let circuit_breaker = state.http_client.circuit_breaker().status();
     (
         StatusCode::OK,
-        Json(HealthResponse {
-            status: "ok".to_string(),
-        }),
+        Json(HealthResponse::new(
+            "ok".to_string(),
+            circuit_breaker_to_sdk(circuit_breaker),
+        )),
+    )
+}
+
+pub async fn status(State(state): State<Arc<ServerState>>) -> impl IntoResponse {
+    (
+        StatusCode::OK,
+        Json(StatusResponse::new(
+            endpoint_status_to_sdk(state.http_client.endpoints().status()),
+            endpoint_status_to_sdk(state.mqtt_brokers.status()),
+        )),
     )
 }
 
@@ -88,6 +122,49 @@ pub async fn hash_schema(
     }
 }
 
+#[derive(Debug, Deserialize)]
+pub struct ValidateConfigQueryArgs {
+    pub config_type_slug: String,
+    pub config_schema_digest: String,
+}
+
+pub async fn validate_config(
+    Query(query): Query<ValidateConfigQueryArgs>,
+    State(state): State<Arc<ServerState>>,
+    Json(content): Json<serde_json::Value>,
+) -> impl IntoResponse {
+    let service = async move {
+        let args = ValidateArgs {
+            config_type_slug: query.config_type_slug,
+            config_schema_digest: query.config_schema_digest,
+            content,
+        };
+        validate::validate(
+            &args,
+            &state.caches.cfg_schema,
+            &state.caches.cfg_schema_content,
+            &state.http_client,
+            &state.token_mngr,
+        )
+        .await
+        .map_err(|e| {
+            ServerErr::ServiceErr(Box::new(ServerServiceErr {
+                source: e,
+                trace: trace!(),
+            }))
+        })
+    };
+
+    // an invalid config is still a successful validation
+    match service.await {
+        Ok(response) => (StatusCode::OK, Json(json!(response))),
+        Err(e) => {
+            error!("Error validating config: {e:?}");
+            (e.http_status(), Json(json!(to_error_response(e))))
+        }
+    }
+}
+
 // ============================= CONFIG INSTANCES ================================ //
 #[derive(Debug, Deserialize)]
 pub struct GetDeployedQueryArgs {
@@ -147,6 +224,102 @@ pub async fn get_deployed_config_instance(
     }
 }
 
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct ListConfigInstancesQueryArgs {
+    #[serde(deserialize_with = "comma_separated")]
+    pub target_status: Option<Vec<ConfigInstanceTargetStatus>>,
+    #[serde(deserialize_with = "comma_separated")]
+    pub activity_status: Option<Vec<ConfigInstanceActivityStatus>>,
+    #[serde(deserialize_with = "comma_separated")]
+    pub error_status: Option<Vec<ConfigInstanceErrorStatus>>,
+    #[serde(deserialize_with = "comma_separated")]
+    pub config_type_id: Option<Vec<String>>,
+    #[serde(deserialize_with = "comma_separated")]
+    pub config_schema_id: Option<Vec<String>>,
+}
+
+impl From<ListConfigInstancesQueryArgs> for ConfigInstanceFilters {
+    fn from(query: ListConfigInstancesQueryArgs) -> Self {
+        ConfigInstanceFilters {
+            target_statuses: query
+                .target_status
+                .map(|statuses| statuses.iter().map(TargetStatus::from_sdk).collect()),
+            activity_statuses: query
+                .activity_status
+                .map(|statuses| statuses.iter().map(ActivityStatus::from_sdk).collect()),
+            error_statuses: query
+                .error_status
+                .map(|statuses| statuses.iter().map(ErrorStatus::from_sdk).collect()),
+            config_type_ids: query.config_type_id,
+            config_schema_ids: query.config_schema_id,
+        }
+    }
+}
+
+pub async fn list_config_instances(
+    Query(query): Query<ListConfigInstancesQueryArgs>,
+    State(state): State<Arc<ServerState>>,
+) -> impl IntoResponse {
+    let service = async move {
+        list_cfg_insts::list(&state.caches.cfg_inst, query.into())
+            .await
+            .map_err(|e| {
+                ServerErr::ServiceErr(Box::new(ServerServiceErr {
+                    source: e,
+                    trace: trace!(),
+                }))
+            })
+    };
+
+    match service.await {
+        Ok(cfg_insts) => (
+            StatusCode::OK,
+            Json(json!(ConfigInstancesResponse::new(cfg_insts))),
+        ),
+        Err(e) => {
+            error!("Error listing config instances: {e:?}");
+            (e.http_status(), Json(json!(to_error_response(e))))
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct GetConfigInstanceQueryArgs {
+    pub include_content: bool,
+}
+
+pub async fn get_config_instance(
+    Path(config_instance_id): Path<String>,
+    Query(query): Query<GetConfigInstanceQueryArgs>,
+    State(state): State<Arc<ServerState>>,
+) -> impl IntoResponse {
+    let service = async move {
+        get_cfg_inst::get(
+            &state.caches.cfg_inst,
+            &state.caches.cfg_inst_content,
+            &config_instance_id,
+            query.include_content,
+        )
+        .await
+        .map_err(|e| {
+            ServerErr::ServiceErr(Box::new(ServerServiceErr {
+                source: e,
+                trace: trace!(),
+            }))
+        })
+    };
+
+    match service.await {
+        Ok(cfg_inst) => (StatusCode::OK, Json(json!(cfg_inst))),
+        Err(e) => {
+            error!("Error getting config instance: {e:?}");
+            (e.http_status(), Json(json!(to_error_response(e))))
+        }
+    }
+}
+
 // ================================= DEVICE ======================================== //
 pub async fn get_device(State(state): State<Arc<ServerState>>) -> impl IntoResponse {
     let service = async move {
@@ -176,6 +349,36 @@ pub async fn get_device(State(state): State<Arc<ServerState>>) -> impl IntoRespo
     }
 }
 
+pub async fn get_device_connections(State(state): State<Arc<ServerState>>) -> impl IntoResponse {
+    let service = async move {
+        let device = get::get_device(&state.device_file).await.map_err(|e| {
+            ServerErr::ServiceErr(Box::new(ServerServiceErr {
+                source: e,
+                trace: trace!(),
+            }))
+        })?;
+        let connections = device
+            .connection_history
+            .iter()
+            .map(|interval| ConnectionInterval {
+                connected_at: interval.connected_at.to_rfc3339(),
+                disconnected_at: interval
+                    .disconnected_at
+                    .map(|disconnected_at| disconnected_at.to_rfc3339()),
+            })
+            .collect();
+        Ok::<DeviceConnectionsResponse, ServerErr>(DeviceConnectionsResponse::new(connections))
+    };
+
+    match service.await {
+        Ok(connections) => (StatusCode::OK, Json(json!(connections))),
+        Err(e) => {
+            error!("Error getting device connections: {e:?}");
+            (e.http_status(), Json(json!(to_error_response(e))))
+        }
+    }
+}
+
 pub async fn sync_device(State(state): State<Arc<ServerState>>) -> impl IntoResponse {
     let service = async move {
         sync::sync_device(state.syncer.as_ref()).await.map_err(|e| {
@@ -195,8 +398,125 @@ pub async fn sync_device(State(state): State<Arc<ServerState>>) -> impl IntoResp
     }
 }
 
+pub async fn export_device(State(state): State<Arc<ServerState>>) -> impl IntoResponse {
+    let service = async move {
+        export::export_device(&state.device_file, &state.caches)
+            .await
+            .map_err(|e| {
+                ServerErr::ServiceErr(Box::new(ServerServiceErr {
+                    source: e,
+                    trace: trace!(),
+                }))
+            })
+    };
+
+    match service.await {
+        Ok(bundle) => (StatusCode::OK, Json(json!(bundle))),
+        Err(e) => {
+            error!("Error exporting device: {e:?}");
+            (e.http_status(), Json(json!(to_error_response(e))))
+        }
+    }
+}
+
+// ================================ DIAGNOSTICS ==================================== //
+pub async fn get_diagnostics(State(state): State<Arc<ServerState>>) -> impl IntoResponse {
+    let archive = diagnostics::get_diagnostics(&state.diagnostics, state.syncer.as_ref()).await;
+    let file_name = format!(
+        "miru-diagnostics-{}.tar",
+        chrono::Utc::now().format("%Y%m%dT%H%M%SZ")
+    );
+    (
+        StatusCode::OK,
+        [
+            (header::CONTENT_TYPE, "application/x-tar".to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"{file_name}\""),
+            ),
+        ],
+        archive,
+    )
+}
+
+// ================================== METRICS ====================================== //
+pub async fn get_metrics(State(state): State<Arc<ServerState>>) -> impl IntoResponse {
+    let service = async move {
+        metrics::get::get_metrics(
+            registry::global(),
+            state.syncer.as_ref(),
+            state.token_mngr.as_ref(),
+            &state.caches,
+        )
+        .await
+        .map_err(|e| {
+            ServerErr::ServiceErr(Box::new(ServerServiceErr {
+                source: e,
+                trace: trace!(),
+            }))
+        })
+    };
+
+    match service.await {
+        Ok(body) => (
+            StatusCode::OK,
+            [(header::CONTENT_TYPE, prometheus::CONTENT_TYPE)],
+            body,
+        )
+            .into_response(),
+        Err(e) => {
+            error!("Error getting metrics: {e:?}");
+            (e.http_status(), Json(json!(to_error_response(e)))).into_response()
+        }
+    }
+}
+
 // ================================ UTILITIES ====================================== //
-fn to_error_response(e: impl MiruError) -> ErrorResponse {
+// deserializes a comma separated query parameter (e.g. 'deployed,removed') into its
+// (non-empty) values
+fn comma_separated<'de, D, T>(deserializer: D) -> Result<Option<Vec<T>>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    let Some(values) = Option::<String>::deserialize(deserializer)? else {
+        return Ok(None);
+    };
+    values
+        .split(',')
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+        .map(|value| T::deserialize(value.to_string().into_deserializer()))
+        .collect::<Result<Vec<T>, D::Error>>()
+        .map(Some)
+}
+
+pub fn circuit_breaker_to_sdk(status: CircuitBreakerStatus) -> SDKCircuitBreakerStatus {
+    SDKCircuitBreakerStatus {
+        state: match status.state {
+            CircuitState::Closed => CircuitBreakerState::CIRCUIT_BREAKER_STATE_CLOSED,
+            CircuitState::Open => CircuitBreakerState::CIRCUIT_BREAKER_STATE_OPEN,
+            CircuitState::HalfOpen => CircuitBreakerState::CIRCUIT_BREAKER_STATE_HALF_OPEN,
+        },
+        consecutive_failures: i32::try_from(status.consecutive_failures).unwrap_or(i32::MAX),
+        retry_in_ms: status
+            .retry_in
+            .map(|retry_in| i64::try_from(retry_in.as_millis()).unwrap_or(i64::MAX)),
+    }
+}
+
+pub fn endpoint_status_to_sdk(status: EndpointStatus) -> SDKEndpointStatus {
+    SDKEndpointStatus {
+        active: status.active,
+        primary: status.primary,
+        endpoints: status.endpoints,
+        fail_back_in_ms: status
+            .fail_back_in
+            .map(|fail_back_in| i64::try_from(fail_back_in.as_millis()).unwrap_or(i64::MAX)),
+    }
+}
+
+pub(crate) fn to_error_response(e: impl MiruError) -> ErrorResponse {
     ErrorResponse {
         error: Box::new(Error {
             code: e.code().as_str().to_string(),
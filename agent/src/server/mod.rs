@@ -1,4 +1,6 @@
+pub mod access;
 pub mod errors;
 pub mod handlers;
 pub mod serve;
 pub mod state;
+pub mod tokens;
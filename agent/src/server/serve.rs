@@ -1,5 +1,6 @@
 // standard library
 use std::future::Future;
+use std::net::SocketAddr;
 use std::sync::Arc;
 use std::{
     env,
@@ -8,88 +9,154 @@ use std::{
 
 // internal crates
 use crate::filesys::{file::File, path::PathExt};
-use crate::server::errors::{BindUnixSocketErr, RunAxumServerErr, ServerErr, ServerFileSysErr};
+use crate::server::access::{self, AccessPolicy, PeerCredentials};
+use crate::server::errors::{
+    BindTcpSocketErr, BindUnixSocketErr, RunAxumServerErr, ServerErr, ServerFileSysErr,
+};
 use crate::server::handlers;
 use crate::server::state::ServerState;
+use crate::server::tokens::APITokens;
 use crate::trace;
 
 // external
 use axum::{
+    http::header,
     routing::{get, post},
     Router,
 };
-use tokio::net::UnixListener;
+use futures::FutureExt;
+use tokio::net::{TcpListener, UnixListener};
 use tokio::task::JoinHandle;
 use tower::ServiceBuilder;
 use tower_http::{
+    sensitive_headers::SetSensitiveRequestHeadersLayer,
     trace::{DefaultMakeSpan, DefaultOnRequest, DefaultOnResponse, TraceLayer},
     LatencyUnit,
 };
-use tracing::Level;
+use tracing::{error, info, Level};
+
+#[derive(Debug, Clone)]
+pub struct TCPServerOptions {
+    // must be the address of the loopback or of an explicit interface
+    pub address: SocketAddr,
+    // the bearer tokens required by the tcp listener (generated if absent)
+    pub tokens_file: File,
+}
 
 #[derive(Debug)]
 pub struct ServerOptions {
     pub socket_file: File,
+    // when set, the metrics endpoint is also served over tcp at this address so that
+    // it can be scraped by prometheus
+    pub metrics_address: Option<SocketAddr>,
+    // per-endpoint access rules evaluated against the credentials of the process
+    // connecting to the unix socket
+    pub access_policy: AccessPolicy,
+    // when set, every endpoint is also served over tcp for clients which can't mount
+    // the unix socket
+    pub tcp: Option<TCPServerOptions>,
 }
 
 impl Default for ServerOptions {
     fn default() -> Self {
         Self {
             socket_file: File::new("/run/miru/miru.sock"),
+            metrics_address: None,
+            access_policy: AccessPolicy::default(),
+            tcp: None,
         }
     }
 }
 
-pub(crate) async fn serve(
-    options: &ServerOptions,
-    state: Arc<ServerState>,
-    shutdown_signal: impl Future<Output = ()> + Send + 'static,
-) -> Result<JoinHandle<Result<(), ServerErr>>, ServerErr> {
-    // build the app with the test route
-    let state_for_middleware = state.clone();
-    let app = Router::new()
+fn routes() -> Router<Arc<ServerState>> {
+    Router::new()
         // =============================== AGENT INFO ============================== //
         .route("/v1/health", get(handlers::health))
         .route("/v1/version", get(handlers::version))
+        .route("/v1/status", get(handlers::status))
         // ============================ CONFIG INSTANCES =========================== //
+        .route("/v1/config_instances", get(handlers::list_config_instances))
         .route(
             "/v1/config_instances/deployed",
             get(handlers::get_deployed_config_instance),
         )
+        .route(
+            "/v1/config_instances/{config_instance_id}",
+            get(handlers::get_config_instance),
+        )
         // ============================= CONFIG SCHEMAS ============================ //
         .route(
             "/v1/config_schemas/hash/serialized",
             post(handlers::hash_schema),
         )
+        .route(
+            "/v1/config_schemas/validate",
+            post(handlers::validate_config),
+        )
         // ============================= DEVICE ==================================== //
         .route("/v1/device", get(handlers::get_device))
-        .route("/v1/device/sync", post(handlers::sync_device))
-        // ============================= LAYERS ===================================== //
-        .layer(
-            ServiceBuilder::new()
-                // activity middleware
-                .layer(axum::middleware::from_fn(
-                    move |req: axum::extract::Request, next: axum::middleware::Next| {
-                        let state = state_for_middleware.clone();
-                        async move {
-                            state.activity_tracker.touch();
-                            next.run(req).await
-                        }
-                    },
-                ))
-                // logging middleware
-                .layer(
-                    TraceLayer::new_for_http()
-                        .make_span_with(DefaultMakeSpan::new().include_headers(true))
-                        .on_request(DefaultOnRequest::new().level(Level::INFO))
-                        .on_response(
-                            DefaultOnResponse::new()
-                                .level(Level::INFO)
-                                .latency_unit(LatencyUnit::Micros),
-                        ),
-                ),
+        .route(
+            "/v1/device/connections",
+            get(handlers::get_device_connections),
         )
-        .with_state(state);
+        .route("/v1/device/sync", post(handlers::sync_device))
+        .route("/v1/device/export", get(handlers::export_device))
+        // ============================= DIAGNOSTICS =============================== //
+        .route("/v1/diagnostics", get(handlers::get_diagnostics))
+        // ============================= METRICS =================================== //
+        .route("/v1/metrics", get(handlers::get_metrics))
+}
+
+// the activity and logging middleware shared by the unix socket and tcp listeners
+fn with_common_layers(
+    router: Router<Arc<ServerState>>,
+    state: Arc<ServerState>,
+) -> Router<Arc<ServerState>> {
+    router.layer(
+        ServiceBuilder::new()
+            // activity middleware
+            .layer(axum::middleware::from_fn(
+                move |req: axum::extract::Request, next: axum::middleware::Next| {
+                    let state = state.clone();
+                    async move {
+                        state.activity_tracker.touch();
+                        next.run(req).await
+                    }
+                },
+            ))
+            // the bearer tokens of tcp requests are never logged
+            .layer(SetSensitiveRequestHeadersLayer::new([
+                header::AUTHORIZATION,
+            ]))
+            // logging middleware
+            .layer(
+                TraceLayer::new_for_http()
+                    .make_span_with(DefaultMakeSpan::new().include_headers(true))
+                    .on_request(DefaultOnRequest::new().level(Level::INFO))
+                    .on_response(
+                        DefaultOnResponse::new()
+                            .level(Level::INFO)
+                            .latency_unit(LatencyUnit::Micros),
+                    ),
+            ),
+    )
+}
+
+pub(crate) async fn serve(
+    options: &ServerOptions,
+    state: Arc<ServerState>,
+    shutdown_signal: impl Future<Output = ()> + Send + 'static,
+) -> Result<JoinHandle<Result<(), ServerErr>>, ServerErr> {
+    // the unix socket authorizes requests using the peer's credentials
+    let access_policy = Arc::new(options.access_policy.clone());
+    let app = with_common_layers(
+        routes().layer(axum::middleware::from_fn_with_state(
+            access_policy,
+            access::authorize,
+        )),
+        state.clone(),
+    )
+    .with_state(state.clone());
 
     // obtain the unix socket file listener
     let listener = acquire_unix_socket_listener(&options.socket_file, async move {
@@ -97,22 +164,92 @@ pub(crate) async fn serve(
     })
     .await?;
 
+    // obtain the metrics tcp listener
+    let metrics_listener = match options.metrics_address {
+        Some(address) => Some(bind_tcp_listener(address).await?),
+        None => None,
+    };
+
+    // obtain the tcp listener (and the bearer tokens it requires)
+    let tcp_listener = match &options.tcp {
+        Some(tcp) => {
+            let tokens = APITokens::load_or_create(&tcp.tokens_file).await?;
+            let listener = bind_tcp_listener(tcp.address).await?;
+            info!("Serving the socket server over tcp at {}", tcp.address);
+            Some((listener, Arc::new(tokens)))
+        }
+        None => None,
+    };
+
     // serve with graceful shutdown
+    let shutdown_signal = shutdown_signal.shared();
+    let mut tcp_handles = Vec::new();
+    if let Some(listener) = metrics_listener {
+        let metrics_app = Router::new()
+            .route("/v1/metrics", get(handlers::get_metrics))
+            .with_state(state.clone());
+        let shutdown_signal = shutdown_signal.clone();
+        tcp_handles.push(tokio::task::spawn(async move {
+            axum::serve(listener, metrics_app)
+                .with_graceful_shutdown(shutdown_signal)
+                .await
+        }));
+    }
+    if let Some((listener, tokens)) = tcp_listener {
+        // the tcp listener authorizes requests using bearer tokens
+        let tcp_app = with_common_layers(
+            routes().layer(axum::middleware::from_fn_with_state(
+                tokens,
+                access::authorize_bearer,
+            )),
+            state.clone(),
+        )
+        .with_state(state);
+        let shutdown_signal = shutdown_signal.clone();
+        tcp_handles.push(tokio::task::spawn(async move {
+            axum::serve(listener, tcp_app)
+                .with_graceful_shutdown(shutdown_signal)
+                .await
+        }));
+    }
     let server_handle = tokio::task::spawn(async move {
-        axum::serve(listener, app)
-            .with_graceful_shutdown(shutdown_signal)
-            .await
-            .map_err(|e| {
-                ServerErr::RunAxumServerErr(Box::new(RunAxumServerErr {
-                    source: e,
-                    trace: trace!(),
-                }))
-            })
+        let mut result = axum::serve(
+            listener,
+            app.into_make_service_with_connect_info::<PeerCredentials>(),
+        )
+        .with_graceful_shutdown(shutdown_signal)
+        .await;
+        for handle in tcp_handles {
+            let tcp_result = match handle.await {
+                Ok(result) => result,
+                Err(e) => {
+                    error!("tcp server task failed: {e:?}");
+                    Ok(())
+                }
+            };
+            result = result.and(tcp_result);
+        }
+        result.map_err(|e| {
+            ServerErr::RunAxumServerErr(Box::new(RunAxumServerErr {
+                source: e,
+                trace: trace!(),
+            }))
+        })
     });
 
     Ok(server_handle)
 }
 
+async fn bind_tcp_listener(address: SocketAddr) -> Result<TcpListener, ServerErr> {
+    TcpListener::bind(address).await.map_err(|e| {
+        ServerErr::BindTcpSocketErr(Box::new(BindTcpSocketErr {
+            address,
+            source: e,
+            trace: trace!(),
+        }))
+    })
+}
+
 async fn acquire_unix_socket_listener(
     socket_file: &File,
     fallback: impl Future<Output = Result<UnixListener, ServerErr>>,
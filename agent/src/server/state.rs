@@ -4,7 +4,9 @@ use std::sync::Arc;
 // internal crates
 use crate::activity::ActivityTracker;
 use crate::authn::token_mngr::TokenManager;
+use crate::diagnostics::collect as diagnostics;
 use crate::http::client::HTTPClient;
+use crate::network::failover::Endpoints;
 use crate::storage::{caches::Caches, device::DeviceFile};
 use crate::sync::syncer::Syncer;
 
@@ -16,6 +18,8 @@ pub struct ServerState {
     pub caches: Arc<Caches>,
     pub token_mngr: Arc<TokenManager>,
     pub activity_tracker: Arc<ActivityTracker>,
+    pub mqtt_brokers: Arc<Endpoints>,
+    pub diagnostics: Arc<diagnostics::Options>,
 }
 
 impl ServerState {
@@ -26,6 +30,7 @@ impl ServerState {
         caches: Arc<Caches>,
         token_mngr: Arc<TokenManager>,
         activity_tracker: Arc<ActivityTracker>,
+        mqtt_brokers: Arc<Endpoints>,
     ) -> Self {
         ServerState {
             device_file,
@@ -34,6 +39,13 @@ impl ServerState {
             caches,
             token_mngr,
             activity_tracker,
+            mqtt_brokers,
+            diagnostics: Arc::new(diagnostics::Options::default()),
         }
     }
+
+    pub fn with_diagnostics(mut self, diagnostics: diagnostics::Options) -> Self {
+        self.diagnostics = Arc::new(diagnostics);
+        self
+    }
 }
@@ -0,0 +1,133 @@
+// standard library
+use std::fmt;
+
+// internal crates
+use crate::filesys::{file::File, path::PathExt};
+use crate::server::errors::{ServerErr, ServerFileSysErr};
+use crate::trace;
+
+// external crates
+use openssl::memcmp;
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenScope {
+    // may call every endpoint
+    Full,
+    // may only call the endpoints which don't change the state of the device
+    ReadOnly,
+}
+
+impl TokenScope {
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::Full => "full",
+            Self::ReadOnly => "read_only",
+        }
+    }
+}
+
+impl fmt::Display for TokenScope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+// the bearer tokens required by the tcp listener. They are generated on the device
+// and never leave it so a client must be able to read the tokens file to use them.
+#[derive(Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct APITokens {
+    pub full: String,
+    pub read_only: String,
+}
+
+impl fmt::Debug for APITokens {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("APITokens")
+            .field("full", &"<redacted>")
+            .field("read_only", &"<redacted>")
+            .finish()
+    }
+}
+
+impl APITokens {
+    pub fn generate() -> Self {
+        Self {
+            full: gen_token(),
+            read_only: gen_token(),
+        }
+    }
+
+    /// Read the tokens from the file, generating (and writing) new tokens if the file
+    /// doesn't exist or can't be parsed. The file is given read/write permissions
+    /// only to the owner (600).
+    pub async fn load_or_create(file: &File) -> Result<Self, ServerErr> {
+        let tokens = if file.exists() {
+            match file.read_json::<APITokens>().await {
+                Ok(tokens) if tokens.is_valid() => Some(tokens),
+                Ok(_) => {
+                    warn!("API tokens file '{}' is invalid, regenerating tokens", file);
+                    None
+                }
+                Err(e) => {
+                    warn!("Unable to read the API tokens file, regenerating tokens: {e}");
+                    None
+                }
+            }
+        } else {
+            None
+        };
+
+        let tokens = match tokens {
+            Some(tokens) => tokens,
+            None => {
+                let tokens = APITokens::generate();
+                file.write_private_json(&tokens).await.map_err(|e| {
+                    ServerErr::FileSysErr(Box::new(ServerFileSysErr {
+                        source: e,
+                        trace: trace!(),
+                    }))
+                })?;
+                info!("Generated API tokens at '{}'", file);
+                tokens
+            }
+        };
+
+        // tighten the permissions in case the file was created or modified by hand
+        file.set_permissions(0o600).await.map_err(|e| {
+            ServerErr::FileSysErr(Box::new(ServerFileSysErr {
+                source: e,
+                trace: trace!(),
+            }))
+        })?;
+
+        Ok(tokens)
+    }
+
+    fn is_valid(&self) -> bool {
+        !self.full.is_empty() && !self.read_only.is_empty() && self.full != self.read_only
+    }
+
+    // the scope granted by the token (if any); compared in constant time so that the
+    // tokens can't be guessed one byte at a time
+    pub fn scope(&self, token: &str) -> Option<TokenScope> {
+        if constant_time_eq(token, &self.full) {
+            Some(TokenScope::Full)
+        } else if constant_time_eq(token, &self.read_only) {
+            Some(TokenScope::ReadOnly)
+        } else {
+            None
+        }
+    }
+}
+
+fn gen_token() -> String {
+    // v4 uuids are generated from a cryptographically secure random number generator
+    format!("{}{}", Uuid::new_v4().simple(), Uuid::new_v4().simple())
+}
+
+fn constant_time_eq(a: &str, b: &str) -> bool {
+    a.len() == b.len() && memcmp::eq(a.as_bytes(), b.as_bytes())
+}
@@ -0,0 +1,50 @@
+// internal crates
+use crate::crud::prelude::*;
+use crate::models::config_instance::ConfigInstance;
+use crate::services::errors::*;
+use crate::storage::config_instances::{ConfigInstanceCache, ConfigInstanceContentCache};
+use crate::trace;
+use openapi_server::models::LocalConfigInstance;
+
+// reads a config instance stored on the device without contacting the backend
+pub async fn get(
+    cfg_inst_cache: &ConfigInstanceCache,
+    cfg_inst_content_cache: &ConfigInstanceContentCache,
+    config_instance_id: &str,
+    include_content: bool,
+) -> Result<LocalConfigInstance, ServiceErr> {
+    let cfg_inst = cfg_inst_cache
+        .read_optional(config_instance_id.to_string())
+        .await
+        .map_err(|e| {
+            ServiceErr::CrudErr(Box::new(ServiceCrudErr {
+                source: e,
+                trace: trace!(),
+            }))
+        })?
+        .ok_or_else(|| {
+            ServiceErr::ConfigInstanceNotFound(Box::new(ConfigInstanceNotFound {
+                config_instance_id: config_instance_id.to_string(),
+                trace: trace!(),
+            }))
+        })?;
+
+    // the content is stored alongside the config instance so it should always exist
+    let content = if include_content {
+        Some(
+            cfg_inst_content_cache
+                .read(cfg_inst.id.clone())
+                .await
+                .map_err(|e| {
+                    ServiceErr::CrudErr(Box::new(ServiceCrudErr {
+                        source: e,
+                        trace: trace!(),
+                    }))
+                })?,
+        )
+    } else {
+        None
+    };
+
+    Ok(ConfigInstance::to_local_sdk(cfg_inst, content))
+}
@@ -21,7 +21,7 @@ use openapi_server::models::ConfigInstance as SDKConfigInstance;
 
 // external crates
 use serde::Deserialize;
-use tracing::error;
+use tracing::{debug, error};
 
 pub trait GetDeployedArgsI {
     fn device_id(&self) -> &str;
@@ -48,7 +48,10 @@ impl GetDeployedArgsI for GetDeployedArgs {
     }
 }
 
-pub async fn get_deployed<GetDeployedArgsT: GetDeployedArgsI, HTTPClientT: ConfigSchemasExt>(
+pub async fn get_deployed<
+    GetDeployedArgsT: GetDeployedArgsI,
+    HTTPClientT: ConfigSchemasExt + CircuitBreakerExt,
+>(
     args: &GetDeployedArgsT,
     syncer: &Syncer,
     cfg_inst_cache: Arc<ConfigInstanceCache>,
@@ -57,10 +60,20 @@ pub async fn get_deployed<GetDeployedArgsT: GetDeployedArgsI, HTTPClientT: Confi
     http_client: &HTTPClientT,
     token: &str,
 ) -> Result<SDKConfigInstance, ServiceErr> {
-    let (config_schema_id_result, sync_result) = tokio::join!(
-        fetch_config_schema_id(args, http_client, schema_cache, token),
-        sync_with_backend(syncer)
-    );
+    // while the backend's circuit breaker is open a sync is bound to fail so the cached
+    // config instance is returned immediately instead
+    let (config_schema_id_result, sync_result) = if http_client.is_circuit_open() {
+        debug!("backend circuit breaker is open, skipping the sync with the backend");
+        (
+            fetch_config_schema_id(args, http_client, schema_cache, token).await,
+            Ok(()),
+        )
+    } else {
+        tokio::join!(
+            fetch_config_schema_id(args, http_client, schema_cache, token),
+            sync_with_backend(syncer)
+        )
+    };
 
     let config_schema_id = config_schema_id_result?;
 
@@ -172,6 +185,7 @@ async fn fetch_config_schema_id<
 
     // search the backend for the config schema
     let filters = ConfigSchemaFilters {
+        ids: None,
         digests: Some(DigestFilter {
             not: false,
             op: SearchOperator::Equals,
@@ -0,0 +1,58 @@
+// internal crates
+use crate::crud::prelude::*;
+use crate::models::config_instance::{ActivityStatus, ConfigInstance, ErrorStatus, TargetStatus};
+use crate::services::errors::*;
+use crate::storage::config_instances::ConfigInstanceCache;
+use crate::trace;
+use openapi_server::models::LocalConfigInstance;
+
+// a config instance must match every filter which is set (and any of a filter's values)
+#[derive(Debug, Clone, Default)]
+pub struct ConfigInstanceFilters {
+    pub target_statuses: Option<Vec<TargetStatus>>,
+    pub activity_statuses: Option<Vec<ActivityStatus>>,
+    pub error_statuses: Option<Vec<ErrorStatus>>,
+    pub config_type_ids: Option<Vec<String>>,
+    pub config_schema_ids: Option<Vec<String>>,
+}
+
+impl ConfigInstanceFilters {
+    pub fn matches(&self, cfg_inst: &ConfigInstance) -> bool {
+        fn matches_any<T: PartialEq>(filter: &Option<Vec<T>>, value: &T) -> bool {
+            filter.as_ref().is_none_or(|values| values.contains(value))
+        }
+
+        matches_any(&self.target_statuses, &cfg_inst.target_status)
+            && matches_any(&self.activity_statuses, &cfg_inst.activity_status)
+            && matches_any(&self.error_statuses, &cfg_inst.error_status)
+            && matches_any(&self.config_type_ids, &cfg_inst.config_type_id)
+            && matches_any(&self.config_schema_ids, &cfg_inst.config_schema_id)
+    }
+}
+
+// lists the config instances stored on the device (oldest first) without contacting
+// the backend
+pub async fn list(
+    cfg_inst_cache: &ConfigInstanceCache,
+    filters: ConfigInstanceFilters,
+) -> Result<Vec<LocalConfigInstance>, ServiceErr> {
+    let mut cfg_insts = cfg_inst_cache
+        .find_where(move |cfg_inst| filters.matches(cfg_inst))
+        .await
+        .map_err(|e| {
+            ServiceErr::CrudErr(Box::new(ServiceCrudErr {
+                source: e,
+                trace: trace!(),
+            }))
+        })?;
+    cfg_insts.sort_by(|a, b| {
+        a.created_at
+            .cmp(&b.created_at)
+            .then_with(|| a.id.cmp(&b.id))
+    });
+
+    Ok(cfg_insts
+        .into_iter()
+        .map(|cfg_inst| ConfigInstance::to_local_sdk(cfg_inst, None))
+        .collect())
+}
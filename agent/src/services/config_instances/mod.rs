@@ -1 +1,3 @@
+pub mod get;
 pub mod get_deployed;
+pub mod list;
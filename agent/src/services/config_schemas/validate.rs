@@ -0,0 +1,236 @@
+// internal crates
+use crate::authn::token_mngr::TokenManagerExt;
+use crate::crud::config_schema::matches_config_type_slug_and_schema_digest;
+use crate::crud::prelude::*;
+use crate::deploy::validate::validate as validate_content;
+use crate::errors::MiruError;
+use crate::http::prelude::*;
+use crate::http::{
+    config_schemas::{ConfigSchemaFilters, ConfigTypeSlugFilter, DigestFilter},
+    search::SearchOperator,
+};
+use crate::models::config_schema::ConfigSchema;
+use crate::services::errors::*;
+use crate::storage::config_schemas::{ConfigSchemaCache, ConfigSchemaContentCache};
+use crate::trace;
+use openapi_client::models::ConfigSchemaExpand;
+use openapi_server::models::{SchemaViolation, ValidateConfigResponse};
+
+// external crates
+use serde_json::Value;
+
+pub trait ValidateArgsI {
+    fn config_type_slug(&self) -> &str;
+    fn config_schema_digest(&self) -> &str;
+    fn content(&self) -> &Value;
+}
+
+pub struct ValidateArgs {
+    pub config_type_slug: String,
+    pub config_schema_digest: String,
+    pub content: Value,
+}
+
+impl ValidateArgsI for ValidateArgs {
+    fn config_type_slug(&self) -> &str {
+        &self.config_type_slug
+    }
+    fn config_schema_digest(&self) -> &str {
+        &self.config_schema_digest
+    }
+    fn content(&self) -> &Value {
+        &self.content
+    }
+}
+
+/// Validate a candidate config against the json schema of the config schema with the
+/// given config type slug and digest. The schema is read from the cache, falling back
+/// to the backend (and caching the result) if it isn't cached yet. A token is only
+/// requested when the backend is needed.
+pub async fn validate<
+    ArgsT: ValidateArgsI,
+    HTTPClientT: ConfigSchemasExt,
+    TokenManagerT: TokenManagerExt,
+>(
+    args: &ArgsT,
+    cfg_schema_cache: &ConfigSchemaCache,
+    cfg_schema_content_cache: &ConfigSchemaContentCache,
+    http_client: &HTTPClientT,
+    token_mngr: &TokenManagerT,
+) -> Result<ValidateConfigResponse, ServiceErr> {
+    let (config_schema_id, schema) = fetch_schema(
+        args,
+        cfg_schema_cache,
+        cfg_schema_content_cache,
+        http_client,
+        token_mngr,
+    )
+    .await?;
+
+    let violations = validate_content(&config_schema_id, &schema, args.content()).map_err(|e| {
+        ServiceErr::DeployErr(Box::new(ServiceDeployErr {
+            source: e,
+            trace: trace!(),
+        }))
+    })?;
+
+    Ok(ValidateConfigResponse {
+        valid: violations.is_empty(),
+        config_schema_id,
+        errors: violations
+            .into_iter()
+            .map(|v| SchemaViolation {
+                instance_path: v.instance_path,
+                schema_path: v.schema_path,
+                message: v.message,
+            })
+            .collect(),
+    })
+}
+
+async fn fetch_schema<
+    ArgsT: ValidateArgsI,
+    HTTPClientT: ConfigSchemasExt,
+    TokenManagerT: TokenManagerExt,
+>(
+    args: &ArgsT,
+    cfg_schema_cache: &ConfigSchemaCache,
+    cfg_schema_content_cache: &ConfigSchemaContentCache,
+    http_client: &HTTPClientT,
+    token_mngr: &TokenManagerT,
+) -> Result<(String, Value), ServiceErr> {
+    // search the cache for the config schema
+    let digest = args.config_schema_digest().to_string();
+    let config_type_slug = args.config_type_slug().to_string();
+    let cfg_schema = cfg_schema_cache
+        .find_one_optional(
+            "filter by config type slug and schema digest",
+            move |cfg_sch| {
+                matches_config_type_slug_and_schema_digest(cfg_sch, &config_type_slug, &digest)
+            },
+        )
+        .await
+        .map_err(|e| {
+            ServiceErr::CrudErr(Box::new(ServiceCrudErr {
+                source: e,
+                trace: trace!(),
+            }))
+        })?;
+    if let Some(cfg_schema) = cfg_schema {
+        let schema = cfg_schema_content_cache
+            .read_optional(cfg_schema.id.clone())
+            .await
+            .map_err(|e| {
+                ServiceErr::CrudErr(Box::new(ServiceCrudErr {
+                    source: e,
+                    trace: trace!(),
+                }))
+            })?;
+        if let Some(schema) = schema {
+            return Ok((cfg_schema.id, schema));
+        }
+    }
+
+    // search the backend for the config schema (with its json schema expanded)
+    let token = token_mngr.get_token().await.map_err(|e| {
+        ServiceErr::AuthnErr(Box::new(ServiceAuthnErr {
+            source: e,
+            trace: trace!(),
+        }))
+    })?;
+    let filters = ConfigSchemaFilters {
+        ids: None,
+        digests: Some(DigestFilter {
+            not: false,
+            op: SearchOperator::Equals,
+            val: vec![args.config_schema_digest().to_string()],
+        }),
+        config_type_slugs: Some(ConfigTypeSlugFilter {
+            not: false,
+            op: SearchOperator::Equals,
+            val: vec![args.config_type_slug().to_string()],
+        }),
+    };
+    let result = http_client
+        .list_all_config_schemas(
+            filters,
+            [
+                ConfigSchemaExpand::CONFIG_SCHEMA_EXPAND_CONTENT,
+                ConfigSchemaExpand::CONFIG_SCHEMA_EXPAND_CONFIG_TYPE,
+            ],
+            &token.token,
+        )
+        .await;
+    let cfg_schema = match result {
+        Ok(cfg_schemas) => cfg_schemas.into_iter().next(),
+        Err(e) => {
+            if e.is_network_connection_error() {
+                return Err(ServiceErr::ConfigSchemaNotFound(Box::new(
+                    ConfigSchemaNotFound {
+                        digest: args.config_schema_digest().to_string(),
+                        config_type_slug: args.config_type_slug().to_string(),
+                        network_connection_error: true,
+                        trace: trace!(),
+                    },
+                )));
+            }
+            return Err(ServiceErr::HTTPErr(Box::new(ServiceHTTPErr {
+                source: e,
+                trace: trace!(),
+            })));
+        }
+    };
+    let mut cfg_schema = match cfg_schema {
+        Some(cfg_schema) => cfg_schema,
+        None => {
+            return Err(ServiceErr::ConfigSchemaNotFound(Box::new(
+                ConfigSchemaNotFound {
+                    digest: args.config_schema_digest().to_string(),
+                    config_type_slug: args.config_type_slug().to_string(),
+                    network_connection_error: false,
+                    trace: trace!(),
+                },
+            )));
+        }
+    };
+    let schema = match cfg_schema.content.take() {
+        Some(schema) => schema,
+        None => {
+            return Err(ServiceErr::ConfigSchemaContentNotFound(Box::new(
+                ConfigSchemaContentNotFound {
+                    config_schema_id: cfg_schema.id,
+                    trace: trace!(),
+                },
+            )));
+        }
+    };
+
+    // cache the config schema so subsequent validations don't need the backend
+    let id = cfg_schema.id.clone();
+    let overwrite = true;
+    cfg_schema_content_cache
+        .write(id.clone(), schema.clone(), |_, _| false, overwrite)
+        .await
+        .map_err(|e| {
+            ServiceErr::CacheErr(Box::new(ServiceCacheErr {
+                source: e,
+                trace: trace!(),
+            }))
+        })?;
+    cfg_schema_cache
+        .write(
+            id.clone(),
+            ConfigSchema::from_backend(cfg_schema),
+            |_, _| false,
+            overwrite,
+        )
+        .await
+        .map_err(|e| {
+            ServiceErr::CacheErr(Box::new(ServiceCacheErr {
+                source: e,
+                trace: trace!(),
+            }))
+        })?;
+
+    Ok((id, schema))
+}
@@ -0,0 +1,22 @@
+// internal crates
+use crate::services::errors::*;
+use crate::storage::{bundle, bundle::Bundle, caches::Caches, device::DeviceFile};
+use crate::trace;
+
+pub async fn export_device(
+    device_file: &DeviceFile,
+    caches: &Caches,
+) -> Result<Bundle, ServiceErr> {
+    let device = device_file.read().await.map_err(|e| {
+        ServiceErr::FileSysErr(Box::new(ServiceFileSysErr {
+            source: e,
+            trace: trace!(),
+        }))
+    })?;
+    bundle::export(&device, caches).await.map_err(|e| {
+        ServiceErr::StorageErr(Box::new(ServiceStorageErr {
+            source: e,
+            trace: trace!(),
+        }))
+    })
+}
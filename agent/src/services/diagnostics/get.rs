@@ -0,0 +1,19 @@
+// internal crates
+use crate::diagnostics::collect::{self, Options};
+use crate::sync::syncer::SyncerExt;
+
+// external crates
+use tracing::warn;
+
+pub async fn get_diagnostics<SyncerT: SyncerExt>(options: &Options, syncer: &SyncerT) -> Vec<u8> {
+    // the bundle is still useful without the live sync state so failing to retrieve
+    // it doesn't fail the request
+    let sync_state = match syncer.get_sync_state().await {
+        Ok(sync_state) => Some(sync_state),
+        Err(e) => {
+            warn!("Unable to retrieve the sync state for the diagnostics bundle: {e}");
+            None
+        }
+    };
+    collect::collect(options, sync_state.as_ref()).await
+}
@@ -2,8 +2,10 @@
 use std::fmt;
 
 // internal crates
+use crate::authn::errors::AuthnErr;
 use crate::cache::errors::CacheErr;
 use crate::crud::errors::CrudErr;
+use crate::deploy::errors::DeployErr;
 use crate::errors::{Code, HTTPCode, MiruError, Trace};
 use crate::filesys::errors::FileSysErr;
 use crate::http::errors::HTTPErr;
@@ -56,6 +58,42 @@ impl fmt::Display for DeployedConfigInstanceNotFound {
     }
 }
 
+#[derive(Debug)]
+pub struct ConfigInstanceNotFound {
+    pub config_instance_id: String,
+    pub trace: Box<Trace>,
+}
+
+impl MiruError for ConfigInstanceNotFound {
+    fn code(&self) -> Code {
+        Code::ResourceNotFound
+    }
+
+    fn http_status(&self) -> HTTPCode {
+        HTTPCode::NOT_FOUND
+    }
+
+    fn is_network_connection_error(&self) -> bool {
+        false
+    }
+
+    fn params(&self) -> Option<serde_json::Value> {
+        Some(json!({
+            "config_instance_id": self.config_instance_id,
+        }))
+    }
+}
+
+impl fmt::Display for ConfigInstanceNotFound {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Unable to find config instance '{}' on the device",
+            self.config_instance_id
+        )
+    }
+}
+
 #[derive(Debug)]
 pub struct ConfigSchemaNotFound {
     pub digest: String,
@@ -96,6 +134,42 @@ impl fmt::Display for ConfigSchemaNotFound {
     }
 }
 
+#[derive(Debug)]
+pub struct ConfigSchemaContentNotFound {
+    pub config_schema_id: String,
+    pub trace: Box<Trace>,
+}
+
+impl MiruError for ConfigSchemaContentNotFound {
+    fn code(&self) -> Code {
+        Code::ResourceNotFound
+    }
+
+    fn http_status(&self) -> HTTPCode {
+        HTTPCode::NOT_FOUND
+    }
+
+    fn is_network_connection_error(&self) -> bool {
+        false
+    }
+
+    fn params(&self) -> Option<serde_json::Value> {
+        Some(json!({
+            "config_schema_id": self.config_schema_id,
+        }))
+    }
+}
+
+impl fmt::Display for ConfigSchemaContentNotFound {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Config schema '{}' does not have a json schema to validate against",
+            self.config_schema_id
+        )
+    }
+}
+
 #[derive(Debug)]
 pub struct ServiceModelsErr {
     pub source: ModelsErr,
@@ -156,6 +230,66 @@ impl fmt::Display for ServiceSyncErr {
     }
 }
 
+#[derive(Debug)]
+pub struct ServiceDeployErr {
+    pub source: DeployErr,
+    pub trace: Box<Trace>,
+}
+
+impl MiruError for ServiceDeployErr {
+    fn code(&self) -> Code {
+        self.source.code()
+    }
+
+    fn http_status(&self) -> HTTPCode {
+        self.source.http_status()
+    }
+
+    fn is_network_connection_error(&self) -> bool {
+        self.source.is_network_connection_error()
+    }
+
+    fn params(&self) -> Option<serde_json::Value> {
+        self.source.params()
+    }
+}
+
+impl fmt::Display for ServiceDeployErr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Deploy Error: {}", self.source)
+    }
+}
+
+#[derive(Debug)]
+pub struct ServiceAuthnErr {
+    pub source: AuthnErr,
+    pub trace: Box<Trace>,
+}
+
+impl MiruError for ServiceAuthnErr {
+    fn code(&self) -> Code {
+        self.source.code()
+    }
+
+    fn http_status(&self) -> HTTPCode {
+        self.source.http_status()
+    }
+
+    fn is_network_connection_error(&self) -> bool {
+        self.source.is_network_connection_error()
+    }
+
+    fn params(&self) -> Option<serde_json::Value> {
+        self.source.params()
+    }
+}
+
+impl fmt::Display for ServiceAuthnErr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Authn Error: {}", self.source)
+    }
+}
+
 #[derive(Debug)]
 pub struct ServiceCacheErr {
     pub source: CacheErr,
@@ -310,11 +444,15 @@ impl fmt::Display for ServiceHTTPErr {
 pub enum ServiceErr {
     // service errors
     DeployedConfigInstanceNotFound(Box<DeployedConfigInstanceNotFound>),
+    ConfigInstanceNotFound(Box<ConfigInstanceNotFound>),
     ConfigSchemaNotFound(Box<ConfigSchemaNotFound>),
+    ConfigSchemaContentNotFound(Box<ConfigSchemaContentNotFound>),
 
     // internal crate errors
+    AuthnErr(Box<ServiceAuthnErr>),
     CacheErr(Box<ServiceCacheErr>),
     CrudErr(Box<ServiceCrudErr>),
+    DeployErr(Box<ServiceDeployErr>),
     FileSysErr(Box<ServiceFileSysErr>),
     ModelsErr(Box<ServiceModelsErr>),
     StorageErr(Box<ServiceStorageErr>),
@@ -326,10 +464,14 @@ macro_rules! forward_error_method {
     ($self:ident, $method:ident $(, $arg:expr)?) => {
         match $self {
             Self::DeployedConfigInstanceNotFound(e) => e.$method($($arg)?),
+            Self::ConfigInstanceNotFound(e) => e.$method($($arg)?),
             Self::ConfigSchemaNotFound(e) => e.$method($($arg)?),
+            Self::ConfigSchemaContentNotFound(e) => e.$method($($arg)?),
 
+            Self::AuthnErr(e) => e.$method($($arg)?),
             Self::CacheErr(e) => e.$method($($arg)?),
             Self::CrudErr(e) => e.$method($($arg)?),
+            Self::DeployErr(e) => e.$method($($arg)?),
             Self::FileSysErr(e) => e.$method($($arg)?),
             Self::ModelsErr(e) => e.$method($($arg)?),
             Self::StorageErr(e) => e.$method($($arg)?),
@@ -0,0 +1,89 @@
+// internal crates
+use crate::authn::token_mngr::TokenManagerExt;
+use crate::cache::errors::CacheErr;
+use crate::metrics::{
+    prometheus::{encode_registry, Encoder},
+    registry::Metrics,
+};
+use crate::services::errors::*;
+use crate::storage::caches::Caches;
+use crate::sync::syncer::SyncerExt;
+use crate::trace;
+
+// external crates
+use chrono::Utc;
+
+pub async fn get_metrics<SyncerT: SyncerExt, TokenManagerT: TokenManagerExt>(
+    metrics: &Metrics,
+    syncer: &SyncerT,
+    token_mngr: &TokenManagerT,
+    caches: &Caches,
+) -> Result<String, ServiceErr> {
+    let mut encoder = Encoder::new();
+    encode_registry(&mut encoder, metrics);
+
+    // sync state
+    let sync_state = syncer.get_sync_state().await.map_err(|e| {
+        ServiceErr::SyncErr(Box::new(ServiceSyncErr {
+            source: e,
+            trace: trace!(),
+        }))
+    })?;
+    encoder.gauge(
+        "sync_err_streak",
+        "Number of consecutive failed syncs (excluding network connection errors)",
+        sync_state.err_streak as f64,
+    );
+    let cooldown_remaining = (sync_state.cooldown_ends_at - Utc::now())
+        .num_milliseconds()
+        .max(0) as f64
+        / 1000.0;
+    encoder.gauge(
+        "sync_cooldown_remaining_seconds",
+        "Seconds until the syncer's cooldown ends",
+        cooldown_remaining,
+    );
+
+    // token
+    let token = token_mngr.get_token().await.map_err(|e| {
+        ServiceErr::AuthnErr(Box::new(ServiceAuthnErr {
+            source: e,
+            trace: trace!(),
+        }))
+    })?;
+    encoder.gauge(
+        "token_expires_at_seconds",
+        "Unix timestamp at which the device's token expires",
+        token.expires_at.timestamp() as f64,
+    );
+
+    // caches
+    let cfg_inst = cache_size(caches.cfg_inst.size().await)?;
+    let cfg_inst_content = cache_size(caches.cfg_inst_content.size().await)?;
+    let cfg_schema = cache_size(caches.cfg_schema.size().await)?;
+    let cfg_sch_digest = cache_size(caches.cfg_sch_digest.size().await)?;
+    encoder.labeled_gauges(
+        "cache_entries",
+        "Number of entries in each of the agent's caches",
+        &[
+            (&[("cache", "config_instances")], cfg_inst as f64),
+            (
+                &[("cache", "config_instance_contents")],
+                cfg_inst_content as f64,
+            ),
+            (&[("cache", "config_schemas")], cfg_schema as f64),
+            (&[("cache", "config_schema_digests")], cfg_sch_digest as f64),
+        ],
+    );
+
+    Ok(encoder.finish())
+}
+
+fn cache_size(result: Result<usize, CacheErr>) -> Result<usize, ServiceErr> {
+    result.map_err(|e| {
+        ServiceErr::CacheErr(Box::new(ServiceCacheErr {
+            source: e,
+            trace: trace!(),
+        }))
+    })
+}
@@ -1,4 +1,6 @@
 pub mod config_instances;
 pub mod config_schemas;
 pub mod device;
+pub mod diagnostics;
 pub mod errors;
+pub mod metrics;
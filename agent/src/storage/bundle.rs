@@ -0,0 +1,239 @@
+// internal crates
+use crate::cache::{
+    dir::SingleThreadDirCache,
+    entry::CacheEntry,
+    file::SingleThreadFileCache,
+    single_thread::{CacheKey, CacheValue, SingleThreadCache},
+};
+use crate::filesys::file::File;
+use crate::models::config_instance::ConfigInstanceID;
+use crate::models::device::Device;
+use crate::storage::caches::{CacheCapacities, Caches};
+use crate::storage::config_instances::ConfigInstanceCacheEntry;
+use crate::storage::config_schemas::ConfigSchemaCacheEntry;
+use crate::storage::digests::ConfigSchemaDigestCacheEntry;
+use crate::storage::errors::*;
+use crate::storage::layout::StorageLayout;
+use crate::trace;
+use crate::utils::version_info;
+
+// external crates
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+pub const BUNDLE_VERSION: u32 = 1;
+
+pub type ConfigInstanceContentCacheEntry = CacheEntry<ConfigInstanceID, serde_json::Value>;
+
+// a bundle is a portable snapshot of the device's local config state. It intentionally
+// excludes the auth directory (private / public keys and the token file) so that it
+// is safe to share for debugging and to load into a test storage layout.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct Bundle {
+    pub version: u32,
+    pub agent_version: String,
+    pub exported_at: DateTime<Utc>,
+    pub device: Device,
+    pub cfg_insts: Vec<ConfigInstanceCacheEntry>,
+    pub cfg_inst_contents: Vec<ConfigInstanceContentCacheEntry>,
+    pub cfg_schemas: Vec<ConfigSchemaCacheEntry>,
+    pub cfg_sch_digests: Vec<ConfigSchemaDigestCacheEntry>,
+}
+
+pub async fn export(device: &Device, caches: &Caches) -> Result<Bundle, StorageErr> {
+    let mut cfg_insts = caches.cfg_inst.entries().await.map_err(|e| {
+        StorageErr::CacheErr(Box::new(StorageCacheErr {
+            source: e,
+            trace: trace!(),
+        }))
+    })?;
+    cfg_insts.sort_by(|a, b| a.key.cmp(&b.key));
+
+    let mut cfg_inst_contents = caches.cfg_inst_content.entries().await.map_err(|e| {
+        StorageErr::CacheErr(Box::new(StorageCacheErr {
+            source: e,
+            trace: trace!(),
+        }))
+    })?;
+    cfg_inst_contents.sort_by(|a, b| a.key.cmp(&b.key));
+
+    let mut cfg_schemas = caches.cfg_schema.entries().await.map_err(|e| {
+        StorageErr::CacheErr(Box::new(StorageCacheErr {
+            source: e,
+            trace: trace!(),
+        }))
+    })?;
+    cfg_schemas.sort_by(|a, b| a.key.cmp(&b.key));
+
+    let mut cfg_sch_digests = caches.cfg_sch_digest.entries().await.map_err(|e| {
+        StorageErr::CacheErr(Box::new(StorageCacheErr {
+            source: e,
+            trace: trace!(),
+        }))
+    })?;
+    cfg_sch_digests.sort_by(|a, b| a.key.cmp(&b.key));
+
+    Ok(Bundle {
+        version: BUNDLE_VERSION,
+        agent_version: version_info().version,
+        exported_at: Utc::now(),
+        device: device.clone(),
+        cfg_insts,
+        cfg_inst_contents,
+        cfg_schemas,
+        cfg_sch_digests,
+    })
+}
+
+// exports the bundle straight from the storage layout. Used when the agent's caches
+// are not already running (e.g. from the command line).
+pub async fn export_layout(layout: &StorageLayout) -> Result<Bundle, StorageErr> {
+    let device = layout
+        .device_file()
+        .read_json::<Device>()
+        .await
+        .map_err(|e| {
+            StorageErr::FileSysErr(Box::new(StorageFileSysErr {
+                source: e,
+                trace: trace!(),
+            }))
+        })?;
+
+    let (caches, caches_shutdown_handle) = Caches::init(layout, CacheCapacities::default()).await?;
+    let result = export(&device, &caches).await;
+    caches.shutdown().await?;
+    caches_shutdown_handle.await;
+
+    result
+}
+
+pub async fn write(bundle: &Bundle, file: &File) -> Result<(), StorageErr> {
+    file.write_json(bundle, true, true).await.map_err(|e| {
+        StorageErr::FileSysErr(Box::new(StorageFileSysErr {
+            source: e,
+            trace: trace!(),
+        }))
+    })
+}
+
+pub async fn read(file: &File) -> Result<Bundle, StorageErr> {
+    let bundle = file.read_json::<Bundle>().await.map_err(|e| {
+        StorageErr::FileSysErr(Box::new(StorageFileSysErr {
+            source: e,
+            trace: trace!(),
+        }))
+    })?;
+    assert_supported(&bundle)?;
+    Ok(bundle)
+}
+
+// loads the bundle into the storage layout, overwriting the device file and any cache
+// entries with the same keys. The cache entries are written as is so that their dirty
+// flags and timestamps are preserved.
+pub async fn import(layout: &StorageLayout, bundle: &Bundle) -> Result<(), StorageErr> {
+    assert_supported(bundle)?;
+
+    // device
+    layout
+        .device_file()
+        .write_json(&bundle.device, true, true)
+        .await
+        .map_err(|e| {
+            StorageErr::FileSysErr(Box::new(StorageFileSysErr {
+                source: e,
+                trace: trace!(),
+            }))
+        })?;
+
+    // config instances
+    let mut cfg_inst_cache =
+        SingleThreadFileCache::new(layout.config_instance_cache(), bundle.cfg_insts.len())
+            .await
+            .map_err(|e| {
+                StorageErr::CacheErr(Box::new(StorageCacheErr {
+                    source: e,
+                    trace: trace!(),
+                }))
+            })?;
+    for entry in bundle.cfg_insts.iter() {
+        write_entry(&mut cfg_inst_cache, entry).await?;
+    }
+
+    // config instance contents
+    let mut cfg_inst_content_cache = SingleThreadDirCache::new(
+        layout.config_instance_content_cache(),
+        bundle.cfg_inst_contents.len(),
+    )
+    .await
+    .map_err(|e| {
+        StorageErr::CacheErr(Box::new(StorageCacheErr {
+            source: e,
+            trace: trace!(),
+        }))
+    })?;
+    for entry in bundle.cfg_inst_contents.iter() {
+        write_entry(&mut cfg_inst_content_cache, entry).await?;
+    }
+
+    // config schemas
+    let mut cfg_schema_cache =
+        SingleThreadFileCache::new(layout.config_schema_cache(), bundle.cfg_schemas.len())
+            .await
+            .map_err(|e| {
+                StorageErr::CacheErr(Box::new(StorageCacheErr {
+                    source: e,
+                    trace: trace!(),
+                }))
+            })?;
+    for entry in bundle.cfg_schemas.iter() {
+        write_entry(&mut cfg_schema_cache, entry).await?;
+    }
+
+    // config schema digests
+    let mut cfg_sch_digest_cache = SingleThreadFileCache::new(
+        layout.config_schema_digest_cache(),
+        bundle.cfg_sch_digests.len(),
+    )
+    .await
+    .map_err(|e| {
+        StorageErr::CacheErr(Box::new(StorageCacheErr {
+            source: e,
+            trace: trace!(),
+        }))
+    })?;
+    for entry in bundle.cfg_sch_digests.iter() {
+        write_entry(&mut cfg_sch_digest_cache, entry).await?;
+    }
+
+    Ok(())
+}
+
+fn assert_supported(bundle: &Bundle) -> Result<(), StorageErr> {
+    if bundle.version != BUNDLE_VERSION {
+        return Err(StorageErr::UnsupportedBundleVersionErr(Box::new(
+            UnsupportedBundleVersionErr {
+                version: bundle.version,
+                supported: BUNDLE_VERSION,
+                trace: trace!(),
+            },
+        )));
+    }
+    Ok(())
+}
+
+async fn write_entry<CacheT, K, V>(
+    cache: &mut CacheT,
+    entry: &CacheEntry<K, V>,
+) -> Result<(), StorageErr>
+where
+    CacheT: SingleThreadCache<K, V>,
+    K: CacheKey,
+    V: CacheValue,
+{
+    cache.write_entry_impl(entry, true).await.map_err(|e| {
+        StorageErr::CacheErr(Box::new(StorageCacheErr {
+            source: e,
+            trace: trace!(),
+        }))
+    })
+}
@@ -4,7 +4,7 @@ use std::sync::Arc;
 
 // internal crates
 use crate::storage::config_instances::{ConfigInstanceCache, ConfigInstanceContentCache};
-use crate::storage::config_schemas::ConfigSchemaCache;
+use crate::storage::config_schemas::{ConfigSchemaCache, ConfigSchemaContentCache};
 use crate::storage::digests::ConfigSchemaDigestCache;
 use crate::storage::errors::*;
 use crate::storage::layout::StorageLayout;
@@ -16,6 +16,7 @@ pub struct CacheCapacities {
     pub cfg_inst: usize,
     pub cfg_inst_content: usize,
     pub cfg_schema: usize,
+    pub cfg_schema_content: usize,
 }
 
 impl Default for CacheCapacities {
@@ -25,6 +26,7 @@ impl Default for CacheCapacities {
             cfg_inst: 100,
             cfg_inst_content: 100,
             cfg_schema: 100,
+            cfg_schema_content: 100,
         }
     }
 }
@@ -35,6 +37,7 @@ pub struct Caches {
     pub cfg_inst: Arc<ConfigInstanceCache>,
     pub cfg_inst_content: Arc<ConfigInstanceContentCache>,
     pub cfg_schema: Arc<ConfigSchemaCache>,
+    pub cfg_schema_content: Arc<ConfigSchemaContentCache>,
 }
 
 impl Caches {
@@ -69,6 +72,22 @@ impl Caches {
                 })?;
         let cfg_schema_cache = Arc::new(cfg_schema_cache);
 
+        // config schema content
+        let (cfg_schema_content_cache, cfg_schema_content_cache_handle) =
+            ConfigSchemaContentCache::spawn(
+                64,
+                layout.config_schema_content_cache(),
+                capacities.cfg_schema_content,
+            )
+            .await
+            .map_err(|e| {
+                StorageErr::CacheErr(Box::new(StorageCacheErr {
+                    source: e,
+                    trace: trace!(),
+                }))
+            })?;
+        let cfg_schema_content_cache = Arc::new(cfg_schema_content_cache);
+
         // config instance
         let (cfg_inst_cache, cfg_inst_cache_handle) =
             ConfigInstanceCache::spawn(64, layout.config_instance_cache(), capacities.cfg_inst)
@@ -104,6 +123,7 @@ impl Caches {
                 cfg_inst_cache_handle,
                 cfg_inst_content_cache_handle,
                 cfg_schema_cache_handle,
+                cfg_schema_content_cache_handle,
             ];
 
             futures::future::join_all(handles).await;
@@ -115,6 +135,7 @@ impl Caches {
                 cfg_inst: cfg_inst_cache,
                 cfg_inst_content: cfg_inst_content_cache,
                 cfg_schema: cfg_schema_cache,
+                cfg_schema_content: cfg_schema_content_cache,
             },
             shutdown_handle,
         ))
@@ -145,6 +166,12 @@ impl Caches {
                 trace: trace!(),
             }))
         })?;
+        self.cfg_schema_content.shutdown().await.map_err(|e| {
+            StorageErr::CacheErr(Box::new(StorageCacheErr {
+                source: e,
+                trace: trace!(),
+            }))
+        })?;
 
         Ok(())
     }
@@ -1,7 +1,10 @@
 // internal crates
-use crate::cache::{entry::CacheEntry, file::FileCache};
+use crate::cache::{dir::DirCache, entry::CacheEntry, file::FileCache};
 use crate::models::config_schema::{ConfigSchema, ConfigSchemaID};
 
 // config schema cache
 pub type ConfigSchemaCacheEntry = CacheEntry<ConfigSchemaID, ConfigSchema>;
 pub type ConfigSchemaCache = FileCache<ConfigSchemaID, ConfigSchema>;
+
+// config schema content cache (the json schema documents)
+pub type ConfigSchemaContentCache = DirCache<ConfigSchemaID, serde_json::Value>;
@@ -37,6 +37,41 @@ impl fmt::Display for DeviceNotActivatedErr {
     }
 }
 
+#[derive(Debug)]
+pub struct UnsupportedBundleVersionErr {
+    pub version: u32,
+    pub supported: u32,
+    pub trace: Box<Trace>,
+}
+
+impl MiruError for UnsupportedBundleVersionErr {
+    fn code(&self) -> Code {
+        Code::UnsupportedBundleVersion
+    }
+
+    fn http_status(&self) -> HTTPCode {
+        HTTPCode::BAD_REQUEST
+    }
+
+    fn is_network_connection_error(&self) -> bool {
+        false
+    }
+
+    fn params(&self) -> Option<serde_json::Value> {
+        None
+    }
+}
+
+impl fmt::Display for UnsupportedBundleVersionErr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "bundle version {} is not supported (supported version: {})",
+            self.version, self.supported
+        )
+    }
+}
+
 #[derive(Debug)]
 pub struct StorageCacheErr {
     pub source: CacheErr,
@@ -192,6 +227,7 @@ pub enum StorageErr {
     // storage errors
     DeviceNotActivatedErr(Box<DeviceNotActivatedErr>),
     PruneCacheErrs(Box<PruneCacheErrs>),
+    UnsupportedBundleVersionErr(Box<UnsupportedBundleVersionErr>),
 
     // internal crate errors
     CacheErr(Box<StorageCacheErr>),
@@ -207,6 +243,7 @@ macro_rules! forward_error_method {
         match $self {
             Self::DeviceNotActivatedErr(e) => e.$method($($arg)?),
             Self::PruneCacheErrs(e) => e.$method($($arg)?),
+            Self::UnsupportedBundleVersionErr(e) => e.$method($($arg)?),
             Self::CacheErr(e) => e.$method($($arg)?),
             Self::CryptErr(e) => e.$method($($arg)?),
             Self::FileSysErr(e) => e.$method($($arg)?),
@@ -0,0 +1,32 @@
+// standard library
+use std::time::Duration;
+
+// internal crates
+use crate::filesys::{errors::FileSysErr, file::File};
+use crate::mqtt::events::ConfigInstanceEvent;
+use crate::storage::outbox::{Queue, Queued};
+
+// external crates
+use tokio::task::JoinHandle;
+
+// the config instance events waiting to be published over mqtt. Events never expire
+// and are only removed once the broker acknowledged them.
+pub type EventQueue = Queue<ConfigInstanceEvent>;
+
+impl Queued for ConfigInstanceEvent {
+    const NAME: &'static str = "event queue";
+
+    fn id(&self) -> &str {
+        &self.event_id
+    }
+}
+
+impl EventQueue {
+    pub async fn spawn(
+        buffer_size: usize,
+        file: File,
+        capacity: usize,
+    ) -> Result<(Self, JoinHandle<()>), FileSysErr> {
+        Self::open(buffer_size, file, capacity, Duration::MAX).await
+    }
+}
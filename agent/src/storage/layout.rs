@@ -36,10 +36,32 @@ impl StorageLayout {
         self.internal_dir().file("settings.json")
     }
 
+    pub fn events_dir(&self) -> Dir {
+        self.internal_dir().subdir("events")
+    }
+
+    pub fn config_instance_event_queue(&self) -> File {
+        self.events_dir().file("config_instances.json")
+    }
+
+    pub fn mqtt_outbox(&self) -> File {
+        self.internal_dir().subdir("mqtt").file("outbox.json")
+    }
+
+    pub fn local_bridge_topics(&self) -> File {
+        self.internal_dir()
+            .subdir("mqtt")
+            .file("local_bridge_topics.json")
+    }
+
     pub fn caches_dir(&self) -> Dir {
         self.internal_dir().subdir("cache")
     }
 
+    pub fn http_cache_dir(&self) -> Dir {
+        self.caches_dir().subdir("http")
+    }
+
     pub fn config_schema_caches(&self) -> Dir {
         self.caches_dir().subdir("config_schemas")
     }
@@ -52,6 +74,10 @@ impl StorageLayout {
         self.config_schema_caches().file("metadata.json")
     }
 
+    pub fn config_schema_content_cache(&self) -> Dir {
+        self.config_schema_caches().subdir("contents")
+    }
+
     pub fn config_instance_caches(&self) -> Dir {
         self.caches_dir().subdir("config_instances")
     }
@@ -98,4 +124,8 @@ impl AuthLayout {
     pub fn token_file(&self) -> File {
         self.root.file("token.json")
     }
+
+    pub fn api_tokens_file(&self) -> File {
+        self.root.file("api_tokens.json")
+    }
 }
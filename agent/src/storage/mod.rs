@@ -1,9 +1,12 @@
+pub mod bundle;
 pub mod caches;
 pub mod config_instances;
 pub mod config_schemas;
 pub mod device;
 pub mod digests;
 pub mod errors;
+pub mod events;
 pub mod layout;
+pub mod outbox;
 pub mod settings;
 pub mod setup;
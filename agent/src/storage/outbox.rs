@@ -0,0 +1,295 @@
+// standard library
+use std::collections::{HashSet, VecDeque};
+use std::time::Duration;
+
+// internal crates
+use crate::filesys::{cached_file::ConcurrentCachedFile, errors::FileSysErr, file::File};
+use crate::metrics::registry;
+use crate::mqtt::client::Properties;
+use crate::utils::Mergeable;
+
+// external crates
+use chrono::{DateTime, Utc};
+use rumqttc::QoS;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use tokio::sync::Notify;
+use tokio::task::JoinHandle;
+use tracing::warn;
+use uuid::Uuid;
+
+// ================================== CONTENT ====================================== //
+// an item which can be queued for publishing
+pub trait Queued:
+    Clone + std::fmt::Debug + Serialize + DeserializeOwned + PartialEq + Send + Sync + 'static
+{
+    // the queue's name in logs
+    const NAME: &'static str;
+
+    fn id(&self) -> &str;
+
+    // whether pushing this item removes the queued item
+    fn replaces(&self, _queued: &Self) -> bool {
+        false
+    }
+
+    fn is_expired(&self, _now: DateTime<Utc>) -> bool {
+        false
+    }
+
+    fn record_depth(_len: usize) {}
+
+    fn record_dropped(_num_dropped: u64) {}
+}
+
+// a message waiting to be published over mqtt
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OutboundMessage {
+    pub message_id: String,
+    pub topic: String,
+    pub payload: Vec<u8>,
+    // 0 (at most once), 1 (at least once) or 2 (exactly once)
+    pub qos: u8,
+    pub retain: bool,
+    pub properties: Properties,
+    pub queued_at: DateTime<Utc>,
+    // the message is dropped instead of being published once it has expired
+    pub expires_at: DateTime<Utc>,
+}
+
+impl OutboundMessage {
+    pub fn new(
+        topic: &str,
+        qos: QoS,
+        retain: bool,
+        payload: &[u8],
+        properties: Properties,
+        ttl: Duration,
+    ) -> Self {
+        let queued_at = Utc::now();
+        Self {
+            message_id: Uuid::new_v4().to_string(),
+            topic: topic.to_string(),
+            payload: payload.to_vec(),
+            qos: qos as u8,
+            retain,
+            properties,
+            queued_at,
+            expires_at: chrono::Duration::from_std(ttl)
+                .ok()
+                .and_then(|ttl| queued_at.checked_add_signed(ttl))
+                .unwrap_or(DateTime::<Utc>::MAX_UTC),
+        }
+    }
+
+    pub fn qos(&self) -> QoS {
+        rumqttc::qos(self.qos).unwrap_or(QoS::AtLeastOnce)
+    }
+}
+
+impl Queued for OutboundMessage {
+    const NAME: &'static str = "mqtt outbox";
+
+    fn id(&self) -> &str {
+        &self.message_id
+    }
+
+    // only the newest retained message of a topic would be retained by the broker
+    fn replaces(&self, queued: &Self) -> bool {
+        self.retain && queued.retain && self.topic == queued.topic
+    }
+
+    fn is_expired(&self, now: DateTime<Utc>) -> bool {
+        self.expires_at <= now
+    }
+
+    fn record_depth(len: usize) {
+        registry::global().mqtt.outbox_messages.set(len as i64);
+    }
+
+    fn record_dropped(num_dropped: u64) {
+        registry::global().mqtt.outbox_dropped.inc_by(num_dropped);
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QueuedMessages<T> {
+    // oldest first (queues of config instance events used to name them 'events')
+    #[serde(alias = "events")]
+    pub messages: VecDeque<T>,
+    // the number of messages dropped because the queue was full or they expired
+    pub num_dropped: u64,
+}
+
+impl<T> Default for QueuedMessages<T> {
+    fn default() -> Self {
+        Self {
+            messages: VecDeque::new(),
+            num_dropped: 0,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum Updates<T> {
+    // removes the queued messages the message replaces and drops the oldest messages
+    // to make room once the queue is at capacity
+    Push { message: T, capacity: usize },
+    // removes the messages which have been published
+    Remove { message_ids: Vec<String> },
+    // drops the messages which expired before they could be published
+    Expire { now: DateTime<Utc> },
+}
+
+impl<T: Queued> Mergeable<Updates<T>> for QueuedMessages<T> {
+    fn merge(&mut self, updates: Updates<T>) {
+        match updates {
+            Updates::Push { message, capacity } => {
+                self.messages.retain(|queued| !message.replaces(queued));
+                self.messages.push_back(message);
+                while self.messages.len() > capacity {
+                    self.messages.pop_front();
+                    self.num_dropped += 1;
+                }
+            }
+            Updates::Remove { message_ids } => {
+                let message_ids: HashSet<String> = message_ids.into_iter().collect();
+                self.messages
+                    .retain(|message| !message_ids.contains(message.id()));
+            }
+            Updates::Expire { now } => {
+                let num_messages = self.messages.len();
+                self.messages.retain(|message| !message.is_expired(now));
+                self.num_dropped += (num_messages - self.messages.len()) as u64;
+            }
+        }
+    }
+}
+
+pub type OutboxFile<T> = ConcurrentCachedFile<QueuedMessages<T>, Updates<T>>;
+
+// =================================== QUEUE ======================================= //
+// a bounded queue of the messages waiting to be published over mqtt. The queue is
+// persisted so messages survive the agent restarting and are published (in order)
+// once the broker is reachable again.
+#[derive(Debug)]
+pub struct Queue<T: Queued> {
+    file: OutboxFile<T>,
+    capacity: usize,
+    ttl: Duration,
+    pushed: Notify,
+}
+
+// the mqtt messages which couldn't be published because the broker was unreachable
+pub type Outbox = Queue<OutboundMessage>;
+
+impl Outbox {
+    pub async fn spawn(
+        buffer_size: usize,
+        file: File,
+        capacity: usize,
+        ttl: Duration,
+    ) -> Result<(Self, JoinHandle<()>), FileSysErr> {
+        Self::open(buffer_size, file, capacity, ttl).await
+    }
+
+    // how long messages are kept before they're dropped
+    pub fn ttl(&self) -> Duration {
+        self.ttl
+    }
+}
+
+impl<T: Queued> Queue<T> {
+    pub(crate) async fn open(
+        buffer_size: usize,
+        file: File,
+        capacity: usize,
+        ttl: Duration,
+    ) -> Result<(Self, JoinHandle<()>), FileSysErr> {
+        let (file, handle) =
+            OutboxFile::spawn_with_default(buffer_size, file, QueuedMessages::default()).await?;
+        let outbox = Self {
+            file,
+            capacity,
+            ttl,
+            pushed: Notify::new(),
+        };
+        outbox.record_depth().await;
+        Ok((outbox, handle))
+    }
+
+    pub async fn shutdown(&self) -> Result<(), FileSysErr> {
+        self.file.shutdown().await
+    }
+
+    pub async fn push(&self, message: T) -> Result<(), FileSysErr> {
+        let num_dropped = self.file.read().await?.num_dropped;
+        self.file
+            .patch(Updates::Push {
+                message,
+                capacity: self.capacity,
+            })
+            .await?;
+        let dropped = self.file.read().await?.num_dropped - num_dropped;
+        if dropped > 0 {
+            warn!(
+                "{} is full ({} messages), dropped the oldest message",
+                T::NAME,
+                self.capacity
+            );
+            T::record_dropped(dropped);
+        }
+        self.record_depth().await;
+        self.pushed.notify_one();
+        Ok(())
+    }
+
+    // the oldest unexpired messages in the queue (without removing them)
+    pub async fn peek(&self, max: usize) -> Result<Vec<T>, FileSysErr> {
+        let num_dropped = self.file.read().await?.num_dropped;
+        self.file.patch(Updates::Expire { now: Utc::now() }).await?;
+        let queued = self.file.read().await?;
+        let expired = queued.num_dropped - num_dropped;
+        if expired > 0 {
+            warn!("dropped {expired} expired messages from the {}", T::NAME);
+            T::record_dropped(expired);
+            self.record_depth().await;
+        }
+        Ok(queued.messages.iter().take(max).cloned().collect())
+    }
+
+    pub async fn remove(&self, message_ids: Vec<String>) -> Result<(), FileSysErr> {
+        self.file.patch(Updates::Remove { message_ids }).await?;
+        self.record_depth().await;
+        Ok(())
+    }
+
+    pub async fn len(&self) -> Result<usize, FileSysErr> {
+        Ok(self.file.read().await?.messages.len())
+    }
+
+    pub async fn is_empty(&self) -> Result<bool, FileSysErr> {
+        Ok(self.len().await? == 0)
+    }
+
+    pub async fn num_dropped(&self) -> Result<u64, FileSysErr> {
+        Ok(self.file.read().await?.num_dropped)
+    }
+
+    // wakes up whoever is waiting for messages to be pushed (e.g. to publish the
+    // messages left over from a partially published batch)
+    pub fn wake(&self) {
+        self.pushed.notify_one();
+    }
+
+    // resolves once a message has been pushed (immediately if one was pushed since the
+    // last time this resolved)
+    pub async fn pushed(&self) {
+        self.pushed.notified().await
+    }
+
+    async fn record_depth(&self) {
+        if let Ok(len) = self.len().await {
+            T::record_depth(len);
+        }
+    }
+}
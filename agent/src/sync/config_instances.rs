@@ -1,17 +1,29 @@
+// standard crates
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+// internal crates
 use crate::crud::prelude::*;
 use crate::deploy::{apply::apply, fsm};
 use crate::filesys::dir::Dir;
 use crate::http::{
     config_instances::{
         ActivityStatusFilter, ConfigInstanceFiltersBuilder, ConfigInstancesExt, IDFilter,
+        MAX_BULK_UPDATE_SIZE,
     },
+    config_schemas::ConfigSchemasExt,
+    errors::HTTPErr,
     search::SearchOperator,
 };
 use crate::models::config_instance::{ActivityStatus, ConfigInstance, ErrorStatus, TargetStatus};
 use crate::storage::config_instances::{ConfigInstanceCache, ConfigInstanceContentCache};
+use crate::storage::config_schemas::ConfigSchemaContentCache;
+use crate::storage::events::EventQueue;
+use crate::sync::config_schemas;
 use crate::sync::errors::*;
 use crate::trace;
 use openapi_client::models::{
+    BulkUpdateConfigInstance, BulkUpdateConfigInstanceResult, BulkUpdateConfigInstancesRequest,
     ConfigInstance as BackendConfigInstance, ConfigInstanceActivityStatus, ConfigInstanceExpand,
     UpdateConfigInstanceRequest,
 };
@@ -20,13 +32,24 @@ use openapi_client::models::{
 use tracing::{debug, error};
 
 // =================================== SYNC ======================================== //
-pub async fn sync<HTTPClientT: ConfigInstancesExt>(
+// where and how the pulled config instances are deployed
+pub struct DeployArgs<'a> {
+    pub deployment_dir: &'a Dir,
+    pub fsm_settings: &'a fsm::Settings,
+    // queues an event for every config instance transition (if set)
+    pub event_queue: Option<&'a EventQueue>,
+    // caches the json schemas which the config instance contents are validated
+    // against before being deployed (if set)
+    pub cfg_schema_content_cache: Option<&'a ConfigSchemaContentCache>,
+}
+
+pub async fn sync<HTTPClientT: ConfigInstancesExt + ConfigSchemasExt>(
     cfg_inst_cache: &ConfigInstanceCache,
     cfg_inst_content_cache: &ConfigInstanceContentCache,
     http_client: &HTTPClientT,
     device_id: &str,
-    deployment_dir: &Dir,
-    fsm_settings: &fsm::Settings,
+    deploy_args: DeployArgs<'_>,
+    bulk_updates: &mut BulkUpdates,
     token: &str,
 ) -> Result<(), SyncErr> {
     let mut errors = Vec::new();
@@ -48,6 +71,17 @@ pub async fn sync<HTTPClientT: ConfigInstancesExt>(
         }
     };
 
+    // pull the config schemas which the config instances are validated against
+    if let Some(cfg_schema_content_cache) = deploy_args.cfg_schema_content_cache {
+        debug!("Pulling config schemas from server");
+        let result =
+            config_schemas::pull(cfg_inst_cache, cfg_schema_content_cache, http_client, token)
+                .await;
+        if let Err(e) = result {
+            errors.push(e);
+        }
+    }
+
     // read the config instances which need to be applied
     debug!("Reading config instances which need to be applied");
     let cfg_insts_to_apply = cfg_inst_cache
@@ -69,8 +103,10 @@ pub async fn sync<HTTPClientT: ConfigInstancesExt>(
         cfg_insts_to_apply,
         cfg_inst_cache,
         cfg_inst_content_cache,
-        deployment_dir,
-        fsm_settings,
+        deploy_args.cfg_schema_content_cache,
+        deploy_args.deployment_dir,
+        deploy_args.fsm_settings,
+        deploy_args.event_queue,
     )
     .await
     .map_err(|e| {
@@ -82,7 +118,7 @@ pub async fn sync<HTTPClientT: ConfigInstancesExt>(
 
     // push config instances to server
     debug!("Pushing config instances to server");
-    let result = push(cfg_inst_cache, http_client, token).await;
+    let result = push(cfg_inst_cache, http_client, bulk_updates, token).await;
     match result {
         Ok(_) => (),
         Err(e) => {
@@ -406,9 +442,33 @@ async fn update_target_status_instances(
 }
 
 // =================================== PUSH ======================================== //
+// how long config instances are pushed individually once the backend turned out not
+// to support bulk updates before bulk updates are attempted again (the backend may
+// have been upgraded in the meantime)
+pub const BULK_UPDATE_RETRY_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+// remembers across pushes whether the backend supports bulk updates so that every push
+// doesn't first attempt a bulk update the backend rejects
+#[derive(Debug, Default)]
+pub struct BulkUpdates {
+    unsupported_at: Option<Instant>,
+}
+
+impl BulkUpdates {
+    pub fn is_supported(&self) -> bool {
+        self.unsupported_at
+            .is_none_or(|at| at.elapsed() >= BULK_UPDATE_RETRY_INTERVAL)
+    }
+
+    pub fn set_unsupported(&mut self) {
+        self.unsupported_at = Some(Instant::now());
+    }
+}
+
 pub async fn push<HTTPClientT: ConfigInstancesExt>(
     cfg_inst_cache: &ConfigInstanceCache,
     http_client: &HTTPClientT,
+    bulk_updates: &mut BulkUpdates,
     token: &str,
 ) -> Result<(), SyncErr> {
     // get all unsynced config instances
@@ -423,13 +483,138 @@ pub async fn push<HTTPClientT: ConfigInstancesExt>(
         unsynced_cfg_insts.len(),
         unsynced_cfg_insts
     );
+    let mut unsynced_cfg_insts: Vec<ConfigInstance> = unsynced_cfg_insts
+        .into_iter()
+        .map(|entry| entry.value)
+        .collect();
 
     let mut errors = Vec::new();
 
-    // push each unsynced config instance to the server and update the cache
-    for unsynced_cfg_inst in unsynced_cfg_insts {
-        let inst = unsynced_cfg_inst.value;
+    // push the unsynced config instances in batches, falling back to pushing them one
+    // at a time if the backend doesn't support bulk updates
+    while bulk_updates.is_supported() && !unsynced_cfg_insts.is_empty() {
+        let n = unsynced_cfg_insts.len().min(MAX_BULK_UPDATE_SIZE);
+        let batch: Vec<ConfigInstance> = unsynced_cfg_insts.drain(..n).collect();
+        match push_batch(cfg_inst_cache, http_client, &batch, token).await {
+            Ok(batch_errors) => errors.extend(batch_errors),
+            Err(e) if e.is_unsupported_endpoint_error() => {
+                debug!("Backend does not support bulk config instance updates, pushing config instances individually");
+                bulk_updates.set_unsupported();
+                unsynced_cfg_insts.splice(0..0, batch);
+            }
+            Err(e) => {
+                error!(
+                    "Failed to push {} config instances to backend: {}",
+                    batch.len(),
+                    e
+                );
+                errors.push(SyncErr::HTTPClientErr(Box::new(SyncHTTPClientErr {
+                    source: e,
+                    trace: trace!(),
+                })));
+            }
+        }
+    }
+    errors.extend(push_each(cfg_inst_cache, http_client, unsynced_cfg_insts, token).await);
 
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(SyncErr::SyncErrors(Box::new(SyncErrors {
+            source: errors,
+            trace: trace!(),
+        })))
+    }
+}
+
+// pushes the config instances in a single bulk request and marks each config instance
+// which the backend successfully updated as clean. Returns the errors for the config
+// instances which failed to update.
+async fn push_batch<HTTPClientT: ConfigInstancesExt>(
+    cfg_inst_cache: &ConfigInstanceCache,
+    http_client: &HTTPClientT,
+    cfg_insts: &[ConfigInstance],
+    token: &str,
+) -> Result<Vec<SyncErr>, HTTPErr> {
+    let updates = BulkUpdateConfigInstancesRequest {
+        updates: cfg_insts
+            .iter()
+            .map(|inst| BulkUpdateConfigInstance {
+                id: inst.id.clone(),
+                activity_status: Some(ActivityStatus::to_backend(&inst.activity_status)),
+                error_status: Some(ErrorStatus::to_backend(&inst.error_status)),
+            })
+            .collect(),
+    };
+
+    // send to the server
+    debug!(
+        "Pushing {} config instances to the server with updates: {:?}",
+        cfg_insts.len(),
+        updates
+    );
+    let resp = http_client
+        .bulk_update_config_instances(&updates, token)
+        .await?;
+    let mut results: HashMap<String, BulkUpdateConfigInstanceResult> = resp
+        .results
+        .into_iter()
+        .map(|result| (result.id.clone(), result))
+        .collect();
+
+    // update the cache for each config instance the backend successfully updated
+    let mut errors = Vec::new();
+    for inst in cfg_insts.iter() {
+        match results.remove(&inst.id) {
+            Some(result) if result.success => {}
+            Some(result) => {
+                let e = SyncErr::BulkUpdateItemErr(Box::new(BulkUpdateItemErr {
+                    cfg_inst_id: inst.id.clone(),
+                    error: result.error.map(|error| *error),
+                    trace: trace!(),
+                }));
+                error!(
+                    "Failed to push config instance {} to backend: {}",
+                    inst.id, e
+                );
+                errors.push(e);
+                continue;
+            }
+            None => {
+                let e = SyncErr::BulkUpdateItemErr(Box::new(BulkUpdateItemErr {
+                    cfg_inst_id: inst.id.clone(),
+                    error: None,
+                    trace: trace!(),
+                }));
+                error!(
+                    "Failed to push config instance {} to backend: {}",
+                    inst.id, e
+                );
+                errors.push(e);
+                continue;
+            }
+        }
+
+        if let Err(e) = mark_clean(cfg_inst_cache, inst.clone()).await {
+            errors.push(e);
+        }
+    }
+
+    Ok(errors)
+}
+
+// pushes each config instance with its own request. Returns the errors for the config
+// instances which failed to update.
+async fn push_each<HTTPClientT: ConfigInstancesExt>(
+    cfg_inst_cache: &ConfigInstanceCache,
+    http_client: &HTTPClientT,
+    cfg_insts: Vec<ConfigInstance>,
+    token: &str,
+) -> Vec<SyncErr> {
+    let mut errors = Vec::new();
+
+    // push each unsynced config instance to the server and update the cache
+    for inst in cfg_insts {
         // define the updates
         let activity_status = ActivityStatus::to_backend(&inst.activity_status);
         let error_status = ErrorStatus::to_backend(&inst.error_status);
@@ -461,33 +646,32 @@ pub async fn push<HTTPClientT: ConfigInstancesExt>(
             continue;
         }
 
-        // update the cache
-        debug!("Updating cache for config instance {}", inst.id);
-        let inst_id = inst.id.clone();
-        if let Err(e) = cfg_inst_cache
-            .write(inst.id.clone(), inst, |_, _| false, true)
-            .await
-            .map_err(|e| {
-                SyncErr::CacheErr(Box::new(SyncCacheErr {
-                    source: e,
-                    trace: trace!(),
-                }))
-            })
-        {
-            error!(
-                "Failed to update cache for config instance {} after pushing to the server: {}",
-                inst_id, e
-            );
+        if let Err(e) = mark_clean(cfg_inst_cache, inst).await {
             errors.push(e);
         }
     }
 
-    if errors.is_empty() {
-        Ok(())
-    } else {
-        Err(SyncErr::SyncErrors(Box::new(SyncErrors {
-            source: errors,
-            trace: trace!(),
-        })))
-    }
+    errors
+}
+
+async fn mark_clean(
+    cfg_inst_cache: &ConfigInstanceCache,
+    inst: ConfigInstance,
+) -> Result<(), SyncErr> {
+    debug!("Updating cache for config instance {}", inst.id);
+    let inst_id = inst.id.clone();
+    cfg_inst_cache
+        .write(inst.id.clone(), inst, |_, _| false, true)
+        .await
+        .map_err(|e| {
+            let e = SyncErr::CacheErr(Box::new(SyncCacheErr {
+                source: e,
+                trace: trace!(),
+            }));
+            error!(
+                "Failed to update cache for config instance {} after pushing to the server: {}",
+                inst_id, e
+            );
+            e
+        })
 }
@@ -0,0 +1,113 @@
+// standard crates
+use std::collections::HashSet;
+
+// internal crates
+use crate::crud::prelude::*;
+use crate::http::{
+    config_schemas::{ConfigSchemaFilters, ConfigSchemasExt, IDFilter},
+    search::SearchOperator,
+};
+use crate::models::config_instance::TargetStatus;
+use crate::storage::config_instances::ConfigInstanceCache;
+use crate::storage::config_schemas::ConfigSchemaContentCache;
+use crate::sync::errors::*;
+use crate::trace;
+use openapi_client::models::ConfigSchemaExpand;
+
+// external crates
+use tracing::{debug, error};
+
+// =================================== PULL ======================================== //
+/// Fetch the json schema documents of the config schemas which the config instances
+/// to deploy adhere to (and which aren't cached yet) so that the content of the config
+/// instances can be validated before being deployed.
+pub async fn pull<HTTPClientT: ConfigSchemasExt>(
+    cfg_inst_cache: &ConfigInstanceCache,
+    cfg_schema_content_cache: &ConfigSchemaContentCache,
+    http_client: &HTTPClientT,
+    token: &str,
+) -> Result<(), SyncErr> {
+    let cfg_insts = cfg_inst_cache
+        .find_where(|cfg_inst| cfg_inst.target_status == TargetStatus::Deployed)
+        .await
+        .map_err(|e| {
+            SyncErr::CrudErr(Box::new(SyncCrudErr {
+                source: e,
+                trace: trace!(),
+            }))
+        })?;
+
+    let mut missing_ids = Vec::new();
+    let mut seen_ids = HashSet::new();
+    for cfg_inst in cfg_insts {
+        if !seen_ids.insert(cfg_inst.config_schema_id.clone()) {
+            continue;
+        }
+        let cached = cfg_schema_content_cache
+            .read_optional(cfg_inst.config_schema_id.clone())
+            .await
+            .map_err(|e| {
+                SyncErr::CrudErr(Box::new(SyncCrudErr {
+                    source: e,
+                    trace: trace!(),
+                }))
+            })?;
+        if cached.is_none() {
+            missing_ids.push(cfg_inst.config_schema_id);
+        }
+    }
+    if missing_ids.is_empty() {
+        return Ok(());
+    }
+    debug!(
+        "Fetching {} uncached config schemas: {:?}",
+        missing_ids.len(),
+        missing_ids
+    );
+
+    let filters = ConfigSchemaFilters {
+        ids: Some(IDFilter {
+            not: false,
+            op: SearchOperator::Equals,
+            val: missing_ids,
+        }),
+        digests: None,
+        config_type_slugs: None,
+    };
+    let cfg_schemas = http_client
+        .list_all_config_schemas(
+            filters,
+            [ConfigSchemaExpand::CONFIG_SCHEMA_EXPAND_CONTENT],
+            token,
+        )
+        .await
+        .map_err(|e| {
+            SyncErr::HTTPClientErr(Box::new(SyncHTTPClientErr {
+                source: e,
+                trace: trace!(),
+            }))
+        })?;
+
+    for cfg_schema in cfg_schemas {
+        let content = match cfg_schema.content {
+            Some(content) => content,
+            None => {
+                debug!("Config schema '{}' has no content", cfg_schema.id);
+                continue;
+            }
+        };
+
+        let overwrite = true;
+        if let Err(e) = cfg_schema_content_cache
+            .write(cfg_schema.id.clone(), content, |_, _| false, overwrite)
+            .await
+        {
+            error!(
+                "Failed to write config schema '{}' content to cache: {}",
+                cfg_schema.id, e
+            );
+        }
+    }
+
+    Ok(())
+}
@@ -1,5 +1,6 @@
 // standard crates
 use std::fmt;
+use std::time::Duration;
 
 // internal crates
 use crate::authn::errors::AuthnErr;
@@ -10,6 +11,7 @@ use crate::errors::{Code, HTTPCode, MiruError, Trace};
 use crate::filesys::errors::FileSysErr;
 use crate::http::errors::HTTPErr;
 use crate::storage::errors::StorageErr;
+use openapi_client::models::Error as BackendError;
 
 // external crates
 use chrono::{DateTime, Utc};
@@ -259,6 +261,56 @@ impl fmt::Display for MissingExpandedInstancesErr {
     }
 }
 
+#[derive(Debug)]
+pub struct BulkUpdateItemErr {
+    pub cfg_inst_id: String,
+    // the backend's reason for rejecting the update (none if the backend did not return
+    // a result for the config instance)
+    pub error: Option<BackendError>,
+    pub trace: Box<Trace>,
+}
+
+impl MiruError for BulkUpdateItemErr {
+    fn code(&self) -> Code {
+        match &self.error {
+            Some(error) => Code::BackendError(error.code.clone()),
+            None => Code::InternalServerError,
+        }
+    }
+
+    fn http_status(&self) -> HTTPCode {
+        HTTPCode::INTERNAL_SERVER_ERROR
+    }
+
+    fn is_network_connection_error(&self) -> bool {
+        false
+    }
+
+    fn params(&self) -> Option<serde_json::Value> {
+        match &self.error {
+            Some(error) => error.params.clone(),
+            None => None,
+        }
+    }
+}
+
+impl fmt::Display for BulkUpdateItemErr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.error {
+            Some(error) => write!(
+                f,
+                "Failed to update config instance {}: {}",
+                self.cfg_inst_id, error.debug_message
+            ),
+            None => write!(
+                f,
+                "Failed to update config instance {}: no result returned by the backend",
+                self.cfg_inst_id
+            ),
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct SyncErrors {
     pub source: Vec<SyncErr>,
@@ -412,6 +464,7 @@ pub enum SyncErr {
     SyncErrors(Box<SyncErrors>),
 
     MissingExpandedInstancesErr(Box<MissingExpandedInstancesErr>),
+    BulkUpdateItemErr(Box<BulkUpdateItemErr>),
     InCooldownErr(Box<SyncerInCooldownErr>),
     ConfigInstanceContentNotFound(Box<ConfigInstanceContentNotFoundErr>),
     SendActorMessageErr(Box<SendActorMessageErr>),
@@ -433,6 +486,7 @@ macro_rules! forward_error_method {
             SyncErr::SyncErrors(e) => e.$method($($arg)?),
 
             SyncErr::MissingExpandedInstancesErr(e) => e.$method($($arg)?),
+            SyncErr::BulkUpdateItemErr(e) => e.$method($($arg)?),
             SyncErr::InCooldownErr(e) => e.$method($($arg)?),
             SyncErr::ConfigInstanceContentNotFound(e) => e.$method($($arg)?),
             SyncErr::SendActorMessageErr(e) => e.$method($($arg)?),
@@ -443,6 +497,19 @@ macro_rules! forward_error_method {
     };
 }
 
+impl SyncErr {
+    // how long the backend asked us to wait before syncing again (if at all). When
+    // there are multiple errors, the longest requested wait is honored.
+    pub fn retry_after(&self) -> Option<Duration> {
+        match self {
+            SyncErr::AuthnErr(e) => e.source.retry_after(),
+            SyncErr::HTTPClientErr(e) => e.source.retry_after(),
+            SyncErr::SyncErrors(e) => e.source.iter().filter_map(|e| e.retry_after()).max(),
+            _ => None,
+        }
+    }
+}
+
 impl fmt::Display for SyncErr {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         forward_error_method!(self, fmt, f)
@@ -1,4 +1,5 @@
 pub mod agent_version;
 pub mod config_instances;
+pub mod config_schemas;
 pub mod errors;
 pub mod syncer;
@@ -1,21 +1,27 @@
 // standard crates
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 // internal crates
 use crate::authn::token_mngr::{TokenManager, TokenManagerExt};
 use crate::deploy::fsm;
 use crate::errors::*;
 use crate::filesys::dir::Dir;
-use crate::http::{client::HTTPClient, config_instances::ConfigInstancesExt, devices::DevicesExt};
+use crate::http::{
+    client::HTTPClient, config_instances::ConfigInstancesExt, config_schemas::ConfigSchemasExt,
+    devices::DevicesExt,
+};
+use crate::metrics::registry;
 use crate::storage::{
     config_instances::{ConfigInstanceCache, ConfigInstanceContentCache},
+    config_schemas::ConfigSchemaContentCache,
     device::DeviceFile,
+    events::EventQueue,
 };
 use crate::sync::errors::*;
 use crate::sync::{agent_version, config_instances};
 use crate::trace;
-use crate::utils::{calc_exp_backoff, CooldownOptions};
+use crate::utils::{calc_exp_backoff, calc_retry_after_secs, CooldownOptions};
 
 // external crates
 use chrono::{DateTime, TimeDelta, Utc};
@@ -50,8 +56,13 @@ pub struct SyncerArgs<HTTPClientT: ConfigInstancesExt, TokenManagerT: TokenManag
     pub token_mngr: Arc<TokenManagerT>,
     pub cfg_inst_cache: Arc<ConfigInstanceCache>,
     pub cfg_inst_content_cache: Arc<ConfigInstanceContentCache>,
+    // validates the config instance contents against their config schemas before
+    // deploying them (if set)
+    pub cfg_schema_content_cache: Option<Arc<ConfigSchemaContentCache>>,
     pub deployment_dir: Dir,
     pub fsm_settings: fsm::Settings,
+    // queues config instance events for the mqtt worker to publish (if set)
+    pub event_queue: Option<Arc<EventQueue>>,
     pub cooldown_options: CooldownOptions,
     pub agent_version: String,
 }
@@ -88,8 +99,10 @@ pub struct SingleThreadSyncer<HTTPClientT: ConfigInstancesExt> {
     token_mngr: Arc<TokenManager>,
     cfg_inst_cache: Arc<ConfigInstanceCache>,
     cfg_inst_content_cache: Arc<ConfigInstanceContentCache>,
+    cfg_schema_content_cache: Option<Arc<ConfigSchemaContentCache>>,
     deployment_dir: Dir,
     fsm_settings: fsm::Settings,
+    event_queue: Option<Arc<EventQueue>>,
     agent_version: String,
 
     // subscribers
@@ -99,9 +112,12 @@ pub struct SingleThreadSyncer<HTTPClientT: ConfigInstancesExt> {
     // syncer state
     cooldown_options: CooldownOptions,
     state: SyncState,
+    bulk_updates: config_instances::BulkUpdates,
 }
 
-impl<HTTPClientT: ConfigInstancesExt + DevicesExt> SingleThreadSyncer<HTTPClientT> {
+impl<HTTPClientT: ConfigInstancesExt + ConfigSchemasExt + DevicesExt>
+    SingleThreadSyncer<HTTPClientT>
+{
     pub fn new(args: SyncerArgs<HTTPClientT, TokenManager>) -> Self {
         let (subscriber_tx, subscriber_rx) = watch::channel(SyncEvent::SyncSuccess);
         Self {
@@ -111,8 +127,10 @@ impl<HTTPClientT: ConfigInstancesExt + DevicesExt> SingleThreadSyncer<HTTPClient
             token_mngr: args.token_mngr,
             cfg_inst_cache: args.cfg_inst_cache,
             cfg_inst_content_cache: args.cfg_inst_content_cache,
+            cfg_schema_content_cache: args.cfg_schema_content_cache,
             deployment_dir: args.deployment_dir,
             fsm_settings: args.fsm_settings,
+            event_queue: args.event_queue,
             cooldown_options: args.cooldown_options,
             agent_version: args.agent_version,
             state: SyncState {
@@ -121,6 +139,7 @@ impl<HTTPClientT: ConfigInstancesExt + DevicesExt> SingleThreadSyncer<HTTPClient
                 cooldown_ends_at: DateTime::<Utc>::UNIX_EPOCH,
                 err_streak: 0,
             },
+            bulk_updates: config_instances::BulkUpdates::default(),
             subscriber_tx,
             subscriber_rx,
         }
@@ -185,7 +204,11 @@ impl<HTTPClientT: ConfigInstancesExt + DevicesExt> SingleThreadSyncer<HTTPClient
         }
 
         self.state.last_attempted_sync_at = Utc::now();
+        let started_at = Instant::now();
         let result = self.sync_impl().await;
+        registry::global()
+            .sync
+            .record(result.is_ok(), started_at.elapsed());
         let (success, cooldown_secs) = match &result {
             Ok(_) => {
                 if let Err(e) = self.subscriber_tx.send(SyncEvent::SyncSuccess) {
@@ -212,7 +235,7 @@ impl<HTTPClientT: ConfigInstancesExt + DevicesExt> SingleThreadSyncer<HTTPClient
                 }
                 // network connection errors are expected to happen and do not count
                 // toward the error streak. We want to be able to retry syncing from network connection errors even if the previous errors were not network connection errors so we use an error streak of 0 when calculating the cooldown period
-                if e.is_network_connection_error() {
+                let (success, cooldown_secs) = if e.is_network_connection_error() {
                     debug!(
                         "unable to sync with backend due to a network connection error: {:?}",
                         e
@@ -230,6 +253,17 @@ impl<HTTPClientT: ConfigInstancesExt + DevicesExt> SingleThreadSyncer<HTTPClient
                             self.cooldown_options.max_secs,
                         ),
                     )
+                };
+
+                // the backend's requested wait (e.g. while it is shedding load) takes
+                // precedence over our own cooldown
+                match e.retry_after() {
+                    Some(retry_after) => {
+                        let secs = calc_retry_after_secs(retry_after, self.cooldown_options);
+                        info!("backend requested a retry after {secs} seconds");
+                        (success, secs)
+                    }
+                    None => (success, cooldown_secs),
                 }
             }
         };
@@ -270,8 +304,13 @@ impl<HTTPClientT: ConfigInstancesExt + DevicesExt> SingleThreadSyncer<HTTPClient
             self.cfg_inst_content_cache.as_ref(),
             self.http_client.as_ref(),
             &self.device_id,
-            &self.deployment_dir,
-            &self.fsm_settings,
+            config_instances::DeployArgs {
+                deployment_dir: &self.deployment_dir,
+                fsm_settings: &self.fsm_settings,
+                event_queue: self.event_queue.as_deref(),
+                cfg_schema_content_cache: self.cfg_schema_content_cache.as_deref(),
+            },
+            &mut self.bulk_updates,
             &token.token,
         )
         .await
@@ -328,7 +367,7 @@ impl<HTTPClientT: ConfigInstancesExt + Send> Worker<HTTPClientT> {
     }
 }
 
-impl<HTTPClientT: ConfigInstancesExt + DevicesExt + Send> Worker<HTTPClientT> {
+impl<HTTPClientT: ConfigInstancesExt + ConfigSchemasExt + DevicesExt + Send> Worker<HTTPClientT> {
     pub async fn run(mut self) {
         while let Some(cmd) = self.receiver.recv().await {
             match cmd {
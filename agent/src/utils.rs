@@ -31,6 +31,15 @@ pub fn calc_exp_backoff(base: i64, growth_factor: i64, exp: u32, max: i64) -> i6
     min(base.saturating_mul(growth_factor.saturating_pow(exp)), max)
 }
 
+// converts a backend requested retry delay into a cooldown (in seconds), rounding up to
+// the nearest second and capping it at the cooldown's maximum
+pub fn calc_retry_after_secs(retry_after: std::time::Duration, cooldown: CooldownOptions) -> i64 {
+    let secs = retry_after
+        .as_secs()
+        .saturating_add(u64::from(retry_after.subsec_nanos() > 0));
+    min(i64::try_from(secs).unwrap_or(i64::MAX), cooldown.max_secs)
+}
+
 #[derive(Debug, Clone, Copy)]
 pub struct CooldownOptions {
     pub base_secs: i64,
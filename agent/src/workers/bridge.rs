@@ -0,0 +1,290 @@
+// standard crates
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
+use std::pin::Pin;
+use std::time::Duration;
+
+// internal modules
+use crate::crud::{errors::CrudErr, prelude::*};
+use crate::errors::*;
+use crate::filesys::{file::File, path::PathExt};
+use crate::models::config_instance::{ActivityStatus, ConfigInstance};
+use crate::mqtt::{
+    bridge::{LocalBridgeExt, LocalConfig},
+    client::{
+        self, poll, ConnectAddress, Credentials, Event, MQTTClient, OptionsBuilder, Protocol,
+    },
+    topics,
+};
+use crate::storage::{
+    config_instances::{ConfigInstanceCache, ConfigInstanceContentCache},
+    config_schemas::ConfigSchemaCache,
+};
+use crate::sync::syncer::{SyncEvent, SyncerExt};
+use crate::utils::{calc_exp_backoff, CooldownOptions};
+
+// external crates
+use tokio::sync::watch;
+use tracing::{debug, error, info, warn};
+
+#[derive(Debug, Clone)]
+pub struct Options {
+    pub cooldown: CooldownOptions,
+    // the local broker's connection (which doesn't use TLS)
+    pub client: client::Options,
+    // configs are published to '<topic_prefix>/<config type slug>'
+    pub topic_prefix: String,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            cooldown: CooldownOptions {
+                base_secs: 1,
+                growth_factor: 2,
+                max_secs: 60,
+            },
+            client: OptionsBuilder::new(Credentials::default())
+                .with_connect_address(ConnectAddress::new(
+                    Protocol::TCP,
+                    "localhost".to_string(),
+                    1883,
+                ))
+                .with_client_id("miru-agent-bridge".to_string())
+                .build(),
+            topic_prefix: "miru/configs".to_string(),
+        }
+    }
+}
+
+// the agent state the bridge reads from
+pub struct Args<'a, SyncerT: SyncerExt> {
+    pub syncer: &'a SyncerT,
+    pub cfg_inst_cache: &'a ConfigInstanceCache,
+    pub cfg_inst_content_cache: &'a ConfigInstanceContentCache,
+    pub cfg_schema_cache: &'a ConfigSchemaCache,
+    // where the topics with retained configs are persisted across restarts
+    pub published_topics: &'a File,
+}
+
+// the config published to each of the local broker's topics. The configs retained by
+// a previous run of the agent are unknown (None) since only their topics are persisted.
+pub type Published = HashMap<String, Option<LocalConfig>>;
+
+pub async fn run<F, Fut, SyncerT: SyncerExt>(
+    options: &Options,
+    args: Args<'_, SyncerT>,
+    sleep_fn: F,
+    mut shutdown_signal: Pin<Box<impl Future<Output = ()> + Send + 'static>>,
+) where
+    F: Fn(Duration) -> Fut,
+    Fut: Future<Output = ()> + Send,
+{
+    tokio::select! {
+        _ = shutdown_signal.as_mut() => {
+            info!("Local bridge worker shutdown complete");
+        }
+        // doesn't return but we do need to run it in the background
+        _ = run_impl(options, args, sleep_fn) => {}
+    }
+}
+
+pub async fn run_impl<F, Fut, SyncerT: SyncerExt>(
+    options: &Options,
+    args: Args<'_, SyncerT>,
+    sleep_fn: F,
+) where
+    F: Fn(Duration) -> Fut,
+    Fut: Future<Output = ()> + Send,
+{
+    info!("Running local bridge worker");
+
+    // deployments happen while syncing so the deployed configs are reconciled after
+    // every syncer event
+    let mut syncer_subscriber = args.syncer.subscribe().await.unwrap_or_else(|e| {
+        error!("error subscribing to syncer events: {e:?}");
+        // create a dummy receiver that never sends anything
+        watch::channel(SyncEvent::SyncSuccess).1
+    });
+    let mut syncer_open = true;
+
+    let (client, mut eventloop) = MQTTClient::new(&options.client).await;
+    let mut published = load_published(args.published_topics).await;
+    let mut connected = false;
+    let mut err_streak = 0;
+
+    loop {
+        tokio::select! {
+            result = syncer_subscriber.changed(), if connected && syncer_open => {
+                if result.is_err() {
+                    warn!("syncer stopped publishing events");
+                    syncer_open = false;
+                    continue;
+                }
+                reconcile(&client, &options.topic_prefix, &args, &mut published, false)
+                    .await;
+            }
+
+            mqtt_result = poll(&mut eventloop) => {
+                if let Ok(event) = &mqtt_result {
+                    client.record_delivery(event);
+                }
+                match mqtt_result {
+                    // the local broker may have lost its retained messages (e.g. after a
+                    // restart) so every deployed config is republished on connecting
+                    Ok(Event::Connected { .. }) => {
+                        info!("Established connection to local mqtt broker");
+                        connected = true;
+                        err_streak = 0;
+                        reconcile(&client, &options.topic_prefix, &args, &mut published, true)
+                            .await;
+                    }
+                    Ok(Event::Disconnected) => {
+                        info!("Disconnected from local mqtt broker");
+                        connected = false;
+                    }
+                    Ok(_) => {}
+                    Err(e) => {
+                        connected = false;
+                        if e.is_network_connection_error() {
+                            debug!("network error while polling local mqtt broker: {e:?}");
+                        } else {
+                            error!("error polling local mqtt broker: {e:?}");
+                        }
+
+                        // the client reconnects on the next poll so back off to prevent
+                        // spinning while the local broker is unavailable
+                        let cooldown_secs = calc_exp_backoff(
+                            options.cooldown.base_secs,
+                            options.cooldown.growth_factor,
+                            err_streak,
+                            options.cooldown.max_secs,
+                        );
+                        err_streak = err_streak.saturating_add(1);
+                        sleep_fn(Duration::from_secs(cooldown_secs as u64)).await;
+                    }
+                }
+            }
+        }
+    }
+}
+
+// the deployed config for each of the local broker's topics. If several config
+// instances of a config type are deployed, the most recently updated one is used.
+pub async fn deployed_configs<SyncerT: SyncerExt>(
+    topic_prefix: &str,
+    args: &Args<'_, SyncerT>,
+) -> Result<HashMap<String, LocalConfig>, CrudErr> {
+    let mut cfg_insts = args
+        .cfg_inst_cache
+        .find_where(|cfg_inst: &ConfigInstance| {
+            cfg_inst.activity_status == ActivityStatus::Deployed
+        })
+        .await?;
+    cfg_insts.sort_by_key(|cfg_inst| cfg_inst.updated_at);
+
+    let mut configs = HashMap::new();
+    for cfg_inst in cfg_insts {
+        let content = args
+            .cfg_inst_content_cache
+            .read(cfg_inst.id.clone())
+            .await?;
+
+        // the config type's id is used until its slug is known
+        let config_type_slug = args
+            .cfg_schema_cache
+            .read_optional(cfg_inst.config_schema_id.clone())
+            .await?
+            .and_then(|cfg_schema| cfg_schema.config_type_slug);
+        let topic = topics::local_config(
+            topic_prefix,
+            config_type_slug
+                .as_deref()
+                .unwrap_or(&cfg_inst.config_type_id),
+        );
+
+        let config = LocalConfig::new(&cfg_inst, config_type_slug, content);
+        if let Some(replaced) = configs.insert(topic.clone(), config) {
+            debug!(
+                "config instance {} replaced by a more recent one on topic {topic}",
+                replaced.metadata.config_instance_id
+            );
+        }
+    }
+    Ok(configs)
+}
+
+// the topics published by a previous run of the agent so that their retained configs
+// are cleared if they're no longer deployed
+pub async fn load_published(published_topics: &File) -> Published {
+    if !published_topics.exists() {
+        return Published::new();
+    }
+    match published_topics.read_json::<Vec<String>>().await {
+        Ok(topics) => topics.into_iter().map(|topic| (topic, None)).collect(),
+        Err(e) => {
+            error!("error reading the local bridge's published topics: {e:?}");
+            Published::new()
+        }
+    }
+}
+
+async fn persist_published(published_topics: &File, published: &Published) {
+    let mut topics: Vec<&String> = published.keys().collect();
+    topics.sort();
+    if let Err(e) = published_topics.write_json(&topics, true, true).await {
+        error!("error persisting the local bridge's published topics: {e:?}");
+    }
+}
+
+// publishes the deployed configs which changed since they were last published (or
+// every deployed config if republishing) and clears the configs which were removed.
+// Configs which fail to publish or clear are retried on the next reconciliation.
+pub async fn reconcile<MQTTClientT: LocalBridgeExt, SyncerT: SyncerExt>(
+    client: &MQTTClientT,
+    topic_prefix: &str,
+    args: &Args<'_, SyncerT>,
+    published: &mut Published,
+    republish: bool,
+) {
+    let configs = match deployed_configs(topic_prefix, args).await {
+        Ok(configs) => configs,
+        Err(e) => {
+            error!("error reading the deployed configs: {e:?}");
+            return;
+        }
+    };
+
+    let topics: HashSet<String> = published.keys().cloned().collect();
+    for (topic, config) in configs.iter() {
+        if !republish && published.get(topic).and_then(Option::as_ref) == Some(config) {
+            continue;
+        }
+        match client.publish_local_config(topic, config).await {
+            Ok(_) => {
+                debug!("published config to local topic {topic}");
+                published.insert(topic.clone(), Some(config.clone()));
+            }
+            Err(e) => error!("error publishing config to local topic {topic}: {e:?}"),
+        }
+    }
+
+    let removed: Vec<String> = published
+        .keys()
+        .filter(|topic| !configs.contains_key(*topic))
+        .cloned()
+        .collect();
+    for topic in removed {
+        match client.clear_local_config(&topic).await {
+            Ok(_) => {
+                debug!("cleared config from local topic {topic}");
+                published.remove(&topic);
+            }
+            Err(e) => error!("error clearing config from local topic {topic}: {e:?}"),
+        }
+    }
+
+    if published.len() != topics.len() || !published.keys().all(|topic| topics.contains(topic)) {
+        persist_published(args.published_topics, published).await;
+    }
+}
@@ -0,0 +1,481 @@
+// standard crates
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+// internal crates
+use crate::crud::prelude::*;
+use crate::filesys::{dir::Dir, path::PathExt};
+use crate::logs::{self, LogOptions};
+use crate::models::config_instance::{ActivityStatus, ConfigInstance};
+use crate::mqtt::{client::Message, commands::*, device::DeviceExt};
+use crate::storage::config_instances::{ConfigInstanceCache, ConfigInstanceContentCache};
+use crate::sync::syncer::SyncerExt;
+
+// external crates
+use serde::{de::DeserializeOwned, Serialize};
+use tokio::sync::Notify;
+use tracing::{error, info, warn};
+
+// ================================== OPTIONS ====================================== //
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimit {
+    pub max_calls: u32,
+    pub interval: Duration,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Policy {
+    // whether the command may be executed at all
+    pub authorized: bool,
+    pub rate_limit: RateLimit,
+}
+
+impl Policy {
+    pub fn default_for(command: CommandName) -> Self {
+        let one_min = Duration::from_secs(60);
+        match command {
+            CommandName::SyncState | CommandName::DeployedInstances => Policy {
+                authorized: true,
+                rate_limit: RateLimit {
+                    max_calls: 30,
+                    interval: one_min,
+                },
+            },
+            CommandName::Logs | CommandName::DriftCheck => Policy {
+                authorized: true,
+                rate_limit: RateLimit {
+                    max_calls: 6,
+                    interval: one_min,
+                },
+            },
+            // restarting the agent must be explicitly authorized by the device's owner
+            CommandName::Restart => Policy {
+                authorized: false,
+                rate_limit: RateLimit {
+                    max_calls: 1,
+                    interval: Duration::from_secs(10 * 60),
+                },
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Options {
+    pub policies: HashMap<CommandName, Policy>,
+    pub log_dir: Dir,
+    pub max_log_lines: usize,
+    // only the end of the log file is read, however few lines it holds
+    pub max_log_bytes: usize,
+    // gives the event loop time to publish the response before restarting
+    pub restart_delay: Duration,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            policies: CommandName::variants()
+                .into_iter()
+                .map(|command| (command, Policy::default_for(command)))
+                .collect(),
+            log_dir: Dir::new(LogOptions::default().log_dir),
+            max_log_lines: 1000,
+            max_log_bytes: 512 * 1024,
+            restart_delay: Duration::from_secs(2),
+        }
+    }
+}
+
+impl Options {
+    // authorizes exactly the given commands (leaving their rate limits untouched)
+    pub fn with_authorized(mut self, authorized: &[CommandName]) -> Self {
+        for command in CommandName::variants() {
+            let policy = self
+                .policies
+                .entry(command)
+                .or_insert_with(|| Policy::default_for(command));
+            policy.authorized = authorized.contains(&command);
+        }
+        self
+    }
+
+    pub fn policy(&self, command: CommandName) -> Policy {
+        self.policies
+            .get(&command)
+            .copied()
+            .unwrap_or_else(|| Policy::default_for(command))
+    }
+}
+
+// ================================== REGISTRY ===================================== //
+pub struct CommandsArgs {
+    pub cfg_inst_cache: Arc<ConfigInstanceCache>,
+    pub cfg_inst_content_cache: Arc<ConfigInstanceContentCache>,
+    pub deployment_dir: Dir,
+    // notified to restart the agent
+    pub restart: Arc<Notify>,
+}
+
+pub struct Commands {
+    options: Options,
+    cfg_inst_cache: Arc<ConfigInstanceCache>,
+    cfg_inst_content_cache: Arc<ConfigInstanceContentCache>,
+    deployment_dir: Dir,
+    restart: Arc<Notify>,
+
+    // when each command was last executed (within its rate limit's interval)
+    calls: Mutex<HashMap<CommandName, VecDeque<Instant>>>,
+}
+
+impl Commands {
+    pub fn new(options: Options, args: CommandsArgs) -> Self {
+        Self {
+            options,
+            cfg_inst_cache: args.cfg_inst_cache,
+            cfg_inst_content_cache: args.cfg_inst_content_cache,
+            deployment_dir: args.deployment_dir,
+            restart: args.restart,
+            calls: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub async fn handle<MQTTClientT: DeviceExt, SyncerT: SyncerExt>(
+        &self,
+        command: CommandName,
+        message: &Message,
+        mqtt_client: &MQTTClientT,
+        syncer: &SyncerT,
+        device_id: &str,
+    ) {
+        let response = self.execute(command, &message.payload, syncer).await;
+        let restart = command == CommandName::Restart && response.error.is_none();
+
+        // mqtt v5 requests are answered on their response topic
+        let reply_to = message.reply_to();
+        match mqtt_client
+            .publish_command_response(device_id, &response, reply_to.as_ref())
+            .await
+        {
+            Ok(_) => info!(
+                "published response to {command} command with message id {}",
+                response.message_id
+            ),
+            Err(e) => error!("error publishing response to {command} command: {e:?}"),
+        }
+
+        if restart {
+            let notify = self.restart.clone();
+            let delay = self.options.restart_delay;
+            tokio::spawn(async move {
+                tokio::time::sleep(delay).await;
+                notify.notify_one();
+            });
+        }
+    }
+
+    pub async fn execute<SyncerT: SyncerExt>(
+        &self,
+        command: CommandName,
+        payload: &[u8],
+        syncer: &SyncerT,
+    ) -> CommandResponse {
+        let request = match serde_json::from_slice::<CommandRequest>(payload) {
+            Ok(request) => request,
+            Err(e) => {
+                error!("error deserializing {command} command request: {e:?}");
+                let error = CommandError::new(CommandErrorCode::InvalidRequest, e.to_string());
+                return CommandResponse::new(String::new(), command, Err(error));
+            }
+        };
+        info!(
+            "received {command} command with message id {}",
+            request.message_id
+        );
+
+        let result = match self.authorize(command) {
+            Ok(_) => self.dispatch(command, request.params, syncer).await,
+            Err(e) => Err(e),
+        };
+        if let Err(e) = &result {
+            warn!("{command} command failed: {e:?}");
+        }
+        CommandResponse::new(request.message_id, command, result)
+    }
+
+    fn authorize(&self, command: CommandName) -> Result<(), CommandError> {
+        let policy = self.options.policy(command);
+        if !policy.authorized {
+            return Err(CommandError::new(
+                CommandErrorCode::Unauthorized,
+                format!("the {command} command is not authorized on this device"),
+            ));
+        }
+
+        let now = Instant::now();
+        let mut calls = self.calls.lock().unwrap_or_else(|e| e.into_inner());
+        let calls = calls.entry(command).or_default();
+        while let Some(called_at) = calls.front() {
+            if now.duration_since(*called_at) < policy.rate_limit.interval {
+                break;
+            }
+            calls.pop_front();
+        }
+        if calls.len() >= policy.rate_limit.max_calls as usize {
+            return Err(CommandError::new(
+                CommandErrorCode::RateLimited,
+                format!(
+                    "the {command} command is limited to {} calls every {}s",
+                    policy.rate_limit.max_calls,
+                    policy.rate_limit.interval.as_secs()
+                ),
+            ));
+        }
+        calls.push_back(now);
+        Ok(())
+    }
+
+    async fn dispatch<SyncerT: SyncerExt>(
+        &self,
+        command: CommandName,
+        params: serde_json::Value,
+        syncer: &SyncerT,
+    ) -> Result<serde_json::Value, CommandError> {
+        match command {
+            CommandName::SyncState => run::<SyncStateCommand, _>(self, syncer, params).await,
+            CommandName::DeployedInstances => {
+                run::<DeployedInstancesCommand, _>(self, syncer, params).await
+            }
+            CommandName::Logs => run::<LogsCommand, _>(self, syncer, params).await,
+            CommandName::DriftCheck => run::<DriftCheckCommand, _>(self, syncer, params).await,
+            CommandName::Restart => run::<RestartCommand, _>(self, syncer, params).await,
+        }
+    }
+
+    async fn deployed_instances(&self) -> Result<Vec<ConfigInstance>, CommandError> {
+        self.cfg_inst_cache
+            .find_where(|cfg_inst| cfg_inst.activity_status == ActivityStatus::Deployed)
+            .await
+            .map_err(CommandError::failed)
+    }
+}
+
+// deserializes the command's params, executes the command and serializes its result
+async fn run<C: Command, SyncerT: SyncerExt>(
+    commands: &Commands,
+    syncer: &SyncerT,
+    params: serde_json::Value,
+) -> Result<serde_json::Value, CommandError> {
+    // commands without any params may omit them
+    let params = match params {
+        serde_json::Value::Null => serde_json::Value::Object(Default::default()),
+        params => params,
+    };
+    let params = serde_json::from_value::<C::Params>(params).map_err(|e| {
+        CommandError::new(
+            CommandErrorCode::InvalidRequest,
+            format!("invalid {} params: {e}", C::NAME),
+        )
+    })?;
+    let result = C::execute(commands, syncer, params).await?;
+    serde_json::to_value(result).map_err(CommandError::failed)
+}
+
+// =================================== COMMANDS ==================================== //
+#[allow(async_fn_in_trait)]
+pub trait Command {
+    const NAME: CommandName;
+    type Params: DeserializeOwned;
+    type Result: Serialize;
+
+    async fn execute<SyncerT: SyncerExt>(
+        commands: &Commands,
+        syncer: &SyncerT,
+        params: Self::Params,
+    ) -> Result<Self::Result, CommandError>;
+}
+
+pub struct SyncStateCommand;
+
+impl Command for SyncStateCommand {
+    const NAME: CommandName = CommandName::SyncState;
+    type Params = SyncStateParams;
+    type Result = SyncStateResult;
+
+    async fn execute<SyncerT: SyncerExt>(
+        _: &Commands,
+        syncer: &SyncerT,
+        _: SyncStateParams,
+    ) -> Result<SyncStateResult, CommandError> {
+        let state = syncer
+            .get_sync_state()
+            .await
+            .map_err(CommandError::failed)?;
+        Ok(SyncStateResult {
+            last_attempted_sync_at: state.last_attempted_sync_at,
+            last_synced_at: state.last_synced_at,
+            cooldown_ends_at: state.cooldown_ends_at,
+            err_streak: state.err_streak,
+            in_cooldown: state.is_in_cooldown(),
+        })
+    }
+}
+
+pub struct DeployedInstancesCommand;
+
+impl Command for DeployedInstancesCommand {
+    const NAME: CommandName = CommandName::DeployedInstances;
+    type Params = DeployedInstancesParams;
+    type Result = DeployedInstancesResult;
+
+    async fn execute<SyncerT: SyncerExt>(
+        commands: &Commands,
+        _: &SyncerT,
+        _: DeployedInstancesParams,
+    ) -> Result<DeployedInstancesResult, CommandError> {
+        let mut config_instances: Vec<DeployedInstance> = commands
+            .deployed_instances()
+            .await?
+            .into_iter()
+            .map(|cfg_inst| DeployedInstance {
+                id: cfg_inst.id,
+                config_schema_id: cfg_inst.config_schema_id,
+                config_type_id: cfg_inst.config_type_id,
+                relative_filepath: cfg_inst.relative_filepath,
+                patch_id: cfg_inst.patch_id,
+                updated_at: cfg_inst.updated_at,
+            })
+            .collect();
+        config_instances.sort_by(|a, b| a.relative_filepath.cmp(&b.relative_filepath));
+        Ok(DeployedInstancesResult { config_instances })
+    }
+}
+
+pub struct LogsCommand;
+
+const DEFAULT_LOG_LINES: usize = 100;
+
+impl Command for LogsCommand {
+    const NAME: CommandName = CommandName::Logs;
+    type Params = LogsParams;
+    type Result = LogsResult;
+
+    async fn execute<SyncerT: SyncerExt>(
+        commands: &Commands,
+        _: &SyncerT,
+        params: LogsParams,
+    ) -> Result<LogsResult, CommandError> {
+        let num_lines = params
+            .lines
+            .unwrap_or(DEFAULT_LOG_LINES)
+            .min(commands.options.max_log_lines);
+
+        // the log files are rotated hourly so the most recently modified one is
+        // the one currently being written to
+        let log_dir = &commands.options.log_dir;
+        let mut latest = None;
+        for file in log_dir.files().await.map_err(CommandError::failed)? {
+            let is_log_file = file.name().is_ok_and(|name| name.starts_with("miru.log"));
+            if !is_log_file {
+                continue;
+            }
+            let modified = file.last_modified().await.map_err(CommandError::failed)?;
+            if latest.as_ref().is_none_or(|(latest, _)| modified > *latest) {
+                latest = Some((modified, file));
+            }
+        }
+        let Some((_, file)) = latest else {
+            return Err(CommandError::failed(format!(
+                "no log files found in {log_dir}"
+            )));
+        };
+
+        let content = logs::read_tail(&file, commands.options.max_log_bytes)
+            .await
+            .map_err(CommandError::failed)?;
+        let lines: Vec<&str> = content.lines().collect();
+        let start = lines.len().saturating_sub(num_lines);
+        Ok(LogsResult {
+            file: file.to_string(),
+            lines: lines[start..]
+                .iter()
+                .map(|line| logs::redact(line))
+                .collect(),
+        })
+    }
+}
+
+pub struct DriftCheckCommand;
+
+impl Command for DriftCheckCommand {
+    const NAME: CommandName = CommandName::DriftCheck;
+    type Params = DriftCheckParams;
+    type Result = DriftCheckResult;
+
+    async fn execute<SyncerT: SyncerExt>(
+        commands: &Commands,
+        _: &SyncerT,
+        _: DriftCheckParams,
+    ) -> Result<DriftCheckResult, CommandError> {
+        let mut cfg_insts = commands.deployed_instances().await?;
+        cfg_insts.sort_by(|a, b| a.relative_filepath.cmp(&b.relative_filepath));
+
+        let mut drifted = Vec::new();
+        for cfg_inst in &cfg_insts {
+            let file = commands.deployment_dir.file(&cfg_inst.relative_filepath);
+            let reason = if !file.exists() {
+                Some(DriftReason::Missing)
+            } else {
+                let expected = commands
+                    .cfg_inst_content_cache
+                    .read(cfg_inst.id.clone())
+                    .await
+                    .map_err(CommandError::failed)?;
+                // files which are no longer valid json have been modified too
+                match file.read_json::<serde_json::Value>().await {
+                    Ok(actual) if actual == expected => None,
+                    _ => Some(DriftReason::Modified),
+                }
+            };
+            if let Some(reason) = reason {
+                drifted.push(Drift {
+                    config_instance_id: cfg_inst.id.clone(),
+                    relative_filepath: cfg_inst.relative_filepath.clone(),
+                    reason,
+                });
+            }
+        }
+        if !drifted.is_empty() {
+            warn!(
+                "{} of {} deployed config instances have drifted",
+                drifted.len(),
+                cfg_insts.len()
+            );
+        }
+
+        Ok(DriftCheckResult {
+            checked: cfg_insts.len(),
+            drifted,
+        })
+    }
+}
+
+pub struct RestartCommand;
+
+impl Command for RestartCommand {
+    const NAME: CommandName = CommandName::Restart;
+    type Params = RestartParams;
+    type Result = RestartResult;
+
+    // the restart itself is scheduled once the response has been published
+    async fn execute<SyncerT: SyncerExt>(
+        commands: &Commands,
+        _: &SyncerT,
+        _: RestartParams,
+    ) -> Result<RestartResult, CommandError> {
+        info!("restarting the agent as requested by the backend");
+        Ok(RestartResult {
+            delay_ms: commands.options.restart_delay.as_millis() as u64,
+        })
+    }
+}
@@ -7,26 +7,48 @@ use std::time::Duration;
 // internal modules
 use crate::authn::{token::Token, token_mngr::TokenManagerExt};
 use crate::errors::*;
+use crate::metrics::registry;
 use crate::models::device::{self, Device, DeviceStatus};
 use crate::mqtt::{
-    client::{poll, ConnectAddress, Credentials, MQTTClient, OptionsBuilder},
-    device::{DeviceExt, Ping, SyncDevice},
+    client::{
+        self, poll, ConnectAddress, Credentials, DeliveryExt, Event, EventLoop, LastWill,
+        MQTTClient, Message, OptionsBuilder, ProtocolVersion, TLSConfig, Timeouts,
+        PUBLISH_BATCH_SIZE,
+    },
+    device::{DeviceExt, Ping, Presence, PresenceStatus, SyncDevice},
     errors::*,
     topics,
 };
-use crate::storage::device::DeviceFile;
+use crate::network::failover::Endpoints;
+use crate::storage::{device::DeviceFile, events::EventQueue, outbox::Outbox};
 use crate::sync::syncer::{SyncEvent, SyncerExt};
 use crate::utils::{calc_exp_backoff, CooldownOptions};
+use crate::workers::commands::{self, Commands};
 
 // external crates
-use rumqttc::{ConnectReturnCode, Event, EventLoop, Incoming, Publish};
+use rumqttc::QoS;
 use tokio::sync::watch;
+use tokio::time::timeout;
 use tracing::{debug, error, info, warn};
 
 #[derive(Debug, Clone)]
 pub struct Options {
     pub cooldown: CooldownOptions,
     pub broker_address: ConnectAddress,
+    // the broker hosts to fail over between (the first being the broker address'
+    // host). Only the broker address' host is used if unset.
+    pub brokers: Option<Arc<Endpoints>>,
+    pub tls_config: Option<TLSConfig>,
+    pub protocol_version: ProtocolVersion,
+    pub session_expiry: Duration,
+    pub keep_alive: Duration,
+    pub timeouts: Timeouts,
+    // the number of requests (publishes, subscriptions, etc.) buffered for the broker
+    pub capacity: usize,
+    pub qos: QoS,
+    pub commands: commands::Options,
+    // where messages published while disconnected are queued until reconnected
+    pub outbox: Option<Arc<Outbox>>,
 }
 
 impl Default for Options {
@@ -39,15 +61,54 @@ impl Default for Options {
                 max_secs: five_mins,
             },
             broker_address: ConnectAddress::default(),
+            brokers: None,
+            tls_config: None,
+            protocol_version: ProtocolVersion::default(),
+            session_expiry: Duration::from_secs(5 * 60),
+            keep_alive: Duration::from_secs(20),
+            timeouts: Timeouts::default(),
+            capacity: 64,
+            qos: QoS::AtLeastOnce,
+            commands: commands::Options::default(),
+            outbox: None,
         }
     }
 }
 
+impl Options {
+    // the options of the mqtt clients the worker creates, besides their credentials
+    // which are set when each client is created
+    pub fn client_options(&self) -> client::Options {
+        let mut broker_address = self.broker_address.clone();
+        if let Some(brokers) = &self.brokers {
+            broker_address.broker = brokers.active();
+        }
+        OptionsBuilder::new(Credentials::default())
+            .with_connect_address(broker_address)
+            .with_tls_config(self.tls_config.clone())
+            .with_version(self.protocol_version)
+            .with_session_expiry(self.session_expiry)
+            .with_keep_alive(self.keep_alive)
+            .with_timeouts(self.timeouts)
+            .with_capacity(self.capacity)
+            .with_qos(self.qos)
+            .with_outbox(self.outbox.clone())
+            .build()
+    }
+}
+
+// the agent state the worker reads from and writes to
+pub struct Args<'a, TokenManagerT: TokenManagerExt, SyncerT: SyncerExt> {
+    pub token_mngr: &'a TokenManagerT,
+    pub syncer: &'a SyncerT,
+    pub device_file: &'a DeviceFile,
+    pub commands: &'a Commands,
+    pub event_queue: &'a EventQueue,
+}
+
 pub async fn run<F, Fut, TokenManagerT: TokenManagerExt, SyncerT: SyncerExt>(
     options: &Options,
-    token_mngr: &TokenManagerT,
-    syncer: &SyncerT,
-    device_file: &DeviceFile,
+    args: Args<'_, TokenManagerT, SyncerT>,
     sleep_fn: F,
     mut shutdown_signal: Pin<Box<impl Future<Output = ()> + Send + 'static>>,
 ) where
@@ -59,26 +120,25 @@ pub async fn run<F, Fut, TokenManagerT: TokenManagerExt, SyncerT: SyncerExt>(
             info!("MQTT worker shutdown complete");
         }
         // doesn't return but we do need to run it in the background
-        _ = run_impl(
-            options,
-            token_mngr,
-            syncer,
-            device_file,
-            sleep_fn,
-        ) => {}
+        _ = run_impl(options, args, sleep_fn) => {}
     }
 }
 
 pub async fn run_impl<F, Fut, TokenManagerT: TokenManagerExt, SyncerT: SyncerExt>(
     options: &Options,
-    token_mngr: &TokenManagerT,
-    syncer: &SyncerT,
-    device_file: &DeviceFile,
+    args: Args<'_, TokenManagerT, SyncerT>,
     sleep_fn: F,
 ) where
     F: Fn(Duration) -> Fut,
     Fut: Future<Output = ()> + Send,
 {
+    let Args {
+        token_mngr,
+        syncer,
+        device_file,
+        commands,
+        event_queue,
+    } = args;
     info!("Running mqtt worker");
 
     // subscribe to syncer events
@@ -88,25 +148,30 @@ pub async fn run_impl<F, Fut, TokenManagerT: TokenManagerExt, SyncerT: SyncerExt
         watch::channel(SyncEvent::SyncSuccess).1
     });
 
+    // subscribe to token refreshes since the token is the mqtt password
+    let mut token_subscriber = token_mngr.subscribe().await.unwrap_or_else(|e| {
+        error!("error subscribing to token refreshes: {e:?}");
+        // create a dummy receiver that never sends anything
+        watch::channel(Arc::new(Token::default())).1
+    });
+    let mut token_open = true;
+
     let device = device_file
         .read()
         .await
         .unwrap_or_else(|_| Arc::new(device::Device::default()));
 
     // create the mqtt client
-    let (mqtt_client, eventloop) = init_client(
-        &device.id,
-        &device.session_id,
-        token_mngr,
-        options.broker_address.clone(),
-    )
-    .await;
+    let mut client_options = options.client_options();
+    let (mqtt_client, eventloop) =
+        init_client(&device.id, &device.session_id, token_mngr, &client_options).await;
 
     let mut state = State {
         client: mqtt_client,
         eventloop,
         err_streak: 0,
     };
+    let mut connected = false;
 
     loop {
         tokio::select! {
@@ -120,32 +185,128 @@ pub async fn run_impl<F, Fut, TokenManagerT: TokenManagerExt, SyncerT: SyncerExt
                 ).await;
             }
 
+            // reconnect with the refreshed token before the current one expires. The
+            // device isn't marked offline since the old connection is closed gracefully.
+            result = token_subscriber.changed(), if token_open => {
+                if result.is_err() {
+                    warn!("token manager stopped publishing token refreshes");
+                    token_open = false;
+                    continue;
+                }
+                info!("token refreshed, reconnecting to mqtt broker with the new token");
+                if connected {
+                    disconnect(&mut state).await;
+                }
+                let (mqtt_client, eventloop) =
+                    init_client(&device.id, &device.session_id, token_mngr, &client_options).await;
+                mqtt_client.inherit_acked(&state.client);
+                state.client = mqtt_client;
+                state.eventloop = eventloop;
+                connected = false;
+            }
+
+            // replay the messages queued while disconnected from the broker
+            _ = outbox_pushed(options.outbox.as_deref()), if connected => {
+                if state.client.publish_queued().await {
+                    if let Some(outbox) = &options.outbox {
+                        outbox.wake();
+                    }
+                }
+            }
+
+            // publish queued events while connected to the broker (and remove the ones
+            // the broker acknowledged)
+            _ = event_queue.pushed(), if connected => {
+                if publish_events(&state.client, &device.id, event_queue).await {
+                    event_queue.wake();
+                }
+            }
+
             // listen for sync commands from the backend (via mqtt broker)
             mqtt_result = poll(&mut state.eventloop) => {
                 match mqtt_result {
                     Ok(mqtt_event) => {
+                        if state.client.record_delivery(&mqtt_event) {
+                            if let Some(outbox) = &options.outbox {
+                                outbox.wake();
+                            }
+                            event_queue.wake();
+                        }
+                        if let (Some(brokers), true) = (&options.brokers, is_connected(&mqtt_event)) {
+                            brokers.record_success(&client_options.connect_address.broker);
+                        }
+                        match mqtt_event {
+                            // publish the events queued while disconnected
+                            Event::Connected { .. } => {
+                                connected = true;
+                                state.client.set_connected(true);
+                                if let Some(outbox) = &options.outbox {
+                                    outbox.wake();
+                                }
+                                publish_presence(&state.client, &device.id, &device.session_id)
+                                    .await;
+                                event_queue.wake();
+                            }
+                            Event::Disconnected => {
+                                connected = false;
+                                state.client.set_connected(false);
+                            }
+                            _ => {}
+                        }
                         state.err_streak = handle_event(
                             &mqtt_event,
                             &state.client,
                             syncer,
                             &device.id,
                             device_file,
+                            commands,
                         ).await;
                     }
                     Err(e) => {
+                        connected = false;
+                        state.client.set_connected(false);
+                        if let (Some(brokers), true) = (&options.brokers, e.is_network_connection_error()) {
+                            brokers.record_failure(&client_options.connect_address.broker);
+                        }
+                        let is_auth_err = e.is_authentication_error();
                         state = handle_error(
                             state,
                             e,
                             &device,
                             token_mngr,
-                            &options.broker_address,
+                            &client_options,
                             device_file,
                         ).await;
+                        // the client was already reinitialized with the refreshed token
+                        if is_auth_err {
+                            token_subscriber.mark_unchanged();
+                        }
                     }
                 }
             }
         }
 
+        // reconnect to a different broker after failing over (or back)
+        if let Some(brokers) = &options.brokers {
+            let active = brokers.active();
+            if active != client_options.connect_address.broker {
+                info!(
+                    "switching mqtt broker from {} to {active}",
+                    client_options.connect_address.broker
+                );
+                client_options.connect_address.broker = active;
+                if connected {
+                    disconnect(&mut state).await;
+                }
+                let (mqtt_client, eventloop) =
+                    init_client(&device.id, &device.session_id, token_mngr, &client_options).await;
+                mqtt_client.inherit_acked(&state.client);
+                state.client = mqtt_client;
+                state.eventloop = eventloop;
+                connected = false;
+            }
+        }
+
         // sleep for the cooldown period to prevent throttling from mqtt errors
         let cooldown_secs = calc_exp_backoff(
             options.cooldown.base_secs,
@@ -158,11 +319,53 @@ pub async fn run_impl<F, Fut, TokenManagerT: TokenManagerExt, SyncerT: SyncerExt
     }
 }
 
+// the maximum time spent waiting for the broker to acknowledge a replaced client's
+// in-flight publishes
+const DRAIN_TIMEOUT: Duration = Duration::from_secs(5);
+
+// disconnects the client once the broker acknowledged its in-flight publishes (or the
+// drain timed out) so that they aren't lost and the broker doesn't publish the
+// client's last will. Messages received while draining are dropped.
+async fn disconnect(state: &mut State) {
+    let drained = timeout(DRAIN_TIMEOUT, async {
+        while state.client.has_in_flight() {
+            match poll(&mut state.eventloop).await {
+                Ok(event) => {
+                    state.client.record_delivery(&event);
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(())
+    })
+    .await;
+    match drained {
+        Ok(Ok(())) => {}
+        Ok(Err(e)) => {
+            debug!("connection lost while draining the mqtt client: {e:?}");
+            return;
+        }
+        Err(_) => warn!("timed out waiting for the broker to acknowledge in-flight publishes"),
+    }
+
+    if let Err(e) = state.client.disconnect().await {
+        error!("error disconnecting from mqtt broker: {e:?}");
+        return;
+    }
+    // the disconnect packet is sent by the event loop, after which the broker closes
+    // the connection
+    let _ = timeout(state.client.timeouts.disconnect, async {
+        while poll(&mut state.eventloop).await.is_ok() {}
+    })
+    .await;
+    state.client.set_connected(false);
+}
+
 async fn init_client<TokenManagerT: TokenManagerExt>(
     device_id: &str,
     device_session_id: &str,
     token_mngr: &TokenManagerT,
-    broker_address: ConnectAddress,
+    client_options: &client::Options,
 ) -> (MQTTClient, EventLoop) {
     // update the mqtt password
     let token = match token_mngr.get_token().await {
@@ -170,15 +373,29 @@ async fn init_client<TokenManagerT: TokenManagerExt>(
         Err(_) => Token::default().token,
     };
 
+    // the broker marks the device offline on its behalf if the connection drops
+    let last_will = match Presence::last_will(device_id, device_session_id) {
+        Ok(last_will) => Some(LastWill {
+            qos: client_options.qos,
+            ..last_will
+        }),
+        Err(e) => {
+            error!("error creating the presence last will: {e:?}");
+            None
+        }
+    };
+
     // initialize the mqtt client
     let credentials = Credentials {
         username: device_session_id.to_string(),
         password: token,
     };
-    let options = OptionsBuilder::new(credentials)
-        .with_connect_address(broker_address)
-        .with_client_id(device_id.to_string())
-        .build();
+    let options = client::Options {
+        credentials,
+        client_id: device_id.to_string(),
+        last_will,
+        ..client_options.clone()
+    };
     let (mqtt_client, eventloop) = MQTTClient::new(&options).await;
 
     // subscribe to device synchronization updates
@@ -188,6 +405,9 @@ async fn init_client<TokenManagerT: TokenManagerExt>(
     if let Err(e) = mqtt_client.subscribe_device_ping(device_id).await {
         error!("error subscribing to device ping updates: {e:?}");
     };
+    if let Err(e) = mqtt_client.subscribe_device_commands(device_id).await {
+        error!("error subscribing to device commands: {e:?}");
+    };
 
     (mqtt_client, eventloop)
 }
@@ -213,56 +433,142 @@ pub async fn handle_syncer_event<MQTTClientT: DeviceExt>(
     }
 }
 
+// replaces the retained last will of the previous connection, which the broker may
+// have published when that connection dropped
+pub async fn publish_presence<MQTTClientT: DeviceExt>(
+    mqtt_client: &MQTTClientT,
+    device_id: &str,
+    device_session_id: &str,
+) {
+    let presence = Presence::new(PresenceStatus::Online, device_session_id);
+    match mqtt_client
+        .publish_device_presence(device_id, &presence)
+        .await
+    {
+        Ok(_) => debug!("published device presence"),
+        Err(e) => error!("error publishing device presence: {e:?}"),
+    }
+}
+
+// publishes the oldest queued events which aren't already in flight, removing the
+// events the broker acknowledged from the queue. Events are only removed once
+// acknowledged so that they're republished if the connection (or client) is lost
+// before then. Returns whether acknowledged events were removed, in which case more
+// events may be published.
+pub async fn publish_events<MQTTClientT: DeviceExt + DeliveryExt>(
+    mqtt_client: &MQTTClientT,
+    device_id: &str,
+    event_queue: &EventQueue,
+) -> bool {
+    let events = match event_queue.peek(PUBLISH_BATCH_SIZE).await {
+        Ok(events) => events,
+        Err(e) => {
+            error!("error reading queued events: {e:?}");
+            return false;
+        }
+    };
+
+    let (acked, events): (Vec<_>, Vec<_>) = events
+        .into_iter()
+        .partition(|event| mqtt_client.take_acked(&event.event_id));
+    let acked: Vec<String> = acked.into_iter().map(|event| event.event_id).collect();
+    if !acked.is_empty() {
+        debug!("{} config instance events were delivered", acked.len());
+        if let Err(e) = event_queue.remove(acked.clone()).await {
+            error!("error removing delivered events from the queue: {e:?}");
+            return false;
+        }
+    }
+
+    let mut published = 0;
+    for event in events {
+        if mqtt_client.is_in_flight(&event.event_id) {
+            continue;
+        }
+        if let Err(e) = mqtt_client
+            .publish_config_instance_event(device_id, &event)
+            .await
+        {
+            // leave the rest of the events queued for the next connection
+            error!("error publishing config instance event: {e:?}");
+            break;
+        }
+        published += 1;
+    }
+    if published > 0 {
+        debug!("published {published} config instance events");
+    }
+
+    !acked.is_empty()
+}
+
+// resolves once a message has been pushed to the outbox (never if there isn't one)
+async fn outbox_pushed(outbox: Option<&Outbox>) {
+    match outbox {
+        Some(outbox) => outbox.pushed().await,
+        None => std::future::pending().await,
+    }
+}
+
 type ErrStreak = u32;
 
+fn is_connected(event: &Event) -> bool {
+    matches!(event, Event::Connected { .. })
+}
+
 pub async fn handle_event<MQTTClientT: DeviceExt, SyncerT: SyncerExt>(
     event: &Event,
     mqtt_client: &MQTTClientT,
     syncer: &SyncerT,
     device_id: &str,
     device_file: &DeviceFile,
+    commands: &Commands,
 ) -> ErrStreak {
     let err_streak = 0;
 
     match event {
         // update the device connection status on successful connections
-        Event::Incoming(Incoming::ConnAck(connack)) => {
-            if connack.code != ConnectReturnCode::Success {
-                return err_streak;
-            }
-            info!("Established connection to mqtt broker");
+        Event::Connected { session_present } => {
+            info!("Established connection to mqtt broker (session present: {session_present})");
+            registry::global().mqtt.on_connect();
             let _ = device_file.patch(device::Updates::connected()).await;
         }
         // update the device connection status on successful disconnections
-        Event::Incoming(Incoming::Disconnect) => {
+        Event::Disconnected => {
             info!("Disconnected from mqtt broker");
+            registry::global().mqtt.on_disconnect();
             let _ = device_file.patch(device::Updates::disconnected()).await;
         }
 
         // sync the device if the payload is a sync request
-        Event::Incoming(Incoming::Publish(publish)) => {
-            let topic = topics::parse_subscription(device_id, &publish.topic);
+        Event::Message(message) => {
+            let topic = topics::parse_subscription(device_id, &message.topic);
             match topic {
                 topics::SubscriptionTopics::Sync => {
-                    handle_sync_event(publish, syncer).await;
+                    handle_sync_event(message, syncer).await;
                 }
                 topics::SubscriptionTopics::Ping => {
-                    handle_ping_event(publish, mqtt_client, device_id).await;
+                    handle_ping_event(message, mqtt_client, device_id).await;
+                }
+                topics::SubscriptionTopics::Command(command) => {
+                    commands
+                        .handle(command, message, mqtt_client, syncer, device_id)
+                        .await;
                 }
                 topics::SubscriptionTopics::Unknown => {
-                    warn!("unknown topic: {}", publish.topic);
+                    warn!("unknown topic: {}", message.topic);
                 }
             }
         }
 
-        _ => {}
+        Event::Published { .. } | Event::Acked { .. } | Event::Other => {}
     }
 
     err_streak
 }
 
-async fn handle_sync_event<SyncerT: SyncerExt>(publish: &Publish, syncer: &SyncerT) {
-    let is_synced = match serde_json::from_slice::<SyncDevice>(&publish.payload) {
+async fn handle_sync_event<SyncerT: SyncerExt>(message: &Message, syncer: &SyncerT) {
+    let is_synced = match serde_json::from_slice::<SyncDevice>(&message.payload) {
         Ok(sync_req) => sync_req.is_synced,
         Err(e) => {
             error!("error deserializing sync request: {e:?}");
@@ -278,11 +584,11 @@ async fn handle_sync_event<SyncerT: SyncerExt>(publish: &Publish, syncer: &Synce
 }
 
 async fn handle_ping_event<MQTTClientT: DeviceExt>(
-    publish: &Publish,
+    message: &Message,
     client: &MQTTClientT,
     device_id: &str,
 ) {
-    let message_id = match serde_json::from_slice::<Ping>(&publish.payload) {
+    let message_id = match serde_json::from_slice::<Ping>(&message.payload) {
         Ok(ping) => {
             info!(
                 "received ping request at {} with message id {}",
@@ -295,7 +601,12 @@ async fn handle_ping_event<MQTTClientT: DeviceExt>(
             return;
         }
     };
-    if let Err(e) = client.publish_device_pong(device_id, message_id).await {
+    // mqtt v5 pings are answered on their response topic
+    let reply_to = message.reply_to();
+    if let Err(e) = client
+        .publish_device_pong(device_id, message_id, reply_to.as_ref())
+        .await
+    {
         error!("error publishing ping response: {e:?}");
     } else {
         info!("successfully published ping response");
@@ -313,7 +624,7 @@ pub async fn handle_error<TokenManagerT: TokenManagerExt>(
     e: MQTTError,
     device: &Device,
     token_mngr: &TokenManagerT,
-    broker_address: &ConnectAddress,
+    client_options: &client::Options,
     device_file: &DeviceFile,
 ) -> State {
     state.err_streak = if e.is_network_connection_error() {
@@ -324,6 +635,7 @@ pub async fn handle_error<TokenManagerT: TokenManagerExt>(
     };
 
     // update the device to be offline
+    registry::global().mqtt.on_disconnect();
     match device_file.read().await {
         Ok(device) => {
             if device.status == DeviceStatus::Online {
@@ -342,13 +654,8 @@ pub async fn handle_error<TokenManagerT: TokenManagerExt>(
         if let Err(e) = token_mngr.refresh_token().await {
             error!("error refreshing token for backend sync worker: {e:?}");
         }
-        let (mqtt_client, eventloop) = init_client(
-            &device.id,
-            &device.session_id,
-            token_mngr,
-            broker_address.clone(),
-        )
-        .await;
+        let (mqtt_client, eventloop) =
+            init_client(&device.id, &device.session_id, token_mngr, client_options).await;
         state.client = mqtt_client;
         state.eventloop = eventloop;
         state
@@ -6,7 +6,7 @@ use std::time::Duration;
 // internal crates
 use crate::authn::token_mngr::TokenManagerExt;
 use crate::errors::*;
-use crate::utils::{calc_exp_backoff, CooldownOptions};
+use crate::utils::{calc_exp_backoff, calc_retry_after_secs, CooldownOptions};
 
 // external crates
 use chrono::Utc;
@@ -64,7 +64,10 @@ pub async fn run_token_refresh_worker<F, Fut, TokenManagerT: TokenManagerExt>(
                 .await
             }
             Err(e) => {
-                if e.is_network_connection_error() {
+                // the backend's requested wait (e.g. while it is shedding load) takes
+                // precedence over our own cooldown
+                let retry_after = e.retry_after();
+                let next_wait = if e.is_network_connection_error() {
                     debug!("unable to refresh token due to a network connection error: {e:?}");
                     calc_refresh_wait(
                         token_mngr,
@@ -86,6 +89,14 @@ pub async fn run_token_refresh_worker<F, Fut, TokenManagerT: TokenManagerExt>(
                         options.polling,
                     )
                     .await
+                };
+                match retry_after {
+                    Some(retry_after) => {
+                        let secs = calc_retry_after_secs(retry_after, options.polling);
+                        info!("backend requested a token refresh retry after {secs} seconds");
+                        Duration::from_secs(secs as u64)
+                    }
+                    None => next_wait,
                 }
             }
         };
@@ -65,6 +65,7 @@ async fn max_runtime_reached() {
         },
         server: ServerOptions {
             socket_file: File::new(PathBuf::from("/tmp").join("miru.sock")),
+            ..Default::default()
         },
         ..Default::default()
     };
@@ -98,6 +99,7 @@ async fn is_persistent() {
         },
         server: ServerOptions {
             socket_file: File::new(PathBuf::from("/tmp").join("miru.sock")),
+            ..Default::default()
         },
         ..Default::default()
     };
@@ -131,6 +133,7 @@ async fn idle_timeout_reached() {
         },
         server: ServerOptions {
             socket_file: File::new(PathBuf::from("/tmp").join("miru.sock")),
+            ..Default::default()
         },
         ..Default::default()
     };
@@ -162,6 +165,7 @@ async fn shutdown_signal_received() {
         },
         server: ServerOptions {
             socket_file: File::new(PathBuf::from("/tmp").join("miru.sock")),
+            ..Default::default()
         },
         ..Default::default()
     };
@@ -4,6 +4,9 @@ use std::sync::{Arc, Mutex};
 // internal crates
 use miru_agent::authn::{errors::*, token::Token, token_mngr::TokenManagerExt};
 
+// external crates
+use tokio::sync::watch;
+
 type GetTokenFn = Box<dyn Fn() -> Result<Arc<Token>, AuthnErr> + Send + Sync>;
 type RefreshTokenFn = Box<dyn Fn() -> Result<(), AuthnErr> + Send + Sync>;
 
@@ -18,11 +21,13 @@ pub struct MockTokenManager {
     pub calls: Arc<Mutex<Vec<TokenManagerCall>>>,
     pub get_token_fn: Arc<Mutex<Option<GetTokenFn>>>,
     pub refresh_token_fn: Arc<Mutex<RefreshTokenFn>>,
+    pub refreshed_tx: watch::Sender<Arc<Token>>,
 }
 
 impl MockTokenManager {
     pub fn new(token: Token) -> Self {
         Self {
+            refreshed_tx: watch::Sender::new(Arc::new(token.clone())),
             token: Arc::new(Mutex::new(token)),
             calls: Arc::new(Mutex::new(Vec::new())),
             get_token_fn: Arc::new(Mutex::new(None)),
@@ -34,6 +39,12 @@ impl MockTokenManager {
         *self.token.lock().unwrap() = token;
     }
 
+    // sets the token and notifies the subscribers as a token refresh would
+    pub fn refresh(&self, token: Token) {
+        self.set_token(token.clone());
+        self.refreshed_tx.send_replace(Arc::new(token));
+    }
+
     pub fn get_calls(&self) -> Vec<TokenManagerCall> {
         self.calls.lock().unwrap().clone()
     }
@@ -86,4 +97,8 @@ impl TokenManagerExt for MockTokenManager {
             .push(TokenManagerCall::RefreshToken);
         (*self.refresh_token_fn.lock().unwrap())()
     }
+
+    async fn subscribe(&self) -> Result<watch::Receiver<Arc<Token>>, AuthnErr> {
+        Ok(self.refreshed_tx.subscribe())
+    }
 }
@@ -346,3 +346,61 @@ pub mod refresh_token {
         assert_eq!(token.as_ref(), &expected);
     }
 }
+
+pub mod subscribe {
+    use super::*;
+
+    #[tokio::test]
+    async fn refreshed_tokens_are_published() {
+        // prepare the arguments
+        let dir = Dir::create_temp_dir("testing").await.unwrap();
+        let initial = Token {
+            token: "initial".to_string(),
+            expires_at: Utc::now(),
+        };
+        let token_file = TokenFile::new_with_default(dir.file("token.json"), initial.clone())
+            .await
+            .unwrap();
+        let private_key_file = dir.file("private_key.pem");
+        let public_key_file = dir.file("public_key.pem");
+        rsa::gen_key_pair(4096, &private_key_file, &public_key_file, true)
+            .await
+            .unwrap();
+
+        // prepare the mock http client
+        let expires_at = Utc::now() + Duration::days(1);
+        let resp = TokenResponse {
+            token: "refreshed".to_string(),
+            expires_at: expires_at.to_rfc3339(),
+        };
+        let resp_clone = resp.clone();
+        let mock_http_client = MockDevicesClient {
+            issue_device_token_fn: Box::new(move || Ok(resp_clone.clone())),
+            ..Default::default()
+        };
+
+        // spawn the token manager
+        let (token_mngr, _) = spawn(
+            32,
+            "device_id".to_string(),
+            Arc::new(mock_http_client),
+            token_file,
+            private_key_file,
+        )
+        .unwrap();
+
+        // subscribers start from the current token
+        let mut subscriber = token_mngr.subscribe().await.unwrap();
+        assert!(!subscriber.has_changed().unwrap());
+        assert_eq!(subscriber.borrow().as_ref(), &initial);
+
+        // refresh the token
+        token_mngr.refresh_token().await.unwrap();
+        assert!(subscriber.has_changed().unwrap());
+        let expected = Token {
+            token: resp.token,
+            expires_at,
+        };
+        assert_eq!(subscriber.borrow_and_update().as_ref(), &expected);
+    }
+}
@@ -5,16 +5,22 @@ use std::path::PathBuf;
 // internal crates
 use miru_agent::cache::{entry::CacheEntry, file::FileCache};
 use miru_agent::deploy::{
-    apply::{apply, find_instances_to_replace, find_replacement, is_dirty},
+    apply::{
+        apply, classify_update, find_instances_to_replace, find_replacement, is_dirty,
+        is_transition, EventObserver, MetricsObserver,
+    },
     errors::DeployErr,
     fsm::Settings,
+    observer::Observer,
 };
 use miru_agent::filesys::{dir::Dir, path::PathExt};
 use miru_agent::logs::*;
+use miru_agent::metrics::registry::{DeployMetrics, DeployOperation};
 use miru_agent::models::config_instance::{
     ActivityStatus, ConfigInstance, ErrorStatus, TargetStatus,
 };
 use miru_agent::storage::config_instances::{ConfigInstanceCache, ConfigInstanceContentCache};
+use miru_agent::storage::events::EventQueue;
 use miru_agent::utils::calc_exp_backoff;
 
 // external crates
@@ -127,8 +133,10 @@ pub mod apply_func {
             HashMap::new(),
             &cfg_inst_cache,
             &cfg_inst_content_cache,
+            None,
             &dir,
             &Settings::default(),
+            None,
         )
         .await
         .unwrap();
@@ -166,8 +174,10 @@ pub mod apply_func {
             cfg_insts_to_apply,
             &cfg_inst_cache,
             &cfg_inst_content_cache,
+            None,
             &dir,
             &settings,
+            None,
         )
         .await
         .unwrap();
@@ -229,8 +239,10 @@ pub mod apply_func {
             cfg_insts_to_apply,
             &cfg_inst_cache,
             &cfg_inst_content_cache,
+            None,
             &dir,
             &settings,
+            None,
         )
         .await
         .unwrap();
@@ -248,6 +260,7 @@ pub mod apply_func {
             error_status: ErrorStatus::Retrying,
             attempts: 1,
             cooldown_ends_at: actual2.cooldown_ends_at,
+            last_deploy_error: actual2.last_deploy_error.clone(),
             ..cfg_inst2
         };
         let cooldown = calc_exp_backoff(
@@ -303,8 +316,10 @@ pub mod apply_func {
             cfg_insts_to_apply,
             &cfg_inst_cache,
             &cfg_inst_content_cache,
+            None,
             &dir,
             &settings,
+            None,
         )
         .await
         .unwrap();
@@ -368,8 +383,10 @@ pub mod apply_func {
             cfg_insts_to_apply,
             &cfg_inst_cache,
             &cfg_inst_content_cache,
+            None,
             &dir,
             &settings,
+            None,
         )
         .await
         .unwrap();
@@ -443,8 +460,10 @@ pub mod apply_func {
             cfg_insts_to_apply,
             &cfg_inst_cache,
             &cfg_inst_content_cache,
+            None,
             &dir,
             &settings,
+            None,
         )
         .await
         .unwrap();
@@ -463,6 +482,7 @@ pub mod apply_func {
             error_status: ErrorStatus::Retrying,
             attempts: 1,
             cooldown_ends_at: actual_to_deploy.cooldown_ends_at,
+            last_deploy_error: actual_to_deploy.last_deploy_error.clone(),
             ..to_deploy
         };
         let cooldown = calc_exp_backoff(
@@ -537,8 +557,10 @@ pub mod apply_func {
             cfg_insts_to_apply,
             &cfg_inst_cache,
             &cfg_inst_content_cache,
+            None,
             &dir,
             &settings,
+            None,
         )
         .await
         .unwrap();
@@ -552,6 +574,7 @@ pub mod apply_func {
             error_status: ErrorStatus::Retrying,
             attempts: 1,
             cooldown_ends_at: actual_to_deploy.cooldown_ends_at,
+            last_deploy_error: actual_to_deploy.last_deploy_error.clone(),
             ..to_deploy
         };
         let cooldown = calc_exp_backoff(
@@ -642,8 +665,10 @@ pub mod apply_func {
             cfg_insts_to_apply,
             &cfg_inst_cache,
             &cfg_inst_content_cache,
+            None,
             &dir,
             &settings,
+            None,
         )
         .await
         .unwrap();
@@ -1001,3 +1026,298 @@ pub mod find_replacement_func {
         find_replacement(&cfg_inst, &cache).await.unwrap_err();
     }
 }
+
+pub mod classify_update_func {
+    use super::*;
+
+    #[test]
+    fn unknown_config_instance() {
+        let deployed = ConfigInstance {
+            activity_status: ActivityStatus::Deployed,
+            ..Default::default()
+        };
+        assert_eq!(
+            classify_update(None, &deployed),
+            Some((DeployOperation::Deploy, true))
+        );
+
+        let removed = ConfigInstance {
+            activity_status: ActivityStatus::Removed,
+            ..Default::default()
+        };
+        assert_eq!(
+            classify_update(None, &removed),
+            Some((DeployOperation::Archive, true))
+        );
+
+        let queued = ConfigInstance {
+            activity_status: ActivityStatus::Queued,
+            ..Default::default()
+        };
+        assert_eq!(classify_update(None, &queued), None);
+    }
+
+    #[test]
+    fn successful_transitions() {
+        // deploy
+        let prev = ConfigInstance {
+            target_status: TargetStatus::Deployed,
+            activity_status: ActivityStatus::Queued,
+            ..Default::default()
+        };
+        let new = ConfigInstance {
+            activity_status: ActivityStatus::Deployed,
+            ..prev.clone()
+        };
+        assert_eq!(
+            classify_update(Some(&prev), &new),
+            Some((DeployOperation::Deploy, true))
+        );
+
+        // remove
+        let prev = ConfigInstance {
+            target_status: TargetStatus::Removed,
+            activity_status: ActivityStatus::Deployed,
+            ..Default::default()
+        };
+        let new = ConfigInstance {
+            activity_status: ActivityStatus::Removed,
+            ..prev.clone()
+        };
+        assert_eq!(
+            classify_update(Some(&prev), &new),
+            Some((DeployOperation::Remove, true))
+        );
+
+        // archive
+        let prev = ConfigInstance {
+            target_status: TargetStatus::Removed,
+            activity_status: ActivityStatus::Queued,
+            ..Default::default()
+        };
+        let new = ConfigInstance {
+            activity_status: ActivityStatus::Removed,
+            ..prev.clone()
+        };
+        assert_eq!(
+            classify_update(Some(&prev), &new),
+            Some((DeployOperation::Archive, true))
+        );
+    }
+
+    #[test]
+    fn error_transitions() {
+        let prev = ConfigInstance {
+            target_status: TargetStatus::Deployed,
+            activity_status: ActivityStatus::Queued,
+            ..Default::default()
+        };
+        let new = ConfigInstance {
+            error_status: ErrorStatus::Retrying,
+            attempts: 1,
+            cooldown_ends_at: Utc::now() + TimeDelta::seconds(30),
+            ..prev.clone()
+        };
+        assert_eq!(
+            classify_update(Some(&prev), &new),
+            Some((DeployOperation::Deploy, false))
+        );
+    }
+
+    #[test]
+    fn no_transition() {
+        let prev = ConfigInstance {
+            target_status: TargetStatus::Deployed,
+            activity_status: ActivityStatus::Deployed,
+            ..Default::default()
+        };
+        assert_eq!(classify_update(Some(&prev), &prev.clone()), None);
+
+        // a cooldown change without an error status is not a failure
+        let prev = ConfigInstance {
+            target_status: TargetStatus::Removed,
+            activity_status: ActivityStatus::Deployed,
+            ..Default::default()
+        };
+        let new = ConfigInstance {
+            cooldown_ends_at: Utc::now() + TimeDelta::seconds(30),
+            ..prev.clone()
+        };
+        assert_eq!(classify_update(Some(&prev), &new), None);
+    }
+}
+
+pub mod metrics_observer {
+    use super::*;
+
+    #[tokio::test]
+    async fn records_transitions() {
+        let dir = Dir::create_temp_dir("testing").await.unwrap();
+        let (cfg_inst_cache, _) = ConfigInstanceCache::spawn(16, dir.file("metadata.json"), 1000)
+            .await
+            .unwrap();
+        let metrics = DeployMetrics::default();
+
+        // deploy a config instance the agent already knows about
+        let queued = ConfigInstance {
+            target_status: TargetStatus::Deployed,
+            activity_status: ActivityStatus::Queued,
+            ..Default::default()
+        };
+        cfg_inst_cache
+            .write(queued.id.clone(), queued.clone(), |_, _| false, true)
+            .await
+            .unwrap();
+        let deployed = ConfigInstance {
+            activity_status: ActivityStatus::Deployed,
+            ..queued.clone()
+        };
+        let mut observer = MetricsObserver {
+            cfg_inst_cache: &cfg_inst_cache,
+            metrics: &metrics,
+        };
+        observer.on_update(&deployed).await.unwrap();
+
+        // archive a config instance the agent has never seen
+        let archived = ConfigInstance {
+            id: "unknown".to_string(),
+            target_status: TargetStatus::Removed,
+            activity_status: ActivityStatus::Removed,
+            ..Default::default()
+        };
+        observer.on_update(&archived).await.unwrap();
+
+        assert_eq!(metrics.deploys.get(), 1);
+        assert_eq!(metrics.archives.get(), 1);
+        assert_eq!(metrics.deploy_failures.get(), 0);
+    }
+}
+
+pub mod event_observer {
+    use super::*;
+
+    #[test]
+    fn transitions() {
+        let queued = ConfigInstance {
+            target_status: TargetStatus::Deployed,
+            activity_status: ActivityStatus::Queued,
+            ..Default::default()
+        };
+
+        // unknown config instances
+        assert!(is_transition(None, &queued));
+
+        // no changes
+        assert!(!is_transition(Some(&queued), &queued));
+
+        // fsm fields only
+        let retried = ConfigInstance {
+            attempts: 3,
+            cooldown_ends_at: Utc::now(),
+            ..queued.clone()
+        };
+        assert!(!is_transition(Some(&queued), &retried));
+
+        // activity status
+        let deployed = ConfigInstance {
+            activity_status: ActivityStatus::Deployed,
+            ..queued.clone()
+        };
+        assert!(is_transition(Some(&queued), &deployed));
+
+        // error status
+        let retrying = ConfigInstance {
+            error_status: ErrorStatus::Retrying,
+            ..queued.clone()
+        };
+        assert!(is_transition(Some(&queued), &retrying));
+    }
+
+    #[tokio::test]
+    async fn queues_transitions() {
+        let dir = Dir::create_temp_dir("testing").await.unwrap();
+        let (cfg_inst_cache, _) = ConfigInstanceCache::spawn(16, dir.file("metadata.json"), 1000)
+            .await
+            .unwrap();
+        let (event_queue, _) = EventQueue::spawn(16, dir.file("events.json"), 10)
+            .await
+            .unwrap();
+
+        let queued = ConfigInstance {
+            target_status: TargetStatus::Deployed,
+            activity_status: ActivityStatus::Queued,
+            ..Default::default()
+        };
+        cfg_inst_cache
+            .write(queued.id.clone(), queued.clone(), |_, _| false, true)
+            .await
+            .unwrap();
+        let mut observer = EventObserver {
+            cfg_inst_cache: &cfg_inst_cache,
+            event_queue: &event_queue,
+        };
+
+        // unchanged config instances aren't queued
+        observer.on_update(&queued).await.unwrap();
+        assert!(event_queue.is_empty().await.unwrap());
+
+        let deployed = ConfigInstance {
+            activity_status: ActivityStatus::Deployed,
+            ..queued.clone()
+        };
+        observer.on_update(&deployed).await.unwrap();
+        let events = event_queue.peek(10).await.unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].config_instance_id, queued.id);
+        assert_eq!(events[0].activity_status, ActivityStatus::Deployed);
+        assert_eq!(events[0].error_status, ErrorStatus::None);
+    }
+
+    #[tokio::test]
+    async fn apply_queues_events() {
+        let cfg_inst = ConfigInstance {
+            relative_filepath: "/test/filepath".to_string(),
+            target_status: TargetStatus::Deployed,
+            activity_status: ActivityStatus::Queued,
+            ..Default::default()
+        };
+
+        let dir = Dir::create_temp_dir("deploy").await.unwrap();
+        let (cfg_inst_cache, _) = ConfigInstanceCache::spawn(16, dir.file("metadata.json"), 1000)
+            .await
+            .unwrap();
+        let (cfg_inst_content_cache, _) =
+            ConfigInstanceContentCache::spawn(16, dir.subdir("contents"), 1000)
+                .await
+                .unwrap();
+        let (event_queue, _) = EventQueue::spawn(16, dir.file("events.json"), 10)
+            .await
+            .unwrap();
+        cfg_inst_cache
+            .write(cfg_inst.id.clone(), cfg_inst.clone(), |_, _| false, true)
+            .await
+            .unwrap();
+        cfg_inst_content_cache
+            .write(cfg_inst.id.clone(), json!({"speed": 4}), |_, _| false, true)
+            .await
+            .unwrap();
+
+        let cfg_insts_to_apply = HashMap::from([(cfg_inst.id.clone(), cfg_inst.clone())]);
+        apply(
+            cfg_insts_to_apply,
+            &cfg_inst_cache,
+            &cfg_inst_content_cache,
+            None,
+            &dir.subdir("deployments"),
+            &Settings::default(),
+            Some(&event_queue),
+        )
+        .await
+        .unwrap();
+
+        let events = event_queue.peek(10).await.unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].config_instance_id, cfg_inst.id);
+        assert_eq!(events[0].activity_status, ActivityStatus::Deployed);
+    }
+}
@@ -7,6 +7,7 @@ use miru_agent::filesys::path::PathExt;
 use miru_agent::models::config_instance::{
     ActivityStatus, ConfigInstance, ErrorStatus, TargetStatus,
 };
+use miru_agent::storage::config_schemas::ConfigSchemaContentCache;
 use miru_agent::utils::calc_exp_backoff;
 
 // external crates
@@ -44,6 +45,7 @@ pub mod deploy_with_rollback {
             vec![],
             vec![cfg_inst.clone()],
             &cache,
+            None::<&ConfigSchemaContentCache>,
             &deployment_dir,
             &settings,
             &mut observers,
@@ -57,6 +59,7 @@ pub mod deploy_with_rollback {
             error_status: ErrorStatus::Retrying,
             attempts: 1,
             cooldown_ends_at: deploy_results.to_deploy[0].cooldown_ends_at,
+            last_deploy_error: deploy_results.to_deploy[0].last_deploy_error.clone(),
             ..cfg_inst
         };
         let cooldown = calc_exp_backoff(
@@ -122,6 +125,7 @@ pub mod deploy_with_rollback {
             vec![],
             vec![cfg_inst.clone()],
             &cache,
+            None::<&ConfigSchemaContentCache>,
             &deployment_dir,
             &settings,
             &mut observers,
@@ -187,6 +191,7 @@ pub mod deploy_with_rollback {
             vec![],
             vec![cfg_inst.clone()],
             &cache,
+            None::<&ConfigSchemaContentCache>,
             &deployment_dir,
             &settings,
             &mut observers,
@@ -215,6 +220,229 @@ pub mod deploy_with_rollback {
         assert_eq!(actual, cfg_inst_content);
     }
 
+    // deploy 1 - content is validated against its cached config schema
+    #[tokio::test]
+    async fn deploy_1_valid_schema() {
+        // define the config instance
+        let filepath = "/test/filepath".to_string();
+        let cfg_inst = ConfigInstance {
+            relative_filepath: filepath.clone(),
+            target_status: TargetStatus::Deployed,
+            config_schema_id: "cfg_sch_123".to_string(),
+            ..Default::default()
+        };
+
+        // create the config instance content and config schema in the caches
+        let temp_dir = Dir::create_temp_dir("deploy").await.unwrap();
+        let cache_dir = temp_dir.subdir("caches");
+        let (cache, _) = FileCache::spawn(16, cache_dir.file("cache.json"), 1000)
+            .await
+            .unwrap();
+        let cfg_inst_content = json!({"speed": 4});
+        cache
+            .write(
+                cfg_inst.id.clone(),
+                cfg_inst_content.clone(),
+                |_, _| false,
+                true,
+            )
+            .await
+            .unwrap();
+        let (schema_cache, _) =
+            ConfigSchemaContentCache::spawn(16, cache_dir.subdir("schemas"), 1000)
+                .await
+                .unwrap();
+        schema_cache
+            .write(
+                cfg_inst.config_schema_id.clone(),
+                json!({"type": "object", "properties": {"speed": {"type": "integer", "minimum": 1}}}),
+                |_, _| false,
+                true,
+            )
+            .await
+            .unwrap();
+
+        // deploy the config instance
+        let settings = Settings::default();
+        let deployment_dir = temp_dir.subdir("config_instances");
+        let mut observers: Vec<&mut dyn Observer> = Vec::new();
+        let (deploy_results, result) = deploy_with_rollback(
+            vec![],
+            vec![cfg_inst.clone()],
+            &cache,
+            Some(&schema_cache),
+            &deployment_dir,
+            &settings,
+            &mut observers,
+        )
+        .await;
+        result.unwrap();
+
+        // check that the config instance was deployed
+        let expected = ConfigInstance {
+            activity_status: ActivityStatus::Deployed,
+            ..cfg_inst
+        };
+        assert_eq!(deploy_results.to_deploy, vec![expected]);
+
+        // check that the file was created
+        let file = deployment_dir.file(filepath.as_str());
+        let actual = file.read_json::<serde_json::Value>().await.unwrap();
+        assert_eq!(actual, cfg_inst_content);
+    }
+
+    // deploy 1 - content whose config schema isn't cached is deployed unvalidated
+    #[tokio::test]
+    async fn deploy_1_schema_not_cached() {
+        // define the config instance
+        let filepath = "/test/filepath".to_string();
+        let cfg_inst = ConfigInstance {
+            relative_filepath: filepath.clone(),
+            target_status: TargetStatus::Deployed,
+            config_schema_id: "cfg_sch_123".to_string(),
+            ..Default::default()
+        };
+
+        // create the config instance content but not its config schema
+        let temp_dir = Dir::create_temp_dir("deploy").await.unwrap();
+        let cache_dir = temp_dir.subdir("caches");
+        let (cache, _) = FileCache::spawn(16, cache_dir.file("cache.json"), 1000)
+            .await
+            .unwrap();
+        let cfg_inst_content = json!({"speed": 0});
+        cache
+            .write(
+                cfg_inst.id.clone(),
+                cfg_inst_content.clone(),
+                |_, _| false,
+                true,
+            )
+            .await
+            .unwrap();
+        let (schema_cache, _) =
+            ConfigSchemaContentCache::spawn(16, cache_dir.subdir("schemas"), 1000)
+                .await
+                .unwrap();
+
+        // deploy the config instance
+        let settings = Settings::default();
+        let deployment_dir = temp_dir.subdir("config_instances");
+        let mut observers: Vec<&mut dyn Observer> = Vec::new();
+        let (deploy_results, result) = deploy_with_rollback(
+            vec![],
+            vec![cfg_inst.clone()],
+            &cache,
+            Some(&schema_cache),
+            &deployment_dir,
+            &settings,
+            &mut observers,
+        )
+        .await;
+        result.unwrap();
+
+        // check that the config instance was deployed
+        let expected = ConfigInstance {
+            activity_status: ActivityStatus::Deployed,
+            ..cfg_inst
+        };
+        assert_eq!(deploy_results.to_deploy, vec![expected]);
+
+        // check that the file was created
+        let file = deployment_dir.file(filepath.as_str());
+        let actual = file.read_json::<serde_json::Value>().await.unwrap();
+        assert_eq!(actual, cfg_inst_content);
+    }
+
+    // deploy 1 - content which violates its cached config schema is never written
+    #[tokio::test]
+    async fn deploy_1_failed_schema_validation() {
+        // define the config instance
+        let filepath = "/test/filepath".to_string();
+        let cfg_inst = ConfigInstance {
+            relative_filepath: filepath.clone(),
+            target_status: TargetStatus::Deployed,
+            config_schema_id: "cfg_sch_123".to_string(),
+            ..Default::default()
+        };
+
+        // create the config instance content and config schema in the caches
+        let temp_dir = Dir::create_temp_dir("deploy").await.unwrap();
+        let cache_dir = temp_dir.subdir("caches");
+        let (cache, _) = FileCache::spawn(16, cache_dir.file("cache.json"), 1000)
+            .await
+            .unwrap();
+        cache
+            .write(
+                cfg_inst.id.clone(),
+                json!({"speed": 0, "mode": "fast"}),
+                |_, _| false,
+                true,
+            )
+            .await
+            .unwrap();
+        let (schema_cache, _) =
+            ConfigSchemaContentCache::spawn(16, cache_dir.subdir("schemas"), 1000)
+                .await
+                .unwrap();
+        schema_cache
+            .write(
+                cfg_inst.config_schema_id.clone(),
+                json!({
+                    "type": "object",
+                    "properties": {
+                        "speed": {"type": "integer", "minimum": 1},
+                        "mode": {"enum": ["normal", "eco"]}
+                    }
+                }),
+                |_, _| false,
+                true,
+            )
+            .await
+            .unwrap();
+
+        // deploy the config instance
+        let settings = Settings::default();
+        let deployment_dir = temp_dir.subdir("config_instances");
+        let mut observers: Vec<&mut dyn Observer> = Vec::new();
+        let mut observer = HistoryObserver::new();
+        observers.push(&mut observer);
+        let (deploy_results, result) = deploy_with_rollback(
+            vec![],
+            vec![cfg_inst.clone()],
+            &cache,
+            Some(&schema_cache),
+            &deployment_dir,
+            &settings,
+            &mut observers,
+        )
+        .await;
+        result.unwrap();
+
+        // check that the config instance is retrying with the validation error recorded
+        assert_eq!(deploy_results.to_deploy.len(), 1);
+        let actual = &deploy_results.to_deploy[0];
+        assert_eq!(actual.activity_status, ActivityStatus::Removed);
+        assert_eq!(actual.error_status, ErrorStatus::Retrying);
+        assert_eq!(actual.attempts, 1);
+        let last_deploy_error = actual.last_deploy_error.clone().unwrap();
+        assert_eq!(last_deploy_error.code, "schema_validation_failed");
+        let mut json_pointers = last_deploy_error.params.unwrap()["json_pointers"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|p| p.as_str().unwrap().to_string())
+            .collect::<Vec<_>>();
+        json_pointers.sort();
+        assert_eq!(json_pointers, vec!["/mode", "/speed"]);
+
+        // check that the observer's history ends with the failed config instance
+        assert_eq!(observer.history.last(), Some(actual));
+
+        // check that the file was never created
+        let file = deployment_dir.file(filepath.as_str());
+        assert!(!file.exists());
+    }
+
     // remove failures are essentially impossible since removing a file that doesn't exist
     // does not throw an error
 
@@ -248,6 +476,7 @@ pub mod deploy_with_rollback {
             vec![cfg_inst.clone()],
             vec![],
             &cache,
+            None::<&ConfigSchemaContentCache>,
             &deployment_dir,
             &settings,
             &mut observers,
@@ -313,6 +542,7 @@ pub mod deploy_with_rollback {
             vec![cfg_inst.clone()],
             vec![],
             &cache,
+            None::<&ConfigSchemaContentCache>,
             &deployment_dir,
             &settings,
             &mut observers,
@@ -386,6 +616,7 @@ pub mod deploy_with_rollback {
             vec![to_remove.clone()],
             vec![to_deploy.clone()],
             &cache,
+            None::<&ConfigSchemaContentCache>,
             &deployment_dir,
             &settings,
             &mut observers,
@@ -404,6 +635,7 @@ pub mod deploy_with_rollback {
             error_status: ErrorStatus::Retrying,
             attempts: 1,
             cooldown_ends_at: deploy_results.to_deploy[0].cooldown_ends_at,
+            last_deploy_error: deploy_results.to_deploy[0].last_deploy_error.clone(),
             ..to_deploy
         };
         let cooldown = calc_exp_backoff(
@@ -488,6 +720,7 @@ pub mod deploy_with_rollback {
             to_remove_instances.clone(),
             to_deploy_instances.clone(),
             &cache,
+            None::<&ConfigSchemaContentCache>,
             &deployment_dir,
             &settings,
             &mut observers,
@@ -514,6 +747,7 @@ pub mod deploy_with_rollback {
                 );
                 let approx_cooldown_ends_at = Utc::now() + TimeDelta::seconds(cooldown);
                 cfg_inst.cooldown_ends_at = deploy_results.to_deploy[i].cooldown_ends_at;
+                cfg_inst.last_deploy_error = deploy_results.to_deploy[i].last_deploy_error.clone();
                 assert!(cfg_inst.cooldown_ends_at <= approx_cooldown_ends_at);
                 assert!(
                     cfg_inst.cooldown_ends_at >= approx_cooldown_ends_at - TimeDelta::seconds(1)
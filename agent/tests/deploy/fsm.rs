@@ -545,6 +545,7 @@ pub mod transitions {
             config_type_id: cfg_inst.config_type_id.clone(),
             attempts: 0,
             cooldown_ends_at: actual.cooldown_ends_at,
+            last_deploy_error: None,
         };
         assert!(
             expected == actual,
@@ -599,6 +600,7 @@ pub mod transitions {
             config_type_id: cfg_inst.config_type_id.clone(),
             attempts: 0,
             cooldown_ends_at: actual.cooldown_ends_at,
+            last_deploy_error: None,
         };
         assert!(
             expected == actual,
@@ -671,12 +673,18 @@ pub mod transitions {
             config_type_id: cfg_inst.config_type_id.clone(),
             attempts,
             cooldown_ends_at: actual.cooldown_ends_at,
+            last_deploy_error: actual.last_deploy_error.clone(),
         };
         assert!(
             expected == actual,
             "expected:\n{expected:?}\n actual:\n{actual:?}\n",
         );
 
+        // check the error was recorded
+        let last_deploy_error = actual.last_deploy_error.unwrap();
+        assert_eq!(last_deploy_error.code, e.code().as_str());
+        assert_eq!(last_deploy_error.message, e.to_string());
+
         // check the cooldown
         let now = Utc::now();
         let cooldown = calc_exp_backoff(
@@ -761,4 +769,29 @@ pub mod transitions {
             }
         }
     }
+
+    #[test]
+    fn last_deploy_error_kept_until_recovered() {
+        let settings = fsm::Settings::default();
+        let cfg_inst = ConfigInstance {
+            target_status: TargetStatus::Deployed,
+            activity_status: ActivityStatus::Queued,
+            ..Default::default()
+        };
+
+        // the error is recorded
+        let cfg_inst = fsm::error(cfg_inst, &settings, &MockMiruError::new(false), true);
+        assert!(cfg_inst.last_deploy_error.is_some());
+
+        // removing the config instance (e.g. a rollback) doesn't reach the target
+        // status so the error is kept
+        let cfg_inst = fsm::remove(cfg_inst);
+        assert_eq!(cfg_inst.error_status, ErrorStatus::Retrying);
+        assert!(cfg_inst.last_deploy_error.is_some());
+
+        // deploying the config instance recovers it and clears the error
+        let cfg_inst = fsm::deploy(cfg_inst);
+        assert_eq!(cfg_inst.error_status, ErrorStatus::None);
+        assert_eq!(cfg_inst.last_deploy_error, None);
+    }
 }
@@ -2,3 +2,4 @@ pub mod apply;
 pub mod filesys;
 pub mod fsm;
 pub mod observer;
+pub mod validate;
@@ -0,0 +1,95 @@
+// internal crates
+use miru_agent::deploy::{errors::DeployErr, validate::validate};
+
+// external crates
+use serde_json::json;
+
+fn schema() -> serde_json::Value {
+    json!({
+        "type": "object",
+        "properties": {
+            "speed": { "type": "integer", "minimum": 1 },
+            "telemetry": {
+                "type": "object",
+                "properties": {
+                    "upload_interval_sec": { "type": "integer" }
+                }
+            }
+        },
+        "required": ["speed"]
+    })
+}
+
+pub mod validate_func {
+    use super::*;
+
+    #[test]
+    fn valid_content() {
+        let content = json!({
+            "speed": 4,
+            "telemetry": { "upload_interval_sec": 45 }
+        });
+        let violations = validate("cfg_sch_123", &schema(), &content).unwrap();
+        assert!(violations.is_empty(), "violations: {violations:?}");
+    }
+
+    #[test]
+    fn violations() {
+        let content = json!({
+            "speed": 0,
+            "telemetry": { "upload_interval_sec": "45" }
+        });
+        let violations = validate("cfg_sch_123", &schema(), &content).unwrap();
+        assert_eq!(violations.len(), 2, "violations: {violations:?}");
+
+        let mut instance_paths = violations
+            .iter()
+            .map(|v| v.instance_path.as_str())
+            .collect::<Vec<_>>();
+        instance_paths.sort();
+        assert_eq!(
+            instance_paths,
+            vec!["/speed", "/telemetry/upload_interval_sec"]
+        );
+
+        let speed = violations
+            .iter()
+            .find(|v| v.instance_path == "/speed")
+            .unwrap();
+        assert_eq!(speed.schema_path, "/properties/speed/minimum");
+        assert!(!speed.message.is_empty());
+    }
+
+    #[test]
+    fn missing_required_property() {
+        let violations = validate("cfg_sch_123", &schema(), &json!({})).unwrap();
+        assert_eq!(violations.len(), 1, "violations: {violations:?}");
+        assert_eq!(violations[0].instance_path, "");
+        assert_eq!(violations[0].schema_path, "/required");
+    }
+
+    #[test]
+    fn invalid_schema() {
+        let schema = json!({ "type": "not-a-type" });
+        let err = validate("cfg_sch_123", &schema, &json!({})).unwrap_err();
+        match err {
+            DeployErr::InvalidConfigSchemaErr(e) => {
+                assert_eq!(e.config_schema_id, "cfg_sch_123");
+            }
+            _ => panic!("expected an invalid config schema error but got {err:?}"),
+        }
+    }
+
+    #[test]
+    fn cached_validators_follow_schema_changes() {
+        let id = "cfg_sch_cached";
+        let content = json!({ "speed": 0 });
+        assert_eq!(validate(id, &schema(), &content).unwrap().len(), 1);
+        // the cached validator is reused
+        assert_eq!(validate(id, &schema(), &content).unwrap().len(), 1);
+
+        // a different schema under the same id is compiled again
+        let schema = json!({ "type": "object" });
+        assert!(validate(id, &schema, &content).unwrap().is_empty());
+    }
+}
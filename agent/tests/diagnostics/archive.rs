@@ -0,0 +1,134 @@
+// internal crates
+use miru_agent::diagnostics::archive::Archive;
+
+// external crates
+use chrono::{DateTime, Utc};
+
+pub struct Entry {
+    pub path: String,
+    pub mode: u32,
+    pub modified_at: i64,
+    pub data: Vec<u8>,
+}
+
+fn parse_octal(field: &[u8]) -> u64 {
+    let s = std::str::from_utf8(field)
+        .unwrap()
+        .trim_matches(|c: char| c == '\0' || c == ' ');
+    u64::from_str_radix(s, 8).unwrap()
+}
+
+// reads back the entries of an archive, validating the header checksums
+pub fn read_archive(bytes: &[u8]) -> Vec<Entry> {
+    assert_eq!(bytes.len() % 512, 0);
+    let mut entries = Vec::new();
+    let mut long_name = None;
+    let mut offset = 0;
+    loop {
+        let header = &bytes[offset..offset + 512];
+        if header.iter().all(|b| *b == 0) {
+            // the archive ends with two empty blocks
+            assert!(bytes[offset..].iter().all(|b| *b == 0));
+            assert_eq!(bytes.len() - offset, 1024);
+            return entries;
+        }
+
+        let mut unsigned = header.to_vec();
+        unsigned[148..156].copy_from_slice(b"        ");
+        let checksum: u64 = unsigned.iter().map(|b| *b as u64).sum();
+        assert_eq!(parse_octal(&header[148..156]), checksum);
+        assert_eq!(&header[257..263], b"ustar\0");
+
+        let size = parse_octal(&header[124..136]) as usize;
+        let data = bytes[offset + 512..offset + 512 + size].to_vec();
+        offset += 512 + size.div_ceil(512) * 512;
+
+        // the path of the next entry
+        if header[156] == b'L' {
+            assert_eq!(data.last(), Some(&0));
+            long_name = Some(String::from_utf8(data[..size - 1].to_vec()).unwrap());
+            continue;
+        }
+        assert_eq!(header[156], b'0');
+
+        let field = |field: &[u8]| {
+            let end = field.iter().position(|b| *b == 0).unwrap_or(field.len());
+            String::from_utf8(field[..end].to_vec()).unwrap()
+        };
+        let path = match (long_name.take(), field(&header[345..500])) {
+            (Some(long_name), _) => long_name,
+            (None, prefix) if prefix.is_empty() => field(&header[..100]),
+            (None, prefix) => format!("{prefix}/{}", field(&header[..100])),
+        };
+        entries.push(Entry {
+            path,
+            mode: parse_octal(&header[100..108]) as u32,
+            modified_at: parse_octal(&header[136..148]) as i64,
+            data,
+        });
+    }
+}
+
+pub mod tar_archive {
+    use super::*;
+
+    #[test]
+    fn empty() {
+        let bytes = Archive::new().finish();
+        assert_eq!(bytes, vec![0u8; 1024]);
+        assert!(read_archive(&bytes).is_empty());
+    }
+
+    #[test]
+    fn files() {
+        let modified_at = DateTime::<Utc>::from_timestamp(1_700_000_000, 0).unwrap();
+        let mut archive = Archive::new();
+        archive.append("bundle/empty.txt", b"", modified_at);
+        archive.append("bundle/hello.txt", b"hello world", modified_at);
+        let block = vec![7u8; 512];
+        archive.append("bundle/block.bin", &block, modified_at);
+        let bytes = archive.finish();
+
+        // headers, data padded to whole blocks and the two terminating blocks
+        assert_eq!(bytes.len(), 512 * 3 + 512 + 512 + 1024);
+        let entries = read_archive(&bytes);
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries[0].path, "bundle/empty.txt");
+        assert!(entries[0].data.is_empty());
+        assert_eq!(entries[1].path, "bundle/hello.txt");
+        assert_eq!(entries[1].data, b"hello world");
+        assert_eq!(entries[1].mode, 0o644);
+        assert_eq!(entries[1].modified_at, 1_700_000_000);
+        assert_eq!(entries[2].data, block);
+    }
+
+    #[test]
+    fn long_paths_use_the_prefix() {
+        let dir = format!("bundle/{}", "a".repeat(120));
+        let path = format!("{dir}/{}", "b".repeat(90));
+        let mut archive = Archive::new();
+        archive.append(&path, b"data", Utc::now());
+        let bytes = archive.finish();
+
+        // a single header and data block
+        assert_eq!(bytes.len(), 512 * 2 + 1024);
+        assert_eq!(&bytes[345..345 + dir.len()], dir.as_bytes());
+        let entries = read_archive(&bytes);
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].path, path);
+        assert_eq!(entries[0].data, b"data");
+    }
+
+    #[test]
+    fn paths_too_long_for_the_prefix_use_a_long_name() {
+        let path = format!("bundle/{}", "a".repeat(300));
+        let mut archive = Archive::new();
+        archive.append(&path, b"data", Utc::now());
+        archive.append("bundle/next.txt", b"next", Utc::now());
+        let entries = read_archive(&archive.finish());
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].path, path);
+        assert_eq!(entries[0].data, b"data");
+        assert_eq!(entries[1].path, "bundle/next.txt");
+    }
+}
@@ -204,6 +204,31 @@ pub mod read_bytes {
     }
 }
 
+pub mod read_tail {
+    use super::*;
+
+    #[tokio::test]
+    async fn read_doesnt_exist() {
+        let file = File::new(PathBuf::from("doesnt_exist").join("test-file.json"));
+        assert!(matches!(
+            file.read_tail(4).await.unwrap_err(),
+            FileSysErr::PathDoesNotExistErr { .. }
+        ));
+    }
+
+    #[tokio::test]
+    async fn read_success() {
+        let dir = Dir::create_temp_dir("testing").await.unwrap();
+        let file = dir.file("test-file");
+        file.write_string("arglebargle", false, false)
+            .await
+            .unwrap();
+        assert_eq!(file.read_tail(6).await.unwrap(), b"bargle");
+        assert_eq!(file.read_tail(100).await.unwrap(), b"arglebargle");
+        assert!(file.read_tail(0).await.unwrap().is_empty());
+    }
+}
+
 pub mod read_secret_bytes {
     use super::*;
 
@@ -532,6 +557,35 @@ mod write_json {
     }
 }
 
+pub mod write_private_json {
+    use super::*;
+
+    #[tokio::test]
+    async fn owner_only() {
+        let dir = Dir::create_temp_dir("testing").await.unwrap();
+        let file = dir.subdir("nested").file("secret.json");
+        file.write_private_json(&json!({"secret": 1}))
+            .await
+            .unwrap();
+        assert_eq!(file.permissions().await.unwrap().mode() & 0o777, 0o600);
+        assert_eq!(
+            file.read_json::<serde_json::Value>().await.unwrap(),
+            json!({"secret": 1})
+        );
+
+        // overwriting a file readable by others
+        file.set_permissions(0o644).await.unwrap();
+        file.write_private_json(&json!({"secret": 2}))
+            .await
+            .unwrap();
+        assert_eq!(file.permissions().await.unwrap().mode() & 0o777, 0o600);
+        assert_eq!(
+            file.read_json::<serde_json::Value>().await.unwrap(),
+            json!({"secret": 2})
+        );
+    }
+}
+
 pub mod set_permissions {
     use super::*;
 
@@ -0,0 +1,188 @@
+// standard library
+use std::sync::Arc;
+use std::time::Duration;
+
+// internal crates
+use miru_agent::errors::MiruError;
+use miru_agent::http::breaker::{
+    CircuitBreaker, CircuitBreakerOptions, CircuitBreakerStatus, CircuitState,
+};
+use miru_agent::http::client::HTTPClient;
+use miru_agent::http::errors::HTTPErr;
+use miru_agent::server::handlers::circuit_breaker_to_sdk;
+use openapi_server::models::CircuitBreakerState;
+
+const COOLDOWN: Duration = Duration::from_millis(50);
+
+fn breaker(failure_threshold: u32) -> CircuitBreaker {
+    CircuitBreaker::new(CircuitBreakerOptions {
+        failure_threshold,
+        cooldown: COOLDOWN,
+    })
+}
+
+pub mod state {
+    use super::*;
+
+    #[tokio::test]
+    async fn opens_after_threshold() {
+        let breaker = breaker(3);
+        for i in 1..3 {
+            breaker.record_failure();
+            assert_eq!(breaker.state(), CircuitState::Closed);
+            assert_eq!(breaker.status().consecutive_failures, i);
+            assert!(breaker.allow());
+        }
+        breaker.record_failure();
+        assert_eq!(breaker.state(), CircuitState::Open);
+        assert!(breaker.is_open());
+        assert!(!breaker.allow());
+    }
+
+    #[tokio::test]
+    async fn success_resets_failures() {
+        let breaker = breaker(2);
+        breaker.record_failure();
+        breaker.record_success();
+        breaker.record_failure();
+        assert_eq!(breaker.state(), CircuitState::Closed);
+        assert_eq!(breaker.status().consecutive_failures, 1);
+    }
+
+    #[tokio::test]
+    async fn half_open_single_probe() {
+        let breaker = breaker(1);
+        breaker.record_failure();
+        assert!(!breaker.allow());
+
+        tokio::time::sleep(COOLDOWN).await;
+        assert!(breaker.allow());
+        assert_eq!(breaker.state(), CircuitState::HalfOpen);
+        // only the probe is let through
+        assert!(!breaker.allow());
+        assert!(breaker.is_open());
+    }
+
+    #[tokio::test]
+    async fn probe_success_closes() {
+        let breaker = breaker(1);
+        breaker.record_failure();
+        tokio::time::sleep(COOLDOWN).await;
+        assert!(breaker.allow());
+        breaker.record_success();
+        assert_eq!(
+            breaker.status(),
+            CircuitBreakerStatus {
+                state: CircuitState::Closed,
+                consecutive_failures: 0,
+                retry_in: None,
+            }
+        );
+        assert!(breaker.allow());
+    }
+
+    #[tokio::test]
+    async fn probe_failure_reopens() {
+        let breaker = breaker(1);
+        breaker.record_failure();
+        tokio::time::sleep(COOLDOWN).await;
+        assert!(breaker.allow());
+        breaker.record_failure();
+
+        let status = breaker.status();
+        assert_eq!(status.state, CircuitState::Open);
+        assert_eq!(status.consecutive_failures, 2);
+        assert!(status.retry_in.is_some_and(|retry_in| retry_in <= COOLDOWN));
+        assert!(!breaker.allow());
+    }
+
+    #[tokio::test]
+    async fn lost_probe() {
+        let breaker = breaker(1);
+        breaker.record_failure();
+        tokio::time::sleep(COOLDOWN).await;
+        assert!(breaker.allow());
+
+        // the probe never reports back so another is allowed after the cooldown
+        tokio::time::sleep(COOLDOWN).await;
+        assert!(breaker.allow());
+        assert!(!breaker.allow());
+    }
+}
+
+pub mod client {
+    use super::*;
+
+    #[tokio::test]
+    async fn fails_fast_while_open() {
+        // nothing listens on this port
+        let base_url = "http://127.0.0.1:1";
+        let breaker = Arc::new(breaker(1));
+        let client = HTTPClient::new(base_url)
+            .await
+            .with_circuit_breaker(breaker.clone());
+
+        let (request, mut context) = client
+            .build_get_request(base_url, Duration::from_secs(1), None)
+            .unwrap();
+        let error = client.send(request, &mut context).await.unwrap_err();
+        assert!(error.is_network_connection_error());
+        assert!(!matches!(error, HTTPErr::CircuitOpenErr(_)));
+        // the circuit opened so the request was not retried
+        assert_eq!(context.attempts, 1);
+        assert!(breaker.is_open());
+
+        let (request, mut context) = client
+            .build_get_request(base_url, Duration::from_secs(1), None)
+            .unwrap();
+        match client.send(request, &mut context).await.unwrap_err() {
+            HTTPErr::CircuitOpenErr(e) => {
+                assert!(e.is_network_connection_error());
+                assert!(e.retry_in.is_some());
+            }
+            e => panic!("unexpected error: {e:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn shared_between_clients() {
+        let breaker = Arc::new(breaker(1));
+        let a = HTTPClient::new("http://127.0.0.1:1")
+            .await
+            .with_circuit_breaker(breaker.clone());
+        let b = HTTPClient::new("http://127.0.0.1:1")
+            .await
+            .with_circuit_breaker(breaker.clone());
+        assert!(Arc::ptr_eq(a.circuit_breaker(), b.circuit_breaker()));
+    }
+}
+
+pub mod health {
+    use super::*;
+
+    #[test]
+    fn to_sdk() {
+        let status = circuit_breaker_to_sdk(CircuitBreakerStatus {
+            state: CircuitState::Open,
+            consecutive_failures: 4,
+            retry_in: Some(Duration::from_millis(1500)),
+        });
+        assert_eq!(
+            status.state,
+            CircuitBreakerState::CIRCUIT_BREAKER_STATE_OPEN
+        );
+        assert_eq!(status.consecutive_failures, 4);
+        assert_eq!(status.retry_in_ms, Some(1500));
+
+        let status = circuit_breaker_to_sdk(CircuitBreakerStatus {
+            state: CircuitState::HalfOpen,
+            consecutive_failures: 0,
+            retry_in: None,
+        });
+        assert_eq!(
+            status.state,
+            CircuitBreakerState::CIRCUIT_BREAKER_STATE_HALF_OPEN
+        );
+        assert_eq!(status.retry_in_ms, None);
+    }
+}
@@ -0,0 +1,273 @@
+// standard library
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+use std::time::Duration;
+
+// internal crates
+use miru_agent::filesys::dir::Dir;
+use miru_agent::http::client::HTTPClient;
+use miru_agent::http::conditional::{ConditionalCache, ConditionalEntry};
+use miru_agent::http::config_instances::ConfigInstancesExt;
+use miru_agent::http::errors::HTTPErr;
+use openapi_client::models::ConfigInstanceList;
+
+// external crates
+use axum::extract::State;
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use axum::Router;
+
+#[derive(Clone, Default)]
+struct Server {
+    requests: Arc<AtomicUsize>,
+    not_modified: Arc<AtomicUsize>,
+}
+
+const BODY: &str =
+    r#"{"object":"list","total_count":0,"limit":0,"offset":0,"has_more":false,"data":[]}"#;
+
+async fn etag(State(server): State<Server>, headers: HeaderMap) -> Response {
+    server.requests.fetch_add(1, Ordering::SeqCst);
+    if headers.get("if-none-match").is_some_and(|v| v == "\"v1\"") {
+        server.not_modified.fetch_add(1, Ordering::SeqCst);
+        return StatusCode::NOT_MODIFIED.into_response();
+    }
+    ([("etag", "\"v1\"")], BODY).into_response()
+}
+
+async fn last_modified(State(server): State<Server>, headers: HeaderMap) -> Response {
+    server.requests.fetch_add(1, Ordering::SeqCst);
+    let date = "Wed, 21 Oct 2015 07:28:00 GMT";
+    if headers.get("if-modified-since").is_some_and(|v| v == date) {
+        server.not_modified.fetch_add(1, Ordering::SeqCst);
+        return StatusCode::NOT_MODIFIED.into_response();
+    }
+    ([("last-modified", date)], BODY).into_response()
+}
+
+async fn no_validators(State(server): State<Server>) -> Response {
+    server.requests.fetch_add(1, Ordering::SeqCst);
+    BODY.into_response()
+}
+
+async fn always_not_modified(State(server): State<Server>) -> Response {
+    server.requests.fetch_add(1, Ordering::SeqCst);
+    StatusCode::NOT_MODIFIED.into_response()
+}
+
+async fn serve(server: Server) -> String {
+    let app = Router::new()
+        .route("/config_instances", get(etag).post(etag))
+        .route("/etag", get(etag))
+        .route("/last_modified", get(last_modified))
+        .route("/no_validators", get(no_validators))
+        .route("/not_modified", get(always_not_modified))
+        .with_state(server);
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        axum::serve(listener, app).await.unwrap();
+    });
+    format!("http://{addr}")
+}
+
+async fn setup() -> (Server, String, HTTPClient, ConditionalCache) {
+    let server = Server::default();
+    let base_url = serve(server.clone()).await;
+    let dir = Dir::create_temp_dir("conditional").await.unwrap();
+    let cache = ConditionalCache::new(dir);
+    let client = HTTPClient::new(&base_url)
+        .await
+        .with_conditional_cache(cache.clone());
+    (server, base_url, client, cache)
+}
+
+async fn conditional_get(client: &HTTPClient, url: &str) -> Result<String, HTTPErr> {
+    let (request, mut context) = client.build_get_request(url, Duration::from_secs(3), None)?;
+    client.send_conditional(url, request, &mut context).await
+}
+
+pub mod send_conditional {
+    use super::*;
+
+    #[tokio::test]
+    async fn etag_not_modified() {
+        let (server, base_url, client, cache) = setup().await;
+        let url = format!("{base_url}/etag");
+
+        assert_eq!(conditional_get(&client, &url).await.unwrap(), BODY);
+        let entry = cache.read(&url).await.unwrap();
+        assert_eq!(entry.etag.as_deref(), Some("\"v1\""));
+        assert_eq!(entry.body, BODY);
+
+        for _ in 0..3 {
+            assert_eq!(conditional_get(&client, &url).await.unwrap(), BODY);
+        }
+        assert_eq!(server.requests.load(Ordering::SeqCst), 4);
+        assert_eq!(server.not_modified.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn last_modified_not_modified() {
+        let (server, base_url, client, cache) = setup().await;
+        let url = format!("{base_url}/last_modified");
+
+        assert_eq!(conditional_get(&client, &url).await.unwrap(), BODY);
+        let entry = cache.read(&url).await.unwrap();
+        assert_eq!(entry.etag, None);
+        assert!(entry.last_modified.is_some());
+
+        assert_eq!(conditional_get(&client, &url).await.unwrap(), BODY);
+        assert_eq!(server.not_modified.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn persists_across_clients() {
+        let (server, base_url, client, cache) = setup().await;
+        let url = format!("{base_url}/etag");
+        conditional_get(&client, &url).await.unwrap();
+
+        // e.g. after the agent restarts
+        let client = HTTPClient::new(&base_url)
+            .await
+            .with_conditional_cache(ConditionalCache::new(cache.dir().clone()));
+        assert_eq!(conditional_get(&client, &url).await.unwrap(), BODY);
+        assert_eq!(server.not_modified.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn no_validators() {
+        let (server, base_url, client, cache) = setup().await;
+        let url = format!("{base_url}/no_validators");
+
+        // stale validators are removed once the response no longer has any
+        cache
+            .write(&ConditionalEntry {
+                key: url.clone(),
+                etag: Some("\"old\"".to_string()),
+                last_modified: None,
+                body: "old".to_string(),
+            })
+            .await;
+        assert_eq!(conditional_get(&client, &url).await.unwrap(), BODY);
+        assert!(cache.read(&url).await.is_none());
+
+        assert_eq!(conditional_get(&client, &url).await.unwrap(), BODY);
+        assert_eq!(server.requests.load(Ordering::SeqCst), 2);
+        assert_eq!(server.not_modified.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn not_modified_without_entry() {
+        let (_, base_url, client, _) = setup().await;
+        let url = format!("{base_url}/not_modified");
+        let error = conditional_get(&client, &url).await.unwrap_err();
+        assert!(matches!(error, HTTPErr::RequestFailed(_)));
+    }
+
+    #[tokio::test]
+    async fn corrupt_entry() {
+        let (server, base_url, client, cache) = setup().await;
+        let url = format!("{base_url}/etag");
+        conditional_get(&client, &url).await.unwrap();
+
+        let files = cache.dir().files().await.unwrap();
+        assert_eq!(files.len(), 1);
+        files[0]
+            .write_string("not json", true, false)
+            .await
+            .unwrap();
+
+        // a corrupt entry results in an unconditional request which repairs it
+        assert_eq!(conditional_get(&client, &url).await.unwrap(), BODY);
+        assert_eq!(server.not_modified.load(Ordering::SeqCst), 0);
+        assert!(cache.read(&url).await.is_some());
+    }
+
+    #[tokio::test]
+    async fn post_is_unconditional() {
+        let (server, base_url, client, cache) = setup().await;
+        let url = format!("{base_url}/config_instances");
+        for _ in 0..2 {
+            let (request, mut context) = client
+                .build_post_request(&url, "{}".to_string(), Duration::from_secs(3), None)
+                .unwrap();
+            client
+                .send_conditional(&url, request, &mut context)
+                .await
+                .unwrap();
+        }
+        assert!(cache.read(&url).await.is_none());
+        assert_eq!(server.not_modified.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn without_cache() {
+        let server = Server::default();
+        let base_url = serve(server.clone()).await;
+        let client = HTTPClient::new(&base_url).await;
+        let url = format!("{base_url}/etag");
+        for _ in 0..2 {
+            assert_eq!(conditional_get(&client, &url).await.unwrap(), BODY);
+        }
+        assert_eq!(server.not_modified.load(Ordering::SeqCst), 0);
+    }
+}
+
+pub mod list_config_instances {
+    use super::*;
+
+    #[tokio::test]
+    async fn reuses_cached_body() {
+        let (server, _, client, _) = setup().await;
+
+        let first = client.list_config_instances("", "token").await.unwrap();
+        // outlive the in-memory request cache
+        tokio::time::sleep(Duration::from_millis(2100)).await;
+        let second = client.list_config_instances("", "token").await.unwrap();
+
+        assert_eq!(first, ConfigInstanceList::default());
+        assert_eq!(first, second);
+        assert_eq!(server.requests.load(Ordering::SeqCst), 2);
+        assert_eq!(server.not_modified.load(Ordering::SeqCst), 1);
+    }
+}
+
+pub mod conditional_cache {
+    use super::*;
+
+    fn entry(key: &str) -> ConditionalEntry {
+        ConditionalEntry {
+            key: key.to_string(),
+            etag: Some("\"v1\"".to_string()),
+            last_modified: None,
+            body: BODY.to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn evicts_least_recently_written() {
+        let dir = Dir::create_temp_dir("conditional").await.unwrap();
+        let cache = ConditionalCache::new(dir.clone()).with_max_entries(2);
+
+        for key in ["a", "b", "c"] {
+            cache.write(&entry(key)).await;
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        assert!(cache.read("a").await.is_none());
+        assert_eq!(cache.read("b").await, Some(entry("b")));
+        assert_eq!(cache.read("c").await, Some(entry("c")));
+
+        // rewriting an entry makes it the most recent
+        cache.write(&entry("b")).await;
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        cache.write(&entry("d")).await;
+        assert!(cache.read("c").await.is_none());
+        assert_eq!(cache.read("b").await, Some(entry("b")));
+        assert_eq!(cache.read("d").await, Some(entry("d")));
+        assert_eq!(dir.files().await.unwrap().len(), 2);
+    }
+}
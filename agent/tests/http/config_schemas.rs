@@ -1,5 +1,7 @@
 use miru_agent::http::{
-    config_schemas::{build_search_query, ConfigSchemaFilters, ConfigTypeSlugFilter, DigestFilter},
+    config_schemas::{
+        build_search_query, ConfigSchemaFilters, ConfigTypeSlugFilter, DigestFilter, IDFilter,
+    },
     search::SearchOperator,
 };
 
@@ -9,6 +11,7 @@ pub mod build_search_query_func {
     #[tokio::test]
     async fn empty() {
         let filters = ConfigSchemaFilters {
+            ids: None,
             digests: None,
             config_type_slugs: None,
         };
@@ -19,6 +22,7 @@ pub mod build_search_query_func {
     #[tokio::test]
     async fn config_type_slug() {
         let filters = ConfigSchemaFilters {
+            ids: None,
             digests: None,
             config_type_slugs: Some(ConfigTypeSlugFilter {
                 not: false,
@@ -33,6 +37,7 @@ pub mod build_search_query_func {
     #[tokio::test]
     async fn digest() {
         let filters = ConfigSchemaFilters {
+            ids: None,
             digests: Some(DigestFilter {
                 not: false,
                 op: SearchOperator::Equals,
@@ -43,4 +48,19 @@ pub mod build_search_query_func {
         let query = build_search_query(filters);
         assert_eq!(query, Some("search=digest:1|2|3".to_string()));
     }
+
+    #[tokio::test]
+    async fn id() {
+        let filters = ConfigSchemaFilters {
+            ids: Some(IDFilter {
+                not: false,
+                op: SearchOperator::Equals,
+                val: vec!["1".to_string(), "2".to_string()],
+            }),
+            digests: None,
+            config_type_slugs: None,
+        };
+        let query = build_search_query(filters);
+        assert_eq!(query, Some("search=id:1|2".to_string()));
+    }
 }
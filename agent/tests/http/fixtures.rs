@@ -0,0 +1,270 @@
+// standard library
+use std::time::Duration;
+
+// internal crates
+use miru_agent::filesys::dir::Dir;
+use miru_agent::http::client::HTTPClient;
+use miru_agent::http::errors::HTTPErr;
+use miru_agent::http::fixtures::{
+    diff_requests, edit_distance, redact_secrets, FixtureMode, FixtureRequest, Fixtures, REDACTED,
+};
+
+// external crates
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use serde_json::{json, Value};
+
+// serves a couple of backend-like endpoints on a random local port
+async fn serve() -> String {
+    let app = Router::new()
+        .route(
+            "/devices/{id}",
+            get(|| async { Json(json!({"id": "dvc_1", "name": "robot"})) }),
+        )
+        .route(
+            "/devices/{id}/issue_token",
+            post(|| async {
+                (
+                    [("set-cookie", "session=secret-cookie")],
+                    Json(json!({"token": "secret-token", "expires_at": "2030-01-01T00:00:00Z"})),
+                )
+            }),
+        );
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        axum::serve(listener, app).await.unwrap();
+    });
+    format!("http://{addr}")
+}
+
+async fn get_text(client: &HTTPClient, url: &str, token: Option<&str>) -> Result<String, HTTPErr> {
+    let (request, mut context) = client.build_get_request(url, Duration::from_secs(3), token)?;
+    let response = client.send(request, &mut context).await?;
+    client.handle_response(response, &context).await
+}
+
+async fn post_text(client: &HTTPClient, url: &str, body: Value) -> Result<String, HTTPErr> {
+    let (request, mut context) =
+        client.build_post_request(url, body.to_string(), Duration::from_secs(3), None)?;
+    let response = client.send(request, &mut context).await?;
+    client.handle_response(response, &context).await
+}
+
+fn fixture_request(method: &str, path: &str, query: &[(&str, &str)]) -> FixtureRequest {
+    FixtureRequest {
+        method: method.to_string(),
+        path: path.to_string(),
+        query: query
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect(),
+        body: None,
+    }
+}
+
+pub mod record_replay {
+    use super::*;
+
+    #[tokio::test]
+    async fn replays_recorded_response() {
+        let base_url = serve().await;
+        let dir = Dir::create_temp_dir("fixtures").await.unwrap();
+
+        let recorder = HTTPClient::new(&base_url)
+            .await
+            .with_fixtures(Fixtures::new(FixtureMode::Record, dir.clone()));
+        let url = format!("{base_url}/devices/dvc_1?b=2&a=1");
+        let recorded = get_text(&recorder, &url, None).await.unwrap();
+        assert_eq!(
+            serde_json::from_str::<Value>(&recorded).unwrap(),
+            json!({"id": "dvc_1", "name": "robot"})
+        );
+        assert_eq!(dir.files().await.unwrap().len(), 1);
+
+        // the replayer never touches the network (nothing listens on this port) and
+        // matches regardless of the host and query parameter order
+        let replayer = HTTPClient::new("http://127.0.0.1:1")
+            .await
+            .with_fixtures(Fixtures::new(FixtureMode::Replay, dir.clone()));
+        let replayed = get_text(&replayer, "http://127.0.0.1:1/devices/dvc_1?a=1&b=2", None)
+            .await
+            .unwrap();
+        assert_eq!(replayed, recorded);
+    }
+
+    #[tokio::test]
+    async fn strips_auth_tokens() {
+        let base_url = serve().await;
+        let dir = Dir::create_temp_dir("fixtures").await.unwrap();
+
+        let recorder = HTTPClient::new(&base_url)
+            .await
+            .with_fixtures(Fixtures::new(FixtureMode::Record, dir.clone()));
+        get_text(
+            &recorder,
+            &format!("{base_url}/devices/dvc_1?token=query-secret"),
+            Some("bearer-secret"),
+        )
+        .await
+        .unwrap();
+        let response = post_text(
+            &recorder,
+            &format!("{base_url}/devices/dvc_1/issue_token"),
+            json!({"token": "request-secret", "nonce": "abc"}),
+        )
+        .await
+        .unwrap();
+        // the caller still gets the real token while recording
+        assert!(response.contains("secret-token"));
+
+        for file in dir.files().await.unwrap() {
+            let contents = file.read_string().await.unwrap();
+            for secret in [
+                "secret-token",
+                "bearer-secret",
+                "query-secret",
+                "request-secret",
+                "secret-cookie",
+            ] {
+                assert!(!contents.contains(secret), "{secret} in {contents}");
+            }
+        }
+
+        let replayer = HTTPClient::new(&base_url)
+            .await
+            .with_fixtures(Fixtures::new(FixtureMode::Replay, dir.clone()));
+        let replayed = post_text(
+            &replayer,
+            &format!("{base_url}/devices/dvc_1/issue_token"),
+            json!({}),
+        )
+        .await
+        .unwrap();
+        let replayed: Value = serde_json::from_str(&replayed).unwrap();
+        assert_eq!(replayed["token"], REDACTED);
+        assert_eq!(replayed["expires_at"], "2030-01-01T00:00:00Z");
+    }
+
+    #[tokio::test]
+    async fn unmatched_request() {
+        let base_url = serve().await;
+        let dir = Dir::create_temp_dir("fixtures").await.unwrap();
+
+        let recorder = HTTPClient::new(&base_url)
+            .await
+            .with_fixtures(Fixtures::new(FixtureMode::Record, dir.clone()));
+        get_text(&recorder, &format!("{base_url}/devices/dvc_1?a=1"), None)
+            .await
+            .unwrap();
+
+        let replayer = HTTPClient::new(&base_url)
+            .await
+            .with_fixtures(Fixtures::new(FixtureMode::Replay, dir.clone()));
+        let error = get_text(&replayer, &format!("{base_url}/devices/dvc_2?a=1"), None)
+            .await
+            .unwrap_err();
+        match error {
+            HTTPErr::FixtureNotFoundErr(e) => {
+                assert_eq!(e.key, "GET /devices/dvc_2?a=1");
+                assert_eq!(e.closest.as_deref(), Some("GET /devices/dvc_1?a=1"));
+                assert!(e.diff.contains("- path: /devices/dvc_1"));
+                assert!(e.diff.contains("+ path: /devices/dvc_2"));
+                assert_eq!(e.request.attempts, 1);
+            }
+            e => panic!("unexpected error: {e:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn no_fixtures() {
+        let dir = Dir::create_temp_dir("fixtures").await.unwrap();
+        let replayer = HTTPClient::new("http://127.0.0.1:1")
+            .await
+            .with_fixtures(Fixtures::new(FixtureMode::Replay, dir.subdir("missing")));
+        let error = get_text(&replayer, "http://127.0.0.1:1/devices/dvc_1", None)
+            .await
+            .unwrap_err();
+        match error {
+            HTTPErr::FixtureNotFoundErr(e) => assert!(e.closest.is_none()),
+            e => panic!("unexpected error: {e:?}"),
+        }
+    }
+}
+
+pub mod key {
+    use super::*;
+
+    #[test]
+    fn normalized_query() {
+        let client = reqwest::Client::new();
+        let request = client
+            .get("http://localhost/devices?b=2&a=1&a=0&access_token=abc")
+            .build()
+            .unwrap();
+        let request = FixtureRequest::from_request(&request);
+        assert_eq!(
+            request.key(),
+            "GET /devices?a=0&a=1&access_token=<redacted>&b=2"
+        );
+
+        let request = client.get("http://localhost/devices").build().unwrap();
+        assert_eq!(FixtureRequest::from_request(&request).key(), "GET /devices");
+    }
+
+    #[test]
+    fn file_names_are_unique() {
+        let a = Fixtures::file_name("GET /devices/a_b");
+        let b = Fixtures::file_name("GET /devices/a/b");
+        assert_ne!(a, b);
+        assert!(a.starts_with("GET__devices_a_b-"));
+        assert!(a.ends_with(".json"));
+    }
+}
+
+pub mod diff {
+    use super::*;
+
+    #[test]
+    fn diff_lines() {
+        let fixture = fixture_request("GET", "/devices/dvc_1", &[("a", "1"), ("b", "2")]);
+        let request = fixture_request("POST", "/devices/dvc_1", &[("a", "1"), ("c", "3")]);
+        assert_eq!(
+            diff_requests(&fixture, &request),
+            "- method: GET\n\
+             + method: POST\n  \
+             path: /devices/dvc_1\n  \
+             query: a=1\n\
+             - query: b=2\n\
+             + query: c=3"
+        );
+    }
+
+    #[test]
+    fn edit_distances() {
+        assert_eq!(edit_distance("", ""), 0);
+        assert_eq!(edit_distance("abc", ""), 3);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        assert_eq!(edit_distance("GET /a", "GET /a"), 0);
+    }
+
+    #[test]
+    fn redacts_nested_secrets() {
+        let mut value = json!({
+            "token": "a",
+            "nested": [{"refresh_token": "b", "id": "c"}],
+            "Authorization": "d",
+            "expires_at": null,
+        });
+        redact_secrets(&mut value);
+        assert_eq!(
+            value,
+            json!({
+                "token": REDACTED,
+                "nested": [{"refresh_token": REDACTED, "id": "c"}],
+                "Authorization": REDACTED,
+                "expires_at": null,
+            })
+        );
+    }
+}
@@ -1,16 +1,21 @@
 // standard crates
 use std::fmt;
 use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
 // internal crates
+use miru_agent::http::breaker::CircuitBreakerExt;
+use miru_agent::http::client::RequestContext;
 use miru_agent::http::config_instances::{ConfigInstanceFilters, ConfigInstancesExt};
 use miru_agent::http::config_schemas::{ConfigSchemaFilters, ConfigSchemasExt};
 use miru_agent::http::devices::DevicesExt;
-use miru_agent::http::errors::HTTPErr;
+use miru_agent::http::errors::{HTTPErr, RequestFailed};
+use miru_agent::trace;
 use openapi_client::models::{
-    ActivateDeviceRequest, ConfigInstance, ConfigInstanceList, ConfigSchema, ConfigSchemaList,
-    Device, HashSchemaSerializedRequest, IssueDeviceTokenRequest, SchemaDigestResponse,
-    TokenResponse, UpdateConfigInstanceRequest, UpdateDeviceFromAgentRequest,
+    ActivateDeviceRequest, BulkUpdateConfigInstanceResult, BulkUpdateConfigInstancesRequest,
+    BulkUpdateConfigInstancesResponse, ConfigInstance, ConfigInstanceList, ConfigSchema,
+    ConfigSchemaList, Device, HashSchemaSerializedRequest, IssueDeviceTokenRequest,
+    SchemaDigestResponse, TokenResponse, UpdateConfigInstanceRequest, UpdateDeviceFromAgentRequest,
 };
 
 // ================================ MOCK CLIENT ==================================== //
@@ -92,6 +97,61 @@ impl ConfigInstancesExt for MockClient {
             .update_config_instance(config_instance_id, updates, token)
             .await
     }
+
+    async fn bulk_update_config_instances(
+        &self,
+        updates: &BulkUpdateConfigInstancesRequest,
+        token: &str,
+    ) -> Result<BulkUpdateConfigInstancesResponse, HTTPErr> {
+        self.config_instances_client
+            .bulk_update_config_instances(updates, token)
+            .await
+    }
+}
+
+impl ConfigSchemasExt for MockClient {
+    async fn hash_schema(
+        &self,
+        request: &HashSchemaSerializedRequest,
+        token: &str,
+    ) -> Result<SchemaDigestResponse, HTTPErr> {
+        self.config_schemas_client.hash_schema(request, token).await
+    }
+
+    async fn list_config_schemas(
+        &self,
+        query_params: &str,
+        token: &str,
+    ) -> Result<ConfigSchemaList, HTTPErr> {
+        self.config_schemas_client
+            .list_config_schemas(query_params, token)
+            .await
+    }
+
+    async fn list_all_config_schemas<I>(
+        &self,
+        filters: ConfigSchemaFilters,
+        expansions: I,
+        token: &str,
+    ) -> Result<Vec<ConfigSchema>, HTTPErr>
+    where
+        I: IntoIterator + Send,
+        I::Item: fmt::Display,
+    {
+        self.config_schemas_client
+            .list_all_config_schemas(filters, expansions, token)
+            .await
+    }
+
+    async fn find_one_config_schema(
+        &self,
+        filters: ConfigSchemaFilters,
+        token: &str,
+    ) -> Result<ConfigSchema, HTTPErr> {
+        self.config_schemas_client
+            .find_one_config_schema(filters, token)
+            .await
+    }
 }
 
 impl MockClient {
@@ -183,6 +243,11 @@ impl MockDevicesClient {
 type ListConfigInstancesFn = Box<dyn Fn() -> Result<ConfigInstanceList, HTTPErr> + Send + Sync>;
 type ListAllConfigInstancesFn = Box<dyn Fn() -> Result<Vec<ConfigInstance>, HTTPErr> + Send + Sync>;
 type UpdateConfigInstanceFn = Box<dyn Fn() -> Result<ConfigInstance, HTTPErr> + Send + Sync>;
+type BulkUpdateConfigInstancesFn = Box<
+    dyn Fn(&BulkUpdateConfigInstancesRequest) -> Result<BulkUpdateConfigInstancesResponse, HTTPErr>
+        + Send
+        + Sync,
+>;
 type UpdateDeviceFn = Box<dyn Fn() -> Result<Device, HTTPErr> + Send + Sync>;
 
 #[derive(Clone, Debug, PartialEq)]
@@ -190,12 +255,14 @@ pub enum CfgInstsCall {
     ListConfigInstances,
     ListAllConfigInstances,
     UpdateConfigInstance(UpdateConfigInstanceRequest),
+    BulkUpdateConfigInstances(BulkUpdateConfigInstancesRequest),
 }
 
 pub struct MockCfgInstsClient {
     pub list_config_instances_fn: Arc<Mutex<ListConfigInstancesFn>>,
     pub list_all_config_instances_fn: Arc<Mutex<ListAllConfigInstancesFn>>,
     pub update_config_instance_fn: Arc<Mutex<UpdateConfigInstanceFn>>,
+    pub bulk_update_config_instances_fn: Arc<Mutex<BulkUpdateConfigInstancesFn>>,
     pub update_device_fn: Arc<Mutex<UpdateDeviceFn>>,
 
     pub calls: Arc<Mutex<Vec<CfgInstsCall>>>,
@@ -211,6 +278,10 @@ impl Default for MockCfgInstsClient {
             update_config_instance_fn: Arc::new(Mutex::new(Box::new(|| {
                 Ok(ConfigInstance::default())
             }))),
+            // like older backends, bulk updates are unsupported by default
+            bulk_update_config_instances_fn: Arc::new(Mutex::new(Box::new(|_| {
+                Err(bulk_update_unsupported())
+            }))),
             update_device_fn: Arc::new(Mutex::new(Box::new(|| Ok(Device::default())))),
 
             calls: Arc::new(Mutex::new(Vec::new())),
@@ -240,6 +311,46 @@ impl MockCfgInstsClient {
         *self.update_config_instance_fn.lock().unwrap() = Box::new(update_config_instance_fn);
     }
 
+    pub fn set_bulk_update_config_instances<F>(&self, bulk_update_config_instances_fn: F)
+    where
+        F: Fn(
+                &BulkUpdateConfigInstancesRequest,
+            ) -> Result<BulkUpdateConfigInstancesResponse, HTTPErr>
+            + Send
+            + Sync
+            + 'static,
+    {
+        *self.bulk_update_config_instances_fn.lock().unwrap() =
+            Box::new(bulk_update_config_instances_fn);
+    }
+
+    // sets the bulk update to succeed for every requested config instance
+    pub fn set_bulk_update_config_instances_success(&self) {
+        self.set_bulk_update_config_instances(|request| {
+            Ok(BulkUpdateConfigInstancesResponse {
+                results: request
+                    .updates
+                    .iter()
+                    .map(|update| BulkUpdateConfigInstanceResult {
+                        id: update.id.clone(),
+                        success: true,
+                        config_instance: None,
+                        error: None,
+                    })
+                    .collect(),
+            })
+        });
+    }
+
+    pub fn num_bulk_update_config_instances_calls(&self) -> usize {
+        self.calls
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|call| matches!(call, CfgInstsCall::BulkUpdateConfigInstances(_)))
+            .count()
+    }
+
     pub fn num_update_config_instance_calls(&self) -> usize {
         self.calls
             .lock()
@@ -292,13 +403,44 @@ impl ConfigInstancesExt for MockCfgInstsClient {
             .push(CfgInstsCall::UpdateConfigInstance(request.clone()));
         (*self.update_config_instance_fn.lock().unwrap())()
     }
+
+    async fn bulk_update_config_instances(
+        &self,
+        request: &BulkUpdateConfigInstancesRequest,
+        _: &str,
+    ) -> Result<BulkUpdateConfigInstancesResponse, HTTPErr> {
+        self.calls
+            .lock()
+            .unwrap()
+            .push(CfgInstsCall::BulkUpdateConfigInstances(request.clone()));
+        (*self.bulk_update_config_instances_fn.lock().unwrap())(request)
+    }
+}
+
+pub fn bulk_update_unsupported() -> HTTPErr {
+    HTTPErr::RequestFailed(Box::new(RequestFailed {
+        request: RequestContext {
+            url: "http://localhost/config_instances".to_string(),
+            method: reqwest::Method::PATCH,
+            timeout: Duration::from_secs(10),
+            attempts: 1,
+            endpoint: None,
+        },
+        status: reqwest::StatusCode::METHOD_NOT_ALLOWED,
+        error: None,
+        retry_after: None,
+        trace: trace!(),
+    }))
 }
 
 // ============================= CONFIG SCHEMAS ==================================== //
 pub struct MockCfgSchsClient {
     pub hash_schema_fn: Box<dyn Fn() -> Result<SchemaDigestResponse, HTTPErr> + Send + Sync>,
     pub list_config_schemas_fn: Box<dyn Fn() -> Result<ConfigSchemaList, HTTPErr> + Send + Sync>,
+    pub list_all_config_schemas_fn:
+        Box<dyn Fn() -> Result<Vec<ConfigSchema>, HTTPErr> + Send + Sync>,
     pub find_one_config_schema_fn: Box<dyn Fn() -> Result<ConfigSchema, HTTPErr> + Send + Sync>,
+    pub circuit_open: bool,
 }
 
 impl Default for MockCfgSchsClient {
@@ -306,7 +448,9 @@ impl Default for MockCfgSchsClient {
         Self {
             hash_schema_fn: Box::new(|| Ok(SchemaDigestResponse::default())),
             list_config_schemas_fn: Box::new(|| Ok(ConfigSchemaList::default())),
+            list_all_config_schemas_fn: Box::new(|| Ok(vec![])),
             find_one_config_schema_fn: Box::new(|| Ok(ConfigSchema::default())),
+            circuit_open: false,
         }
     }
 }
@@ -326,6 +470,13 @@ impl MockCfgSchsClient {
         self.list_config_schemas_fn = Box::new(list_config_schemas_fn);
     }
 
+    pub fn set_list_all_config_schemas<F>(&mut self, list_all_config_schemas_fn: F)
+    where
+        F: Fn() -> Result<Vec<ConfigSchema>, HTTPErr> + Send + Sync + 'static,
+    {
+        self.list_all_config_schemas_fn = Box::new(list_all_config_schemas_fn);
+    }
+
     pub fn set_find_one_config_schema<F>(&mut self, find_one_config_schema_fn: F)
     where
         F: Fn() -> Result<ConfigSchema, HTTPErr> + Send + Sync + 'static,
@@ -334,6 +485,12 @@ impl MockCfgSchsClient {
     }
 }
 
+impl CircuitBreakerExt for MockCfgSchsClient {
+    fn is_circuit_open(&self) -> bool {
+        self.circuit_open
+    }
+}
+
 impl ConfigSchemasExt for MockCfgSchsClient {
     async fn hash_schema(
         &self,
@@ -347,6 +504,19 @@ impl ConfigSchemasExt for MockCfgSchsClient {
         (self.list_config_schemas_fn)()
     }
 
+    async fn list_all_config_schemas<I>(
+        &self,
+        _: ConfigSchemaFilters,
+        _: I,
+        _: &str,
+    ) -> Result<Vec<ConfigSchema>, HTTPErr>
+    where
+        I: IntoIterator + Send,
+        I::Item: fmt::Display,
+    {
+        (self.list_all_config_schemas_fn)()
+    }
+
     async fn find_one_config_schema(
         &self,
         _: ConfigSchemaFilters,
@@ -355,3 +525,19 @@ impl ConfigSchemasExt for MockCfgSchsClient {
         (self.find_one_config_schema_fn)()
     }
 }
+
+pub fn rate_limited(retry_after: Duration) -> HTTPErr {
+    HTTPErr::RequestFailed(Box::new(RequestFailed {
+        request: RequestContext {
+            url: "http://localhost/config_instances".to_string(),
+            method: reqwest::Method::GET,
+            timeout: Duration::from_secs(10),
+            attempts: 1,
+            endpoint: None,
+        },
+        status: reqwest::StatusCode::TOO_MANY_REQUESTS,
+        error: None,
+        retry_after: Some(retry_after),
+        trace: trace!(),
+    }))
+}
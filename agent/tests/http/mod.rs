@@ -1,7 +1,11 @@
+pub mod breaker;
 pub mod client;
+pub mod conditional;
 pub mod config_instances;
 pub mod config_schemas;
 pub mod expand;
+pub mod fixtures;
 pub mod mock;
 pub mod query;
+pub mod retry;
 pub mod search;
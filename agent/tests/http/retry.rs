@@ -0,0 +1,129 @@
+// standard library
+use std::time::Duration;
+
+// internal crates
+use miru_agent::http::retry::{
+    is_idempotent, is_retryable, needs_idempotency_key, RetryPolicy, IDEMPOTENCY_KEY_HEADER,
+};
+
+// external crates
+use reqwest::Method;
+
+fn policy() -> RetryPolicy {
+    RetryPolicy {
+        max_attempts: 5,
+        base_delay: Duration::from_millis(100),
+        max_delay: Duration::from_millis(500),
+        time_budget: Duration::from_secs(2),
+    }
+}
+
+pub mod backoff {
+    use super::*;
+
+    #[test]
+    fn exponential() {
+        let policy = policy();
+        let expected = [100, 200, 400, 500, 500];
+        for (i, expected) in expected.iter().enumerate() {
+            let attempt = i as u32 + 1;
+            // without jitter the delay is half the exponential backoff
+            assert_eq!(
+                policy.backoff_with_jitter(attempt, 0.0),
+                Duration::from_millis(expected / 2)
+            );
+            assert_eq!(
+                policy.backoff_with_jitter(attempt, 1.0),
+                Duration::from_millis(*expected)
+            );
+        }
+    }
+
+    #[test]
+    fn jitter_bounds() {
+        let policy = policy();
+        for attempt in 1..10 {
+            let upper = policy.backoff_with_jitter(attempt, 1.0);
+            let lower = policy.backoff_with_jitter(attempt, 0.0);
+            for _ in 0..20 {
+                let delay = policy.backoff(attempt);
+                assert!(delay >= lower && delay <= upper, "{delay:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn large_attempts_dont_overflow() {
+        let policy = RetryPolicy {
+            max_delay: Duration::MAX,
+            ..policy()
+        };
+        policy.backoff(u32::MAX);
+        assert!(policy.backoff_with_jitter(64, 1.0) > Duration::from_secs(60 * 60));
+    }
+}
+
+pub mod allows_retry {
+    use super::*;
+
+    #[test]
+    fn max_attempts() {
+        let policy = policy();
+        for attempt in 1..5 {
+            assert!(policy.allows_retry(attempt, Duration::ZERO, Duration::ZERO));
+        }
+        assert!(!policy.allows_retry(5, Duration::ZERO, Duration::ZERO));
+        assert!(!RetryPolicy::disabled().allows_retry(1, Duration::ZERO, Duration::ZERO));
+    }
+
+    #[test]
+    fn time_budget() {
+        let policy = policy();
+        let delay = Duration::from_millis(500);
+        assert!(policy.allows_retry(1, Duration::from_millis(1400), delay));
+        assert!(!policy.allows_retry(1, Duration::from_millis(1500), delay));
+        assert!(!policy.allows_retry(1, Duration::MAX, delay));
+    }
+}
+
+pub mod methods {
+    use super::*;
+
+    #[test]
+    fn idempotent() {
+        for method in [
+            Method::GET,
+            Method::HEAD,
+            Method::PUT,
+            Method::DELETE,
+            Method::OPTIONS,
+            Method::TRACE,
+        ] {
+            assert!(is_idempotent(&method), "{method}");
+            assert!(!needs_idempotency_key(&method), "{method}");
+        }
+        for method in [Method::POST, Method::PATCH] {
+            assert!(!is_idempotent(&method), "{method}");
+            assert!(needs_idempotency_key(&method), "{method}");
+        }
+    }
+
+    #[test]
+    fn retryable() {
+        let client = reqwest::Client::new();
+        let url = "http://localhost/devices";
+
+        let get = client.get(url).build().unwrap();
+        assert!(is_retryable(&get));
+
+        let post = client.post(url).build().unwrap();
+        assert!(!is_retryable(&post));
+
+        let post = client
+            .post(url)
+            .header(IDEMPOTENCY_KEY_HEADER, "key")
+            .build()
+            .unwrap();
+        assert!(is_retryable(&post));
+    }
+}
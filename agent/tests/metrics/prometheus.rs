@@ -0,0 +1,143 @@
+// standard crates
+use std::time::Duration;
+
+// internal crates
+use miru_agent::metrics::{
+    prometheus::{encode_registry, Encoder},
+    registry::{DeployOperation, Histogram, Metrics},
+};
+
+pub mod encoder {
+    use super::*;
+
+    #[test]
+    fn counter() {
+        let mut encoder = Encoder::new();
+        encoder.counter("syncs_total", "Total syncs", 3);
+        let expected = "# HELP miru_agent_syncs_total Total syncs\n\
+            # TYPE miru_agent_syncs_total counter\n\
+            miru_agent_syncs_total 3\n";
+        assert_eq!(encoder.finish(), expected);
+    }
+
+    #[test]
+    fn gauge() {
+        let mut encoder = Encoder::new();
+        encoder.gauge("cooldown_seconds", "Cooldown\nremaining", 1.5);
+        let expected = "# HELP miru_agent_cooldown_seconds Cooldown\\nremaining\n\
+            # TYPE miru_agent_cooldown_seconds gauge\n\
+            miru_agent_cooldown_seconds 1.5\n";
+        assert_eq!(encoder.finish(), expected);
+    }
+
+    #[test]
+    fn labeled_gauges() {
+        let mut encoder = Encoder::new();
+        encoder.labeled_gauges(
+            "cache_entries",
+            "Cache entries",
+            &[(&[("cache", "a")], 1.0), (&[("cache", "b\"c")], 2.0)],
+        );
+        let expected = "# HELP miru_agent_cache_entries Cache entries\n\
+            # TYPE miru_agent_cache_entries gauge\n\
+            miru_agent_cache_entries{cache=\"a\"} 1\n\
+            miru_agent_cache_entries{cache=\"b\\\"c\"} 2\n";
+        assert_eq!(encoder.finish(), expected);
+    }
+
+    #[test]
+    fn histogram() {
+        let histogram = Histogram::default();
+        histogram.observe(Duration::from_millis(250));
+        histogram.observe(Duration::from_secs(100));
+
+        let mut encoder = Encoder::new();
+        encoder.histogram(
+            "sync_duration_seconds",
+            "Sync duration",
+            &histogram.snapshot(),
+        );
+        let output = encoder.finish();
+
+        assert!(output.contains("# TYPE miru_agent_sync_duration_seconds histogram\n"));
+        assert!(output.contains("miru_agent_sync_duration_seconds_bucket{le=\"0.1\"} 0\n"));
+        assert!(output.contains("miru_agent_sync_duration_seconds_bucket{le=\"0.25\"} 1\n"));
+        assert!(output.contains("miru_agent_sync_duration_seconds_bucket{le=\"60\"} 1\n"));
+        assert!(output.contains("miru_agent_sync_duration_seconds_bucket{le=\"+Inf\"} 2\n"));
+        assert!(output.contains("miru_agent_sync_duration_seconds_sum 100.25\n"));
+        assert!(output.contains("miru_agent_sync_duration_seconds_count 2\n"));
+    }
+
+    #[test]
+    fn labeled_histograms() {
+        let histogram = Histogram::default();
+        histogram.observe(Duration::from_millis(20));
+
+        let mut encoder = Encoder::new();
+        encoder.labeled_histograms(
+            "http_request_duration_seconds",
+            "HTTP latency",
+            &[(
+                vec![("method", "GET"), ("endpoint", "/devices/{id}")],
+                histogram.snapshot(),
+            )],
+        );
+        let output = encoder.finish();
+
+        assert!(output.contains(
+            "miru_agent_http_request_duration_seconds_bucket{method=\"GET\",endpoint=\"/devices/{id}\",le=\"0.025\"} 1\n"
+        ));
+        assert!(output.contains(
+            "miru_agent_http_request_duration_seconds_count{method=\"GET\",endpoint=\"/devices/{id}\"} 1\n"
+        ));
+    }
+}
+
+pub mod encode_registry_func {
+    use super::*;
+
+    #[test]
+    fn empty_registry() {
+        let metrics = Metrics::default();
+        let mut encoder = Encoder::new();
+        encode_registry(&mut encoder, &metrics);
+        let output = encoder.finish();
+
+        assert!(output.contains("miru_agent_sync_attempts_total 0\n"));
+        assert!(output.contains("miru_agent_deploys_total 0\n"));
+        assert!(output.contains("miru_agent_mqtt_connected 0\n"));
+        assert!(output.contains("# TYPE miru_agent_http_request_duration_seconds histogram\n"));
+        assert!(!output.contains("miru_agent_http_request_duration_seconds_bucket"));
+    }
+
+    #[test]
+    fn populated_registry() {
+        let metrics = Metrics::default();
+        metrics.sync.record(true, Duration::from_millis(30));
+        metrics.sync.record(false, Duration::from_millis(30));
+        metrics.deploy.record(DeployOperation::Deploy, true);
+        metrics.deploy.record(DeployOperation::Archive, false);
+        metrics.mqtt.on_connect();
+        metrics.mqtt.on_disconnect();
+        metrics.mqtt.on_connect();
+        metrics
+            .http
+            .observe("GET", "/config_instances", Duration::from_millis(5));
+
+        let mut encoder = Encoder::new();
+        encode_registry(&mut encoder, &metrics);
+        let output = encoder.finish();
+
+        assert!(output.contains("miru_agent_sync_attempts_total 2\n"));
+        assert!(output.contains("miru_agent_sync_successes_total 1\n"));
+        assert!(output.contains("miru_agent_sync_failures_total 1\n"));
+        assert!(output.contains("miru_agent_sync_duration_seconds_count 2\n"));
+        assert!(output.contains("miru_agent_deploys_total 1\n"));
+        assert!(output.contains("miru_agent_archive_failures_total 1\n"));
+        assert!(output.contains("miru_agent_mqtt_connected 1\n"));
+        assert!(output.contains("miru_agent_mqtt_reconnects_total 1\n"));
+        assert!(output.contains(
+            "miru_agent_http_request_duration_seconds_count{method=\"GET\",endpoint=\"/config_instances\"} 1\n"
+        ));
+    }
+}
@@ -0,0 +1,204 @@
+// standard crates
+use std::time::Duration;
+
+// internal crates
+use miru_agent::metrics::registry::{
+    endpoint_label, Counter, DeployMetrics, DeployOperation, Gauge, HTTPMetrics, Histogram,
+    MQTTMetrics, SyncMetrics, DURATION_BUCKETS,
+};
+
+pub mod counter {
+    use super::*;
+
+    #[test]
+    fn inc() {
+        let counter = Counter::default();
+        assert_eq!(counter.get(), 0);
+        counter.inc();
+        counter.inc_by(4);
+        assert_eq!(counter.get(), 5);
+    }
+}
+
+pub mod gauge {
+    use super::*;
+
+    #[test]
+    fn set() {
+        let gauge = Gauge::default();
+        assert_eq!(gauge.get(), 0);
+        gauge.set(-3);
+        assert_eq!(gauge.get(), -3);
+        gauge.set(7);
+        assert_eq!(gauge.get(), 7);
+    }
+}
+
+pub mod histogram {
+    use super::*;
+
+    #[test]
+    fn empty() {
+        let histogram = Histogram::default();
+        let snapshot = histogram.snapshot();
+        assert_eq!(snapshot.count, 0);
+        assert_eq!(snapshot.sum_secs, 0.0);
+        assert_eq!(snapshot.buckets.len(), DURATION_BUCKETS.len() + 1);
+        assert!(snapshot.buckets.iter().all(|(_, count)| *count == 0));
+        assert_eq!(snapshot.buckets.last().unwrap().0, f64::INFINITY);
+    }
+
+    #[test]
+    fn cumulative_buckets() {
+        let histogram = Histogram::default();
+        histogram.observe(Duration::from_millis(1));
+        histogram.observe(Duration::from_millis(200));
+        histogram.observe(Duration::from_secs(120));
+        let snapshot = histogram.snapshot();
+
+        assert_eq!(snapshot.count, 3);
+        assert!((snapshot.sum_secs - 120.201).abs() < 1e-9);
+
+        let count_at = |bound: f64| {
+            snapshot
+                .buckets
+                .iter()
+                .find(|(b, _)| *b == bound)
+                .unwrap()
+                .1
+        };
+        assert_eq!(count_at(0.005), 1);
+        assert_eq!(count_at(0.1), 1);
+        assert_eq!(count_at(0.25), 2);
+        assert_eq!(count_at(60.0), 2);
+        assert_eq!(count_at(f64::INFINITY), 3);
+    }
+}
+
+pub mod sync_metrics {
+    use super::*;
+
+    #[test]
+    fn record() {
+        let metrics = SyncMetrics::default();
+        metrics.record(true, Duration::from_millis(10));
+        metrics.record(false, Duration::from_millis(10));
+        metrics.record(true, Duration::from_millis(10));
+        assert_eq!(metrics.attempts.get(), 3);
+        assert_eq!(metrics.successes.get(), 2);
+        assert_eq!(metrics.failures.get(), 1);
+        assert_eq!(metrics.duration.snapshot().count, 3);
+    }
+}
+
+pub mod deploy_metrics {
+    use super::*;
+
+    #[test]
+    fn record() {
+        let metrics = DeployMetrics::default();
+        metrics.record(DeployOperation::Deploy, true);
+        metrics.record(DeployOperation::Deploy, true);
+        metrics.record(DeployOperation::Deploy, false);
+        metrics.record(DeployOperation::Remove, true);
+        metrics.record(DeployOperation::Remove, false);
+        metrics.record(DeployOperation::Archive, true);
+        metrics.record(DeployOperation::Archive, false);
+        metrics.record(DeployOperation::Archive, false);
+        assert_eq!(metrics.deploys.get(), 2);
+        assert_eq!(metrics.deploy_failures.get(), 1);
+        assert_eq!(metrics.removes.get(), 1);
+        assert_eq!(metrics.remove_failures.get(), 1);
+        assert_eq!(metrics.archives.get(), 1);
+        assert_eq!(metrics.archive_failures.get(), 2);
+    }
+}
+
+pub mod mqtt_metrics {
+    use super::*;
+
+    #[test]
+    fn first_connection_is_not_a_reconnect() {
+        let metrics = MQTTMetrics::default();
+        metrics.on_connect();
+        assert_eq!(metrics.connected.get(), 1);
+        assert_eq!(metrics.reconnects.get(), 0);
+    }
+
+    #[test]
+    fn reconnects() {
+        let metrics = MQTTMetrics::default();
+        metrics.on_connect();
+        metrics.on_disconnect();
+        assert_eq!(metrics.connected.get(), 0);
+        metrics.on_connect();
+        metrics.on_disconnect();
+        metrics.on_connect();
+        assert_eq!(metrics.connected.get(), 1);
+        assert_eq!(metrics.reconnects.get(), 2);
+    }
+}
+
+pub mod http_metrics {
+    use super::*;
+
+    #[test]
+    fn observe_by_endpoint() {
+        let metrics = HTTPMetrics::default();
+        metrics.observe("GET", "/config_instances", Duration::from_millis(5));
+        metrics.observe("GET", "/config_instances", Duration::from_millis(5));
+        metrics.observe("PATCH", "/config_instances/{id}", Duration::from_millis(5));
+
+        let snapshot = metrics.snapshot();
+        assert_eq!(snapshot.len(), 2);
+        assert_eq!(snapshot[0].0.method, "GET");
+        assert_eq!(snapshot[0].0.endpoint, "/config_instances");
+        assert_eq!(snapshot[0].1.count, 2);
+        assert_eq!(snapshot[1].0.method, "PATCH");
+        assert_eq!(snapshot[1].0.endpoint, "/config_instances/{id}");
+        assert_eq!(snapshot[1].1.count, 1);
+    }
+}
+
+pub mod endpoint_label_func {
+    use super::*;
+
+    #[test]
+    fn strips_base_url_and_query() {
+        let base_url = "https://api.mirurobotics.com/agent/v1";
+        assert_eq!(
+            endpoint_label(
+                base_url,
+                "https://api.mirurobotics.com/agent/v1/config_instances?device_id=dvc_1"
+            ),
+            "/config_instances"
+        );
+    }
+
+    #[test]
+    fn replaces_ids() {
+        let base_url = "https://api.mirurobotics.com/agent/v1";
+        assert_eq!(
+            endpoint_label(
+                base_url,
+                "https://api.mirurobotics.com/agent/v1/devices/dvc_123/issue_token"
+            ),
+            "/devices/{id}/issue_token"
+        );
+        assert_eq!(
+            endpoint_label(
+                base_url,
+                "https://api.mirurobotics.com/agent/v1/config_schemas/hash/serialized"
+            ),
+            "/config_schemas/hash/serialized"
+        );
+    }
+
+    #[test]
+    fn url_without_base() {
+        assert_eq!(
+            endpoint_label("http://localhost:8080", "http://example.com/devices/1"),
+            "/devices/{id}"
+        );
+    }
+}
@@ -5,12 +5,15 @@ pub mod cache;
 pub mod crud;
 pub mod crypt;
 pub mod deploy;
+pub mod diagnostics;
 pub mod filesys;
 pub mod http;
 pub mod logs;
+pub mod metrics;
 pub mod mock;
 pub mod models;
 pub mod mqtt;
+pub mod network;
 pub mod server;
 pub mod services;
 pub mod storage;
@@ -3,7 +3,7 @@ use std::collections::HashSet;
 
 // internal crates
 use miru_agent::models::config_instance::{
-    ActivityStatus, ConfigInstance, ErrorStatus, Status, TargetStatus,
+    ActivityStatus, ConfigInstance, DeployError, ErrorStatus, Status, TargetStatus,
 };
 use openapi_client::models::{
     ConfigInstance as BackendConfigInstance, ConfigInstanceActivityStatus,
@@ -490,6 +490,12 @@ fn serialize_deserialize_config_instance() {
         config_type_id: "123".to_string(),
         attempts: 0,
         cooldown_ends_at: Utc::now(),
+        last_deploy_error: Some(DeployError {
+            code: "schema_validation_failed".to_string(),
+            message: "config instance content is invalid".to_string(),
+            params: Some(json!({"json_pointers": ["/speed"]})),
+            occurred_at: Utc::now(),
+        }),
     };
     let serialized = serde_json::to_string(&expected).unwrap();
     let deserialized = serde_json::from_str::<ConfigInstance>(&serialized).unwrap();
@@ -513,6 +519,7 @@ fn deserialize_config_instance() {
         config_type_id: "123".to_string(),
         attempts: 0,
         cooldown_ends_at: Utc::now(),
+        last_deploy_error: None,
     };
     let valid_input = json!({
         "id": expected.id,
@@ -616,6 +623,7 @@ fn config_instance_from_backend() {
             config_type_id: "config_type_id".to_string(),
             attempts: 0,
             cooldown_ends_at: DateTime::<Utc>::UNIX_EPOCH,
+            last_deploy_error: None,
         },
     }];
 
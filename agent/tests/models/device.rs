@@ -1,8 +1,10 @@
 // standard library
-use std::collections::HashSet;
+use std::collections::{HashSet, VecDeque};
 
 // internal crates
-use miru_agent::models::device::{Device, DeviceStatus, Updates};
+use miru_agent::models::device::{
+    ConnectionInterval, Device, DeviceStatus, Updates, CONNECTION_HISTORY_CAPACITY,
+};
 use miru_agent::utils::Mergeable;
 
 // external crates
@@ -93,6 +95,16 @@ fn serialize_deserialize_device() {
         last_synced_at: Utc::now(),
         last_connected_at: Utc::now(),
         last_disconnected_at: Utc::now(),
+        connection_history: VecDeque::from([
+            ConnectionInterval {
+                connected_at: Utc::now() - Duration::minutes(5),
+                disconnected_at: Some(Utc::now() - Duration::minutes(1)),
+            },
+            ConnectionInterval {
+                connected_at: Utc::now(),
+                disconnected_at: None,
+            },
+        ]),
     };
     let serialized = serde_json::to_string(&expected).unwrap();
     let deserialized = serde_json::from_str::<Device>(&serialized).unwrap();
@@ -111,6 +123,7 @@ fn deserialize_device() {
         last_synced_at: Utc::now(),
         last_connected_at: Utc::now(),
         last_disconnected_at: Utc::now(),
+        connection_history: VecDeque::new(),
     };
     let valid_input = json!({
         "device_id": expected.id,
@@ -158,6 +171,7 @@ fn device_merge_empty() {
         last_synced_at: Utc::now(),
         last_connected_at: Utc::now(),
         last_disconnected_at: Utc::now(),
+        connection_history: VecDeque::new(),
     };
     let updates = Updates::empty();
     let expected = initial.clone();
@@ -178,6 +192,7 @@ fn device_merge_all() {
         last_synced_at: Utc::now(),
         last_connected_at: Utc::now(),
         last_disconnected_at: Utc::now(),
+        connection_history: VecDeque::new(),
     };
     let updates = Updates {
         id: Some("456".to_string()),
@@ -199,8 +214,108 @@ fn device_merge_all() {
         last_synced_at: updates.last_synced_at.unwrap(),
         last_connected_at: updates.last_connected_at.unwrap(),
         last_disconnected_at: updates.last_disconnected_at.unwrap(),
+        connection_history: VecDeque::from([ConnectionInterval {
+            connected_at: updates.last_connected_at.unwrap(),
+            disconnected_at: updates.last_disconnected_at,
+        }]),
     };
     let mut actual = initial.clone();
     actual.merge(updates);
     assert_eq!(expected, actual);
 }
+
+#[test]
+fn device_merge_connection_history() {
+    let mut device = Device::default();
+
+    // connecting opens an interval which disconnecting closes
+    device.merge(Updates::connected());
+    let connected_at = device.last_connected_at;
+    assert_eq!(
+        device.connection_history,
+        VecDeque::from([ConnectionInterval {
+            connected_at,
+            disconnected_at: None,
+        }])
+    );
+    device.merge(Updates::disconnected());
+    let disconnected_at = device.last_disconnected_at;
+    assert_eq!(
+        device.connection_history,
+        VecDeque::from([ConnectionInterval {
+            connected_at,
+            disconnected_at: Some(disconnected_at),
+        }])
+    );
+
+    // disconnecting again doesn't move the recorded disconnect
+    device.merge(Updates::disconnected());
+    assert_eq!(
+        device.connection_history[0].disconnected_at,
+        Some(disconnected_at)
+    );
+
+    // unrelated updates don't touch the history
+    device.merge(Updates::connected());
+    device.merge(Updates::set_agent_version("v1.0.1".to_string()));
+    assert_eq!(device.connection_history.len(), 2);
+}
+
+#[test]
+fn device_merge_consecutive_connects() {
+    let mut device = Device::default();
+    let first = Utc::now();
+    let second = first + Duration::seconds(30);
+
+    // a connect without a recorded disconnect closes the open interval
+    for connected_at in [first, second] {
+        device.merge(Updates {
+            last_connected_at: Some(connected_at),
+            ..Updates::empty()
+        });
+    }
+    assert_eq!(
+        device.connection_history,
+        VecDeque::from([
+            ConnectionInterval {
+                connected_at: first,
+                disconnected_at: Some(second),
+            },
+            ConnectionInterval {
+                connected_at: second,
+                disconnected_at: None,
+            },
+        ])
+    );
+
+    // the open interval is closed by the next disconnect as usual
+    let disconnected_at = second + Duration::seconds(30);
+    device.merge(Updates {
+        last_disconnected_at: Some(disconnected_at),
+        ..Updates::empty()
+    });
+    assert_eq!(
+        device.connection_history[1].disconnected_at,
+        Some(disconnected_at)
+    );
+    assert_eq!(device.connection_history[0].disconnected_at, Some(second));
+}
+
+#[test]
+fn device_merge_connection_history_is_bounded() {
+    let mut device = Device::default();
+    let start = Utc::now();
+    for i in 0..(CONNECTION_HISTORY_CAPACITY + 5) {
+        device.merge(Updates {
+            last_connected_at: Some(start + Duration::seconds(i as i64)),
+            ..Updates::empty()
+        });
+    }
+    assert_eq!(device.connection_history.len(), CONNECTION_HISTORY_CAPACITY);
+
+    // the oldest intervals are dropped
+    assert_eq!(
+        device.connection_history[0].connected_at,
+        start + Duration::seconds(5)
+    );
+}
@@ -1,11 +1,30 @@
+// standard library
+use std::sync::Arc;
+use std::time::Duration;
+
 // internal crates
 use miru_agent::errors::MiruError;
+use miru_agent::filesys::dir::Dir;
 use miru_agent::mqtt::client::{
-    poll, ConnectAddress, Credentials, MQTTClient, OptionsBuilder, Protocol,
+    poll, ConnectAddress, Credentials, DeliveryExt, Event, EventLoop, MQTTClient, Options,
+    OptionsBuilder, Properties, Protocol, ProtocolVersion, ReplyTo, AGENT_VERSION_PROPERTY,
 };
+use miru_agent::mqtt::device::{DeviceExt, Ping, Pong, Presence, PresenceStatus};
+use miru_agent::mqtt::errors::MQTTError;
+use miru_agent::mqtt::topics;
+use miru_agent::storage::outbox::Outbox;
+use miru_agent::utils::version_info;
 
 // external crates
+use bytes::{Bytes, BytesMut};
+use rumqttc::v5::{
+    self,
+    mqttbytes::v5::{ConnAck, ConnectReturnCode, Packet, PubAck, Publish, PublishProperties},
+};
 use rumqttc::QoS;
+use serde_json::json;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
 
 #[tokio::test]
 async fn test_mqtt_client() {
@@ -16,6 +35,7 @@ async fn test_mqtt_client() {
             protocol: Protocol::TCP,
             broker: "broker.emqx.io".to_string(),
             port: 1883,
+            ..Default::default()
         })
         .build();
 
@@ -48,6 +68,7 @@ async fn invalid_broker_url() {
             protocol: Protocol::TCP,
             broker: "arglebargle.com".to_string(),
             port: 1883,
+            ..Default::default()
         })
         .build();
 
@@ -66,6 +87,7 @@ async fn invalid_username_or_password() {
             protocol: Protocol::SSL,
             broker: "staging.mqtt.mirurobotics.com".to_string(),
             port: 8883,
+            ..Default::default()
         })
         .build();
 
@@ -75,3 +97,649 @@ async fn invalid_username_or_password() {
     let event = poll(&mut eventloop).await.unwrap_err();
     assert!(!event.is_network_connection_error());
 }
+
+pub mod protocol_version {
+    use super::*;
+
+    #[test]
+    fn deserialize() {
+        for (input, expected) in [
+            ("v3", ProtocolVersion::V3),
+            ("V5", ProtocolVersion::V5),
+            ("5", ProtocolVersion::V5),
+            // invalid versions fall back to the default
+            ("v4", ProtocolVersion::V3),
+        ] {
+            let version = serde_json::from_value::<ProtocolVersion>(json!(input)).unwrap();
+            assert_eq!(version, expected);
+        }
+        assert_eq!(ProtocolVersion::default(), ProtocolVersion::V3);
+        assert_eq!(json!(ProtocolVersion::V5), json!("v5"));
+    }
+}
+
+pub mod protocol {
+    use super::*;
+
+    #[test]
+    fn deserialize() {
+        for (input, expected) in [
+            ("tcp", Protocol::TCP),
+            ("SSL", Protocol::SSL),
+            ("wss", Protocol::WSS),
+            // invalid protocols fall back to the default
+            ("ws", Protocol::SSL),
+        ] {
+            let protocol = serde_json::from_value::<Protocol>(json!(input)).unwrap();
+            assert_eq!(protocol, expected);
+        }
+        assert_eq!(Protocol::default(), Protocol::SSL);
+        assert_eq!(json!(Protocol::WSS), json!("wss"));
+    }
+
+    #[test]
+    fn default_port() {
+        assert_eq!(Protocol::TCP.default_port(), 1883);
+        assert_eq!(Protocol::SSL.default_port(), 8883);
+        assert_eq!(Protocol::WSS.default_port(), 443);
+    }
+}
+
+pub mod connect_address {
+    use super::*;
+
+    #[test]
+    fn broker_addr() {
+        let address = ConnectAddress::new(Protocol::SSL, "mqtt.arglebargle.com".to_string(), 8883);
+        assert_eq!(address.broker_addr(), "mqtt.arglebargle.com");
+
+        // websocket connections are made to the endpoint's url
+        let address = ConnectAddress::new(Protocol::WSS, "mqtt.arglebargle.com".to_string(), 443);
+        assert_eq!(address.broker_addr(), "wss://mqtt.arglebargle.com:443/mqtt");
+        let address = address.with_path("ws".to_string());
+        assert_eq!(address.broker_addr(), "wss://mqtt.arglebargle.com:443/ws");
+    }
+
+    #[tokio::test]
+    async fn unavailable_wss_broker_is_network_error() {
+        // reserve a port which nothing is listening on
+        let port = TcpListener::bind("127.0.0.1:0")
+            .await
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let options = OptionsBuilder::new(Credentials::default())
+            .with_connect_address(ConnectAddress::new(
+                Protocol::WSS,
+                "127.0.0.1".to_string(),
+                port,
+            ))
+            .build();
+        let (_client, mut eventloop) = MQTTClient::new(&options).await;
+        let error = poll(&mut eventloop).await.unwrap_err();
+        assert!(error.is_network_connection_error(), "{error:?}");
+    }
+}
+
+pub mod credentials {
+    use super::*;
+
+    #[test]
+    fn debug_redacts_password() {
+        let credentials = Credentials::new("username".to_string(), "secret-token".to_string());
+        let debug = format!("{credentials:?}");
+        assert!(debug.contains("username"));
+        assert!(!debug.contains("secret-token"));
+
+        let options = OptionsBuilder::new(credentials).build();
+        assert!(!format!("{options:?}").contains("secret-token"));
+    }
+}
+
+pub mod events {
+    use super::*;
+
+    #[test]
+    fn from_v3() {
+        let event = Event::from(rumqttc::Event::Incoming(rumqttc::Incoming::ConnAck(
+            rumqttc::ConnAck {
+                session_present: true,
+                code: rumqttc::ConnectReturnCode::Success,
+            },
+        )));
+        assert_eq!(
+            event,
+            Event::Connected {
+                session_present: true
+            }
+        );
+
+        let event = Event::from(rumqttc::Event::Incoming(rumqttc::Incoming::Publish(
+            rumqttc::Publish::new("a/topic", QoS::AtLeastOnce, "payload"),
+        )));
+        let Event::Message(message) = event else {
+            panic!("expected a message");
+        };
+        assert_eq!(message.topic, "a/topic");
+        assert_eq!(message.payload, b"payload");
+        assert_eq!(message.reply_to(), None);
+    }
+
+    #[test]
+    fn deliveries() {
+        let event = Event::from(rumqttc::Event::Outgoing(rumqttc::Outgoing::Publish(7)));
+        assert_eq!(event, Event::Published { pkid: 7 });
+        let event = Event::from(rumqttc::Event::Incoming(rumqttc::Incoming::PubAck(
+            rumqttc::PubAck::new(7),
+        )));
+        assert_eq!(event, Event::Acked { pkid: 7 });
+        let event = Event::from(v5::Event::Outgoing(rumqttc::Outgoing::Publish(7)));
+        assert_eq!(event, Event::Published { pkid: 7 });
+        let event = Event::from(v5::Event::Incoming(v5::Incoming::PubAck(PubAck::new(
+            7, None,
+        ))));
+        assert_eq!(event, Event::Acked { pkid: 7 });
+        let event = Event::from(rumqttc::Event::Outgoing(rumqttc::Outgoing::PingReq));
+        assert_eq!(event, Event::Other);
+    }
+
+    #[test]
+    fn from_v5() {
+        let properties = PublishProperties {
+            response_topic: Some("reply/topic".to_string()),
+            correlation_data: Some(Bytes::from_static(b"correlation")),
+            ..Default::default()
+        };
+        let event = Event::from(v5::Event::Incoming(v5::Incoming::Publish(Publish::new(
+            "a/topic",
+            v5::mqttbytes::QoS::AtLeastOnce,
+            "payload",
+            Some(properties),
+        ))));
+        let Event::Message(message) = event else {
+            panic!("expected a message");
+        };
+        assert_eq!(message.topic, "a/topic");
+        assert_eq!(
+            message.reply_to(),
+            Some(ReplyTo {
+                topic: "reply/topic".to_string(),
+                correlation_data: Some(b"correlation".to_vec()),
+            })
+        );
+    }
+}
+
+// a broker which speaks just enough mqtt v5 to test the client against
+pub mod v5_broker {
+    use super::*;
+
+    pub async fn listen() -> (TcpListener, u16) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        (listener, port)
+    }
+
+    pub async fn read_packet(stream: &mut TcpStream, buf: &mut BytesMut) -> Packet {
+        loop {
+            match Packet::read(buf, None) {
+                Ok(packet) => return packet,
+                Err(v5::mqttbytes::Error::InsufficientBytes(_)) => {
+                    assert!(stream.read_buf(buf).await.unwrap() > 0, "connection closed");
+                }
+                Err(e) => panic!("invalid packet: {e:?}"),
+            }
+        }
+    }
+
+    pub async fn write_packet(stream: &mut TcpStream, packet: Packet) {
+        let mut buf = BytesMut::new();
+        packet.write(&mut buf).unwrap();
+        stream.write_all(&buf).await.unwrap();
+    }
+
+    pub fn connack(code: ConnectReturnCode) -> Packet {
+        Packet::ConnAck(ConnAck {
+            session_present: false,
+            code,
+            properties: None,
+        })
+    }
+
+    pub fn options(port: u16) -> Options {
+        OptionsBuilder::new(Credentials::new(
+            "username".to_string(),
+            "password".to_string(),
+        ))
+        .with_connect_address(ConnectAddress {
+            protocol: Protocol::TCP,
+            broker: "127.0.0.1".to_string(),
+            port,
+            ..Default::default()
+        })
+        .with_version(ProtocolVersion::V5)
+        .with_session_expiry(Duration::from_secs(120))
+        .build()
+    }
+
+    // polls until an event other than an outgoing packet or an ack is received
+    pub async fn next_event(eventloop: &mut EventLoop) -> Result<Event, MQTTError> {
+        loop {
+            match poll(eventloop).await {
+                Ok(Event::Published { .. } | Event::Acked { .. } | Event::Other) => continue,
+                result => return result,
+            }
+        }
+    }
+}
+
+pub mod v5_client {
+    use super::v5_broker::*;
+    use super::*;
+
+    #[tokio::test]
+    async fn connects_with_session_expiry_and_agent_version() {
+        let (listener, port) = listen().await;
+        let broker = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut buf = BytesMut::new();
+            let packet = read_packet(&mut stream, &mut buf).await;
+            write_packet(&mut stream, connack(ConnectReturnCode::Success)).await;
+            packet
+        });
+
+        let (_client, mut eventloop) = MQTTClient::new(&options(port)).await;
+        let event = next_event(&mut eventloop).await.unwrap();
+        assert_eq!(
+            event,
+            Event::Connected {
+                session_present: false
+            }
+        );
+
+        let Packet::Connect(connect, _, login) = broker.await.unwrap() else {
+            panic!("expected a connect packet");
+        };
+        assert!(!connect.clean_start);
+        let properties = connect.properties.unwrap();
+        assert_eq!(properties.session_expiry_interval, Some(120));
+        assert!(properties
+            .user_properties
+            .contains(&(AGENT_VERSION_PROPERTY.to_string(), version_info().version)));
+        assert_eq!(login.unwrap().username, "username");
+    }
+
+    #[tokio::test]
+    async fn connects_with_presence_last_will() {
+        let (listener, port) = listen().await;
+        let broker = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut buf = BytesMut::new();
+            let packet = read_packet(&mut stream, &mut buf).await;
+            write_packet(&mut stream, connack(ConnectReturnCode::Success)).await;
+            packet
+        });
+
+        let mut options = options(port);
+        options.last_will = Some(Presence::last_will("device_id", "session_id").unwrap());
+        let (_client, mut eventloop) = MQTTClient::new(&options).await;
+        next_event(&mut eventloop).await.unwrap();
+
+        let Packet::Connect(_, last_will, _) = broker.await.unwrap() else {
+            panic!("expected a connect packet");
+        };
+        let last_will = last_will.unwrap();
+        assert_eq!(last_will.topic, topics::device_presence("device_id"));
+        assert!(last_will.retain);
+        assert_eq!(last_will.qos, v5::mqttbytes::QoS::AtLeastOnce);
+        let presence = serde_json::from_slice::<Presence>(&last_will.message).unwrap();
+        assert_eq!(
+            presence,
+            Presence {
+                status: PresenceStatus::Offline,
+                agent_version: version_info().version,
+                session_id: "session_id".to_string(),
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn connects_with_configured_keep_alive_and_qos() {
+        let (listener, port) = listen().await;
+        let broker = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut buf = BytesMut::new();
+            let connect = read_packet(&mut stream, &mut buf).await;
+            write_packet(&mut stream, connack(ConnectReturnCode::Success)).await;
+            loop {
+                if let Packet::Subscribe(subscribe) = read_packet(&mut stream, &mut buf).await {
+                    return (connect, subscribe);
+                }
+            }
+        });
+
+        let mut options = options(port);
+        options.keep_alive = Duration::from_secs(600);
+        options.qos = QoS::ExactlyOnce;
+        let (client, mut eventloop) = MQTTClient::new(&options).await;
+        next_event(&mut eventloop).await.unwrap();
+        assert_eq!(client.qos(), QoS::ExactlyOnce);
+        client.subscribe_device_sync("device_id").await.unwrap();
+
+        // flush the subscription
+        let mut broker = broker;
+        let (connect, subscribe) = loop {
+            tokio::select! {
+                packets = &mut broker => break packets.unwrap(),
+                _ = poll(&mut eventloop) => {}
+            }
+        };
+        let Packet::Connect(connect, _, _) = connect else {
+            panic!("expected a connect packet");
+        };
+        assert_eq!(connect.keep_alive, 600);
+        assert_eq!(subscribe.filters[0].path, topics::device_sync("device_id"));
+        assert_eq!(subscribe.filters[0].qos, v5::mqttbytes::QoS::ExactlyOnce);
+    }
+
+    #[tokio::test]
+    async fn replays_messages_queued_while_disconnected() {
+        let (listener, port) = listen().await;
+        let (tx, mut publishes) = tokio::sync::mpsc::unbounded_channel();
+        let broker = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut buf = BytesMut::new();
+            read_packet(&mut stream, &mut buf).await;
+            write_packet(&mut stream, connack(ConnectReturnCode::Success)).await;
+
+            // only acknowledge the first message
+            loop {
+                if let Packet::Publish(publish) = read_packet(&mut stream, &mut buf).await {
+                    if publish.payload == Bytes::from_static(b"first") {
+                        write_packet(&mut stream, Packet::PubAck(PubAck::new(publish.pkid, None)))
+                            .await;
+                    }
+                    tx.send(publish).unwrap();
+                }
+            }
+        });
+
+        let dir = Dir::create_temp_dir("testing").await.unwrap();
+        let (outbox, _) = Outbox::spawn(16, dir.file("outbox.json"), 10, Duration::from_secs(60))
+            .await
+            .unwrap();
+        let outbox = Arc::new(outbox);
+        let mut options = options(port);
+        options.outbox = Some(outbox.clone());
+        let (client, mut eventloop) = MQTTClient::new(&options).await;
+
+        // not connected yet so the messages are queued
+        client
+            .publish("a/b", QoS::AtLeastOnce, false, b"first")
+            .await
+            .unwrap();
+        client
+            .publish("a/b", QoS::AtLeastOnce, true, b"second")
+            .await
+            .unwrap();
+        assert_eq!(outbox.len().await.unwrap(), 2);
+
+        next_event(&mut eventloop).await.unwrap();
+        client.set_connected(true);
+        assert!(!client.publish_queued().await);
+
+        // the messages stay queued until the broker acknowledges them
+        assert_eq!(outbox.len().await.unwrap(), 2);
+        loop {
+            let event = poll(&mut eventloop).await.unwrap();
+            if client.record_delivery(&event) {
+                break;
+            }
+        }
+        assert!(client.publish_queued().await);
+        let queued = outbox.peek(10).await.unwrap();
+        assert_eq!(queued.len(), 1);
+        assert_eq!(queued[0].payload, b"second".to_vec());
+
+        // the unacknowledged message is still in flight so it isn't published again
+        assert!(!client.publish_queued().await);
+        let first = publishes.recv().await.unwrap();
+        assert_eq!(first.payload, Bytes::from_static(b"first"));
+        assert!(!first.retain);
+        let second = publishes.recv().await.unwrap();
+        assert_eq!(second.payload, Bytes::from_static(b"second"));
+        assert!(second.retain);
+        tokio::time::timeout(Duration::from_millis(100), async {
+            loop {
+                poll(&mut eventloop).await.unwrap();
+            }
+        })
+        .await
+        .unwrap_err();
+        assert!(publishes.try_recv().is_err());
+        broker.abort();
+    }
+
+    #[tokio::test]
+    async fn tracks_deliveries_until_acked() {
+        let (listener, port) = listen().await;
+        let broker = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut buf = BytesMut::new();
+            read_packet(&mut stream, &mut buf).await;
+            write_packet(&mut stream, connack(ConnectReturnCode::Success)).await;
+
+            // acknowledge all but the last publish
+            let mut publishes = Vec::new();
+            while publishes.len() < 3 {
+                if let Packet::Publish(publish) = read_packet(&mut stream, &mut buf).await {
+                    publishes.push(publish);
+                }
+            }
+            for publish in &publishes[..2] {
+                write_packet(&mut stream, Packet::PubAck(PubAck::new(publish.pkid, None))).await;
+            }
+            // keep the connection open
+            read_packet(&mut stream, &mut buf).await;
+        });
+
+        let (client, mut eventloop) = MQTTClient::new(&options(port)).await;
+        next_event(&mut eventloop).await.unwrap();
+        client
+            .publish("a/b", QoS::AtLeastOnce, false, b"untracked")
+            .await
+            .unwrap();
+        for id in ["first", "second"] {
+            client
+                .publish_tracked(
+                    id,
+                    "a/b",
+                    QoS::AtLeastOnce,
+                    false,
+                    id.as_bytes(),
+                    Properties::default(),
+                )
+                .await
+                .unwrap();
+        }
+        assert!(client.is_in_flight("first"));
+        assert!(client.is_in_flight("second"));
+
+        loop {
+            let event = poll(&mut eventloop).await.unwrap();
+            if client.record_delivery(&event) {
+                break;
+            }
+        }
+        assert!(!client.is_in_flight("first"));
+        assert!(client.take_acked("first"));
+        assert!(!client.take_acked("first"));
+        assert!(client.is_in_flight("second"));
+        assert!(!client.take_acked("second"));
+        broker.abort();
+    }
+
+    #[tokio::test]
+    async fn replies_on_response_topic() {
+        let (listener, port) = listen().await;
+        let broker = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut buf = BytesMut::new();
+            read_packet(&mut stream, &mut buf).await;
+            write_packet(&mut stream, connack(ConnectReturnCode::Success)).await;
+
+            // a ping request with a response topic
+            let ping = Ping {
+                message_id: "message_id".to_string(),
+                timestamp: "2025-01-01T00:00:00Z".to_string(),
+            };
+            let properties = PublishProperties {
+                response_topic: Some("reply/device_id".to_string()),
+                correlation_data: Some(Bytes::from_static(b"correlation")),
+                ..Default::default()
+            };
+            let request = Publish::new(
+                topics::device_ping("device_id"),
+                v5::mqttbytes::QoS::AtMostOnce,
+                serde_json::to_vec(&ping).unwrap(),
+                Some(properties),
+            );
+            write_packet(&mut stream, Packet::Publish(request)).await;
+
+            // the pong
+            loop {
+                if let Packet::Publish(publish) = read_packet(&mut stream, &mut buf).await {
+                    return publish;
+                }
+            }
+        });
+
+        let (client, mut eventloop) = MQTTClient::new(&options(port)).await;
+        next_event(&mut eventloop).await.unwrap();
+        let Event::Message(message) = next_event(&mut eventloop).await.unwrap() else {
+            panic!("expected a message");
+        };
+        client
+            .publish_device_pong(
+                "device_id",
+                "message_id".to_string(),
+                message.reply_to().as_ref(),
+            )
+            .await
+            .unwrap();
+        // flush the pong
+        let mut broker = broker;
+        let pong = loop {
+            tokio::select! {
+                pong = &mut broker => break pong.unwrap(),
+                _ = poll(&mut eventloop) => {}
+            }
+        };
+
+        assert_eq!(pong.topic, "reply/device_id");
+        let properties = pong.properties.unwrap();
+        assert_eq!(
+            properties.correlation_data,
+            Some(Bytes::from_static(b"correlation"))
+        );
+        assert!(properties
+            .user_properties
+            .contains(&(AGENT_VERSION_PROPERTY.to_string(), version_info().version)));
+        let pong = serde_json::from_slice::<Pong>(&pong.payload).unwrap();
+        assert_eq!(pong.message_id, "message_id");
+    }
+
+    #[tokio::test]
+    async fn connack_reason_code() {
+        let (listener, port) = listen().await;
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut buf = BytesMut::new();
+            read_packet(&mut stream, &mut buf).await;
+            write_packet(&mut stream, connack(ConnectReturnCode::NotAuthorized)).await;
+        });
+
+        let (_client, mut eventloop) = MQTTClient::new(&options(port)).await;
+        let error = next_event(&mut eventloop).await.unwrap_err();
+        assert!(error.is_authentication_error());
+        assert!(!error.is_network_connection_error());
+        let reason_code = error.reason_code().unwrap();
+        assert_eq!(reason_code.packet, "connack");
+        assert_eq!(reason_code.code, "NotAuthorized");
+        assert_eq!(error.params().unwrap()["reason_code"], "NotAuthorized");
+    }
+
+    #[tokio::test]
+    async fn unavailable_broker_is_network_error() {
+        let (listener, port) = listen().await;
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut buf = BytesMut::new();
+            read_packet(&mut stream, &mut buf).await;
+            write_packet(&mut stream, connack(ConnectReturnCode::ServerBusy)).await;
+        });
+
+        let (_client, mut eventloop) = MQTTClient::new(&options(port)).await;
+        let error = next_event(&mut eventloop).await.unwrap_err();
+        assert!(error.is_network_connection_error());
+        assert_eq!(error.reason_code().unwrap().code, "ServerBusy");
+    }
+
+    #[tokio::test]
+    async fn disconnect_reason_code() {
+        let (listener, port) = listen().await;
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut buf = BytesMut::new();
+            read_packet(&mut stream, &mut buf).await;
+            write_packet(&mut stream, connack(ConnectReturnCode::Success)).await;
+            // the disconnect properties can't be constructed outside of rumqttc so the
+            // packet is written by hand: session taken over (0x8e) with a reason string
+            // (0x1f)
+            let reason = b"another client connected";
+            let mut packet = vec![
+                0xe0,
+                (reason.len() + 5) as u8,
+                0x8e,
+                (reason.len() + 3) as u8,
+            ];
+            packet.extend([0x1f, 0x00, reason.len() as u8]);
+            packet.extend(reason);
+            stream.write_all(&packet).await.unwrap();
+            // keep the connection open until the client has read the disconnect
+            tokio::time::sleep(Duration::from_secs(1)).await;
+        });
+
+        let (_client, mut eventloop) = MQTTClient::new(&options(port)).await;
+        next_event(&mut eventloop).await.unwrap();
+        let error = next_event(&mut eventloop).await.unwrap_err();
+        assert!(!error.is_network_connection_error());
+        assert!(!error.is_authentication_error());
+        let reason_code = error.reason_code().unwrap();
+        assert_eq!(reason_code.packet, "disconnect");
+        assert_eq!(reason_code.code, "SessionTakenOver");
+        assert_eq!(
+            reason_code.reason.as_deref(),
+            Some("another client connected")
+        );
+    }
+
+    #[tokio::test]
+    async fn v3_errors_have_no_reason_code() {
+        let (listener, port) = listen().await;
+        drop(listener);
+        let options = OptionsBuilder::new(Credentials::default())
+            .with_connect_address(ConnectAddress {
+                protocol: Protocol::TCP,
+                broker: "127.0.0.1".to_string(),
+                port,
+                ..Default::default()
+            })
+            .build();
+        let (_client, mut eventloop) = MQTTClient::new(&options).await;
+        let error = poll(&mut eventloop).await.unwrap_err();
+        assert!(error.is_network_connection_error());
+        assert_eq!(error.reason_code(), None);
+    }
+}
@@ -1,9 +1,14 @@
 // standard crates
+use std::collections::HashSet;
 use std::sync::{Arc, Mutex};
 
 // internal crates
-use miru_agent::mqtt::device::DeviceExt;
+use miru_agent::mqtt::bridge::{LocalBridgeExt, LocalConfig};
+use miru_agent::mqtt::client::{DeliveryExt, ReplyTo};
+use miru_agent::mqtt::commands::CommandResponse;
+use miru_agent::mqtt::device::{DeviceExt, Presence};
 use miru_agent::mqtt::errors::MQTTError;
+use miru_agent::mqtt::events::ConfigInstanceEvent;
 
 #[derive(Clone)]
 pub enum MQTTDeviceClientCall {
@@ -11,6 +16,10 @@ pub enum MQTTDeviceClientCall {
     SubscribeDeviceSync(SubscribeDeviceSyncCall),
     SubscribeDevicePing(SubscribeDevicePingCall),
     PublishDevicePong(PublishDevicePongCall),
+    SubscribeDeviceCommands(SubscribeDeviceCommandsCall),
+    PublishCommandResponse(PublishCommandResponseCall),
+    PublishConfigInstanceEvent(PublishConfigInstanceEventCall),
+    PublishDevicePresence(PublishDevicePresenceCall),
 }
 
 #[derive(Clone)]
@@ -32,6 +41,31 @@ pub struct SubscribeDevicePingCall {
 pub struct PublishDevicePongCall {
     pub device_id: String,
     pub ping_message_id: String,
+    pub reply_to: Option<ReplyTo>,
+}
+
+#[derive(Clone)]
+pub struct SubscribeDeviceCommandsCall {
+    pub device_id: String,
+}
+
+#[derive(Clone)]
+pub struct PublishCommandResponseCall {
+    pub device_id: String,
+    pub response: CommandResponse,
+    pub reply_to: Option<ReplyTo>,
+}
+
+#[derive(Clone)]
+pub struct PublishConfigInstanceEventCall {
+    pub device_id: String,
+    pub event: ConfigInstanceEvent,
+}
+
+#[derive(Clone)]
+pub struct PublishDevicePresenceCall {
+    pub device_id: String,
+    pub presence: Presence,
 }
 
 pub struct MockDeviceClient {
@@ -39,7 +73,14 @@ pub struct MockDeviceClient {
     pub subscribe_device_sync_fn: Box<dyn Fn() -> Result<(), MQTTError> + Send + Sync>,
     pub subscribe_device_ping_fn: Box<dyn Fn() -> Result<(), MQTTError> + Send + Sync>,
     pub publish_device_pong_fn: Box<dyn Fn() -> Result<(), MQTTError> + Send + Sync>,
+    pub subscribe_device_commands_fn: Box<dyn Fn() -> Result<(), MQTTError> + Send + Sync>,
+    pub publish_command_response_fn: Box<dyn Fn() -> Result<(), MQTTError> + Send + Sync>,
+    pub publish_config_instance_event_fn: Box<dyn Fn() -> Result<(), MQTTError> + Send + Sync>,
+    pub publish_device_presence_fn: Box<dyn Fn() -> Result<(), MQTTError> + Send + Sync>,
     pub calls: Arc<Mutex<Vec<MQTTDeviceClientCall>>>,
+    // the ids of the published config instance events awaiting an ack
+    pub in_flight: Arc<Mutex<HashSet<String>>>,
+    pub acked: Arc<Mutex<HashSet<String>>>,
 }
 
 impl Default for MockDeviceClient {
@@ -49,7 +90,13 @@ impl Default for MockDeviceClient {
             subscribe_device_sync_fn: Box::new(|| Ok(())),
             subscribe_device_ping_fn: Box::new(|| Ok(())),
             publish_device_pong_fn: Box::new(|| Ok(())),
+            subscribe_device_commands_fn: Box::new(|| Ok(())),
+            publish_command_response_fn: Box::new(|| Ok(())),
+            publish_config_instance_event_fn: Box::new(|| Ok(())),
+            publish_device_presence_fn: Box::new(|| Ok(())),
             calls: Arc::new(Mutex::new(Vec::new())),
+            in_flight: Arc::new(Mutex::new(HashSet::new())),
+            acked: Arc::new(Mutex::new(HashSet::new())),
         }
     }
 }
@@ -83,10 +130,37 @@ impl MockDeviceClient {
         self.publish_device_pong_fn = Box::new(publish_device_pong_fn);
     }
 
+    pub fn set_publish_command_response<F>(&mut self, publish_command_response_fn: F)
+    where
+        F: Fn() -> Result<(), MQTTError> + Send + Sync + 'static,
+    {
+        self.publish_command_response_fn = Box::new(publish_command_response_fn);
+    }
+
+    pub fn set_publish_config_instance_event<F>(&mut self, publish_config_instance_event_fn: F)
+    where
+        F: Fn() -> Result<(), MQTTError> + Send + Sync + 'static,
+    {
+        self.publish_config_instance_event_fn = Box::new(publish_config_instance_event_fn);
+    }
+
+    pub fn set_publish_device_presence<F>(&mut self, publish_device_presence_fn: F)
+    where
+        F: Fn() -> Result<(), MQTTError> + Send + Sync + 'static,
+    {
+        self.publish_device_presence_fn = Box::new(publish_device_presence_fn);
+    }
+
     pub fn get_calls(&self) -> Vec<MQTTDeviceClientCall> {
         self.calls.lock().unwrap().clone()
     }
 
+    // acknowledges every config instance event in flight
+    pub fn ack_all(&self) {
+        let in_flight = std::mem::take(&mut *self.in_flight.lock().unwrap());
+        self.acked.lock().unwrap().extend(in_flight);
+    }
+
     pub fn num_publish_device_sync_calls(&self) -> usize {
         self.calls
             .lock()
@@ -122,6 +196,51 @@ impl MockDeviceClient {
             .filter(|call| matches!(call, MQTTDeviceClientCall::PublishDevicePong(_)))
             .count()
     }
+
+    pub fn num_subscribe_device_commands_calls(&self) -> usize {
+        self.calls
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|call| matches!(call, MQTTDeviceClientCall::SubscribeDeviceCommands(_)))
+            .count()
+    }
+
+    pub fn command_responses(&self) -> Vec<PublishCommandResponseCall> {
+        self.calls
+            .lock()
+            .unwrap()
+            .iter()
+            .filter_map(|call| match call {
+                MQTTDeviceClientCall::PublishCommandResponse(call) => Some(call.clone()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    pub fn config_instance_events(&self) -> Vec<PublishConfigInstanceEventCall> {
+        self.calls
+            .lock()
+            .unwrap()
+            .iter()
+            .filter_map(|call| match call {
+                MQTTDeviceClientCall::PublishConfigInstanceEvent(call) => Some(call.clone()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    pub fn device_presences(&self) -> Vec<PublishDevicePresenceCall> {
+        self.calls
+            .lock()
+            .unwrap()
+            .iter()
+            .filter_map(|call| match call {
+                MQTTDeviceClientCall::PublishDevicePresence(call) => Some(call.clone()),
+                _ => None,
+            })
+            .collect()
+    }
 }
 
 impl DeviceExt for MockDeviceClient {
@@ -162,10 +281,12 @@ impl DeviceExt for MockDeviceClient {
         &self,
         device_id: &str,
         ping_message_id: String,
+        reply_to: Option<&ReplyTo>,
     ) -> Result<(), MQTTError> {
         let call = PublishDevicePongCall {
             device_id: device_id.to_string(),
             ping_message_id,
+            reply_to: reply_to.cloned(),
         };
         self.calls
             .lock()
@@ -173,4 +294,151 @@ impl DeviceExt for MockDeviceClient {
             .push(MQTTDeviceClientCall::PublishDevicePong(call));
         (self.publish_device_pong_fn)()
     }
+
+    async fn subscribe_device_commands(&self, device_id: &str) -> Result<(), MQTTError> {
+        let call = SubscribeDeviceCommandsCall {
+            device_id: device_id.to_string(),
+        };
+        self.calls
+            .lock()
+            .unwrap()
+            .push(MQTTDeviceClientCall::SubscribeDeviceCommands(call));
+        (self.subscribe_device_commands_fn)()
+    }
+
+    async fn publish_command_response(
+        &self,
+        device_id: &str,
+        response: &CommandResponse,
+        reply_to: Option<&ReplyTo>,
+    ) -> Result<(), MQTTError> {
+        let call = PublishCommandResponseCall {
+            device_id: device_id.to_string(),
+            response: response.clone(),
+            reply_to: reply_to.cloned(),
+        };
+        self.calls
+            .lock()
+            .unwrap()
+            .push(MQTTDeviceClientCall::PublishCommandResponse(call));
+        (self.publish_command_response_fn)()
+    }
+
+    async fn publish_config_instance_event(
+        &self,
+        device_id: &str,
+        event: &ConfigInstanceEvent,
+    ) -> Result<(), MQTTError> {
+        let call = PublishConfigInstanceEventCall {
+            device_id: device_id.to_string(),
+            event: event.clone(),
+        };
+        self.calls
+            .lock()
+            .unwrap()
+            .push(MQTTDeviceClientCall::PublishConfigInstanceEvent(call));
+        (self.publish_config_instance_event_fn)()?;
+        self.in_flight
+            .lock()
+            .unwrap()
+            .insert(event.event_id.clone());
+        Ok(())
+    }
+
+    async fn publish_device_presence(
+        &self,
+        device_id: &str,
+        presence: &Presence,
+    ) -> Result<(), MQTTError> {
+        let call = PublishDevicePresenceCall {
+            device_id: device_id.to_string(),
+            presence: presence.clone(),
+        };
+        self.calls
+            .lock()
+            .unwrap()
+            .push(MQTTDeviceClientCall::PublishDevicePresence(call));
+        (self.publish_device_presence_fn)()
+    }
+}
+
+impl DeliveryExt for MockDeviceClient {
+    fn is_in_flight(&self, id: &str) -> bool {
+        self.in_flight.lock().unwrap().contains(id)
+    }
+
+    fn take_acked(&self, id: &str) -> bool {
+        self.acked.lock().unwrap().remove(id)
+    }
+}
+
+// ================================ LOCAL BRIDGE =================================== //
+#[derive(Clone, Debug, PartialEq)]
+pub enum LocalBridgeCall {
+    PublishLocalConfig { topic: String, config: LocalConfig },
+    ClearLocalConfig { topic: String },
+}
+
+pub struct MockLocalBridgeClient {
+    pub publish_local_config_fn: Box<dyn Fn() -> Result<(), MQTTError> + Send + Sync>,
+    pub clear_local_config_fn: Box<dyn Fn() -> Result<(), MQTTError> + Send + Sync>,
+    pub calls: Arc<Mutex<Vec<LocalBridgeCall>>>,
+}
+
+impl Default for MockLocalBridgeClient {
+    fn default() -> Self {
+        Self {
+            publish_local_config_fn: Box::new(|| Ok(())),
+            clear_local_config_fn: Box::new(|| Ok(())),
+            calls: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+}
+
+impl MockLocalBridgeClient {
+    pub fn set_publish_local_config<F>(&mut self, publish_local_config_fn: F)
+    where
+        F: Fn() -> Result<(), MQTTError> + Send + Sync + 'static,
+    {
+        self.publish_local_config_fn = Box::new(publish_local_config_fn);
+    }
+
+    pub fn set_clear_local_config<F>(&mut self, clear_local_config_fn: F)
+    where
+        F: Fn() -> Result<(), MQTTError> + Send + Sync + 'static,
+    {
+        self.clear_local_config_fn = Box::new(clear_local_config_fn);
+    }
+
+    // returns and clears the calls made so far
+    pub fn take_calls(&self) -> Vec<LocalBridgeCall> {
+        std::mem::take(&mut *self.calls.lock().unwrap())
+    }
+}
+
+impl LocalBridgeExt for MockLocalBridgeClient {
+    async fn publish_local_config(
+        &self,
+        topic: &str,
+        config: &LocalConfig,
+    ) -> Result<(), MQTTError> {
+        self.calls
+            .lock()
+            .unwrap()
+            .push(LocalBridgeCall::PublishLocalConfig {
+                topic: topic.to_string(),
+                config: config.clone(),
+            });
+        (self.publish_local_config_fn)()
+    }
+
+    async fn clear_local_config(&self, topic: &str) -> Result<(), MQTTError> {
+        self.calls
+            .lock()
+            .unwrap()
+            .push(LocalBridgeCall::ClearLocalConfig {
+                topic: topic.to_string(),
+            });
+        (self.clear_local_config_fn)()
+    }
 }
@@ -1,4 +1,5 @@
 // internal crates
+use miru_agent::mqtt::commands::CommandName;
 use miru_agent::mqtt::topics;
 
 mod parse_subscription {
@@ -16,11 +17,24 @@ mod parse_subscription {
         assert!(topics::parse_subscription("123", &topic) == topics::SubscriptionTopics::Ping);
     }
 
+    #[test]
+    fn test_device_command() {
+        for command in CommandName::variants() {
+            let topic = topics::device_command("123", command);
+            assert!(
+                topics::parse_subscription("123", &topic)
+                    == topics::SubscriptionTopics::Command(command)
+            );
+        }
+    }
+
     #[test]
     fn test_device_unknown() {
         let unknown_topics = vec![
             "v1/cmd/devices/123/unknown",
             "v2/cmd/devices/123/ping",
+            "v1/cmd/devices/123/commands/self_destruct",
+            "v1/cmd/devices/456/commands/restart",
             "arglechargle",
         ];
         for topic in unknown_topics {
@@ -30,3 +44,32 @@ mod parse_subscription {
         }
     }
 }
+
+mod local_config {
+    use super::*;
+
+    #[test]
+    fn test_local_config() {
+        assert_eq!(
+            topics::local_config("miru/configs", "motion-control"),
+            "miru/configs/motion-control"
+        );
+        // trailing slashes aren't doubled
+        assert_eq!(
+            topics::local_config("miru/configs/", "motion-control"),
+            "miru/configs/motion-control"
+        );
+    }
+}
+
+mod device_presence {
+    use super::*;
+
+    #[test]
+    fn test_device_presence() {
+        let topic = topics::device_presence("123");
+        assert_eq!(topic, "v1/evt/devices/123/presence");
+        // the device doesn't subscribe to its own presence
+        assert!(topics::parse_subscription("123", &topic) == topics::SubscriptionTopics::Unknown);
+    }
+}
@@ -0,0 +1,223 @@
+// standard library
+use std::sync::Arc;
+use std::time::Duration;
+
+// internal crates
+use miru_agent::errors::MiruError;
+use miru_agent::http::client::HTTPClient;
+use miru_agent::http::retry::RetryPolicy;
+use miru_agent::network::failover::{EndpointStatus, Endpoints, FailoverOptions};
+use miru_agent::server::handlers::endpoint_status_to_sdk;
+
+// external crates
+use axum::{routing::get, Router};
+
+const PRIMARY: &str = "https://primary.mirurobotics.com/agent/v1";
+const SECONDARY: &str = "https://secondary.mirurobotics.com/agent/v1";
+const TERTIARY: &str = "https://tertiary.mirurobotics.com/agent/v1";
+
+fn endpoints(failure_threshold: u32, fail_back_after: Duration) -> Endpoints {
+    Endpoints::new(
+        PRIMARY,
+        &[SECONDARY.to_string(), TERTIARY.to_string()],
+        FailoverOptions {
+            failure_threshold,
+            fail_back_after,
+        },
+    )
+}
+
+pub mod endpoints {
+    use super::*;
+
+    #[test]
+    fn dedupes_endpoints() {
+        let endpoints = Endpoints::new(
+            PRIMARY,
+            &[
+                PRIMARY.to_string(),
+                SECONDARY.to_string(),
+                SECONDARY.to_string(),
+            ],
+            FailoverOptions::default(),
+        );
+        assert_eq!(endpoints.endpoints(), &[PRIMARY, SECONDARY]);
+        assert_eq!(endpoints.primary(), PRIMARY);
+        assert_eq!(endpoints.active(), PRIMARY);
+    }
+
+    #[test]
+    fn fails_over_after_threshold() {
+        let endpoints = endpoints(2, Duration::from_secs(60));
+        assert_eq!(endpoints.record_failure(PRIMARY), PRIMARY);
+        assert_eq!(endpoints.record_failure(PRIMARY), SECONDARY);
+        assert_eq!(endpoints.active(), SECONDARY);
+    }
+
+    #[test]
+    fn success_resets_failures() {
+        let endpoints = endpoints(2, Duration::from_secs(60));
+        endpoints.record_failure(PRIMARY);
+        endpoints.record_success(PRIMARY);
+        assert_eq!(endpoints.record_failure(PRIMARY), PRIMARY);
+        assert_eq!(endpoints.record_failure(PRIMARY), SECONDARY);
+    }
+
+    #[test]
+    fn sticky_until_active_endpoint_fails() {
+        let endpoints = endpoints(1, Duration::from_secs(60));
+        assert_eq!(endpoints.record_failure(PRIMARY), SECONDARY);
+
+        // stale failures of the previously active endpoint are ignored
+        assert_eq!(endpoints.record_failure(PRIMARY), SECONDARY);
+        endpoints.record_success(SECONDARY);
+        assert_eq!(endpoints.active(), SECONDARY);
+
+        assert_eq!(endpoints.record_failure(SECONDARY), TERTIARY);
+        // wraps around to the primary after the last endpoint
+        assert_eq!(endpoints.record_failure(TERTIARY), PRIMARY);
+    }
+
+    #[test]
+    fn single_endpoint_never_fails_over() {
+        let endpoints = Endpoints::single(PRIMARY);
+        for _ in 0..5 {
+            assert_eq!(endpoints.record_failure(PRIMARY), PRIMARY);
+        }
+        assert_eq!(endpoints.status().fail_back_in, None);
+    }
+
+    #[tokio::test]
+    async fn fails_back_to_primary() {
+        let endpoints = endpoints(1, Duration::from_millis(50));
+        assert_eq!(endpoints.record_failure(PRIMARY), SECONDARY);
+        assert!(endpoints.status().fail_back_in.is_some());
+
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        assert_eq!(endpoints.active(), PRIMARY);
+        assert_eq!(endpoints.status().fail_back_in, None);
+    }
+
+    #[test]
+    fn endpoint_of() {
+        let endpoints = Endpoints::new(
+            "http://localhost:8080",
+            &["http://localhost:8080/v2".to_string()],
+            FailoverOptions::default(),
+        );
+        assert_eq!(
+            endpoints.endpoint_of("http://localhost:8080/devices"),
+            Some("http://localhost:8080")
+        );
+        // the longest matching endpoint wins
+        assert_eq!(
+            endpoints.endpoint_of("http://localhost:8080/v2/devices"),
+            Some("http://localhost:8080/v2")
+        );
+        assert_eq!(endpoints.endpoint_of("http://localhost:9090/devices"), None);
+    }
+
+    #[test]
+    fn status() {
+        let endpoints = endpoints(1, Duration::from_secs(60));
+        endpoints.record_failure(PRIMARY);
+        let status = endpoints.status();
+        assert_eq!(status.active, SECONDARY);
+        assert_eq!(status.primary, PRIMARY);
+        assert_eq!(status.endpoints, vec![PRIMARY, SECONDARY, TERTIARY]);
+        assert!(status.fail_back_in.unwrap() <= Duration::from_secs(60));
+    }
+}
+
+pub mod http_client {
+    use super::*;
+
+    async fn serve() -> String {
+        let app = Router::new().route("/devices", get(|| async { "{}" }));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.unwrap();
+        });
+        format!("http://{addr}")
+    }
+
+    fn policy() -> RetryPolicy {
+        RetryPolicy {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(10),
+            max_delay: Duration::from_millis(20),
+            time_budget: Duration::from_secs(10),
+        }
+    }
+
+    #[tokio::test]
+    async fn retries_against_fallback() {
+        // nothing listens on the primary
+        let primary = "http://127.0.0.1:1";
+        let fallback = serve().await;
+        let endpoints = Arc::new(Endpoints::new(
+            primary,
+            std::slice::from_ref(&fallback),
+            FailoverOptions {
+                failure_threshold: 1,
+                fail_back_after: Duration::from_secs(60),
+            },
+        ));
+        let http_client = HTTPClient::new(primary)
+            .await
+            .with_retry_policy(policy())
+            .with_endpoints(endpoints.clone());
+        assert_eq!(http_client.base_url(), primary);
+
+        let url = format!("{}/devices", http_client.base_url());
+        let mut request = http_client
+            .build_get_request(&url, Duration::from_secs(1), None)
+            .unwrap();
+        assert_eq!(request.1.endpoint.as_deref(), Some(primary));
+        http_client.send(request.0, &mut request.1).await.unwrap();
+
+        assert_eq!(request.1.attempts, 2);
+        assert_eq!(request.1.endpoint.as_deref(), Some(fallback.as_str()));
+        assert_eq!(request.1.url, format!("{fallback}/devices"));
+        // subsequent requests stick with the fallback
+        assert_eq!(http_client.base_url(), fallback);
+        assert_eq!(endpoints.active(), fallback);
+    }
+
+    #[tokio::test]
+    async fn error_context_includes_endpoint() {
+        let primary = "http://127.0.0.1:1";
+        let http_client = HTTPClient::new(primary)
+            .await
+            .with_retry_policy(RetryPolicy::disabled());
+        let url = format!("{primary}/devices");
+        let mut request = http_client
+            .build_get_request(&url, Duration::from_secs(1), None)
+            .unwrap();
+        let error = http_client
+            .send(request.0, &mut request.1)
+            .await
+            .unwrap_err();
+        assert!(error.is_network_connection_error());
+        assert!(error.to_string().contains(&format!("endpoint: {primary}")));
+    }
+}
+
+pub mod status {
+    use super::*;
+
+    #[test]
+    fn to_sdk() {
+        let status = endpoint_status_to_sdk(EndpointStatus {
+            active: SECONDARY.to_string(),
+            primary: PRIMARY.to_string(),
+            endpoints: vec![PRIMARY.to_string(), SECONDARY.to_string()],
+            fail_back_in: Some(Duration::from_millis(1500)),
+        });
+        assert_eq!(status.active, SECONDARY);
+        assert_eq!(status.primary, PRIMARY);
+        assert_eq!(status.endpoints, vec![PRIMARY, SECONDARY]);
+        assert_eq!(status.fail_back_in_ms, Some(1500));
+    }
+}
@@ -0,0 +1,5 @@
+pub mod failover;
+pub mod options;
+pub mod pinning;
+pub mod proxy;
+pub mod tls;
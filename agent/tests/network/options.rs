@@ -0,0 +1,60 @@
+// internal crates
+use miru_agent::filesys::dir::Dir;
+use miru_agent::network::{errors::NetworkErr, options::NetworkOptions, proxy::ProxyOptions};
+use miru_agent::storage::settings::{self, Settings};
+
+use crate::network::tls::{gen_cert, write_file};
+
+pub mod load {
+    use super::*;
+
+    #[tokio::test]
+    async fn defaults() {
+        let options = NetworkOptions::load(&Settings::default()).await.unwrap();
+        assert!(options.is_default());
+        assert_eq!(options, NetworkOptions::default());
+    }
+
+    #[tokio::test]
+    async fn proxy_and_tls() {
+        let dir = Dir::create_temp_dir("network").await.unwrap();
+        let (ca, _) = gen_cert("ca");
+        let ca_file = write_file(&dir, "ca.pem", &ca).await;
+
+        let settings = Settings {
+            proxy: settings::Proxy {
+                url: Some("http://proxy.example.com:3128".to_string()),
+                no_proxy: vec!["localhost".to_string()],
+            },
+            tls: settings::TLS {
+                ca_cert_files: vec![ca_file.to_string()],
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let options = NetworkOptions::load(&settings).await.unwrap();
+        assert!(!options.is_default());
+        assert_eq!(
+            options.proxy,
+            Some(ProxyOptions {
+                url: "http://proxy.example.com:3128".to_string(),
+                no_proxy: vec!["localhost".to_string()],
+            })
+        );
+        assert_eq!(options.tls.ca_certs, vec![ca]);
+    }
+
+    #[tokio::test]
+    async fn invalid_tls() {
+        let dir = Dir::create_temp_dir("network").await.unwrap();
+        let settings = Settings {
+            tls: settings::TLS {
+                ca_cert_files: vec![dir.file("missing.pem").to_string()],
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let error = NetworkOptions::load(&settings).await.unwrap_err();
+        assert!(matches!(error, NetworkErr::FileSysErr(_)));
+    }
+}
@@ -0,0 +1,477 @@
+// standard library
+use std::io::{self, Read, Write};
+use std::sync::Arc;
+use std::time::Duration;
+
+// internal crates
+use crate::network::tls::write_file;
+use miru_agent::errors::MiruError;
+use miru_agent::filesys::dir::Dir;
+use miru_agent::http::client::HTTPClient;
+use miru_agent::http::errors::HTTPErr;
+use miru_agent::mqtt::client::{poll, EventLoop};
+use miru_agent::mqtt::errors::MQTTError;
+use miru_agent::network::errors::NetworkErr;
+use miru_agent::network::options::NetworkOptions;
+use miru_agent::network::pinning::{find_pin_mismatch, Pin, PinMismatch, PinSet};
+use miru_agent::network::tls::TLSOptions;
+use miru_agent::storage::settings;
+
+// external crates
+use openssl::asn1::Asn1Time;
+use openssl::hash::MessageDigest;
+use openssl::pkey::{PKey, Private};
+use openssl::rsa::Rsa;
+use openssl::ssl::{SslAcceptor, SslMethod};
+use openssl::x509::extension::{BasicConstraints, KeyUsage, SubjectAlternativeName};
+use openssl::x509::{X509NameBuilder, X509};
+use rumqttc::tokio_rustls::rustls::pki_types::ServerName;
+use rumqttc::{AsyncClient, MqttOptions, TlsConfiguration, Transport};
+use rustls::pki_types::{CertificateDer, TrustAnchor, UnixTime};
+
+const BACKUP_PIN: &str = "sha256/AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA=";
+const OTHER_PIN: &str = "sha256/AQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQE=";
+
+// generates a self-signed certificate for localhost and its private key
+fn gen_server_cert() -> (Vec<u8>, Vec<u8>) {
+    let key = gen_key();
+    let cert = gen_cert("localhost", &key, None, false);
+    (
+        cert.to_pem().unwrap(),
+        key.private_key_to_pem_pkcs8().unwrap(),
+    )
+}
+
+// generates a certificate authority and its private key
+fn gen_ca(name: &str) -> (X509, PKey<Private>) {
+    let key = gen_key();
+    (gen_cert(name, &key, None, true), key)
+}
+
+// generates a certificate for localhost issued by the certificate authority
+fn gen_issued_cert(issuer: &(X509, PKey<Private>)) -> (Vec<u8>, Vec<u8>) {
+    let key = gen_key();
+    let cert = gen_cert("localhost", &key, Some(issuer), false);
+    (
+        cert.to_pem().unwrap(),
+        key.private_key_to_pem_pkcs8().unwrap(),
+    )
+}
+
+fn gen_key() -> PKey<Private> {
+    PKey::from_rsa(Rsa::generate(2048).unwrap()).unwrap()
+}
+
+// self-signed unless an issuer is given
+fn gen_cert(
+    common_name: &str,
+    key: &PKey<Private>,
+    issuer: Option<&(X509, PKey<Private>)>,
+    is_ca: bool,
+) -> X509 {
+    let mut name = X509NameBuilder::new().unwrap();
+    name.append_entry_by_text("CN", common_name).unwrap();
+    let name = name.build();
+
+    let mut builder = X509::builder().unwrap();
+    builder.set_version(2).unwrap();
+    builder.set_subject_name(&name).unwrap();
+    match issuer {
+        Some((issuer, _)) => builder.set_issuer_name(issuer.subject_name()).unwrap(),
+        None => builder.set_issuer_name(&name).unwrap(),
+    }
+    builder.set_pubkey(key).unwrap();
+    builder
+        .set_not_before(&Asn1Time::days_from_now(0).unwrap())
+        .unwrap();
+    builder
+        .set_not_after(&Asn1Time::days_from_now(1).unwrap())
+        .unwrap();
+    if is_ca {
+        let constraints = BasicConstraints::new().critical().ca().build().unwrap();
+        builder.append_extension(constraints).unwrap();
+        let usage = KeyUsage::new().critical().key_cert_sign().build().unwrap();
+        builder.append_extension(usage).unwrap();
+    } else {
+        let san = SubjectAlternativeName::new()
+            .dns("localhost")
+            .build(&builder.x509v3_context(issuer.map(|(cert, _)| cert.as_ref()), None))
+            .unwrap();
+        builder.append_extension(san).unwrap();
+    }
+    let signing_key = issuer.map(|(_, key)| key).unwrap_or(key);
+    builder.sign(signing_key, MessageDigest::sha256()).unwrap();
+    builder.build()
+}
+
+fn to_der(cert_pem: &[u8]) -> CertificateDer<'static> {
+    CertificateDer::from(X509::from_pem(cert_pem).unwrap().to_der().unwrap())
+}
+
+fn trust_anchors(roots: &[&X509]) -> Vec<TrustAnchor<'static>> {
+    let mut store = rustls::RootCertStore::empty();
+    for root in roots {
+        store
+            .add(CertificateDer::from(root.to_der().unwrap()))
+            .unwrap();
+    }
+    store.roots
+}
+
+// computes the pin with openssl's command line equivalent of
+// `openssl x509 -pubkey | openssl pkey -pubin -outform der | openssl dgst -sha256`
+fn pin_of(cert_pem: &[u8]) -> String {
+    let spki = X509::from_pem(cert_pem)
+        .unwrap()
+        .public_key()
+        .unwrap()
+        .public_key_to_der()
+        .unwrap();
+    let digest = openssl::sha::sha256(&spki);
+    format!("sha256/{}", openssl::base64::encode_block(&digest))
+}
+
+// a TLS server answering every request with an empty json object
+fn serve_tls(cert_pem: &[u8], key_pem: &[u8]) -> u16 {
+    serve_tls_with_chain(cert_pem, key_pem, &[])
+}
+
+// also presents the extra certificates after its own
+fn serve_tls_with_chain(cert_pem: &[u8], key_pem: &[u8], chain: &[&X509]) -> u16 {
+    let mut acceptor = SslAcceptor::mozilla_intermediate_v5(SslMethod::tls()).unwrap();
+    acceptor
+        .set_certificate(&X509::from_pem(cert_pem).unwrap())
+        .unwrap();
+    for cert in chain {
+        acceptor.add_extra_chain_cert((*cert).clone()).unwrap();
+    }
+    acceptor
+        .set_private_key(&PKey::private_key_from_pem(key_pem).unwrap())
+        .unwrap();
+    let acceptor = Arc::new(acceptor.build());
+
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    std::thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let acceptor = acceptor.clone();
+            std::thread::spawn(move || {
+                let Ok(mut stream) = acceptor.accept(stream) else {
+                    return;
+                };
+                let mut buf = [0; 4096];
+                let _ = stream.read(&mut buf);
+                let _ = stream.write_all(
+                    b"HTTP/1.1 200 OK\r\ncontent-type: application/json\r\n\
+                      content-length: 2\r\nconnection: close\r\n\r\n{}",
+                );
+                let _ = stream.shutdown();
+            });
+        }
+    });
+    port
+}
+
+async fn tls_options(
+    cert_pem: &[u8],
+    backend_pins: &[&str],
+    mqtt_broker_pins: &[&str],
+) -> TLSOptions {
+    let dir = Dir::create_temp_dir("pinning").await.unwrap();
+    let ca_file = write_file(&dir, "ca.pem", cert_pem).await;
+    let settings = settings::TLS {
+        ca_cert_files: vec![ca_file.to_string()],
+        backend_pins: backend_pins.iter().map(|pin| pin.to_string()).collect(),
+        mqtt_broker_pins: mqtt_broker_pins.iter().map(|pin| pin.to_string()).collect(),
+        ..Default::default()
+    };
+    TLSOptions::load(&settings).await.unwrap()
+}
+
+fn pins(pins: &[&str]) -> Vec<String> {
+    pins.iter().map(|pin| pin.to_string()).collect()
+}
+
+pub mod pin {
+    use super::*;
+
+    #[test]
+    fn parse_and_display() {
+        let pin = Pin::parse(BACKUP_PIN).unwrap();
+        assert_eq!(pin.to_string(), BACKUP_PIN);
+        assert_eq!(
+            Pin::parse(&format!(" {OTHER_PIN} ")).unwrap().to_string(),
+            OTHER_PIN
+        );
+    }
+
+    #[test]
+    fn parse_invalid() {
+        // missing prefix
+        assert!(Pin::parse("AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA=").is_none());
+        // not base64
+        assert!(Pin::parse("sha256/not base64").is_none());
+        // not a sha256 digest
+        assert!(Pin::parse("sha256/AAAA").is_none());
+    }
+
+    #[test]
+    fn from_cert_der() {
+        let (cert, _) = gen_server_cert();
+        let der = X509::from_pem(&cert).unwrap().to_der().unwrap();
+        let pin = Pin::from_cert_der(&der).unwrap();
+        assert_eq!(pin.to_string(), pin_of(&cert));
+
+        assert!(Pin::from_cert_der(b"not a certificate").is_none());
+    }
+}
+
+pub mod pin_set {
+    use super::*;
+
+    #[test]
+    fn disabled() {
+        assert_eq!(PinSet::new("backend", &[]).unwrap(), None);
+    }
+
+    #[test]
+    fn requires_backup_pin() {
+        let error = PinSet::new("backend", &pins(&[BACKUP_PIN])).unwrap_err();
+        assert!(matches!(error, NetworkErr::InvalidPinsErr(_)));
+
+        // duplicates don't count as a backup
+        let error = PinSet::new("backend", &pins(&[BACKUP_PIN, BACKUP_PIN])).unwrap_err();
+        assert!(matches!(error, NetworkErr::InvalidPinsErr(_)));
+    }
+
+    #[test]
+    fn invalid_pin() {
+        let error = PinSet::new("mqtt broker", &pins(&[BACKUP_PIN, "md5/abc"])).unwrap_err();
+        assert!(error.to_string().contains("md5/abc"));
+        assert!(error.to_string().contains("mqtt broker"));
+    }
+
+    #[test]
+    fn check() {
+        let ca = gen_ca("ca");
+        let (leaf, _) = gen_issued_cert(&ca);
+        let leaf_pin = Pin::parse(&pin_of(&leaf)).unwrap();
+        let leaf = to_der(&leaf);
+        let ca_pin = pin_of(&ca.0.to_pem().unwrap());
+        let roots = trust_anchors(&[&ca.0]);
+        let now = UnixTime::now();
+
+        // pinning an issuer's key matches any certificate it issues
+        let pin_set = PinSet::new("backend", &pins(&[&ca_pin, BACKUP_PIN]))
+            .unwrap()
+            .unwrap();
+        assert_eq!(pin_set.endpoint(), "backend");
+        assert_eq!(pin_set.pins().len(), 2);
+        pin_set.check(&leaf, &[], &roots, now).unwrap();
+
+        let pin_set = PinSet::new("backend", &pins(&[BACKUP_PIN, OTHER_PIN]))
+            .unwrap()
+            .unwrap();
+        let mismatch = pin_set.check(&leaf, &[], &roots, now).unwrap_err();
+        assert_eq!(mismatch.endpoint, "backend");
+        assert_eq!(
+            mismatch.presented,
+            vec![leaf_pin, Pin::parse(&ca_pin).unwrap()]
+        );
+        assert!(mismatch.to_string().contains(&leaf_pin.to_string()));
+    }
+
+    #[test]
+    fn check_intermediate() {
+        let root = gen_ca("root");
+        let intermediate_key = gen_key();
+        let intermediate = (
+            gen_cert("intermediate", &intermediate_key, Some(&root), true),
+            intermediate_key,
+        );
+        let (leaf, _) = gen_issued_cert(&intermediate);
+        let intermediate_pem = intermediate.0.to_pem().unwrap();
+        let roots = trust_anchors(&[&root.0]);
+
+        let pin_set = PinSet::new("backend", &pins(&[&pin_of(&intermediate_pem), BACKUP_PIN]))
+            .unwrap()
+            .unwrap();
+        pin_set
+            .check(
+                &to_der(&leaf),
+                &[to_der(&intermediate_pem)],
+                &roots,
+                UnixTime::now(),
+            )
+            .unwrap();
+    }
+
+    #[test]
+    fn ignores_certificates_outside_the_verified_path() {
+        // an interceptor whose certificate authority is trusted attaches a copy of the
+        // pinned certificate authority's certificate to its own chain
+        let pinned = gen_ca("pinned");
+        let interceptor = gen_ca("interceptor");
+        let (leaf, _) = gen_issued_cert(&interceptor);
+        let pinned_pem = pinned.0.to_pem().unwrap();
+        let roots = trust_anchors(&[&pinned.0, &interceptor.0]);
+
+        let pin_set = PinSet::new("backend", &pins(&[&pin_of(&pinned_pem), BACKUP_PIN]))
+            .unwrap()
+            .unwrap();
+        let mismatch = pin_set
+            .check(
+                &to_der(&leaf),
+                &[to_der(&pinned_pem)],
+                &roots,
+                UnixTime::now(),
+            )
+            .unwrap_err();
+        assert!(!mismatch
+            .presented
+            .contains(&Pin::parse(&pin_of(&pinned_pem)).unwrap()));
+    }
+
+    #[tokio::test]
+    async fn loaded_from_settings() {
+        let (cert, _) = gen_server_cert();
+        let options = tls_options(&cert, &[BACKUP_PIN, OTHER_PIN], &[]).await;
+        assert!(!options.is_default());
+        assert_eq!(options.backend_pins.unwrap().pins().len(), 2);
+        assert!(options.mqtt_broker_pins.is_none());
+    }
+}
+
+pub mod find_pin_mismatch {
+    use super::*;
+
+    #[test]
+    fn wrapped() {
+        let mismatch = PinMismatch {
+            endpoint: "backend".to_string(),
+            presented: vec![],
+        };
+        let error = rustls::Error::InvalidCertificate(rustls::CertificateError::Other(
+            rustls::OtherError(Arc::new(mismatch.clone())),
+        ));
+        let error = io::Error::new(io::ErrorKind::InvalidData, error);
+        assert_eq!(find_pin_mismatch(&error), Some(&mismatch));
+
+        let error = io::Error::new(io::ErrorKind::InvalidData, "handshake failed");
+        assert_eq!(find_pin_mismatch(&error), None);
+    }
+}
+
+pub mod http_client {
+    use super::*;
+
+    async fn get(tls: TLSOptions, port: u16) -> (HTTPClient, Result<String, HTTPErr>, u32) {
+        let network = NetworkOptions { proxy: None, tls };
+        let base_url = format!("https://localhost:{port}");
+        let client = HTTPClient::new_with_network(&base_url, &network)
+            .await
+            .unwrap();
+        let (request, mut context) = client
+            .build_get_request(&base_url, Duration::from_secs(5), None)
+            .unwrap();
+        let result = match client.send(request, &mut context).await {
+            Ok(response) => client.handle_response(response, &context).await,
+            Err(e) => Err(e),
+        };
+        (client, result, context.attempts)
+    }
+
+    #[tokio::test]
+    async fn matching_pin() {
+        let (cert, key) = gen_server_cert();
+        let port = serve_tls(&cert, &key);
+        let tls = tls_options(&cert, &[BACKUP_PIN, &pin_of(&cert)], &[]).await;
+
+        let (_, result, _) = get(tls, port).await;
+        assert_eq!(result.unwrap(), "{}");
+    }
+
+    #[tokio::test]
+    async fn mismatched_pin() {
+        let (cert, key) = gen_server_cert();
+        let port = serve_tls(&cert, &key);
+        // the certificate is trusted but its key isn't pinned
+        let tls = tls_options(&cert, &[BACKUP_PIN, OTHER_PIN], &[]).await;
+
+        let (client, result, attempts) = get(tls, port).await;
+        match result.unwrap_err() {
+            HTTPErr::PinMismatchErr(e) => {
+                assert_eq!(e.code().as_str(), "tls_pin_mismatch");
+                assert!(!e.is_network_connection_error());
+                assert_eq!(e.mismatch.endpoint, "backend");
+                assert_eq!(e.mismatch.presented[0].to_string(), pin_of(&cert));
+            }
+            e => panic!("unexpected error: {e:?}"),
+        }
+        // never retried but counted against the circuit breaker
+        assert_eq!(attempts, 1);
+        assert_eq!(client.circuit_breaker().status().consecutive_failures, 1);
+    }
+
+    #[tokio::test]
+    async fn intercepted_with_pinned_certificate_attached() {
+        let pinned = gen_ca("pinned");
+        let interceptor = gen_ca("interceptor");
+        let (cert, key) = gen_issued_cert(&interceptor);
+        let port = serve_tls_with_chain(&cert, &key, &[&pinned.0]);
+        // both certificate authorities are trusted but only one is pinned
+        let pinned_pem = pinned.0.to_pem().unwrap();
+        let ca_pems = [pinned_pem.clone(), interceptor.0.to_pem().unwrap()].concat();
+        let tls = tls_options(&ca_pems, &[&pin_of(&pinned_pem), BACKUP_PIN], &[]).await;
+
+        let (_, result, _) = get(tls, port).await;
+        match result.unwrap_err() {
+            HTTPErr::PinMismatchErr(e) => {
+                assert_eq!(e.mismatch.presented[0].to_string(), pin_of(&cert));
+            }
+            e => panic!("unexpected error: {e:?}"),
+        }
+    }
+}
+
+pub mod mqtt_client {
+    use super::*;
+
+    #[tokio::test]
+    async fn matching_pin() {
+        let (cert, key) = gen_server_cert();
+        let port = serve_tls(&cert, &key);
+        let tls = tls_options(&cert, &[], &[&pin_of(&cert), BACKUP_PIN]).await;
+
+        let connector =
+            rumqttc::tokio_rustls::TlsConnector::from(tls.rustls_client_config().unwrap());
+        let stream = tokio::net::TcpStream::connect(("127.0.0.1", port))
+            .await
+            .unwrap();
+        let domain = ServerName::try_from("localhost").unwrap().to_owned();
+        connector.connect(domain, stream).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn mismatched_pin() {
+        let (cert, key) = gen_server_cert();
+        let port = serve_tls(&cert, &key);
+        let tls = tls_options(&cert, &[], &[BACKUP_PIN, OTHER_PIN]).await;
+
+        let mut options = MqttOptions::new("device", "localhost", port);
+        options.set_transport(Transport::Tls(TlsConfiguration::Rustls(
+            tls.rustls_client_config().unwrap(),
+        )));
+        let (_client, eventloop) = AsyncClient::new(options, 10);
+        let mut eventloop = EventLoop::V3(Box::new(eventloop));
+
+        match poll(&mut eventloop).await.unwrap_err() {
+            MQTTError::PinMismatchErr(e) => {
+                assert_eq!(e.code().as_str(), "tls_pin_mismatch");
+                assert!(!e.is_network_connection_error());
+                assert_eq!(e.mismatch.endpoint, "mqtt broker");
+            }
+            e => panic!("unexpected error: {e:?}"),
+        }
+    }
+}
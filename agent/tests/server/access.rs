@@ -0,0 +1,441 @@
+// standard library
+use std::sync::Arc;
+
+// internal crates
+use miru_agent::errors::{Code, MiruError};
+use miru_agent::server::access::{self, AccessPolicy, AccessRule, PeerCredentials};
+use miru_agent::server::tokens::{APITokens, TokenScope};
+
+// external crates
+use axum::{
+    body::Body,
+    extract::ConnectInfo,
+    http::{header, Method, Request, StatusCode},
+    routing::{get, post},
+    Router,
+};
+use tower::ServiceExt;
+
+fn peer(uid: u32, gid: u32) -> PeerCredentials {
+    PeerCredentials {
+        uid,
+        gid,
+        pid: Some(4242),
+    }
+}
+
+fn rule(endpoints: &[&str], uids: &[u32], gids: &[u32]) -> AccessRule {
+    AccessRule {
+        endpoints: endpoints.iter().map(|e| e.to_string()).collect(),
+        uids: uids.to_vec(),
+        gids: gids.to_vec(),
+        config_type_slugs: None,
+    }
+}
+
+pub mod policy {
+    use super::*;
+
+    #[test]
+    fn empty_policy_allows_everyone() {
+        let policy = AccessPolicy::new(vec![]);
+        assert!(policy.is_authorized(&peer(1000, 1000), "POST", "/v1/device/sync", None));
+        assert!(policy.is_authorized(&PeerCredentials::unknown(), "GET", "/v1/health", None));
+    }
+
+    #[test]
+    fn default_policy_limits_sensitive_endpoints_to_root() {
+        let policy = AccessPolicy::default();
+        assert!(policy.is_authorized(&peer(0, 0), "POST", "/v1/device/sync", None));
+        assert!(!policy.is_authorized(&peer(1000, 1000), "POST", "/v1/device/sync", None));
+        assert!(policy.is_authorized(&peer(0, 0), "GET", "/v1/device/export", None));
+        assert!(!policy.is_authorized(&peer(1000, 1000), "GET", "/v1/device/export", None));
+        assert!(!policy.is_authorized(&peer(1000, 1000), "GET", "/v1/diagnostics", None));
+        assert!(policy.is_authorized(&peer(1000, 1000), "GET", "/v1/device", None));
+        assert!(policy.is_authorized(
+            &peer(1000, 1000),
+            "POST",
+            "/v1/config_schemas/validate",
+            None
+        ));
+    }
+
+    #[test]
+    fn root_bypasses_rules() {
+        let policy = AccessPolicy::new(vec![rule(&["*"], &[], &[])]);
+        assert!(policy.is_authorized(&peer(0, 0), "POST", "/v1/device/sync", None));
+        assert!(!policy.is_authorized(&peer(1000, 1000), "POST", "/v1/device/sync", None));
+    }
+
+    #[test]
+    fn endpoints_without_rules_are_open() {
+        let policy = AccessPolicy::new(vec![rule(&["POST /v1/device/sync"], &[], &[])]);
+        assert!(!policy.is_authorized(&peer(1000, 1000), "POST", "/v1/device/sync", None));
+        assert!(policy.is_authorized(&peer(1000, 1000), "GET", "/v1/device", None));
+        assert!(policy.is_authorized(&peer(1000, 1000), "GET", "/v1/health", None));
+    }
+
+    #[test]
+    fn config_type_restriction_closes_endpoints_without_rules() {
+        let mut rules = access::default_rules();
+        rules.push(AccessRule {
+            config_type_slugs: Some(vec!["motion-control".to_string()]),
+            ..rule(&["GET /v1/config_instances/deployed"], &[1001], &[])
+        });
+        let policy = AccessPolicy::new(rules);
+        let app_user = peer(1001, 1001);
+        let slug = Some("motion-control");
+        assert!(policy.is_authorized(&app_user, "GET", "/v1/config_instances/deployed", slug));
+        // listing config instances would expose every config type
+        assert!(!policy.is_authorized(&app_user, "GET", "/v1/config_instances", slug));
+        assert!(!policy.is_authorized(&peer(1002, 1002), "GET", "/v1/config_instances", None));
+        assert!(!policy.is_authorized(&app_user, "GET", "/v1/device/export", None));
+        assert!(!policy.is_authorized(&app_user, "GET", "/v1/health", None));
+        assert!(policy.is_authorized(&peer(0, 0), "GET", "/v1/config_instances", None));
+    }
+
+    #[test]
+    fn uid_or_gid_grants_access() {
+        let policy = AccessPolicy::new(vec![rule(&["/v1/device/*"], &[1001], &[2000])]);
+        assert!(policy.is_authorized(&peer(1001, 1001), "GET", "/v1/device/export", None));
+        assert!(policy.is_authorized(&peer(1002, 2000), "GET", "/v1/device/export", None));
+        assert!(!policy.is_authorized(&peer(1002, 1002), "GET", "/v1/device/export", None));
+    }
+
+    #[test]
+    fn any_matching_rule_grants_access() {
+        let policy = AccessPolicy::new(vec![
+            rule(&["*"], &[1001], &[]),
+            rule(&["GET /v1/metrics"], &[1002], &[]),
+        ]);
+        assert!(policy.is_authorized(&peer(1002, 1002), "GET", "/v1/metrics", None));
+        assert!(!policy.is_authorized(&peer(1002, 1002), "GET", "/v1/device", None));
+        assert!(policy.is_authorized(&peer(1001, 1001), "GET", "/v1/device", None));
+    }
+
+    #[test]
+    fn method_patterns() {
+        let policy = AccessPolicy::new(vec![rule(&["post /v1/device/sync/"], &[], &[])]);
+        let rule = &policy.rules[0];
+        assert!(rule.applies_to("POST", "/v1/device/sync"));
+        assert!(!rule.applies_to("GET", "/v1/device/sync"));
+        assert!(!rule.applies_to("POST", "/v1/device/sync/now"));
+
+        let rule = super::rule(&["/v1/config_instances*"], &[], &[]);
+        assert!(rule.applies_to("GET", "/v1/config_instances"));
+        assert!(rule.applies_to("GET", "/v1/config_instances/deployed"));
+        assert!(!rule.applies_to("GET", "/v1/config_schemas/hash/serialized"));
+    }
+
+    #[test]
+    fn config_type_slug_restriction() {
+        let policy = AccessPolicy::new(vec![AccessRule {
+            config_type_slugs: Some(vec!["motion-control".to_string()]),
+            ..rule(&["GET /v1/config_instances/deployed"], &[1001], &[])
+        }]);
+        let path = "/v1/config_instances/deployed";
+        let app_user = peer(1001, 1001);
+        assert!(policy.is_authorized(&app_user, "GET", path, Some("motion-control")));
+        assert!(!policy.is_authorized(&app_user, "GET", path, Some("navigation")));
+        assert!(!policy.is_authorized(&app_user, "GET", path, None));
+    }
+
+    #[test]
+    fn config_type_slug_restriction_ignores_unscoped_endpoints() {
+        let policy = AccessPolicy::new(vec![AccessRule {
+            config_type_slugs: Some(vec!["motion-control".to_string()]),
+            ..rule(&["/v1/config_instances*"], &[1001], &[])
+        }]);
+        let app_user = peer(1001, 1001);
+        let slug = Some("motion-control");
+        assert!(policy.is_authorized(&app_user, "GET", "/v1/config_instances/deployed", slug));
+        // listing or getting config instances by id isn't scoped to the config type
+        assert!(!policy.is_authorized(&app_user, "GET", "/v1/config_instances", slug));
+        assert!(!policy.is_authorized(&app_user, "GET", "/v1/config_instances/cfg_inst_1", slug));
+    }
+
+    #[test]
+    fn denied_error() {
+        let policy = AccessPolicy::new(vec![rule(&["POST /v1/device/sync"], &[], &[])]);
+        let error = policy
+            .authorize(&peer(1000, 1001), "POST", "/v1/device/sync", None)
+            .unwrap_err();
+        assert_eq!(error.code().as_str(), Code::Forbidden.as_str());
+        assert_eq!(error.http_status(), StatusCode::FORBIDDEN);
+        let params = error.params().unwrap();
+        assert_eq!(params["uid"], 1000);
+        assert_eq!(params["gid"], 1001);
+        assert_eq!(params["pid"], 4242);
+        assert_eq!(params["path"], "/v1/device/sync");
+    }
+}
+
+pub mod middleware {
+    use super::*;
+
+    fn app(policy: AccessPolicy) -> Router {
+        Router::new()
+            .route("/v1/health", get(|| async { "ok" }))
+            .route("/v1/device/sync", post(|| async { "synced" }))
+            .route("/v1/device/export", get(|| async { "export" }))
+            .route("/v1/config_instances/deployed", get(|| async { "config" }))
+            .route("/v1/config_instances", get(|| async { "configs" }))
+            .route(
+                "/v1/config_instances/{config_instance_id}",
+                get(|| async { "config" }),
+            )
+            .layer(axum::middleware::from_fn_with_state(
+                Arc::new(policy),
+                access::authorize,
+            ))
+    }
+
+    fn request(method: &str, uri: &str, peer: Option<PeerCredentials>) -> Request<Body> {
+        let mut request = Request::builder()
+            .method(method)
+            .uri(uri)
+            .body(Body::empty())
+            .unwrap();
+        if let Some(peer) = peer {
+            request.extensions_mut().insert(ConnectInfo(peer));
+        }
+        request
+    }
+
+    async fn body_json(response: axum::response::Response) -> serde_json::Value {
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        serde_json::from_slice(&bytes).unwrap()
+    }
+
+    #[tokio::test]
+    async fn allowed() {
+        let app = app(AccessPolicy::new(vec![rule(
+            &["POST /v1/device/sync"],
+            &[],
+            &[],
+        )]));
+        let response = app
+            .clone()
+            .oneshot(request("POST", "/v1/device/sync", Some(peer(0, 0))))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = app
+            .oneshot(request("GET", "/v1/health", Some(peer(1000, 1000))))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn denied() {
+        let app = app(AccessPolicy::new(vec![rule(
+            &["POST /v1/device/sync"],
+            &[],
+            &[],
+        )]));
+        let response = app
+            .oneshot(request("POST", "/v1/device/sync", Some(peer(1000, 1000))))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        let body = body_json(response).await;
+        assert_eq!(body["error"]["code"], "forbidden");
+        assert_eq!(body["error"]["params"]["uid"], 1000);
+        assert_eq!(body["error"]["params"]["method"], "POST");
+    }
+
+    #[tokio::test]
+    async fn default_policy() {
+        let app = app(AccessPolicy::default());
+        let response = app
+            .clone()
+            .oneshot(request("GET", "/v1/device/export", Some(peer(1000, 1000))))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+        let response = app
+            .clone()
+            .oneshot(request("GET", "/v1/device/export", Some(peer(0, 0))))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = app
+            .oneshot(request(
+                "GET",
+                "/v1/config_instances",
+                Some(peer(1000, 1000)),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn config_type_slug_from_query() {
+        let app = app(AccessPolicy::new(vec![AccessRule {
+            config_type_slugs: Some(vec!["motion-control".to_string()]),
+            ..rule(&["/v1/config_instances/*"], &[1001], &[])
+        }]));
+        let response = app
+            .clone()
+            .oneshot(request(
+                "GET",
+                "/v1/config_instances/deployed?config_type_slug=motion-control&config_schema_digest=abc",
+                Some(peer(1001, 1001)),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = app
+            .oneshot(request(
+                "GET",
+                "/v1/config_instances/deployed?config_type_slug=navigation&config_schema_digest=abc",
+                Some(peer(1001, 1001)),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    }
+
+    #[tokio::test]
+    async fn config_type_slug_does_not_scope_other_endpoints() {
+        let app = app(AccessPolicy::new(vec![AccessRule {
+            config_type_slugs: Some(vec!["motion-control".to_string()]),
+            ..rule(&["/v1/config_instances*"], &[1001], &[])
+        }]));
+        for uri in [
+            "/v1/config_instances?config_type_slug=motion-control",
+            "/v1/config_instances/cfg_inst_1?config_type_slug=motion-control",
+        ] {
+            let response = app
+                .clone()
+                .oneshot(request("GET", uri, Some(peer(1001, 1001))))
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::FORBIDDEN, "{uri}");
+        }
+    }
+
+    #[tokio::test]
+    async fn unknown_peer_is_denied_by_rules() {
+        let app = app(AccessPolicy::new(vec![rule(&["*"], &[1000], &[])]));
+        let response = app
+            .oneshot(request("GET", "/v1/health", None))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    }
+}
+
+pub mod bearer {
+    use super::*;
+
+    #[test]
+    fn read_only_requests() {
+        assert!(access::is_read_only_request(&Method::GET, "/v1/device"));
+        assert!(access::is_read_only_request(
+            &Method::POST,
+            "/v1/config_schemas/hash/serialized"
+        ));
+        assert!(access::is_read_only_request(
+            &Method::POST,
+            "/v1/config_schemas/validate"
+        ));
+        assert!(!access::is_read_only_request(
+            &Method::POST,
+            "/v1/device/sync"
+        ));
+        assert!(!access::is_read_only_request(&Method::DELETE, "/v1/device"));
+    }
+
+    #[test]
+    fn authorize_token() {
+        let tokens = APITokens::generate();
+        let full = format!("Bearer {}", tokens.full);
+        let read_only = format!("Bearer {}", tokens.read_only);
+        let sync = "/v1/device/sync";
+
+        let scope = access::authorize_token(&tokens, Some(&full), &Method::POST, sync).unwrap();
+        assert_eq!(scope, TokenScope::Full);
+        let scope =
+            access::authorize_token(&tokens, Some(&read_only), &Method::GET, "/v1/device").unwrap();
+        assert_eq!(scope, TokenScope::ReadOnly);
+
+        let error =
+            access::authorize_token(&tokens, Some(&read_only), &Method::POST, sync).unwrap_err();
+        assert_eq!(error.http_status(), StatusCode::FORBIDDEN);
+
+        let error = access::authorize_token(&tokens, None, &Method::GET, sync).unwrap_err();
+        assert_eq!(error.http_status(), StatusCode::UNAUTHORIZED);
+        let error =
+            access::authorize_token(&tokens, Some(&tokens.full), &Method::GET, sync).unwrap_err();
+        assert_eq!(error.http_status(), StatusCode::UNAUTHORIZED);
+        let error = access::authorize_token(&tokens, Some("Bearer invalid"), &Method::GET, sync)
+            .unwrap_err();
+        assert_eq!(error.code().as_str(), Code::Unauthorized.as_str());
+    }
+
+    fn app(tokens: APITokens) -> Router {
+        Router::new()
+            .route("/v1/health", get(|| async { "ok" }))
+            .route("/v1/device/sync", post(|| async { "synced" }))
+            .route("/v1/device/export", get(|| async { "export" }))
+            .layer(axum::middleware::from_fn_with_state(
+                Arc::new(tokens),
+                access::authorize_bearer,
+            ))
+    }
+
+    fn request(method: &str, uri: &str, token: Option<&str>) -> Request<Body> {
+        let mut builder = Request::builder().method(method).uri(uri);
+        if let Some(token) = token {
+            builder = builder.header(header::AUTHORIZATION, format!("Bearer {token}"));
+        }
+        builder.body(Body::empty()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn middleware() {
+        let tokens = APITokens::generate();
+        let app = app(tokens.clone());
+
+        let response = app
+            .clone()
+            .oneshot(request("POST", "/v1/device/sync", Some(&tokens.full)))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = app
+            .clone()
+            .oneshot(request("GET", "/v1/health", Some(&tokens.read_only)))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = app
+            .clone()
+            .oneshot(request("POST", "/v1/device/sync", Some(&tokens.read_only)))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+        let response = app
+            .oneshot(request("GET", "/v1/health", None))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(body["error"]["code"], "unauthorized");
+    }
+}
@@ -0,0 +1,96 @@
+// internal crates
+use miru_agent::models::config_instance::{ActivityStatus, TargetStatus};
+use miru_agent::server::handlers::{
+    GetConfigInstanceQueryArgs, ListConfigInstancesQueryArgs, ValidateConfigQueryArgs,
+};
+use miru_agent::services::config_instances::list::ConfigInstanceFilters;
+
+// external crates
+use axum::extract::Query;
+use axum::http::Uri;
+
+fn parse<T: serde::de::DeserializeOwned>(uri: &str) -> Option<T> {
+    let uri: Uri = uri.parse().unwrap();
+    Query::<T>::try_from_uri(&uri).ok().map(|query| query.0)
+}
+
+pub mod list_config_instances_query {
+    use super::*;
+
+    #[test]
+    fn no_filters() {
+        let query = parse::<ListConfigInstancesQueryArgs>("/v1/config_instances").unwrap();
+        let filters = ConfigInstanceFilters::from(query);
+        assert!(filters.target_statuses.is_none());
+        assert!(filters.activity_statuses.is_none());
+        assert!(filters.error_statuses.is_none());
+        assert!(filters.config_type_ids.is_none());
+        assert!(filters.config_schema_ids.is_none());
+    }
+
+    #[test]
+    fn comma_separated_filters() {
+        let query = parse::<ListConfigInstancesQueryArgs>(
+            "/v1/config_instances?target_status=deployed,removed&activity_status=queued&config_type_id=cfg_type_1,%20cfg_type_2",
+        )
+        .unwrap();
+        let filters = ConfigInstanceFilters::from(query);
+        assert_eq!(
+            filters.target_statuses,
+            Some(vec![TargetStatus::Deployed, TargetStatus::Removed])
+        );
+        assert_eq!(
+            filters.activity_statuses,
+            Some(vec![ActivityStatus::Queued])
+        );
+        assert_eq!(
+            filters.config_type_ids,
+            Some(vec!["cfg_type_1".to_string(), "cfg_type_2".to_string()])
+        );
+        assert!(filters.error_statuses.is_none());
+    }
+
+    #[test]
+    fn unknown_status_is_rejected() {
+        assert!(parse::<ListConfigInstancesQueryArgs>(
+            "/v1/config_instances?target_status=deployed,exploded"
+        )
+        .is_none());
+    }
+}
+
+pub mod get_config_instance_query {
+    use super::*;
+
+    #[test]
+    fn include_content() {
+        let query = parse::<GetConfigInstanceQueryArgs>("/v1/config_instances/1").unwrap();
+        assert!(!query.include_content);
+        let query =
+            parse::<GetConfigInstanceQueryArgs>("/v1/config_instances/1?include_content=true")
+                .unwrap();
+        assert!(query.include_content);
+    }
+}
+
+pub mod validate_config_query {
+    use super::*;
+
+    #[test]
+    fn slug_and_digest() {
+        let query = parse::<ValidateConfigQueryArgs>(
+            "/v1/config_schemas/validate?config_type_slug=motion-control&config_schema_digest=sha256:123",
+        )
+        .unwrap();
+        assert_eq!(query.config_type_slug, "motion-control");
+        assert_eq!(query.config_schema_digest, "sha256:123");
+    }
+
+    #[test]
+    fn missing_digest() {
+        assert!(parse::<ValidateConfigQueryArgs>(
+            "/v1/config_schemas/validate?config_type_slug=motion-control"
+        )
+        .is_none());
+    }
+}
@@ -1 +1,3 @@
-
+pub mod access;
+pub mod handlers;
+pub mod tokens;
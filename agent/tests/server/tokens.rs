@@ -0,0 +1,81 @@
+// standard library
+use std::os::unix::fs::PermissionsExt;
+
+// internal crates
+use miru_agent::filesys::{dir::Dir, path::PathExt};
+use miru_agent::server::tokens::{APITokens, TokenScope};
+
+pub mod load_or_create {
+    use super::*;
+
+    #[tokio::test]
+    async fn creates_tokens_file() {
+        let dir = Dir::create_temp_dir("api_tokens").await.unwrap();
+        let file = dir.file("api_tokens.json");
+        assert!(!file.exists());
+
+        let tokens = APITokens::load_or_create(&file).await.unwrap();
+        assert!(file.exists());
+        assert_eq!(tokens.full.len(), 64);
+        assert_eq!(tokens.read_only.len(), 64);
+        assert_ne!(tokens.full, tokens.read_only);
+        let mode = file.permissions().await.unwrap().mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+
+    #[tokio::test]
+    async fn reuses_existing_tokens() {
+        let dir = Dir::create_temp_dir("api_tokens").await.unwrap();
+        let file = dir.file("api_tokens.json");
+        let tokens = APITokens::load_or_create(&file).await.unwrap();
+        file.set_permissions(0o644).await.unwrap();
+
+        let reloaded = APITokens::load_or_create(&file).await.unwrap();
+        assert_eq!(reloaded, tokens);
+        let mode = file.permissions().await.unwrap().mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+
+    #[tokio::test]
+    async fn regenerates_invalid_tokens() {
+        let dir = Dir::create_temp_dir("api_tokens").await.unwrap();
+        let file = dir.file("api_tokens.json");
+
+        file.write_string("invalid-json", true, false)
+            .await
+            .unwrap();
+        let tokens = APITokens::load_or_create(&file).await.unwrap();
+        assert_eq!(tokens.full.len(), 64);
+
+        let same = APITokens {
+            full: "token".to_string(),
+            read_only: "token".to_string(),
+        };
+        file.write_json(&same, true, false).await.unwrap();
+        let tokens = APITokens::load_or_create(&file).await.unwrap();
+        assert_ne!(tokens, same);
+        assert_eq!(file.read_json::<APITokens>().await.unwrap(), tokens);
+    }
+}
+
+pub mod scope {
+    use super::*;
+
+    #[test]
+    fn scopes() {
+        let tokens = APITokens::generate();
+        assert_eq!(tokens.scope(&tokens.full), Some(TokenScope::Full));
+        assert_eq!(tokens.scope(&tokens.read_only), Some(TokenScope::ReadOnly));
+        assert_eq!(tokens.scope(""), None);
+        assert_eq!(tokens.scope(&tokens.full[..63]), None);
+        assert_eq!(tokens.scope("not-a-token"), None);
+    }
+
+    #[test]
+    fn debug_redacts_tokens() {
+        let tokens = APITokens::generate();
+        let debug = format!("{tokens:?}");
+        assert!(!debug.contains(&tokens.full));
+        assert!(!debug.contains(&tokens.read_only));
+    }
+}
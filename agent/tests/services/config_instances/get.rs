@@ -0,0 +1,87 @@
+// internal crates
+use miru_agent::filesys::dir::Dir;
+use miru_agent::models::config_instance::ConfigInstance;
+use miru_agent::services::{config_instances::get, errors::ServiceErr};
+use miru_agent::storage::config_instances::{ConfigInstanceCache, ConfigInstanceContentCache};
+
+// external crates
+use serde_json::json;
+
+async fn create_caches(
+    dir: &Dir,
+    cfg_inst: &ConfigInstance,
+    content: serde_json::Value,
+) -> (ConfigInstanceCache, ConfigInstanceContentCache) {
+    let (cfg_inst_cache, _) = ConfigInstanceCache::spawn(32, dir.file("instances.json"), 1000)
+        .await
+        .unwrap();
+    cfg_inst_cache
+        .write(cfg_inst.id.clone(), cfg_inst.clone(), |_, _| false, true)
+        .await
+        .unwrap();
+    let (cfg_inst_content_cache, _) =
+        ConfigInstanceContentCache::spawn(32, dir.subdir("instances"), 1000)
+            .await
+            .unwrap();
+    cfg_inst_content_cache
+        .write(cfg_inst.id.clone(), content, |_, _| false, true)
+        .await
+        .unwrap();
+    (cfg_inst_cache, cfg_inst_content_cache)
+}
+
+pub mod success {
+    use super::*;
+
+    #[tokio::test]
+    async fn without_content() {
+        let dir = Dir::create_temp_dir("get_cfg_inst").await.unwrap();
+        let cfg_inst = ConfigInstance {
+            attempts: 2,
+            ..Default::default()
+        };
+        let (cfg_inst_cache, cfg_inst_content_cache) =
+            create_caches(&dir, &cfg_inst, json!({"speed": 4})).await;
+
+        let result = get::get(
+            &cfg_inst_cache,
+            &cfg_inst_content_cache,
+            &cfg_inst.id,
+            false,
+        )
+        .await
+        .unwrap();
+        assert_eq!(result, ConfigInstance::to_local_sdk(cfg_inst, None));
+        assert_eq!(result.attempts, 2);
+    }
+
+    #[tokio::test]
+    async fn with_content() {
+        let dir = Dir::create_temp_dir("get_cfg_inst").await.unwrap();
+        let cfg_inst = ConfigInstance::default();
+        let (cfg_inst_cache, cfg_inst_content_cache) =
+            create_caches(&dir, &cfg_inst, json!({"speed": 4})).await;
+
+        let result = get::get(&cfg_inst_cache, &cfg_inst_content_cache, &cfg_inst.id, true)
+            .await
+            .unwrap();
+        assert_eq!(result.content, Some(json!({"speed": 4})));
+    }
+}
+
+pub mod errors {
+    use super::*;
+
+    #[tokio::test]
+    async fn config_instance_not_found() {
+        let dir = Dir::create_temp_dir("get_cfg_inst").await.unwrap();
+        let (cfg_inst_cache, cfg_inst_content_cache) =
+            create_caches(&dir, &ConfigInstance::default(), json!({})).await;
+
+        let result = get::get(&cfg_inst_cache, &cfg_inst_content_cache, "unknown", true).await;
+        assert!(matches!(
+            result,
+            Err(ServiceErr::ConfigInstanceNotFound(ref e)) if e.config_instance_id == "unknown"
+        ));
+    }
+}
@@ -61,8 +61,10 @@ pub async fn create_syncer(dir: &Dir, http_client: Arc<MockClient>) -> (Syncer,
             token_mngr: Arc::new(token_mngr),
             cfg_inst_cache: Arc::new(cfg_inst_cache),
             cfg_inst_content_cache: Arc::new(cfg_inst_content_cache),
+            cfg_schema_content_cache: None,
             deployment_dir: dir.subdir("syncer"),
             fsm_settings: fsm::Settings::default(),
+            event_queue: None,
             cooldown_options: CooldownOptions::default(),
             agent_version: device.agent_version.clone(),
         },
@@ -369,4 +371,83 @@ pub mod success {
 
         assert_eq!(deployed_inst.id, cfg_inst_id);
     }
+
+    #[tokio::test]
+    async fn circuit_open_returns_cached() {
+        let cfg_sch_id = "cfg-sch-id".to_string();
+        let cfg_sch_digest = "cfg-schema-digest".to_string();
+        let cfg_type_slug = "cfg-type-slug".to_string();
+        let cfg_sch = ConfigSchema {
+            id: cfg_sch_id.clone(),
+            digest: cfg_sch_digest.clone(),
+            config_type_slug: Some(cfg_type_slug.clone()),
+            ..Default::default()
+        };
+        let cfg_inst_id = "cfg-inst-id".to_string();
+        let cfg_inst = ConfigInstance {
+            id: cfg_inst_id.clone(),
+            target_status: TargetStatus::Deployed,
+            activity_status: ActivityStatus::Deployed,
+            config_schema_id: cfg_sch_id.clone(),
+            ..Default::default()
+        };
+
+        // create the caches
+        let dir = Dir::create_temp_dir("get_deployed").await.unwrap();
+        let (cfg_inst_cache, _) = ConfigInstanceCache::spawn(32, dir.file("instances.json"), 1000)
+            .await
+            .unwrap();
+        cfg_inst_cache
+            .write(cfg_inst_id.clone(), cfg_inst.clone(), |_, _| false, true)
+            .await
+            .unwrap();
+        let (cfg_inst_content_cache, _) =
+            ConfigInstanceContentCache::spawn(32, dir.subdir("instances"), 1000)
+                .await
+                .unwrap();
+        cfg_inst_content_cache
+            .write(cfg_inst_id.clone(), json!({"speed": 4}), |_, _| false, true)
+            .await
+            .unwrap();
+        let (schema_cache, _) = ConfigSchemaCache::spawn(32, dir.file("schemas.json"), 1000)
+            .await
+            .unwrap();
+        schema_cache
+            .write(cfg_sch_id.clone(), cfg_sch.clone(), |_, _| false, true)
+            .await
+            .unwrap();
+
+        // the backend's circuit breaker is open
+        let cfg_sch_client = MockCfgSchsClient {
+            circuit_open: true,
+            ..Default::default()
+        };
+
+        // create the syncer
+        let http_client = Arc::new(MockClient::default());
+        let (syncer, _) = create_syncer(&dir, http_client.clone()).await;
+
+        // run the test
+        let args = GetDeployedArgs {
+            device_id: "device-id".to_string(),
+            config_type_slug: cfg_type_slug.clone(),
+            config_schema_digest: cfg_sch_digest.clone(),
+        };
+        let deployed_inst = get_deployed::get_deployed(
+            &args,
+            &syncer,
+            Arc::new(cfg_inst_cache),
+            Arc::new(cfg_inst_content_cache),
+            &schema_cache,
+            &cfg_sch_client,
+            "doesntmatter",
+        )
+        .await
+        .unwrap();
+        assert_eq!(deployed_inst.id, cfg_inst_id);
+        assert_eq!(deployed_inst.content, json!({"speed": 4}));
+
+        // the sync with the backend was skipped
+        assert!(http_client.config_instances_client.get_calls().is_empty());
+    }
 }
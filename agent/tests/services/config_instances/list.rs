@@ -0,0 +1,170 @@
+// internal crates
+use miru_agent::filesys::dir::Dir;
+use miru_agent::models::config_instance::{
+    ActivityStatus, ConfigInstance, ErrorStatus, TargetStatus,
+};
+use miru_agent::services::config_instances::{list, list::ConfigInstanceFilters};
+use miru_agent::storage::config_instances::ConfigInstanceCache;
+
+// external crates
+use chrono::{DateTime, Duration, Utc};
+
+fn new_cfg_inst(id: &str, created_secs: i64) -> ConfigInstance {
+    ConfigInstance {
+        id: id.to_string(),
+        created_at: DateTime::<Utc>::UNIX_EPOCH + Duration::seconds(created_secs),
+        config_type_id: "cfg_type_1".to_string(),
+        config_schema_id: "cfg_sch_1".to_string(),
+        ..Default::default()
+    }
+}
+
+async fn create_cache(dir: &Dir, cfg_insts: Vec<ConfigInstance>) -> ConfigInstanceCache {
+    let (cache, _) = ConfigInstanceCache::spawn(32, dir.file("instances.json"), 1000)
+        .await
+        .unwrap();
+    for cfg_inst in cfg_insts {
+        cache
+            .write(cfg_inst.id.clone(), cfg_inst, |_, _| false, true)
+            .await
+            .unwrap();
+    }
+    cache
+}
+
+fn ids(cfg_insts: &[openapi_server::models::LocalConfigInstance]) -> Vec<String> {
+    cfg_insts
+        .iter()
+        .map(|cfg_inst| cfg_inst.id.clone())
+        .collect()
+}
+
+pub mod success {
+    use super::*;
+
+    #[tokio::test]
+    async fn empty() {
+        let dir = Dir::create_temp_dir("list_cfg_insts").await.unwrap();
+        let cache = create_cache(&dir, vec![]).await;
+
+        let cfg_insts = list::list(&cache, ConfigInstanceFilters::default())
+            .await
+            .unwrap();
+        assert!(cfg_insts.is_empty());
+    }
+
+    #[tokio::test]
+    async fn oldest_first_with_attempts_and_cooldown() {
+        let dir = Dir::create_temp_dir("list_cfg_insts").await.unwrap();
+        let cooldown_ends_at = DateTime::<Utc>::UNIX_EPOCH + Duration::seconds(100);
+        let cache = create_cache(
+            &dir,
+            vec![
+                new_cfg_inst("b", 2),
+                ConfigInstance {
+                    attempts: 3,
+                    cooldown_ends_at,
+                    error_status: ErrorStatus::Retrying,
+                    ..new_cfg_inst("a", 1)
+                },
+                new_cfg_inst("c", 2),
+            ],
+        )
+        .await;
+
+        let cfg_insts = list::list(&cache, ConfigInstanceFilters::default())
+            .await
+            .unwrap();
+        assert_eq!(ids(&cfg_insts), vec!["a", "b", "c"]);
+        assert_eq!(cfg_insts[0].attempts, 3);
+        assert_eq!(cfg_insts[0].cooldown_ends_at, cooldown_ends_at.to_rfc3339());
+        assert_eq!(
+            cfg_insts[0].status,
+            openapi_server::models::ConfigInstanceStatus::CONFIG_INSTANCE_STATUS_RETRYING
+        );
+        // content is only included when getting a single config instance
+        assert!(cfg_insts.iter().all(|cfg_inst| cfg_inst.content.is_none()));
+    }
+
+    #[tokio::test]
+    async fn filters() {
+        let dir = Dir::create_temp_dir("list_cfg_insts").await.unwrap();
+        let cache = create_cache(
+            &dir,
+            vec![
+                ConfigInstance {
+                    target_status: TargetStatus::Deployed,
+                    activity_status: ActivityStatus::Deployed,
+                    ..new_cfg_inst("deployed", 1)
+                },
+                ConfigInstance {
+                    target_status: TargetStatus::Deployed,
+                    activity_status: ActivityStatus::Queued,
+                    error_status: ErrorStatus::Retrying,
+                    ..new_cfg_inst("retrying", 2)
+                },
+                ConfigInstance {
+                    target_status: TargetStatus::Removed,
+                    activity_status: ActivityStatus::Removed,
+                    config_type_id: "cfg_type_2".to_string(),
+                    config_schema_id: "cfg_sch_2".to_string(),
+                    ..new_cfg_inst("removed", 3)
+                },
+            ],
+        )
+        .await;
+
+        let cases = vec![
+            (
+                ConfigInstanceFilters {
+                    target_statuses: Some(vec![TargetStatus::Deployed]),
+                    ..Default::default()
+                },
+                vec!["deployed", "retrying"],
+            ),
+            (
+                ConfigInstanceFilters {
+                    activity_statuses: Some(vec![
+                        ActivityStatus::Deployed,
+                        ActivityStatus::Removed,
+                    ]),
+                    ..Default::default()
+                },
+                vec!["deployed", "removed"],
+            ),
+            (
+                ConfigInstanceFilters {
+                    error_statuses: Some(vec![ErrorStatus::Retrying]),
+                    ..Default::default()
+                },
+                vec!["retrying"],
+            ),
+            (
+                ConfigInstanceFilters {
+                    config_type_ids: Some(vec!["cfg_type_2".to_string()]),
+                    ..Default::default()
+                },
+                vec!["removed"],
+            ),
+            (
+                ConfigInstanceFilters {
+                    config_schema_ids: Some(vec!["cfg_sch_1".to_string()]),
+                    error_statuses: Some(vec![ErrorStatus::None]),
+                    ..Default::default()
+                },
+                vec!["deployed"],
+            ),
+            (
+                ConfigInstanceFilters {
+                    target_statuses: Some(vec![]),
+                    ..Default::default()
+                },
+                vec![],
+            ),
+        ];
+        for (filters, expected) in cases {
+            let cfg_insts = list::list(&cache, filters.clone()).await.unwrap();
+            assert_eq!(ids(&cfg_insts), expected, "filters: {filters:?}");
+        }
+    }
+}
@@ -0,0 +1,263 @@
+// internal crates
+use miru_agent::authn::{errors::*, token::Token};
+use miru_agent::crud::prelude::*;
+use miru_agent::filesys::dir::Dir;
+use miru_agent::http::errors::{HTTPErr, MockErr};
+use miru_agent::models::config_schema::ConfigSchema;
+use miru_agent::services::config_schemas::validate::{validate, ValidateArgs};
+use miru_agent::services::errors::ServiceErr;
+use miru_agent::storage::config_schemas::{ConfigSchemaCache, ConfigSchemaContentCache};
+use miru_agent::trace;
+use openapi_client::models::{ConfigSchema as BackendConfigSchema, ConfigType};
+
+// test crates
+use crate::authn::mock::MockTokenManager;
+use crate::http::mock::MockCfgSchsClient;
+
+// external crates
+use serde_json::json;
+
+fn schema() -> serde_json::Value {
+    json!({
+        "type": "object",
+        "properties": {
+            "speed": { "type": "integer", "minimum": 1 }
+        }
+    })
+}
+
+fn args(content: serde_json::Value) -> ValidateArgs {
+    ValidateArgs {
+        config_type_slug: "motion-control".to_string(),
+        config_schema_digest: "sha256:123".to_string(),
+        content,
+    }
+}
+
+async fn create_caches(dir: &Dir) -> (ConfigSchemaCache, ConfigSchemaContentCache) {
+    let (cfg_schema_cache, _) = ConfigSchemaCache::spawn(16, dir.file("cfg_schemas.json"), 1000)
+        .await
+        .unwrap();
+    let (cfg_schema_content_cache, _) =
+        ConfigSchemaContentCache::spawn(16, dir.subdir("cfg_schema_contents"), 1000)
+            .await
+            .unwrap();
+    (cfg_schema_cache, cfg_schema_content_cache)
+}
+
+pub mod errors {
+    use super::*;
+
+    #[tokio::test]
+    async fn not_found() {
+        let dir = Dir::create_temp_dir("validate").await.unwrap();
+        let (cfg_schema_cache, cfg_schema_content_cache) = create_caches(&dir).await;
+        let token_mngr = MockTokenManager::new(Token::default());
+        let mock_client = MockCfgSchsClient::default();
+
+        let err = validate(
+            &args(json!({"speed": 4})),
+            &cfg_schema_cache,
+            &cfg_schema_content_cache,
+            &mock_client,
+            &token_mngr,
+        )
+        .await
+        .unwrap_err();
+        match err {
+            ServiceErr::ConfigSchemaNotFound(e) => assert!(!e.network_connection_error),
+            _ => panic!("expected a config schema not found error but got {err:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn token_error() {
+        let dir = Dir::create_temp_dir("validate").await.unwrap();
+        let (cfg_schema_cache, cfg_schema_content_cache) = create_caches(&dir).await;
+        let token_mngr = MockTokenManager::new(Token::default());
+        token_mngr.set_get_token(Box::new(|| {
+            Err(AuthnErr::MockError(Box::new(MockError {
+                is_network_connection_error: false,
+                trace: trace!(),
+            })))
+        }));
+        let mut mock_client = MockCfgSchsClient::default();
+        mock_client.set_list_all_config_schemas(|| panic!("unexpected backend call"));
+
+        let err = validate(
+            &args(json!({"speed": 4})),
+            &cfg_schema_cache,
+            &cfg_schema_content_cache,
+            &mock_client,
+            &token_mngr,
+        )
+        .await
+        .unwrap_err();
+        assert!(matches!(err, ServiceErr::AuthnErr(_)));
+    }
+
+    #[tokio::test]
+    async fn network_error() {
+        let dir = Dir::create_temp_dir("validate").await.unwrap();
+        let (cfg_schema_cache, cfg_schema_content_cache) = create_caches(&dir).await;
+        let token_mngr = MockTokenManager::new(Token::default());
+        let mut mock_client = MockCfgSchsClient::default();
+        mock_client.set_list_all_config_schemas(|| {
+            Err(HTTPErr::MockErr(Box::new(MockErr {
+                is_network_connection_error: true,
+            })))
+        });
+
+        let err = validate(
+            &args(json!({"speed": 4})),
+            &cfg_schema_cache,
+            &cfg_schema_content_cache,
+            &mock_client,
+            &token_mngr,
+        )
+        .await
+        .unwrap_err();
+        match err {
+            ServiceErr::ConfigSchemaNotFound(e) => assert!(e.network_connection_error),
+            _ => panic!("expected a config schema not found error but got {err:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn missing_content() {
+        let dir = Dir::create_temp_dir("validate").await.unwrap();
+        let (cfg_schema_cache, cfg_schema_content_cache) = create_caches(&dir).await;
+        let token_mngr = MockTokenManager::new(Token::default());
+        let mut mock_client = MockCfgSchsClient::default();
+        mock_client.set_list_all_config_schemas(|| {
+            Ok(vec![BackendConfigSchema {
+                id: "cfg_sch_123".to_string(),
+                content: None,
+                ..Default::default()
+            }])
+        });
+
+        let err = validate(
+            &args(json!({"speed": 4})),
+            &cfg_schema_cache,
+            &cfg_schema_content_cache,
+            &mock_client,
+            &token_mngr,
+        )
+        .await
+        .unwrap_err();
+        assert!(matches!(err, ServiceErr::ConfigSchemaContentNotFound(_)));
+    }
+}
+
+pub mod success {
+    use super::*;
+
+    #[tokio::test]
+    async fn from_cache() {
+        let dir = Dir::create_temp_dir("validate").await.unwrap();
+        let (cfg_schema_cache, cfg_schema_content_cache) = create_caches(&dir).await;
+        let token_mngr = MockTokenManager::new(Token::default());
+        let cfg_schema = ConfigSchema {
+            id: "cfg_sch_123".to_string(),
+            digest: "sha256:123".to_string(),
+            config_type_slug: Some("motion-control".to_string()),
+            ..Default::default()
+        };
+        cfg_schema_cache
+            .write(
+                cfg_schema.id.clone(),
+                cfg_schema.clone(),
+                |_, _| false,
+                true,
+            )
+            .await
+            .unwrap();
+        cfg_schema_content_cache
+            .write(cfg_schema.id.clone(), schema(), |_, _| false, true)
+            .await
+            .unwrap();
+
+        // the backend should never be called
+        let mut mock_client = MockCfgSchsClient::default();
+        mock_client.set_list_all_config_schemas(|| panic!("unexpected backend call"));
+
+        // valid content
+        let response = validate(
+            &args(json!({"speed": 4})),
+            &cfg_schema_cache,
+            &cfg_schema_content_cache,
+            &mock_client,
+            &token_mngr,
+        )
+        .await
+        .unwrap();
+        assert!(response.valid);
+        assert_eq!(response.config_schema_id, "cfg_sch_123");
+        assert!(response.errors.is_empty());
+
+        // invalid content
+        let response = validate(
+            &args(json!({"speed": 0})),
+            &cfg_schema_cache,
+            &cfg_schema_content_cache,
+            &mock_client,
+            &token_mngr,
+        )
+        .await
+        .unwrap();
+        assert!(!response.valid);
+        assert_eq!(response.errors.len(), 1);
+        assert_eq!(response.errors[0].instance_path, "/speed");
+        assert_eq!(response.errors[0].schema_path, "/properties/speed/minimum");
+
+        // the token is only needed by the backend
+        assert_eq!(token_mngr.num_get_token_calls(), 0);
+    }
+
+    #[tokio::test]
+    async fn from_server() {
+        let dir = Dir::create_temp_dir("validate").await.unwrap();
+        let (cfg_schema_cache, cfg_schema_content_cache) = create_caches(&dir).await;
+        let token_mngr = MockTokenManager::new(Token::default());
+        let mut mock_client = MockCfgSchsClient::default();
+        mock_client.set_list_all_config_schemas(|| {
+            Ok(vec![BackendConfigSchema {
+                id: "cfg_sch_123".to_string(),
+                digest: "sha256:123".to_string(),
+                content: Some(schema()),
+                config_type: Some(Box::new(ConfigType {
+                    slug: "motion-control".to_string(),
+                    ..Default::default()
+                })),
+                ..Default::default()
+            }])
+        });
+
+        let response = validate(
+            &args(json!({"speed": "fast"})),
+            &cfg_schema_cache,
+            &cfg_schema_content_cache,
+            &mock_client,
+            &token_mngr,
+        )
+        .await
+        .unwrap();
+        assert!(!response.valid);
+        assert_eq!(response.config_schema_id, "cfg_sch_123");
+        assert_eq!(response.errors.len(), 1);
+        assert_eq!(response.errors[0].instance_path, "/speed");
+
+        // check the config schema and its content were cached
+        let cached = cfg_schema_cache
+            .read("cfg_sch_123".to_string())
+            .await
+            .unwrap();
+        assert_eq!(cached.config_type_slug, Some("motion-control".to_string()));
+        let cached_content = cfg_schema_content_cache
+            .read("cfg_sch_123".to_string())
+            .await
+            .unwrap();
+        assert_eq!(cached_content, schema());
+    }
+}
@@ -0,0 +1,71 @@
+// internal crates
+use miru_agent::filesys::dir::Dir;
+use miru_agent::models::device::Device;
+use miru_agent::services::device::export;
+use miru_agent::services::errors::*;
+use miru_agent::storage::bundle::BUNDLE_VERSION;
+use miru_agent::storage::caches::{CacheCapacities, Caches};
+use miru_agent::storage::{device::DeviceFile, layout::StorageLayout};
+
+pub mod errors {
+    use super::*;
+
+    #[tokio::test]
+    async fn device_file_shutdown() {
+        let dir = Dir::create_temp_dir("testing").await.unwrap();
+        let layout = StorageLayout::new(dir);
+        let (caches, _) = Caches::init(&layout, CacheCapacities::default())
+            .await
+            .unwrap();
+
+        let (device_file, _) =
+            DeviceFile::spawn_with_default(64, layout.device_file(), Device::default())
+                .await
+                .unwrap();
+        device_file.shutdown().await.unwrap();
+
+        let result = export::export_device(&device_file, &caches).await;
+        assert!(matches!(result, Err(ServiceErr::FileSysErr(_))));
+    }
+
+    #[tokio::test]
+    async fn caches_shutdown() {
+        let dir = Dir::create_temp_dir("testing").await.unwrap();
+        let layout = StorageLayout::new(dir);
+        let (caches, _) = Caches::init(&layout, CacheCapacities::default())
+            .await
+            .unwrap();
+        caches.shutdown().await.unwrap();
+
+        let (device_file, _) =
+            DeviceFile::spawn_with_default(64, layout.device_file(), Device::default())
+                .await
+                .unwrap();
+
+        let result = export::export_device(&device_file, &caches).await;
+        assert!(matches!(result, Err(ServiceErr::StorageErr(_))));
+    }
+}
+
+pub mod success {
+    use super::*;
+
+    #[tokio::test]
+    async fn empty_caches() {
+        let dir = Dir::create_temp_dir("testing").await.unwrap();
+        let layout = StorageLayout::new(dir);
+        let (caches, _) = Caches::init(&layout, CacheCapacities::default())
+            .await
+            .unwrap();
+
+        let (device_file, _) =
+            DeviceFile::spawn_with_default(64, layout.device_file(), Device::default())
+                .await
+                .unwrap();
+
+        let bundle = export::export_device(&device_file, &caches).await.unwrap();
+        assert_eq!(bundle.version, BUNDLE_VERSION);
+        assert_eq!(bundle.device, Device::default());
+        assert!(bundle.cfg_insts.is_empty());
+    }
+}
@@ -0,0 +1,36 @@
+// internal crates
+use crate::diagnostics::archive::read_archive;
+use crate::sync::mock::MockSyncer;
+use miru_agent::diagnostics::collect::{Options, ARCHIVE_ROOT};
+use miru_agent::filesys::dir::Dir;
+use miru_agent::services::diagnostics::get;
+use miru_agent::storage::layout::StorageLayout;
+use miru_agent::sync::syncer::SyncState;
+
+pub mod success {
+    use super::*;
+
+    #[tokio::test]
+    async fn includes_live_sync_state() {
+        let dir = Dir::create_temp_dir("testing").await.unwrap();
+        let options = Options {
+            layout: StorageLayout::new(dir.subdir("root")),
+            log_dir: dir.subdir("logs"),
+            ..Default::default()
+        };
+        let syncer = MockSyncer::default();
+        syncer.set_state(SyncState {
+            err_streak: 7,
+            ..Default::default()
+        });
+
+        let archive = get::get_diagnostics(&options, &syncer).await;
+        let entries = read_archive(&archive);
+        let sync_state = entries
+            .iter()
+            .find(|entry| entry.path == format!("{ARCHIVE_ROOT}/sync_state.json"))
+            .unwrap();
+        let sync_state: serde_json::Value = serde_json::from_slice(&sync_state.data).unwrap();
+        assert_eq!(sync_state["live"]["err_streak"], 7);
+    }
+}
@@ -0,0 +1,120 @@
+// internal crates
+use crate::authn::mock::MockTokenManager;
+use crate::sync::mock::MockSyncer;
+use miru_agent::authn::{errors::*, token::Token};
+use miru_agent::filesys::dir::Dir;
+use miru_agent::metrics::registry::Metrics;
+use miru_agent::models::config_instance::ConfigInstance;
+use miru_agent::services::{errors::*, metrics::get};
+use miru_agent::storage::caches::{CacheCapacities, Caches};
+use miru_agent::storage::layout::StorageLayout;
+use miru_agent::sync::syncer::SyncState;
+use miru_agent::trace;
+
+// external crates
+use chrono::{DateTime, Duration, Utc};
+
+pub mod errors {
+    use super::*;
+
+    #[tokio::test]
+    async fn token_manager_error() {
+        let dir = Dir::create_temp_dir("testing").await.unwrap();
+        let layout = StorageLayout::new(dir);
+        let (caches, _) = Caches::init(&layout, CacheCapacities::default())
+            .await
+            .unwrap();
+
+        let syncer = MockSyncer::default();
+        let token_mngr = MockTokenManager::new(Token::default());
+        token_mngr.set_get_token(Box::new(|| {
+            Err(AuthnErr::MockError(Box::new(MockError {
+                is_network_connection_error: false,
+                trace: trace!(),
+            })))
+        }));
+
+        let result = get::get_metrics(&Metrics::default(), &syncer, &token_mngr, &caches).await;
+        assert!(matches!(result, Err(ServiceErr::AuthnErr(_))));
+    }
+
+    #[tokio::test]
+    async fn caches_shutdown() {
+        let dir = Dir::create_temp_dir("testing").await.unwrap();
+        let layout = StorageLayout::new(dir);
+        let (caches, _) = Caches::init(&layout, CacheCapacities::default())
+            .await
+            .unwrap();
+        caches.shutdown().await.unwrap();
+
+        let syncer = MockSyncer::default();
+        let token_mngr = MockTokenManager::new(Token::default());
+
+        let result = get::get_metrics(&Metrics::default(), &syncer, &token_mngr, &caches).await;
+        assert!(matches!(result, Err(ServiceErr::CacheErr(_))));
+    }
+}
+
+pub mod success {
+    use super::*;
+
+    #[tokio::test]
+    async fn scrape_time_gauges() {
+        let dir = Dir::create_temp_dir("testing").await.unwrap();
+        let layout = StorageLayout::new(dir);
+        let (caches, _) = Caches::init(&layout, CacheCapacities::default())
+            .await
+            .unwrap();
+        let cfg_inst = ConfigInstance::default();
+        caches
+            .cfg_inst
+            .write(cfg_inst.id.clone(), cfg_inst, |_, _| false, true)
+            .await
+            .unwrap();
+
+        let syncer = MockSyncer::default();
+        syncer.set_state(SyncState {
+            err_streak: 3,
+            cooldown_ends_at: Utc::now() + Duration::hours(1),
+            ..SyncState::default()
+        });
+        let expires_at = DateTime::<Utc>::from_timestamp(2_000_000_000, 0).unwrap();
+        let token_mngr = MockTokenManager::new(Token {
+            token: "token".to_string(),
+            expires_at,
+        });
+
+        let metrics = Metrics::default();
+        metrics
+            .sync
+            .record(true, std::time::Duration::from_millis(10));
+
+        let output = get::get_metrics(&metrics, &syncer, &token_mngr, &caches)
+            .await
+            .unwrap();
+        assert!(output.contains("miru_agent_sync_attempts_total 1\n"));
+        assert!(output.contains("miru_agent_sync_err_streak 3\n"));
+        assert!(!output.contains("miru_agent_sync_cooldown_remaining_seconds 0\n"));
+        assert!(output.contains("miru_agent_token_expires_at_seconds 2000000000\n"));
+        assert!(output.contains("miru_agent_cache_entries{cache=\"config_instances\"} 1\n"));
+        assert!(output.contains("miru_agent_cache_entries{cache=\"config_instance_contents\"} 0\n"));
+    }
+
+    #[tokio::test]
+    async fn cooldown_elapsed() {
+        let dir = Dir::create_temp_dir("testing").await.unwrap();
+        let layout = StorageLayout::new(dir);
+        let (caches, _) = Caches::init(&layout, CacheCapacities::default())
+            .await
+            .unwrap();
+
+        let syncer = MockSyncer::default();
+        syncer.set_state(SyncState::default());
+        let token_mngr = MockTokenManager::new(Token::default());
+
+        let output = get::get_metrics(&Metrics::default(), &syncer, &token_mngr, &caches)
+            .await
+            .unwrap();
+        assert!(output.contains("miru_agent_sync_cooldown_remaining_seconds 0\n"));
+    }
+}
@@ -1,3 +1,5 @@
 pub mod config_instances;
 pub mod config_schemas;
 pub mod device;
+pub mod diagnostics;
+pub mod metrics;
@@ -0,0 +1,263 @@
+// internal crates
+use miru_agent::errors::MiruError;
+use miru_agent::filesys::{dir::Dir, path::PathExt};
+use miru_agent::models::config_instance::{ActivityStatus, ConfigInstance, TargetStatus};
+use miru_agent::models::config_schema::ConfigSchema;
+use miru_agent::models::device::{Device, DeviceStatus};
+use miru_agent::storage::bundle::{self, Bundle, BUNDLE_VERSION};
+use miru_agent::storage::caches::{CacheCapacities, Caches};
+use miru_agent::storage::digests::ConfigSchemaDigests;
+use miru_agent::storage::errors::StorageErr;
+use miru_agent::storage::layout::StorageLayout;
+
+// external crates
+use axum::http::StatusCode;
+use serde_json::json;
+
+async fn create_layout(device: &Device) -> (StorageLayout, Caches) {
+    let dir = Dir::create_temp_dir("testing").await.unwrap();
+    let layout = StorageLayout::new(dir);
+    layout
+        .device_file()
+        .write_json(device, true, true)
+        .await
+        .unwrap();
+
+    // auth material which must never be exported
+    let auth = layout.auth_dir();
+    auth.private_key_file()
+        .write_string("private-key", true, true)
+        .await
+        .unwrap();
+    auth.token_file()
+        .write_string("{\"token\":\"secret-token\"}", true, true)
+        .await
+        .unwrap();
+
+    let (caches, _) = Caches::init(&layout, CacheCapacities::default())
+        .await
+        .unwrap();
+    (layout, caches)
+}
+
+async fn populate(caches: &Caches) {
+    let cfg_sch = ConfigSchema {
+        id: "cfg-sch-1".to_string(),
+        config_type_slug: Some("motion-control".to_string()),
+        ..Default::default()
+    };
+    caches
+        .cfg_schema
+        .write(cfg_sch.id.clone(), cfg_sch.clone(), |_, _| false, true)
+        .await
+        .unwrap();
+
+    let cfg_insts = vec![
+        ConfigInstance {
+            id: "cfg-inst-2".to_string(),
+            target_status: TargetStatus::Deployed,
+            activity_status: ActivityStatus::Deployed,
+            config_schema_id: cfg_sch.id.clone(),
+            ..Default::default()
+        },
+        ConfigInstance {
+            id: "cfg-inst-1".to_string(),
+            target_status: TargetStatus::Removed,
+            activity_status: ActivityStatus::Queued,
+            config_schema_id: cfg_sch.id.clone(),
+            attempts: 3,
+            ..Default::default()
+        },
+    ];
+    for (i, cfg_inst) in cfg_insts.into_iter().enumerate() {
+        let is_dirty = i % 2 == 0;
+        caches
+            .cfg_inst
+            .write(
+                cfg_inst.id.clone(),
+                cfg_inst.clone(),
+                move |_, _| is_dirty,
+                true,
+            )
+            .await
+            .unwrap();
+        caches
+            .cfg_inst_content
+            .write(cfg_inst.id, json!({"speed": i}), |_, _| false, true)
+            .await
+            .unwrap();
+    }
+
+    caches
+        .cfg_sch_digest
+        .write(
+            "raw-digest".to_string(),
+            ConfigSchemaDigests {
+                raw: "raw-digest".to_string(),
+                resolved: "resolved-digest".to_string(),
+            },
+            |_, _| false,
+            true,
+        )
+        .await
+        .unwrap();
+}
+
+fn assert_same_state(actual: &Bundle, expected: &Bundle) {
+    assert_eq!(actual.version, expected.version);
+    assert_eq!(actual.device, expected.device);
+    assert_eq!(actual.cfg_insts, expected.cfg_insts);
+    assert_eq!(actual.cfg_inst_contents, expected.cfg_inst_contents);
+    assert_eq!(actual.cfg_schemas, expected.cfg_schemas);
+    assert_eq!(actual.cfg_sch_digests, expected.cfg_sch_digests);
+}
+
+pub mod export {
+    use super::*;
+
+    #[tokio::test]
+    async fn empty() {
+        let device = Device::default();
+        let (_, caches) = create_layout(&device).await;
+
+        let bundle = bundle::export(&device, &caches).await.unwrap();
+        assert_eq!(bundle.version, BUNDLE_VERSION);
+        assert_eq!(bundle.device, device);
+        assert!(bundle.cfg_insts.is_empty());
+        assert!(bundle.cfg_inst_contents.is_empty());
+        assert!(bundle.cfg_schemas.is_empty());
+        assert!(bundle.cfg_sch_digests.is_empty());
+    }
+
+    #[tokio::test]
+    async fn populated() {
+        let device = Device {
+            id: "dvc-1".to_string(),
+            status: DeviceStatus::Online,
+            activated: true,
+            ..Default::default()
+        };
+        let (_, caches) = create_layout(&device).await;
+        populate(&caches).await;
+
+        let bundle = bundle::export(&device, &caches).await.unwrap();
+        assert_eq!(bundle.device, device);
+
+        // entries are sorted by key and keep their statuses, attempts and dirty flags
+        let cfg_inst_ids: Vec<&str> = bundle.cfg_insts.iter().map(|e| e.key.as_str()).collect();
+        assert_eq!(cfg_inst_ids, vec!["cfg-inst-1", "cfg-inst-2"]);
+        assert_eq!(bundle.cfg_insts[0].value.attempts, 3);
+        assert_eq!(
+            bundle.cfg_insts[0].value.target_status,
+            TargetStatus::Removed
+        );
+        assert!(!bundle.cfg_insts[0].is_dirty);
+        assert!(bundle.cfg_insts[1].is_dirty);
+
+        assert_eq!(bundle.cfg_inst_contents.len(), 2);
+        assert_eq!(bundle.cfg_inst_contents[0].value, json!({"speed": 1}));
+        assert_eq!(bundle.cfg_schemas.len(), 1);
+        assert_eq!(bundle.cfg_sch_digests.len(), 1);
+        assert_eq!(bundle.cfg_sch_digests[0].value.resolved, "resolved-digest");
+    }
+
+    #[tokio::test]
+    async fn excludes_auth_material() {
+        let device = Device::default();
+        let (layout, caches) = create_layout(&device).await;
+        populate(&caches).await;
+
+        let bundle = bundle::export(&device, &caches).await.unwrap();
+        let file = layout.temp_dir().file("bundle.json");
+        bundle::write(&bundle, &file).await.unwrap();
+        let serialized = file.read_string().await.unwrap();
+        assert!(!serialized.contains("private-key"));
+        assert!(!serialized.contains("secret-token"));
+    }
+
+    #[tokio::test]
+    async fn from_layout() {
+        let device = Device {
+            id: "dvc-1".to_string(),
+            ..Default::default()
+        };
+        let (layout, caches) = create_layout(&device).await;
+        populate(&caches).await;
+        let expected = bundle::export(&device, &caches).await.unwrap();
+        caches.shutdown().await.unwrap();
+
+        let bundle = bundle::export_layout(&layout).await.unwrap();
+        assert_same_state(&bundle, &expected);
+    }
+}
+
+pub mod read {
+    use super::*;
+
+    #[tokio::test]
+    async fn unsupported_version() {
+        let device = Device::default();
+        let (layout, caches) = create_layout(&device).await;
+        let mut bundle = bundle::export(&device, &caches).await.unwrap();
+        bundle.version = BUNDLE_VERSION + 1;
+
+        let file = layout.temp_dir().file("bundle.json");
+        bundle::write(&bundle, &file).await.unwrap();
+        let result = bundle::read(&file).await;
+        assert!(matches!(
+            result,
+            Err(StorageErr::UnsupportedBundleVersionErr(_))
+        ));
+    }
+}
+
+pub mod import {
+    use super::*;
+
+    #[tokio::test]
+    async fn round_trip() {
+        let device = Device {
+            id: "dvc-1".to_string(),
+            name: "robot".to_string(),
+            ..Default::default()
+        };
+        let (layout, caches) = create_layout(&device).await;
+        populate(&caches).await;
+        let expected = bundle::export(&device, &caches).await.unwrap();
+
+        // write the bundle to disk and read it back
+        let file = layout.temp_dir().file("bundle.json");
+        bundle::write(&expected, &file).await.unwrap();
+        let bundle = bundle::read(&file).await.unwrap();
+        assert_eq!(bundle, expected);
+
+        // load it into a fresh layout
+        let dir = Dir::create_temp_dir("testing").await.unwrap();
+        let test_layout = StorageLayout::new(dir);
+        bundle::import(&test_layout, &bundle).await.unwrap();
+        assert!(!test_layout.auth_dir().root.exists());
+
+        let actual = bundle::export_layout(&test_layout).await.unwrap();
+        assert_same_state(&actual, &expected);
+    }
+
+    #[tokio::test]
+    async fn unsupported_version() {
+        let device = Device::default();
+        let (_, caches) = create_layout(&device).await;
+        let mut bundle = bundle::export(&device, &caches).await.unwrap();
+        bundle.version = 0;
+
+        let dir = Dir::create_temp_dir("testing").await.unwrap();
+        let test_layout = StorageLayout::new(dir);
+        let result = bundle::import(&test_layout, &bundle).await;
+        match result {
+            Err(StorageErr::UnsupportedBundleVersionErr(e)) => {
+                assert_eq!(e.code().as_str(), "unsupported_bundle_version");
+                assert_eq!(e.http_status(), StatusCode::BAD_REQUEST);
+            }
+            result => panic!("unexpected result: {result:?}"),
+        }
+        assert!(!test_layout.device_file().exists());
+    }
+}
@@ -0,0 +1,120 @@
+// standard crates
+use std::time::Duration;
+
+// internal crates
+use miru_agent::filesys::dir::Dir;
+use miru_agent::models::config_instance::ConfigInstance;
+use miru_agent::mqtt::events::ConfigInstanceEvent;
+use miru_agent::storage::events::EventQueue;
+
+fn new_event() -> ConfigInstanceEvent {
+    ConfigInstanceEvent::new(&ConfigInstance::default())
+}
+
+pub mod event_queue {
+    use super::*;
+
+    #[tokio::test]
+    async fn push_peek_remove() {
+        let dir = Dir::create_temp_dir("testing").await.unwrap();
+        let (queue, _) = EventQueue::spawn(16, dir.file("events.json"), 10)
+            .await
+            .unwrap();
+        assert!(queue.is_empty().await.unwrap());
+
+        let events: Vec<ConfigInstanceEvent> = (0..3).map(|_| new_event()).collect();
+        for event in events.iter() {
+            queue.push(event.clone()).await.unwrap();
+        }
+        assert_eq!(queue.len().await.unwrap(), 3);
+
+        // oldest first
+        assert_eq!(queue.peek(2).await.unwrap(), events[..2].to_vec());
+        assert_eq!(queue.peek(10).await.unwrap(), events);
+
+        queue
+            .remove(vec![events[0].event_id.clone(), "unknown".to_string()])
+            .await
+            .unwrap();
+        assert_eq!(queue.peek(10).await.unwrap(), events[1..].to_vec());
+    }
+
+    #[tokio::test]
+    async fn drops_the_oldest_events_when_full() {
+        let dir = Dir::create_temp_dir("testing").await.unwrap();
+        let (queue, _) = EventQueue::spawn(16, dir.file("events.json"), 2)
+            .await
+            .unwrap();
+
+        let events: Vec<ConfigInstanceEvent> = (0..5).map(|_| new_event()).collect();
+        for event in events.iter() {
+            queue.push(event.clone()).await.unwrap();
+        }
+        assert_eq!(queue.peek(10).await.unwrap(), events[3..].to_vec());
+        assert_eq!(queue.num_dropped().await.unwrap(), 3);
+    }
+
+    #[tokio::test]
+    async fn persists_across_restarts() {
+        let dir = Dir::create_temp_dir("testing").await.unwrap();
+        let file = dir.file("events.json");
+        let event = new_event();
+
+        let (queue, handle) = EventQueue::spawn(16, file.clone(), 10).await.unwrap();
+        queue.push(event.clone()).await.unwrap();
+        queue.shutdown().await.unwrap();
+        handle.await.unwrap();
+
+        let (queue, _) = EventQueue::spawn(16, file, 10).await.unwrap();
+        assert_eq!(queue.peek(10).await.unwrap(), vec![event]);
+    }
+
+    #[tokio::test]
+    async fn loads_queues_persisted_with_events() {
+        let dir = Dir::create_temp_dir("testing").await.unwrap();
+        let file = dir.file("events.json");
+        let event = new_event();
+        let queue = serde_json::json!({ "events": [event], "num_dropped": 2 });
+        file.write_json(&queue, true, true).await.unwrap();
+
+        let (queue, _) = EventQueue::spawn(16, file, 10).await.unwrap();
+        assert_eq!(queue.peek(10).await.unwrap(), vec![event]);
+        assert_eq!(queue.num_dropped().await.unwrap(), 2);
+    }
+
+    #[tokio::test]
+    async fn invalid_file_contents() {
+        let dir = Dir::create_temp_dir("testing").await.unwrap();
+        let file = dir.file("events.json");
+        file.write_string("not a valid queue", true, true)
+            .await
+            .unwrap();
+
+        let (queue, _) = EventQueue::spawn(16, file, 10).await.unwrap();
+        assert!(queue.is_empty().await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn pushed_wakes_waiters() {
+        let dir = Dir::create_temp_dir("testing").await.unwrap();
+        let (queue, _) = EventQueue::spawn(16, dir.file("events.json"), 10)
+            .await
+            .unwrap();
+
+        // nothing pushed yet
+        tokio::time::timeout(Duration::from_millis(50), queue.pushed())
+            .await
+            .unwrap_err();
+
+        // pushes before waiting aren't missed
+        queue.push(new_event()).await.unwrap();
+        tokio::time::timeout(Duration::from_secs(1), queue.pushed())
+            .await
+            .unwrap();
+
+        queue.wake();
+        tokio::time::timeout(Duration::from_secs(1), queue.pushed())
+            .await
+            .unwrap();
+    }
+}
@@ -1,4 +1,7 @@
+pub mod bundle;
 pub mod caches;
 pub mod device;
+pub mod events;
+pub mod outbox;
 pub mod settings;
 pub mod setup;
@@ -0,0 +1,165 @@
+// standard crates
+use std::time::Duration;
+
+// internal crates
+use miru_agent::filesys::dir::Dir;
+use miru_agent::mqtt::client::Properties;
+use miru_agent::storage::outbox::{OutboundMessage, Outbox};
+
+// external crates
+use rumqttc::QoS;
+
+const TTL: Duration = Duration::from_secs(60);
+
+fn new_message(topic: &str, retain: bool, payload: &str) -> OutboundMessage {
+    OutboundMessage::new(
+        topic,
+        QoS::AtLeastOnce,
+        retain,
+        payload.as_bytes(),
+        Properties::default(),
+        TTL,
+    )
+}
+
+pub mod outbox_queue {
+    use super::*;
+
+    #[tokio::test]
+    async fn push_peek_remove() {
+        let dir = Dir::create_temp_dir("testing").await.unwrap();
+        let (outbox, _) = Outbox::spawn(16, dir.file("outbox.json"), 10, TTL)
+            .await
+            .unwrap();
+        assert!(outbox.is_empty().await.unwrap());
+
+        let messages: Vec<OutboundMessage> = (0..3)
+            .map(|i| new_message("a/b", false, &i.to_string()))
+            .collect();
+        for message in messages.iter() {
+            outbox.push(message.clone()).await.unwrap();
+        }
+        assert_eq!(outbox.len().await.unwrap(), 3);
+
+        // oldest first
+        assert_eq!(outbox.peek(2).await.unwrap(), messages[..2].to_vec());
+        assert_eq!(outbox.peek(10).await.unwrap(), messages);
+
+        outbox
+            .remove(vec![messages[0].message_id.clone(), "unknown".to_string()])
+            .await
+            .unwrap();
+        assert_eq!(outbox.peek(10).await.unwrap(), messages[1..].to_vec());
+    }
+
+    #[tokio::test]
+    async fn keeps_the_newest_retained_message_per_topic() {
+        let dir = Dir::create_temp_dir("testing").await.unwrap();
+        let (outbox, _) = Outbox::spawn(16, dir.file("outbox.json"), 10, TTL)
+            .await
+            .unwrap();
+
+        let retained_a1 = new_message("a", true, "1");
+        let not_retained_a = new_message("a", false, "2");
+        let retained_b = new_message("b", true, "3");
+        let retained_a2 = new_message("a", true, "4");
+        for message in [&retained_a1, &not_retained_a, &retained_b, &retained_a2] {
+            outbox.push(message.clone()).await.unwrap();
+        }
+
+        assert_eq!(
+            outbox.peek(10).await.unwrap(),
+            vec![not_retained_a, retained_b, retained_a2]
+        );
+        // replaced retained messages aren't counted as dropped
+        assert_eq!(outbox.num_dropped().await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn drops_the_oldest_messages_when_full() {
+        let dir = Dir::create_temp_dir("testing").await.unwrap();
+        let (outbox, _) = Outbox::spawn(16, dir.file("outbox.json"), 2, TTL)
+            .await
+            .unwrap();
+
+        let messages: Vec<OutboundMessage> = (0..5)
+            .map(|i| new_message("a/b", false, &i.to_string()))
+            .collect();
+        for message in messages.iter() {
+            outbox.push(message.clone()).await.unwrap();
+        }
+        assert_eq!(outbox.peek(10).await.unwrap(), messages[3..].to_vec());
+        assert_eq!(outbox.num_dropped().await.unwrap(), 3);
+    }
+
+    #[tokio::test]
+    async fn drops_expired_messages() {
+        let dir = Dir::create_temp_dir("testing").await.unwrap();
+        let (outbox, _) = Outbox::spawn(16, dir.file("outbox.json"), 10, TTL)
+            .await
+            .unwrap();
+
+        let expired = OutboundMessage::new(
+            "a/b",
+            QoS::AtLeastOnce,
+            false,
+            b"expired",
+            Properties::default(),
+            Duration::ZERO,
+        );
+        let message = new_message("a/b", false, "fresh");
+        outbox.push(expired).await.unwrap();
+        outbox.push(message.clone()).await.unwrap();
+
+        assert_eq!(outbox.peek(10).await.unwrap(), vec![message]);
+        assert_eq!(outbox.len().await.unwrap(), 1);
+        assert_eq!(outbox.num_dropped().await.unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn persists_across_restarts() {
+        let dir = Dir::create_temp_dir("testing").await.unwrap();
+        let file = dir.file("outbox.json");
+        let message = OutboundMessage::new(
+            "a/b",
+            QoS::ExactlyOnce,
+            true,
+            b"payload",
+            Properties {
+                response_topic: Some("reply".to_string()),
+                correlation_data: Some(b"id".to_vec()),
+                user_properties: vec![("key".to_string(), "value".to_string())],
+            },
+            TTL,
+        );
+
+        let (outbox, handle) = Outbox::spawn(16, file.clone(), 10, TTL).await.unwrap();
+        outbox.push(message.clone()).await.unwrap();
+        outbox.shutdown().await.unwrap();
+        handle.await.unwrap();
+
+        let (outbox, _) = Outbox::spawn(16, file, 10, TTL).await.unwrap();
+        let messages = outbox.peek(10).await.unwrap();
+        assert_eq!(messages, vec![message]);
+        assert_eq!(messages[0].qos(), QoS::ExactlyOnce);
+    }
+
+    #[tokio::test]
+    async fn pushed_wakes_waiters() {
+        let dir = Dir::create_temp_dir("testing").await.unwrap();
+        let (outbox, _) = Outbox::spawn(16, dir.file("outbox.json"), 10, TTL)
+            .await
+            .unwrap();
+
+        // nothing pushed yet
+        tokio::time::timeout(Duration::from_millis(50), outbox.pushed())
+            .await
+            .unwrap_err();
+
+        // pushes before waiting aren't missed
+        outbox.push(new_message("a/b", false, "1")).await.unwrap();
+        tokio::time::timeout(Duration::from_secs(1), outbox.pushed())
+            .await
+            .unwrap();
+    }
+}
@@ -10,11 +10,16 @@ use miru_agent::models::config_instance::{
     ActivityStatus, ConfigInstance, ErrorStatus, TargetStatus,
 };
 use miru_agent::storage::config_instances::{ConfigInstanceCache, ConfigInstanceContentCache};
-use miru_agent::sync::config_instances::{pull, push, sync};
+use miru_agent::sync::config_instances::{pull, push, sync, BulkUpdates, DeployArgs};
+use miru_agent::sync::errors::SyncErr;
 
-use crate::http::mock::{CfgInstsCall, MockCfgInstsClient};
+use crate::http::mock::{CfgInstsCall, MockCfgInstsClient, MockClient};
 
-use openapi_client::models::UpdateConfigInstanceRequest;
+use openapi_client::models::{
+    BulkUpdateConfigInstance, BulkUpdateConfigInstanceResult, BulkUpdateConfigInstancesRequest,
+    BulkUpdateConfigInstancesResponse, ConfigInstanceActivityStatus, ConfigInstanceErrorStatus,
+    Error as BackendError, UpdateConfigInstanceRequest,
+};
 
 // external crates
 use serde_json::json;
@@ -37,7 +42,7 @@ pub mod sync {
             relative_filepath: "/test/filepath".to_string(),
             ..Default::default()
         };
-        let http_client = MockCfgInstsClient::default();
+        let http_client = MockClient::default();
         let new_instance_cloned = new_instance.clone();
         http_client.set_list_all_config_instances(move || Ok(vec![new_instance_cloned.clone()]));
 
@@ -58,8 +63,13 @@ pub mod sync {
             &cfg_inst_content_cache,
             &http_client,
             "device_id",
-            &dir,
-            &fsm::Settings::default(),
+            DeployArgs {
+                deployment_dir: &dir,
+                fsm_settings: &fsm::Settings::default(),
+                event_queue: None,
+                cfg_schema_content_cache: None,
+            },
+            &mut BulkUpdates::default(),
             "token",
         )
         .await
@@ -74,7 +84,12 @@ pub mod sync {
         assert_eq!(cache_cfg_inst_content, new_instance_data);
 
         // check that the http client was called to update the config instance
-        assert_eq!(http_client.num_update_config_instance_calls(), 1);
+        assert_eq!(
+            http_client
+                .config_instances_client
+                .num_update_config_instance_calls(),
+            1
+        );
 
         // check that the metadata cache isn't dirty
         let unsynced_entries = cfg_inst_cache.get_dirty_entries().await.unwrap();
@@ -86,7 +101,7 @@ pub mod sync {
         let dir = Dir::create_temp_dir("spawn").await.unwrap();
 
         // pull fails
-        let http_client = MockCfgInstsClient::default();
+        let http_client = MockClient::default();
         http_client.set_list_all_config_instances(move || {
             Err(HTTPErr::MockErr(Box::new(MockErr {
                 is_network_connection_error: false,
@@ -128,8 +143,13 @@ pub mod sync {
             &cfg_inst_content_cache,
             &http_client,
             "device_id",
-            &dir,
-            &fsm::Settings::default(),
+            DeployArgs {
+                deployment_dir: &dir,
+                fsm_settings: &fsm::Settings::default(),
+                event_queue: None,
+                cfg_schema_content_cache: None,
+            },
+            &mut BulkUpdates::default(),
             "token",
         )
         .await
@@ -140,7 +160,12 @@ pub mod sync {
         assert_eq!(cache_cfg_inst.activity_status, ActivityStatus::Deployed);
 
         // check that the http client was called to update the config instance
-        assert_eq!(http_client.num_update_config_instance_calls(), 1);
+        assert_eq!(
+            http_client
+                .config_instances_client
+                .num_update_config_instance_calls(),
+            1
+        );
 
         // check that the metadata cache isn't dirty
         let unsynced_entries = cfg_inst_cache.get_dirty_entries().await.unwrap();
@@ -152,7 +177,7 @@ pub mod sync {
         let dir = Dir::create_temp_dir("spawn").await.unwrap();
 
         // pull fails
-        let http_client = MockCfgInstsClient::default();
+        let http_client = MockClient::default();
         http_client.set_update_config_instance(move || {
             Err(HTTPErr::MockErr(Box::new(MockErr {
                 is_network_connection_error: false,
@@ -194,8 +219,13 @@ pub mod sync {
             &cfg_inst_content_cache,
             &http_client,
             "device_id",
-            &dir,
-            &fsm::Settings::default(),
+            DeployArgs {
+                deployment_dir: &dir,
+                fsm_settings: &fsm::Settings::default(),
+                event_queue: None,
+                cfg_schema_content_cache: None,
+            },
+            &mut BulkUpdates::default(),
             "token",
         )
         .await
@@ -206,7 +236,12 @@ pub mod sync {
         assert_eq!(cache_cfg_inst.activity_status, ActivityStatus::Deployed);
 
         // check that the http client was called to update the config instance
-        assert_eq!(http_client.num_update_config_instance_calls(), 1);
+        assert_eq!(
+            http_client
+                .config_instances_client
+                .num_update_config_instance_calls(),
+            1
+        );
 
         // check that the metadata cache is dirty
         let unsynced_entries = cfg_inst_cache.get_dirty_entries().await.unwrap();
@@ -533,7 +568,14 @@ pub mod push {
         let http_client = MockCfgInstsClient::default();
 
         // push the config instances
-        push(&metadata_cache, &http_client, "token").await.unwrap();
+        push(
+            &metadata_cache,
+            &http_client,
+            &mut BulkUpdates::default(),
+            "token",
+        )
+        .await
+        .unwrap();
 
         // check the history
         assert_eq!(http_client.num_update_config_instance_calls(), 0);
@@ -558,7 +600,14 @@ pub mod push {
         let http_client = MockCfgInstsClient::default();
 
         // push the config instances
-        push(&metadata_cache, &http_client, "token").await.unwrap();
+        push(
+            &metadata_cache,
+            &http_client,
+            &mut BulkUpdates::default(),
+            "token",
+        )
+        .await
+        .unwrap();
 
         // check the history
         assert_eq!(http_client.num_update_config_instance_calls(), 0);
@@ -587,11 +636,20 @@ pub mod push {
         let http_client = MockCfgInstsClient::default();
 
         // push the config instances
-        push(&metadata_cache, &http_client, "token").await.unwrap();
+        push(
+            &metadata_cache,
+            &http_client,
+            &mut BulkUpdates::default(),
+            "token",
+        )
+        .await
+        .unwrap();
 
-        // check the history
+        // check the history (the bulk update is unsupported so it falls back to
+        // updating the config instance individually)
+        assert_eq!(http_client.num_bulk_update_config_instances_calls(), 1);
         assert_eq!(http_client.num_update_config_instance_calls(), 1);
-        let actual = match &http_client.get_calls()[0] {
+        let actual = match &http_client.get_calls()[1] {
             CfgInstsCall::UpdateConfigInstance(request) => request.clone(),
             _ => panic!("Expected UpdateConfigInstance call"),
         };
@@ -634,9 +692,14 @@ pub mod push {
 
         // push the config instances -> should fail since the updates fail but each
         // config instance should be attempted to be updated
-        push(&metadata_cache, &http_client, "token")
-            .await
-            .unwrap_err();
+        push(
+            &metadata_cache,
+            &http_client,
+            &mut BulkUpdates::default(),
+            "token",
+        )
+        .await
+        .unwrap_err();
 
         // check the history
         assert_eq!(http_client.num_update_config_instance_calls(), n_cfg_insts);
@@ -646,3 +709,248 @@ pub mod push {
         assert_eq!(unsynced_entries.len(), n_cfg_insts);
     }
 }
+
+pub mod push_bulk {
+    use super::*;
+
+    async fn write_unsynced(cache: &ConfigInstanceCache, n: usize) -> Vec<ConfigInstance> {
+        let mut cfg_insts = Vec::new();
+        for _ in 0..n {
+            let cfg_inst = ConfigInstance {
+                activity_status: ActivityStatus::Deployed,
+                error_status: ErrorStatus::Retrying,
+                ..Default::default()
+            };
+            cache
+                .write(cfg_inst.id.clone(), cfg_inst.clone(), |_, _| true, true)
+                .await
+                .unwrap();
+            cfg_insts.push(cfg_inst);
+        }
+        cfg_insts
+    }
+
+    #[tokio::test]
+    async fn one_unsynced_instance() {
+        let dir = Dir::create_temp_dir("apply").await.unwrap();
+        let (metadata_cache, _) = ConfigInstanceCache::spawn(16, dir.file("metadata.json"), 1000)
+            .await
+            .unwrap();
+        let cfg_insts = write_unsynced(&metadata_cache, 1).await;
+
+        let http_client = MockCfgInstsClient::default();
+        http_client.set_bulk_update_config_instances_success();
+
+        push(
+            &metadata_cache,
+            &http_client,
+            &mut BulkUpdates::default(),
+            "token",
+        )
+        .await
+        .unwrap();
+
+        // check the history
+        let expected = vec![CfgInstsCall::BulkUpdateConfigInstances(
+            BulkUpdateConfigInstancesRequest {
+                updates: vec![BulkUpdateConfigInstance {
+                    id: cfg_insts[0].id.clone(),
+                    activity_status: Some(
+                        ConfigInstanceActivityStatus::CONFIG_INSTANCE_ACTIVITY_STATUS_DEPLOYED,
+                    ),
+                    error_status: Some(
+                        ConfigInstanceErrorStatus::CONFIG_INSTANCE_ERROR_STATUS_RETRYING,
+                    ),
+                }],
+            },
+        )];
+        assert_eq!(http_client.get_calls(), expected);
+
+        // check that the metadata cache is clean
+        let unsynced_entries = metadata_cache.get_dirty_entries().await.unwrap();
+        assert!(unsynced_entries.is_empty());
+    }
+
+    #[tokio::test]
+    async fn batches_large_pushes() {
+        let dir = Dir::create_temp_dir("apply").await.unwrap();
+        let (metadata_cache, _) = ConfigInstanceCache::spawn(16, dir.file("metadata.json"), 1000)
+            .await
+            .unwrap();
+        write_unsynced(&metadata_cache, 150).await;
+
+        let http_client = MockCfgInstsClient::default();
+        http_client.set_bulk_update_config_instances_success();
+
+        push(
+            &metadata_cache,
+            &http_client,
+            &mut BulkUpdates::default(),
+            "token",
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(http_client.num_bulk_update_config_instances_calls(), 2);
+        assert_eq!(http_client.num_update_config_instance_calls(), 0);
+        let unsynced_entries = metadata_cache.get_dirty_entries().await.unwrap();
+        assert!(unsynced_entries.is_empty());
+    }
+
+    #[tokio::test]
+    async fn partial_failure() {
+        let dir = Dir::create_temp_dir("apply").await.unwrap();
+        let (metadata_cache, _) = ConfigInstanceCache::spawn(16, dir.file("metadata.json"), 1000)
+            .await
+            .unwrap();
+        let cfg_insts = write_unsynced(&metadata_cache, 3).await;
+
+        // the first update fails, the second succeeds and the third is missing from
+        // the response
+        let failed_id = cfg_insts[0].id.clone();
+        let succeeded_id = cfg_insts[1].id.clone();
+        let http_client = MockCfgInstsClient::default();
+        http_client.set_bulk_update_config_instances(move |_| {
+            Ok(BulkUpdateConfigInstancesResponse {
+                results: vec![
+                    BulkUpdateConfigInstanceResult {
+                        id: failed_id.clone(),
+                        success: false,
+                        config_instance: None,
+                        error: Some(Box::new(BackendError {
+                            code: "invalid_transition".to_string(),
+                            params: None,
+                            message: "invalid transition".to_string(),
+                            debug_message: "invalid transition".to_string(),
+                        })),
+                    },
+                    BulkUpdateConfigInstanceResult {
+                        id: succeeded_id.clone(),
+                        success: true,
+                        config_instance: None,
+                        error: None,
+                    },
+                ],
+            })
+        });
+
+        let err = push(
+            &metadata_cache,
+            &http_client,
+            &mut BulkUpdates::default(),
+            "token",
+        )
+        .await
+        .unwrap_err();
+        match err {
+            SyncErr::SyncErrors(e) => {
+                assert_eq!(e.source.len(), 2);
+                assert!(e
+                    .source
+                    .iter()
+                    .all(|e| matches!(e, SyncErr::BulkUpdateItemErr(_))));
+            }
+            _ => panic!("Expected SyncErrors"),
+        }
+
+        // only the successfully updated config instance should be marked clean
+        assert_eq!(http_client.num_update_config_instance_calls(), 0);
+        let mut unsynced_ids: Vec<String> = metadata_cache
+            .get_dirty_entries()
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|entry| entry.key)
+            .collect();
+        unsynced_ids.sort();
+        let mut expected = vec![cfg_insts[0].id.clone(), cfg_insts[2].id.clone()];
+        expected.sort();
+        assert_eq!(unsynced_ids, expected);
+    }
+
+    #[tokio::test]
+    async fn bulk_request_error_does_not_fall_back() {
+        let dir = Dir::create_temp_dir("apply").await.unwrap();
+        let (metadata_cache, _) = ConfigInstanceCache::spawn(16, dir.file("metadata.json"), 1000)
+            .await
+            .unwrap();
+        write_unsynced(&metadata_cache, 5).await;
+
+        let http_client = MockCfgInstsClient::default();
+        http_client.set_bulk_update_config_instances(|_| {
+            Err(HTTPErr::MockErr(Box::new(MockErr {
+                is_network_connection_error: true,
+            })))
+        });
+
+        push(
+            &metadata_cache,
+            &http_client,
+            &mut BulkUpdates::default(),
+            "token",
+        )
+        .await
+        .unwrap_err();
+
+        assert_eq!(http_client.num_bulk_update_config_instances_calls(), 1);
+        assert_eq!(http_client.num_update_config_instance_calls(), 0);
+        let unsynced_entries = metadata_cache.get_dirty_entries().await.unwrap();
+        assert_eq!(unsynced_entries.len(), 5);
+    }
+
+    #[tokio::test]
+    async fn unsupported_falls_back_to_individual_updates() {
+        let dir = Dir::create_temp_dir("apply").await.unwrap();
+        let (metadata_cache, _) = ConfigInstanceCache::spawn(16, dir.file("metadata.json"), 1000)
+            .await
+            .unwrap();
+        write_unsynced(&metadata_cache, 150).await;
+
+        // bulk updates are unsupported by default
+        let http_client = MockCfgInstsClient::default();
+
+        push(
+            &metadata_cache,
+            &http_client,
+            &mut BulkUpdates::default(),
+            "token",
+        )
+        .await
+        .unwrap();
+
+        // the bulk update is only attempted once before falling back
+        assert_eq!(http_client.num_bulk_update_config_instances_calls(), 1);
+        assert_eq!(http_client.num_update_config_instance_calls(), 150);
+        let unsynced_entries = metadata_cache.get_dirty_entries().await.unwrap();
+        assert!(unsynced_entries.is_empty());
+    }
+
+    #[tokio::test]
+    async fn remembers_unsupported_bulk_updates() {
+        let dir = Dir::create_temp_dir("apply").await.unwrap();
+        let (metadata_cache, _) = ConfigInstanceCache::spawn(16, dir.file("metadata.json"), 1000)
+            .await
+            .unwrap();
+        let http_client = MockCfgInstsClient::default();
+        let mut bulk_updates = BulkUpdates::default();
+        assert!(bulk_updates.is_supported());
+
+        write_unsynced(&metadata_cache, 2).await;
+        push(&metadata_cache, &http_client, &mut bulk_updates, "token")
+            .await
+            .unwrap();
+        assert!(!bulk_updates.is_supported());
+        assert_eq!(http_client.num_bulk_update_config_instances_calls(), 1);
+        assert_eq!(http_client.num_update_config_instance_calls(), 2);
+
+        // the second push goes straight to individual updates
+        write_unsynced(&metadata_cache, 3).await;
+        push(&metadata_cache, &http_client, &mut bulk_updates, "token")
+            .await
+            .unwrap();
+        assert_eq!(http_client.num_bulk_update_config_instances_calls(), 1);
+        assert_eq!(http_client.num_update_config_instance_calls(), 5);
+        let unsynced_entries = metadata_cache.get_dirty_entries().await.unwrap();
+        assert!(unsynced_entries.is_empty());
+    }
+}
@@ -0,0 +1,174 @@
+// standard crates
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+
+// internal crates
+use miru_agent::crud::prelude::*;
+use miru_agent::filesys::dir::Dir;
+use miru_agent::http::errors::{HTTPErr, MockErr};
+use miru_agent::models::config_instance::{ConfigInstance, TargetStatus};
+use miru_agent::storage::config_instances::ConfigInstanceCache;
+use miru_agent::storage::config_schemas::ConfigSchemaContentCache;
+use miru_agent::sync::config_schemas::pull;
+use openapi_client::models::ConfigSchema as BackendConfigSchema;
+
+use crate::http::mock::MockCfgSchsClient;
+
+// external crates
+use serde_json::json;
+
+async fn create_caches(
+    dir: &Dir,
+    cfg_insts: Vec<ConfigInstance>,
+) -> (ConfigInstanceCache, ConfigSchemaContentCache) {
+    let (cfg_inst_cache, _) = ConfigInstanceCache::spawn(16, dir.file("cfg_insts.json"), 1000)
+        .await
+        .unwrap();
+    for cfg_inst in cfg_insts {
+        cfg_inst_cache
+            .write(cfg_inst.id.clone(), cfg_inst, |_, _| false, true)
+            .await
+            .unwrap();
+    }
+    let (cfg_schema_content_cache, _) =
+        ConfigSchemaContentCache::spawn(16, dir.subdir("cfg_schema_contents"), 1000)
+            .await
+            .unwrap();
+    (cfg_inst_cache, cfg_schema_content_cache)
+}
+
+pub mod pull_func {
+    use super::*;
+
+    #[tokio::test]
+    async fn nothing_to_pull() {
+        let dir = Dir::create_temp_dir("pull").await.unwrap();
+        let cfg_inst = ConfigInstance {
+            target_status: TargetStatus::Removed,
+            config_schema_id: "cfg_sch_123".to_string(),
+            ..Default::default()
+        };
+        let (cfg_inst_cache, cfg_schema_content_cache) = create_caches(&dir, vec![cfg_inst]).await;
+
+        // config instances which aren't being deployed don't need their schemas
+        let mut http_client = MockCfgSchsClient::default();
+        http_client.set_list_all_config_schemas(|| panic!("unexpected backend call"));
+
+        pull(
+            &cfg_inst_cache,
+            &cfg_schema_content_cache,
+            &http_client,
+            "token",
+        )
+        .await
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn pulls_uncached_schemas() {
+        let dir = Dir::create_temp_dir("pull").await.unwrap();
+        let cfg_insts = vec![
+            ConfigInstance {
+                target_status: TargetStatus::Deployed,
+                config_schema_id: "cfg_sch_1".to_string(),
+                ..Default::default()
+            },
+            ConfigInstance {
+                target_status: TargetStatus::Deployed,
+                config_schema_id: "cfg_sch_2".to_string(),
+                ..Default::default()
+            },
+        ];
+        let (cfg_inst_cache, cfg_schema_content_cache) = create_caches(&dir, cfg_insts).await;
+
+        let num_calls = Arc::new(AtomicUsize::new(0));
+        let num_calls_cloned = num_calls.clone();
+        let mut http_client = MockCfgSchsClient::default();
+        http_client.set_list_all_config_schemas(move || {
+            num_calls_cloned.fetch_add(1, Ordering::SeqCst);
+            Ok(vec![
+                BackendConfigSchema {
+                    id: "cfg_sch_1".to_string(),
+                    content: Some(json!({"type": "object"})),
+                    ..Default::default()
+                },
+                // schemas without content are skipped
+                BackendConfigSchema {
+                    id: "cfg_sch_2".to_string(),
+                    content: None,
+                    ..Default::default()
+                },
+            ])
+        });
+
+        pull(
+            &cfg_inst_cache,
+            &cfg_schema_content_cache,
+            &http_client,
+            "token",
+        )
+        .await
+        .unwrap();
+        assert_eq!(num_calls.load(Ordering::SeqCst), 1);
+
+        let content = cfg_schema_content_cache
+            .read("cfg_sch_1".to_string())
+            .await
+            .unwrap();
+        assert_eq!(content, json!({"type": "object"}));
+        let content = cfg_schema_content_cache
+            .read_optional("cfg_sch_2".to_string())
+            .await
+            .unwrap();
+        assert_eq!(content, None);
+
+        // cached schemas aren't pulled again
+        let (cfg_inst_cache, _) = create_caches(
+            &dir.subdir("cached"),
+            vec![ConfigInstance {
+                target_status: TargetStatus::Deployed,
+                config_schema_id: "cfg_sch_1".to_string(),
+                ..Default::default()
+            }],
+        )
+        .await;
+        pull(
+            &cfg_inst_cache,
+            &cfg_schema_content_cache,
+            &http_client,
+            "token",
+        )
+        .await
+        .unwrap();
+        assert_eq!(num_calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn pull_failure() {
+        let dir = Dir::create_temp_dir("pull").await.unwrap();
+        let cfg_inst = ConfigInstance {
+            target_status: TargetStatus::Deployed,
+            config_schema_id: "cfg_sch_123".to_string(),
+            ..Default::default()
+        };
+        let (cfg_inst_cache, cfg_schema_content_cache) = create_caches(&dir, vec![cfg_inst]).await;
+
+        let mut http_client = MockCfgSchsClient::default();
+        http_client.set_list_all_config_schemas(|| {
+            Err(HTTPErr::MockErr(Box::new(MockErr {
+                is_network_connection_error: false,
+            })))
+        });
+
+        pull(
+            &cfg_inst_cache,
+            &cfg_schema_content_cache,
+            &http_client,
+            "token",
+        )
+        .await
+        .unwrap_err();
+    }
+}
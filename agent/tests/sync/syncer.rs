@@ -1,5 +1,6 @@
 // standard crates
 use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
 // internal crates
 use miru_agent::authn::{
@@ -29,7 +30,7 @@ use miru_agent::sync::{
 use miru_agent::utils::{calc_exp_backoff, CooldownOptions};
 
 use crate::authn::token_mngr::spawn as spawn_token_manager;
-use crate::http::mock::{MockClient, MockDevicesClient};
+use crate::http::mock::{rate_limited, MockClient, MockDevicesClient};
 
 // external crates
 use chrono::{DateTime, TimeDelta, Utc};
@@ -127,8 +128,10 @@ pub mod shutdown {
                 token_mngr: Arc::new(token_mngr),
                 cfg_inst_cache: Arc::new(cfg_inst_cache),
                 cfg_inst_content_cache: Arc::new(cfg_inst_content_cache),
+                cfg_schema_content_cache: None,
                 deployment_dir: dir,
                 fsm_settings: fsm::Settings::default(),
+                event_queue: None,
                 cooldown_options: CooldownOptions::default(),
                 agent_version: Device::default().agent_version,
             },
@@ -177,8 +180,10 @@ pub mod subscribe {
                 token_mngr: Arc::new(token_mngr),
                 cfg_inst_cache: Arc::new(cfg_inst_cache),
                 cfg_inst_content_cache: Arc::new(cfg_inst_content_cache),
+                cfg_schema_content_cache: None,
                 deployment_dir: dir.clone(),
                 fsm_settings: fsm::Settings::default(),
+                event_queue: None,
                 cooldown_options,
                 agent_version: Device::default().agent_version,
             },
@@ -267,8 +272,10 @@ pub mod subscribe {
                 token_mngr: Arc::new(token_mngr),
                 cfg_inst_cache: Arc::new(cfg_inst_cache),
                 cfg_inst_content_cache: Arc::new(cfg_inst_content_cache),
+                cfg_schema_content_cache: None,
                 deployment_dir: dir.clone(),
                 fsm_settings: fsm::Settings::default(),
+                event_queue: None,
                 cooldown_options,
                 agent_version: Device::default().agent_version,
             },
@@ -374,8 +381,10 @@ pub mod sync {
                 token_mngr: Arc::new(token_mngr),
                 cfg_inst_cache: cfg_inst_cache.clone(),
                 cfg_inst_content_cache: cfg_inst_content_cache.clone(),
+                cfg_schema_content_cache: None,
                 deployment_dir: dir.clone(),
                 fsm_settings: fsm::Settings::default(),
+                event_queue: None,
                 cooldown_options,
                 agent_version: Device::default().agent_version,
             },
@@ -454,8 +463,10 @@ pub mod sync {
                 token_mngr: Arc::new(token_mngr),
                 cfg_inst_cache: cfg_inst_cache.clone(),
                 cfg_inst_content_cache: cfg_inst_content_cache.clone(),
+                cfg_schema_content_cache: None,
                 deployment_dir: dir.clone(),
                 fsm_settings: fsm::Settings::default(),
+                event_queue: None,
                 cooldown_options,
                 agent_version: new_agent_version.clone(),
             },
@@ -531,8 +542,10 @@ pub mod sync {
                 token_mngr: Arc::new(token_mngr),
                 cfg_inst_cache: Arc::new(cfg_inst_cache),
                 cfg_inst_content_cache: Arc::new(cfg_inst_content_cache),
+                cfg_schema_content_cache: None,
                 deployment_dir: dir.clone(),
                 fsm_settings: fsm::Settings::default(),
+                event_queue: None,
                 cooldown_options,
                 agent_version: Device::default().agent_version,
             },
@@ -623,8 +636,10 @@ pub mod sync {
                 token_mngr: Arc::new(token_mngr),
                 cfg_inst_cache: Arc::new(cfg_inst_cache),
                 cfg_inst_content_cache: Arc::new(cfg_inst_content_cache),
+                cfg_schema_content_cache: None,
                 deployment_dir: dir.clone(),
                 fsm_settings: fsm::Settings::default(),
+                event_queue: None,
                 cooldown_options,
                 agent_version: Device::default().agent_version,
             },
@@ -719,8 +734,10 @@ pub mod sync {
                 token_mngr: Arc::new(token_mngr),
                 cfg_inst_cache: Arc::new(cfg_inst_cache),
                 cfg_inst_content_cache: Arc::new(cfg_inst_content_cache),
+                cfg_schema_content_cache: None,
                 deployment_dir: dir.clone(),
                 fsm_settings: fsm::Settings::default(),
+                event_queue: None,
                 cooldown_options,
                 agent_version: Device::default().agent_version,
             },
@@ -894,8 +911,10 @@ pub mod sync {
                 token_mngr: Arc::new(token_mngr),
                 cfg_inst_cache: Arc::new(cfg_inst_cache),
                 cfg_inst_content_cache: Arc::new(cfg_inst_content_cache),
+                cfg_schema_content_cache: None,
                 deployment_dir: dir.clone(),
                 fsm_settings: fsm::Settings::default(),
+                event_queue: None,
                 cooldown_options,
                 agent_version: Device::default().agent_version,
             },
@@ -917,6 +936,86 @@ pub mod sync {
         let error = syncer.sync().await.unwrap_err();
         assert!(matches!(error, SyncErr::InCooldownErr(_)));
     }
+
+    #[tokio::test]
+    async fn retry_after() {
+        let dir = Dir::create_temp_dir("spawn").await.unwrap();
+        let auth_client = Arc::new(MockDevicesClient::default());
+        let (token_mngr, _) = create_token_manager(&dir, auth_client.clone()).await;
+
+        // the backend asks the agent to back off for longer than its own cooldown
+        let http_client = Arc::new(MockClient::default());
+        http_client.set_list_all_config_instances(|| Err(rate_limited(Duration::from_secs(600))));
+
+        // create the caches
+        let (cfg_inst_cache, _) =
+            ConfigInstanceCache::spawn(16, dir.file("cfg_inst_cache.json"), 1000)
+                .await
+                .unwrap();
+        let (cfg_inst_content_cache, _) =
+            ConfigInstanceContentCache::spawn(16, dir.subdir("cfg_inst_content_cache"), 1000)
+                .await
+                .unwrap();
+        let (device_file, _) =
+            DeviceFile::spawn_with_default(64, dir.file("device.json"), Device::default())
+                .await
+                .unwrap();
+
+        let cooldown_options = CooldownOptions {
+            base_secs: 10,
+            max_secs: 60 * 60,
+            ..CooldownOptions::default()
+        };
+        let (syncer, _) = spawn(
+            32,
+            SyncerArgs {
+                device_id: "device_id".to_string(),
+                device_file: Arc::new(device_file),
+                http_client: http_client.clone(),
+                token_mngr: Arc::new(token_mngr),
+                cfg_inst_cache: Arc::new(cfg_inst_cache),
+                cfg_inst_content_cache: Arc::new(cfg_inst_content_cache),
+                cfg_schema_content_cache: None,
+                deployment_dir: dir.clone(),
+                fsm_settings: fsm::Settings::default(),
+                event_queue: None,
+                cooldown_options,
+                agent_version: Device::default().agent_version,
+            },
+        )
+        .unwrap();
+
+        let before = Utc::now();
+        let error = syncer.sync().await.unwrap_err();
+        let after = Utc::now();
+        assert_eq!(error.retry_after(), Some(Duration::from_secs(600)));
+
+        // the cooldown is taken from the retry after value
+        let state = syncer.get_sync_state().await.unwrap();
+        let cooldown_duration = TimeDelta::seconds(600);
+        assert!(state.cooldown_ends_at > before + cooldown_duration);
+        assert!(state.cooldown_ends_at < after + cooldown_duration);
+        assert_eq!(state.err_streak, 1);
+
+        // the retry after value is capped at the max cooldown
+        #[cfg(feature = "test")]
+        syncer
+            .set_sync_state(SyncState {
+                cooldown_ends_at: before,
+                ..state
+            })
+            .await
+            .unwrap();
+        http_client
+            .set_list_all_config_instances(|| Err(rate_limited(Duration::from_secs(24 * 60 * 60))));
+        let before = Utc::now();
+        syncer.sync().await.unwrap_err();
+        let after = Utc::now();
+        let state = syncer.get_sync_state().await.unwrap();
+        let cooldown_duration = TimeDelta::seconds(cooldown_options.max_secs);
+        assert!(state.cooldown_ends_at > before + cooldown_duration);
+        assert!(state.cooldown_ends_at < after + cooldown_duration);
+    }
 }
 
 pub mod sync_if_not_in_cooldown {
@@ -956,8 +1055,10 @@ pub mod sync_if_not_in_cooldown {
                 token_mngr: Arc::new(token_mngr),
                 cfg_inst_cache: Arc::new(cfg_inst_cache),
                 cfg_inst_content_cache: Arc::new(cfg_inst_content_cache),
+                cfg_schema_content_cache: None,
                 deployment_dir: dir.clone(),
                 fsm_settings: fsm::Settings::default(),
+                event_queue: None,
                 cooldown_options,
                 agent_version: Device::default().agent_version,
             },
@@ -2,7 +2,7 @@
 use std::time::Duration;
 
 // internal crates
-use miru_agent::utils::{as_duration, calc_exp_backoff};
+use miru_agent::utils::{as_duration, calc_exp_backoff, calc_retry_after_secs, CooldownOptions};
 
 // external crates
 use chrono::TimeDelta;
@@ -39,3 +39,22 @@ fn test_calc_exp_backoff() {
     assert_eq!(calc_exp_backoff(3, 4, 2, 56), 48);
     assert_eq!(calc_exp_backoff(3, 4, 3, 56), 56);
 }
+
+#[test]
+fn test_calc_retry_after_secs() {
+    let cooldown = CooldownOptions {
+        base_secs: 1,
+        growth_factor: 2,
+        max_secs: 60,
+    };
+    assert_eq!(calc_retry_after_secs(Duration::ZERO, cooldown), 0);
+    assert_eq!(calc_retry_after_secs(Duration::from_secs(30), cooldown), 30);
+    // partial seconds are rounded up
+    assert_eq!(
+        calc_retry_after_secs(Duration::from_millis(1500), cooldown),
+        2
+    );
+    // capped at the max cooldown
+    assert_eq!(calc_retry_after_secs(Duration::from_secs(61), cooldown), 60);
+    assert_eq!(calc_retry_after_secs(Duration::MAX, cooldown), 60);
+}
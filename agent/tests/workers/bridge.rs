@@ -0,0 +1,493 @@
+// standard crates
+use std::collections::HashMap;
+
+// internal crates
+use miru_agent::filesys::{dir::Dir, file::File};
+use miru_agent::models::config_instance::{ActivityStatus, ConfigInstance};
+use miru_agent::models::config_schema::ConfigSchema;
+use miru_agent::mqtt::{
+    bridge::LocalConfig,
+    errors::{MQTTError, MockErr},
+};
+use miru_agent::storage::{
+    config_instances::{ConfigInstanceCache, ConfigInstanceContentCache},
+    config_schemas::ConfigSchemaCache,
+};
+use miru_agent::workers::bridge::{deployed_configs, load_published, reconcile, Args};
+
+use crate::mqtt::mock::{LocalBridgeCall, MockLocalBridgeClient};
+use crate::sync::mock::MockSyncer;
+
+// external crates
+use chrono::{DateTime, Duration, Utc};
+use serde_json::json;
+
+const TOPIC_PREFIX: &str = "miru/configs";
+
+struct Fixture {
+    syncer: MockSyncer,
+    cfg_inst_cache: ConfigInstanceCache,
+    cfg_inst_content_cache: ConfigInstanceContentCache,
+    cfg_schema_cache: ConfigSchemaCache,
+    published_topics: File,
+}
+
+impl Fixture {
+    async fn new() -> Self {
+        let dir = Dir::create_temp_dir("testing").await.unwrap();
+        let (cfg_inst_cache, _) = ConfigInstanceCache::spawn(32, dir.file("cfg_insts.json"), 1000)
+            .await
+            .unwrap();
+        let (cfg_inst_content_cache, _) =
+            ConfigInstanceContentCache::spawn(32, dir.subdir("contents"), 1000)
+                .await
+                .unwrap();
+        let (cfg_schema_cache, _) = ConfigSchemaCache::spawn(32, dir.file("schemas.json"), 1000)
+            .await
+            .unwrap();
+        Self {
+            syncer: MockSyncer::default(),
+            cfg_inst_cache,
+            cfg_inst_content_cache,
+            cfg_schema_cache,
+            published_topics: dir.file("published_topics.json"),
+        }
+    }
+
+    fn args(&self) -> Args<'_, MockSyncer> {
+        Args {
+            syncer: &self.syncer,
+            cfg_inst_cache: &self.cfg_inst_cache,
+            cfg_inst_content_cache: &self.cfg_inst_content_cache,
+            cfg_schema_cache: &self.cfg_schema_cache,
+            published_topics: &self.published_topics,
+        }
+    }
+
+    async fn write_schema(&self, cfg_schema_id: &str, config_type_slug: &str) {
+        let cfg_schema = ConfigSchema {
+            id: cfg_schema_id.to_string(),
+            config_type_slug: Some(config_type_slug.to_string()),
+            ..Default::default()
+        };
+        self.cfg_schema_cache
+            .write(cfg_schema.id.clone(), cfg_schema, |_, _| false, true)
+            .await
+            .unwrap();
+    }
+
+    async fn write_instance(&self, cfg_inst: &ConfigInstance, content: serde_json::Value) {
+        self.cfg_inst_cache
+            .write(cfg_inst.id.clone(), cfg_inst.clone(), |_, _| false, true)
+            .await
+            .unwrap();
+        self.cfg_inst_content_cache
+            .write(cfg_inst.id.clone(), content, |_, _| false, true)
+            .await
+            .unwrap();
+    }
+}
+
+fn new_instance(
+    id: &str,
+    cfg_schema_id: &str,
+    activity_status: ActivityStatus,
+    updated_at: DateTime<Utc>,
+) -> ConfigInstance {
+    ConfigInstance {
+        id: id.to_string(),
+        activity_status,
+        config_schema_id: cfg_schema_id.to_string(),
+        config_type_id: format!("type_{cfg_schema_id}"),
+        updated_at,
+        ..Default::default()
+    }
+}
+
+fn mock_err() -> MQTTError {
+    MQTTError::MockErr(Box::new(MockErr {
+        is_authentication_error: false,
+        is_network_connection_error: true,
+    }))
+}
+
+pub mod deployed_configs {
+    use super::*;
+
+    #[tokio::test]
+    async fn empty() {
+        let fixture = Fixture::new().await;
+        let configs = deployed_configs(TOPIC_PREFIX, &fixture.args())
+            .await
+            .unwrap();
+        assert!(configs.is_empty());
+    }
+
+    #[tokio::test]
+    async fn published_per_config_type_slug() {
+        let fixture = Fixture::new().await;
+        fixture.write_schema("schema_1", "motion-control").await;
+        let cfg_inst = new_instance("inst_1", "schema_1", ActivityStatus::Deployed, Utc::now());
+        fixture
+            .write_instance(&cfg_inst, json!({"max_speed": 2}))
+            .await;
+
+        let configs = deployed_configs(TOPIC_PREFIX, &fixture.args())
+            .await
+            .unwrap();
+        let expected = HashMap::from([(
+            "miru/configs/motion-control".to_string(),
+            LocalConfig::new(
+                &cfg_inst,
+                Some("motion-control".to_string()),
+                json!({"max_speed": 2}),
+            ),
+        )]);
+        assert_eq!(configs, expected);
+    }
+
+    #[tokio::test]
+    async fn unknown_slug_uses_config_type_id() {
+        let fixture = Fixture::new().await;
+        let cfg_inst = new_instance("inst_1", "schema_1", ActivityStatus::Deployed, Utc::now());
+        fixture.write_instance(&cfg_inst, json!({})).await;
+
+        let configs = deployed_configs(TOPIC_PREFIX, &fixture.args())
+            .await
+            .unwrap();
+        let config = &configs["miru/configs/type_schema_1"];
+        assert_eq!(config.metadata.config_type_slug, None);
+        assert_eq!(config.metadata.config_instance_id, "inst_1");
+    }
+
+    #[tokio::test]
+    async fn ignores_instances_which_are_not_deployed() {
+        let fixture = Fixture::new().await;
+        fixture.write_schema("schema_1", "motion-control").await;
+        for (i, activity_status) in ActivityStatus::variants().into_iter().enumerate() {
+            if activity_status == ActivityStatus::Deployed {
+                continue;
+            }
+            let cfg_inst = new_instance(
+                &format!("inst_{i}"),
+                "schema_1",
+                activity_status,
+                Utc::now(),
+            );
+            fixture.write_instance(&cfg_inst, json!({})).await;
+        }
+
+        let configs = deployed_configs(TOPIC_PREFIX, &fixture.args())
+            .await
+            .unwrap();
+        assert!(configs.is_empty());
+    }
+
+    #[tokio::test]
+    async fn most_recently_updated_instance_wins() {
+        let fixture = Fixture::new().await;
+        fixture.write_schema("schema_1", "motion-control").await;
+        fixture.write_schema("schema_2", "motion-control").await;
+        let now = Utc::now();
+        let newer = new_instance("newer", "schema_2", ActivityStatus::Deployed, now);
+        let older = new_instance(
+            "older",
+            "schema_1",
+            ActivityStatus::Deployed,
+            now - Duration::minutes(5),
+        );
+        fixture.write_instance(&newer, json!({"version": 2})).await;
+        fixture.write_instance(&older, json!({"version": 1})).await;
+
+        let configs = deployed_configs(TOPIC_PREFIX, &fixture.args())
+            .await
+            .unwrap();
+        assert_eq!(configs.len(), 1);
+        let config = &configs["miru/configs/motion-control"];
+        assert_eq!(config.metadata.config_instance_id, "newer");
+        assert_eq!(config.content, json!({"version": 2}));
+    }
+}
+
+pub mod reconcile {
+    use super::*;
+
+    #[tokio::test]
+    async fn publishes_only_changes() {
+        let fixture = Fixture::new().await;
+        fixture.write_schema("schema_1", "motion-control").await;
+        let mut cfg_inst = new_instance("inst_1", "schema_1", ActivityStatus::Deployed, Utc::now());
+        fixture
+            .write_instance(&cfg_inst, json!({"max_speed": 2}))
+            .await;
+        let client = MockLocalBridgeClient::default();
+        let mut published = HashMap::new();
+
+        reconcile(
+            &client,
+            TOPIC_PREFIX,
+            &fixture.args(),
+            &mut published,
+            false,
+        )
+        .await;
+        let expected = LocalConfig::new(
+            &cfg_inst,
+            Some("motion-control".to_string()),
+            json!({"max_speed": 2}),
+        );
+        assert_eq!(
+            client.take_calls(),
+            vec![LocalBridgeCall::PublishLocalConfig {
+                topic: "miru/configs/motion-control".to_string(),
+                config: expected,
+            }]
+        );
+
+        // unchanged configs aren't republished
+        reconcile(
+            &client,
+            TOPIC_PREFIX,
+            &fixture.args(),
+            &mut published,
+            false,
+        )
+        .await;
+        assert!(client.take_calls().is_empty());
+
+        // unless republishing every config
+        reconcile(&client, TOPIC_PREFIX, &fixture.args(), &mut published, true).await;
+        assert_eq!(client.take_calls().len(), 1);
+
+        // changed configs are republished
+        cfg_inst.patch_id = Some("patch_1".to_string());
+        fixture
+            .write_instance(&cfg_inst, json!({"max_speed": 3}))
+            .await;
+        reconcile(
+            &client,
+            TOPIC_PREFIX,
+            &fixture.args(),
+            &mut published,
+            false,
+        )
+        .await;
+        let expected = LocalConfig::new(
+            &cfg_inst,
+            Some("motion-control".to_string()),
+            json!({"max_speed": 3}),
+        );
+        assert_eq!(
+            client.take_calls(),
+            vec![LocalBridgeCall::PublishLocalConfig {
+                topic: "miru/configs/motion-control".to_string(),
+                config: expected,
+            }]
+        );
+    }
+
+    #[tokio::test]
+    async fn clears_removed_configs() {
+        let fixture = Fixture::new().await;
+        fixture.write_schema("schema_1", "motion-control").await;
+        let mut cfg_inst = new_instance("inst_1", "schema_1", ActivityStatus::Deployed, Utc::now());
+        fixture.write_instance(&cfg_inst, json!({})).await;
+        let client = MockLocalBridgeClient::default();
+        let mut published = HashMap::new();
+
+        reconcile(
+            &client,
+            TOPIC_PREFIX,
+            &fixture.args(),
+            &mut published,
+            false,
+        )
+        .await;
+        client.take_calls();
+
+        cfg_inst.activity_status = ActivityStatus::Removed;
+        fixture.write_instance(&cfg_inst, json!({})).await;
+        reconcile(
+            &client,
+            TOPIC_PREFIX,
+            &fixture.args(),
+            &mut published,
+            false,
+        )
+        .await;
+        assert_eq!(
+            client.take_calls(),
+            vec![LocalBridgeCall::ClearLocalConfig {
+                topic: "miru/configs/motion-control".to_string(),
+            }]
+        );
+        assert!(published.is_empty());
+
+        // cleared configs aren't cleared again
+        reconcile(
+            &client,
+            TOPIC_PREFIX,
+            &fixture.args(),
+            &mut published,
+            false,
+        )
+        .await;
+        assert!(client.take_calls().is_empty());
+    }
+
+    #[tokio::test]
+    async fn failures_are_retried() {
+        let fixture = Fixture::new().await;
+        fixture.write_schema("schema_1", "motion-control").await;
+        let mut cfg_inst = new_instance("inst_1", "schema_1", ActivityStatus::Deployed, Utc::now());
+        fixture.write_instance(&cfg_inst, json!({})).await;
+        let mut client = MockLocalBridgeClient::default();
+        client.set_publish_local_config(|| Err(mock_err()));
+        client.set_clear_local_config(|| Err(mock_err()));
+        let mut published = HashMap::new();
+
+        // failed publishes are retried
+        reconcile(
+            &client,
+            TOPIC_PREFIX,
+            &fixture.args(),
+            &mut published,
+            false,
+        )
+        .await;
+        assert!(published.is_empty());
+        client.set_publish_local_config(|| Ok(()));
+        reconcile(
+            &client,
+            TOPIC_PREFIX,
+            &fixture.args(),
+            &mut published,
+            false,
+        )
+        .await;
+        assert_eq!(published.len(), 1);
+        client.take_calls();
+
+        // failed clears are retried
+        cfg_inst.activity_status = ActivityStatus::Removed;
+        fixture.write_instance(&cfg_inst, json!({})).await;
+        reconcile(
+            &client,
+            TOPIC_PREFIX,
+            &fixture.args(),
+            &mut published,
+            false,
+        )
+        .await;
+        assert_eq!(published.len(), 1);
+        client.set_clear_local_config(|| Ok(()));
+        reconcile(
+            &client,
+            TOPIC_PREFIX,
+            &fixture.args(),
+            &mut published,
+            false,
+        )
+        .await;
+        assert!(published.is_empty());
+        assert_eq!(client.take_calls().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn persists_published_topics() {
+        let fixture = Fixture::new().await;
+        fixture.write_schema("schema_1", "motion-control").await;
+        let mut cfg_inst = new_instance("inst_1", "schema_1", ActivityStatus::Deployed, Utc::now());
+        fixture.write_instance(&cfg_inst, json!({})).await;
+        let client = MockLocalBridgeClient::default();
+        let mut published = HashMap::new();
+
+        reconcile(
+            &client,
+            TOPIC_PREFIX,
+            &fixture.args(),
+            &mut published,
+            false,
+        )
+        .await;
+        let topics = fixture
+            .published_topics
+            .read_json::<Vec<String>>()
+            .await
+            .unwrap();
+        assert_eq!(topics, vec!["miru/configs/motion-control".to_string()]);
+
+        cfg_inst.activity_status = ActivityStatus::Removed;
+        fixture.write_instance(&cfg_inst, json!({})).await;
+        reconcile(
+            &client,
+            TOPIC_PREFIX,
+            &fixture.args(),
+            &mut published,
+            false,
+        )
+        .await;
+        let topics = fixture
+            .published_topics
+            .read_json::<Vec<String>>()
+            .await
+            .unwrap();
+        assert!(topics.is_empty());
+    }
+
+    #[tokio::test]
+    async fn clears_topics_published_by_a_previous_run() {
+        let fixture = Fixture::new().await;
+        fixture.write_schema("schema_1", "motion-control").await;
+        let cfg_inst = new_instance("inst_1", "schema_1", ActivityStatus::Deployed, Utc::now());
+        fixture.write_instance(&cfg_inst, json!({})).await;
+        let topics = vec![
+            "miru/configs/motion-control".to_string(),
+            "miru/configs/removed".to_string(),
+        ];
+        fixture
+            .published_topics
+            .write_json(&topics, true, false)
+            .await
+            .unwrap();
+        let client = MockLocalBridgeClient::default();
+
+        // the deployed config is republished (its retained config is unknown) and the
+        // removed config is cleared
+        let mut published = load_published(&fixture.published_topics).await;
+        assert_eq!(published.len(), 2);
+        reconcile(
+            &client,
+            TOPIC_PREFIX,
+            &fixture.args(),
+            &mut published,
+            false,
+        )
+        .await;
+        let expected = LocalConfig::new(&cfg_inst, Some("motion-control".to_string()), json!({}));
+        assert_eq!(
+            client.take_calls(),
+            vec![
+                LocalBridgeCall::PublishLocalConfig {
+                    topic: "miru/configs/motion-control".to_string(),
+                    config: expected,
+                },
+                LocalBridgeCall::ClearLocalConfig {
+                    topic: "miru/configs/removed".to_string(),
+                },
+            ]
+        );
+        let topics = fixture
+            .published_topics
+            .read_json::<Vec<String>>()
+            .await
+            .unwrap();
+        assert_eq!(topics, vec!["miru/configs/motion-control".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn nothing_published_by_a_previous_run() {
+        let fixture = Fixture::new().await;
+        assert!(load_published(&fixture.published_topics).await.is_empty());
+    }
+}
@@ -1,3 +1,5 @@
+pub mod bridge;
+pub mod commands;
 pub mod mqtt;
 pub mod poller;
 pub mod token_refresh;
@@ -1,23 +1,33 @@
 // internal crates
 use miru_agent::authn::token::Token;
 use miru_agent::filesys::dir::Dir;
+use miru_agent::models::config_instance::ConfigInstance;
 use miru_agent::models::device::{Device, DeviceStatus};
 use miru_agent::mqtt::{
     client::{MQTTClient, Options},
-    device::{Ping, SyncDevice},
+    device::{Ping, Presence, PresenceStatus, SyncDevice},
     errors::*,
+    events::ConfigInstanceEvent,
     topics,
 };
-use miru_agent::storage::{device::DeviceFile, layout::StorageLayout};
+use miru_agent::storage::{device::DeviceFile, events::EventQueue, layout::StorageLayout};
 use miru_agent::sync::{
     errors::{MockErr as SyncMockErr, SyncErr},
     syncer::{CooldownEnd, SyncEvent, SyncFailure},
 };
-use miru_agent::workers::mqtt::{self, handle_error, handle_event, handle_syncer_event};
+use miru_agent::workers::commands;
+use miru_agent::workers::mqtt::{
+    self, handle_error, handle_event, handle_syncer_event, publish_events, publish_presence,
+};
 
 use crate::authn::mock::MockTokenManager;
 use crate::mqtt::mock::MockDeviceClient;
 use crate::sync::mock::MockSyncer;
+use crate::workers::commands::create_commands;
+
+// standard crates
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
 
 // external crates
 use chrono::Utc;
@@ -69,8 +79,16 @@ pub mod handle_connection_events {
         }));
         let mqtt_client = MockDeviceClient::default();
         let syncer = MockSyncer::default();
-        let err_streak =
-            handle_event(&event, &mqtt_client, &syncer, "device_id", &device_file).await;
+        let (commands, _) = create_commands(&layout, commands::Options::default()).await;
+        let err_streak = handle_event(
+            &event.into(),
+            &mqtt_client,
+            &syncer,
+            "device_id",
+            &device_file,
+            &commands,
+        )
+        .await;
         assert_eq!(err_streak, 0);
 
         assert_eq!(syncer.num_sync_calls(), 0);
@@ -99,9 +117,17 @@ pub mod handle_connection_events {
         }));
         let mqtt_client = MockDeviceClient::default();
         let syncer = MockSyncer::default();
+        let (commands, _) = create_commands(&layout, commands::Options::default()).await;
         let before_event = Utc::now();
-        let err_streak =
-            handle_event(&event, &mqtt_client, &syncer, "device_id", &device_file).await;
+        let err_streak = handle_event(
+            &event.into(),
+            &mqtt_client,
+            &syncer,
+            "device_id",
+            &device_file,
+            &commands,
+        )
+        .await;
         assert_eq!(err_streak, 0);
 
         let device = device_file.read().await.unwrap();
@@ -130,9 +156,17 @@ pub mod handle_connection_events {
         let event = Event::Incoming(Incoming::Disconnect);
         let mqtt_client = MockDeviceClient::default();
         let syncer = MockSyncer::default();
+        let (commands, _) = create_commands(&layout, commands::Options::default()).await;
         let before_event = Utc::now();
-        let err_streak =
-            handle_event(&event, &mqtt_client, &syncer, "device_id", &device_file).await;
+        let err_streak = handle_event(
+            &event.into(),
+            &mqtt_client,
+            &syncer,
+            "device_id",
+            &device_file,
+            &commands,
+        )
+        .await;
         assert_eq!(err_streak, 0);
 
         let device = device_file.read().await.unwrap();
@@ -163,8 +197,16 @@ pub mod handle_sync_events {
         )));
         let mqtt_client = MockDeviceClient::default();
         let syncer = MockSyncer::default();
-        let err_streak =
-            handle_event(&event, &mqtt_client, &syncer, &device.id, &device_file).await;
+        let (commands, _) = create_commands(&layout, commands::Options::default()).await;
+        let err_streak = handle_event(
+            &event.into(),
+            &mqtt_client,
+            &syncer,
+            &device.id,
+            &device_file,
+            &commands,
+        )
+        .await;
         assert_eq!(err_streak, 0);
 
         assert_eq!(syncer.num_sync_calls(), 1);
@@ -190,8 +232,16 @@ pub mod handle_sync_events {
         )));
         let mqtt_client = MockDeviceClient::default();
         let syncer = MockSyncer::default();
-        let err_streak =
-            handle_event(&event, &mqtt_client, &syncer, &device.id, &device_file).await;
+        let (commands, _) = create_commands(&layout, commands::Options::default()).await;
+        let err_streak = handle_event(
+            &event.into(),
+            &mqtt_client,
+            &syncer,
+            &device.id,
+            &device_file,
+            &commands,
+        )
+        .await;
         assert_eq!(err_streak, 0);
 
         assert_eq!(syncer.num_sync_calls(), 0);
@@ -217,8 +267,16 @@ pub mod handle_sync_events {
         )));
         let mqtt_client = MockDeviceClient::default();
         let syncer = MockSyncer::default();
-        let err_streak =
-            handle_event(&event, &mqtt_client, &syncer, &device.id, &device_file).await;
+        let (commands, _) = create_commands(&layout, commands::Options::default()).await;
+        let err_streak = handle_event(
+            &event.into(),
+            &mqtt_client,
+            &syncer,
+            &device.id,
+            &device_file,
+            &commands,
+        )
+        .await;
         assert_eq!(err_streak, 0);
 
         assert_eq!(syncer.num_sync_calls(), 1);
@@ -244,13 +302,21 @@ pub mod handle_sync_events {
         )));
         let mqtt_client = MockDeviceClient::default();
         let syncer = MockSyncer::default();
+        let (commands, _) = create_commands(&layout, commands::Options::default()).await;
         syncer.set_sync(|| {
             Err(SyncErr::MockErr(Box::new(SyncMockErr {
                 is_network_connection_error: false,
             })))
         });
-        let err_streak =
-            handle_event(&event, &mqtt_client, &syncer, &device.id, &device_file).await;
+        let err_streak = handle_event(
+            &event.into(),
+            &mqtt_client,
+            &syncer,
+            &device.id,
+            &device_file,
+            &commands,
+        )
+        .await;
         assert_eq!(err_streak, 0);
 
         assert_eq!(syncer.num_sync_calls(), 1);
@@ -278,8 +344,16 @@ pub mod handle_ping_events {
         )));
         let mqtt_client = MockDeviceClient::default();
         let syncer = MockSyncer::default();
-        let err_streak =
-            handle_event(&event, &mqtt_client, &syncer, &device.id, &device_file).await;
+        let (commands, _) = create_commands(&layout, commands::Options::default()).await;
+        let err_streak = handle_event(
+            &event.into(),
+            &mqtt_client,
+            &syncer,
+            &device.id,
+            &device_file,
+            &commands,
+        )
+        .await;
         assert_eq!(err_streak, 0);
 
         assert_eq!(mqtt_client.num_publish_device_pong_calls(), 0);
@@ -308,8 +382,16 @@ pub mod handle_ping_events {
         )));
         let mqtt_client = MockDeviceClient::default();
         let syncer = MockSyncer::default();
-        let err_streak =
-            handle_event(&event, &mqtt_client, &syncer, &device.id, &device_file).await;
+        let (commands, _) = create_commands(&layout, commands::Options::default()).await;
+        let err_streak = handle_event(
+            &event.into(),
+            &mqtt_client,
+            &syncer,
+            &device.id,
+            &device_file,
+            &commands,
+        )
+        .await;
         assert_eq!(err_streak, 0);
 
         assert_eq!(mqtt_client.num_publish_device_pong_calls(), 1);
@@ -355,15 +437,7 @@ pub mod handle_mqtt_error {
             eventloop,
             err_streak: 2,
         };
-        let state = handle_error(
-            state,
-            error,
-            &device,
-            &token_mngr,
-            &options.connect_address,
-            &device_file,
-        )
-        .await;
+        let state = handle_error(state, error, &device, &token_mngr, &options, &device_file).await;
         assert_eq!(token_mngr.num_refresh_token_calls(), 1);
 
         // should increment the error streak
@@ -414,15 +488,7 @@ pub mod handle_mqtt_error {
             eventloop,
             err_streak: 1,
         };
-        let state = handle_error(
-            state,
-            error,
-            &device,
-            &token_mngr,
-            &options.connect_address,
-            &device_file,
-        )
-        .await;
+        let state = handle_error(state, error, &device, &token_mngr, &options, &device_file).await;
         assert_eq!(token_mngr.num_refresh_token_calls(), 0);
 
         // should not increment the error streak
@@ -438,3 +504,319 @@ pub mod handle_mqtt_error {
         assert!(device.last_disconnected_at <= Utc::now());
     }
 }
+
+pub mod publish_device_presence {
+    use super::*;
+
+    #[tokio::test]
+    async fn publishes_online_presence() {
+        let mqtt_client = MockDeviceClient::default();
+        publish_presence(&mqtt_client, "device_id", "session_id").await;
+        let presences = mqtt_client.device_presences();
+        assert_eq!(presences.len(), 1);
+        assert_eq!(presences[0].device_id, "device_id");
+        assert_eq!(
+            presences[0].presence,
+            Presence::new(PresenceStatus::Online, "session_id")
+        );
+    }
+
+    #[tokio::test]
+    async fn publish_error_is_ignored() {
+        let mut mqtt_client = MockDeviceClient::default();
+        mqtt_client.set_publish_device_presence(|| {
+            Err(MQTTError::MockErr(Box::new(MockErr {
+                is_authentication_error: false,
+                is_network_connection_error: true,
+            })))
+        });
+        publish_presence(&mqtt_client, "device_id", "session_id").await;
+        assert_eq!(mqtt_client.device_presences().len(), 1);
+    }
+}
+
+pub mod publish_queued_events {
+    use super::*;
+
+    async fn create_queue(num_events: usize) -> (EventQueue, Vec<ConfigInstanceEvent>) {
+        let dir = Dir::create_temp_dir("testing").await.unwrap();
+        let (event_queue, _) = EventQueue::spawn(16, dir.file("events.json"), 100)
+            .await
+            .unwrap();
+        let mut events = Vec::new();
+        for _ in 0..num_events {
+            let event = ConfigInstanceEvent::new(&ConfigInstance::default());
+            event_queue.push(event.clone()).await.unwrap();
+            events.push(event);
+        }
+        (event_queue, events)
+    }
+
+    #[tokio::test]
+    async fn publishes_and_removes_acked_events() {
+        let (event_queue, events) = create_queue(3).await;
+        let mqtt_client = MockDeviceClient::default();
+
+        let removed = publish_events(&mqtt_client, "device_id", &event_queue).await;
+        assert!(!removed);
+        let published = mqtt_client.config_instance_events();
+        assert_eq!(published.len(), 3);
+        for (call, event) in published.iter().zip(events.iter()) {
+            assert_eq!(call.device_id, "device_id");
+            assert_eq!(&call.event, event);
+        }
+        assert_eq!(
+            topics::device_config_instance_events("device_id"),
+            "v1/evt/devices/device_id/config_instances"
+        );
+
+        // events stay queued until the broker acknowledges them
+        assert_eq!(event_queue.peek(100).await.unwrap(), events);
+        mqtt_client.ack_all();
+        let removed = publish_events(&mqtt_client, "device_id", &event_queue).await;
+        assert!(removed);
+        assert!(event_queue.is_empty().await.unwrap());
+        assert_eq!(mqtt_client.config_instance_events().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn empty_queue() {
+        let (event_queue, _) = create_queue(0).await;
+        let mqtt_client = MockDeviceClient::default();
+
+        let removed = publish_events(&mqtt_client, "device_id", &event_queue).await;
+        assert!(!removed);
+        assert!(mqtt_client.config_instance_events().is_empty());
+    }
+
+    #[tokio::test]
+    async fn in_flight_events_are_not_republished() {
+        let (event_queue, events) = create_queue(3).await;
+        let mqtt_client = MockDeviceClient::default();
+
+        publish_events(&mqtt_client, "device_id", &event_queue).await;
+        let removed = publish_events(&mqtt_client, "device_id", &event_queue).await;
+        assert!(!removed);
+        assert_eq!(mqtt_client.config_instance_events().len(), 3);
+        assert_eq!(event_queue.peek(100).await.unwrap(), events);
+    }
+
+    #[tokio::test]
+    async fn unacked_events_are_republished_by_a_new_client() {
+        let (event_queue, events) = create_queue(3).await;
+        let mqtt_client = MockDeviceClient::default();
+        publish_events(&mqtt_client, "device_id", &event_queue).await;
+
+        // the client was replaced before the broker acknowledged the events
+        let mqtt_client = MockDeviceClient::default();
+        publish_events(&mqtt_client, "device_id", &event_queue).await;
+        let published = mqtt_client.config_instance_events();
+        assert_eq!(published.len(), 3);
+        for (call, event) in published.iter().zip(events.iter()) {
+            assert_eq!(&call.event, event);
+        }
+    }
+
+    #[tokio::test]
+    async fn publishes_in_batches() {
+        let (event_queue, events) = create_queue(40).await;
+        let mqtt_client = MockDeviceClient::default();
+
+        let removed = publish_events(&mqtt_client, "device_id", &event_queue).await;
+        assert!(!removed);
+        assert_eq!(mqtt_client.config_instance_events().len(), 32);
+
+        mqtt_client.ack_all();
+        let removed = publish_events(&mqtt_client, "device_id", &event_queue).await;
+        assert!(removed);
+        assert_eq!(event_queue.peek(100).await.unwrap(), events[32..].to_vec());
+
+        // the events which moved into the batch are published once woken again
+        let removed = publish_events(&mqtt_client, "device_id", &event_queue).await;
+        assert!(!removed);
+        assert_eq!(mqtt_client.config_instance_events().len(), 40);
+
+        mqtt_client.ack_all();
+        let removed = publish_events(&mqtt_client, "device_id", &event_queue).await;
+        assert!(removed);
+        assert!(event_queue.is_empty().await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn failed_publishes_stay_queued() {
+        let (event_queue, events) = create_queue(3).await;
+        let mut mqtt_client = MockDeviceClient::default();
+        let num_calls = Arc::new(AtomicUsize::new(0));
+        mqtt_client.set_publish_config_instance_event(move || {
+            if num_calls.fetch_add(1, Ordering::SeqCst) == 0 {
+                Ok(())
+            } else {
+                Err(MQTTError::MockErr(Box::new(MockErr {
+                    is_authentication_error: false,
+                    is_network_connection_error: true,
+                })))
+            }
+        });
+
+        publish_events(&mqtt_client, "device_id", &event_queue).await;
+        assert_eq!(mqtt_client.config_instance_events().len(), 2);
+        mqtt_client.ack_all();
+        let removed = publish_events(&mqtt_client, "device_id", &event_queue).await;
+        assert!(removed);
+        assert_eq!(event_queue.peek(100).await.unwrap(), events[1..].to_vec());
+    }
+}
+
+pub mod token_refresh {
+    use super::*;
+    use crate::mqtt::client::v5_broker::*;
+    use miru_agent::mqtt::client::{ConnectAddress, Protocol, ProtocolVersion};
+
+    use bytes::BytesMut;
+    use rumqttc::v5::mqttbytes::v5::{ConnectReturnCode as V5ConnectReturnCode, Packet, PubAck};
+    use std::time::Duration;
+    use tokio::io::AsyncReadExt;
+    use tokio::sync::oneshot;
+
+    // accepts a connection and returns its password once connected
+    async fn accept(listener: &tokio::net::TcpListener) -> (tokio::net::TcpStream, String) {
+        let (mut stream, _) = listener.accept().await.unwrap();
+        let mut buf = BytesMut::new();
+        let Packet::Connect(_, _, login) = read_packet(&mut stream, &mut buf).await else {
+            panic!("expected a connect packet");
+        };
+        write_packet(&mut stream, connack(V5ConnectReturnCode::Success)).await;
+        (stream, login.unwrap().password)
+    }
+
+    // acknowledges the connection's publishes until the client disconnects, returning
+    // the acknowledged publishes' topics
+    fn serve(mut stream: tokio::net::TcpStream) -> oneshot::Receiver<Vec<String>> {
+        let (disconnected_tx, disconnected_rx) = oneshot::channel();
+        tokio::spawn(async move {
+            let mut buf = BytesMut::new();
+            let mut acked = Vec::new();
+            loop {
+                // rumqttc can't parse disconnect packets without a reason code
+                if buf.first() == Some(&0xE0) {
+                    break;
+                }
+                match Packet::read(&mut buf, None) {
+                    Ok(Packet::Publish(publish)) => {
+                        write_packet(&mut stream, Packet::PubAck(PubAck::new(publish.pkid, None)))
+                            .await;
+                        acked.push(String::from_utf8_lossy(&publish.topic).to_string());
+                    }
+                    Ok(_) => {}
+                    Err(_) => {
+                        if stream.read_buf(&mut buf).await.unwrap() == 0 {
+                            panic!("connection closed without a disconnect packet");
+                        }
+                    }
+                }
+            }
+            let _ = disconnected_tx.send(acked);
+        });
+        disconnected_rx
+    }
+
+    async fn wait_for(device_file: &DeviceFile, f: impl Fn(&Device) -> bool) -> Arc<Device> {
+        for _ in 0..500 {
+            let device = device_file.read().await.unwrap();
+            if f(&device) {
+                return device;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        panic!("timed out waiting for the device to be updated");
+    }
+
+    #[tokio::test]
+    async fn reconnects_with_refreshed_token_without_going_offline() {
+        let dir = Dir::create_temp_dir("testing").await.unwrap();
+        let layout = StorageLayout::new(dir.clone());
+        let (device_file, _) = DeviceFile::spawn_with_default(
+            64,
+            layout.device_file(),
+            Device {
+                id: "device_id".to_string(),
+                session_id: "device_session_id".to_string(),
+                status: DeviceStatus::Offline,
+                ..Device::default()
+            },
+        )
+        .await
+        .unwrap();
+        let (event_queue, _) = EventQueue::spawn(16, dir.file("events.json"), 100)
+            .await
+            .unwrap();
+        let (commands, _deps) = create_commands(&layout, commands::Options::default()).await;
+        let token_mngr = MockTokenManager::new(Token {
+            token: "token_1".to_string(),
+            expires_at: Utc::now(),
+        });
+        let syncer = MockSyncer::default();
+
+        let (listener, port) = listen().await;
+        let options = mqtt::Options {
+            broker_address: ConnectAddress::new(Protocol::TCP, "127.0.0.1".to_string(), port),
+            protocol_version: ProtocolVersion::V5,
+            ..Default::default()
+        };
+        let args = mqtt::Args {
+            token_mngr: &token_mngr,
+            syncer: &syncer,
+            device_file: &device_file,
+            commands: &commands,
+            event_queue: &event_queue,
+        };
+
+        // the fake sleep function doesn't wait for the cooldown
+        let num_sleeps = Arc::new(AtomicUsize::new(0));
+        let sleep_fn = {
+            let num_sleeps = num_sleeps.clone();
+            move |_| {
+                num_sleeps.fetch_add(1, Ordering::SeqCst);
+                tokio::task::yield_now()
+            }
+        };
+
+        let (shutdown_tx, shutdown_rx) = oneshot::channel::<()>();
+        let shutdown_signal = Box::pin(async move {
+            let _ = shutdown_rx.await;
+        });
+
+        let test = async {
+            // connects with the current token
+            let (stream, password) = accept(&listener).await;
+            assert_eq!(password, "token_1");
+            let mut disconnected = serve(stream);
+            let connected = wait_for(&device_file, |d| d.status == DeviceStatus::Online).await;
+
+            // reconnects with the refreshed token once the previous connection was closed
+            // gracefully (after its publishes were acknowledged)
+            token_mngr.refresh(Token {
+                token: "token_2".to_string(),
+                expires_at: Utc::now(),
+            });
+            let (_stream, password) = accept(&listener).await;
+            assert_eq!(password, "token_2");
+            let acked = disconnected.try_recv().unwrap();
+            assert_eq!(acked, vec![topics::device_presence("device_id")]);
+            let device = wait_for(&device_file, |d| {
+                d.last_connected_at > connected.last_connected_at
+            })
+            .await;
+
+            // the device never went offline
+            assert_eq!(device.status, DeviceStatus::Online);
+            assert_eq!(device.last_disconnected_at, connected.last_disconnected_at);
+            assert_eq!(token_mngr.num_refresh_token_calls(), 0);
+            assert!(num_sleeps.load(Ordering::SeqCst) > 0);
+
+            shutdown_tx.send(()).unwrap();
+        };
+
+        tokio::join!(mqtt::run(&options, args, sleep_fn, shutdown_signal), test);
+    }
+}
@@ -12,6 +12,7 @@ use miru_agent::workers::token_refresh::{
 };
 
 use crate::authn::mock::MockTokenManager;
+use crate::http::mock::rate_limited;
 use crate::mock::SleepController;
 
 // external crates
@@ -264,6 +265,70 @@ pub mod run_refresh_token_worker {
         token_refresh_handle.await.unwrap();
     }
 
+    #[tokio::test]
+    async fn retry_after() {
+        // create the token manager
+        let token = Token {
+            token: "token".to_string(),
+            expires_at: Utc::now(),
+        };
+        let token_mngr = MockTokenManager::new(token);
+        token_mngr.set_refresh_token(Box::new(|| {
+            Err(AuthnErr::HTTPErr(Box::new(AuthHTTPErr {
+                source: rate_limited(Duration::from_secs(300)),
+                trace: trace!(),
+            })))
+        }));
+        let token_mngr = Arc::new(token_mngr);
+
+        // create a controllable sleep function
+        let sleep_ctrl = Arc::new(SleepController::new());
+
+        // create the shutdown signal
+        let (shutdown_tx, _shutdown_rx): (tokio::sync::broadcast::Sender<()>, _) =
+            tokio::sync::broadcast::channel(1);
+        let mut shutdown_rx = shutdown_tx.subscribe();
+        let shutdown_signal = async move {
+            let _ = shutdown_rx.recv().await;
+        };
+
+        // set the cooldown options
+        let refresh_advance_secs = 10 * 60; // 10 minutes
+        let cooldown = CooldownOptions {
+            base_secs: 30,
+            ..Default::default()
+        };
+
+        // run the worker
+        let token_mngr_for_spawn = token_mngr.clone();
+        let sleep_ctrl_for_spawn = sleep_ctrl.clone();
+        let options = TokenRefreshWorkerOptions {
+            refresh_advance_secs,
+            polling: cooldown,
+        };
+        let token_refresh_handle = tokio::spawn(async move {
+            run_token_refresh_worker(
+                &options,
+                token_mngr_for_spawn.as_ref(),
+                sleep_ctrl_for_spawn.sleep_fn(),
+                Box::pin(shutdown_signal),
+            )
+            .await;
+        });
+
+        // sleeps should wait for as long as the backend requested
+        for _ in 0..3 {
+            sleep_ctrl.release().await;
+            sleep_ctrl.await_sleep().await;
+            let last_sleep = sleep_ctrl.get_last_attempted_sleep().unwrap();
+            assert_eq!(last_sleep.as_secs(), 300);
+        }
+
+        // shutdown the token manager and refresh loop
+        shutdown_tx.send(()).unwrap();
+        token_refresh_handle.await.unwrap();
+    }
+
     #[tokio::test]
     async fn error_recovery() {
         // create the token manager
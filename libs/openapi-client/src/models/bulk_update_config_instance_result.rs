@@ -0,0 +1,43 @@
+/*
+ * Miru API
+ *
+ * No description provided (generated by Openapi Generator https://github.com/openapitools/openapi-generator)
+ *
+ * The version of the OpenAPI document: 0.1.0
+ *
+ * Generated by: https://openapi-generator.tech
+ */
+
+use crate::models;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Default, Debug, PartialEq, Serialize, Deserialize)]
+pub struct BulkUpdateConfigInstanceResult {
+    #[serde(rename = "id")]
+    pub id: String,
+    /// True if the config instance was updated
+    #[serde(rename = "success")]
+    pub success: bool,
+    /// The updated config instance (null if the update failed)
+    #[serde(rename = "config_instance", deserialize_with = "Option::deserialize")]
+    pub config_instance: Option<Box<models::ConfigInstance>>,
+    /// The reason the update failed (null if the update succeeded)
+    #[serde(rename = "error", deserialize_with = "Option::deserialize")]
+    pub error: Option<Box<models::Error>>,
+}
+
+impl BulkUpdateConfigInstanceResult {
+    pub fn new(
+        id: String,
+        success: bool,
+        config_instance: Option<models::ConfigInstance>,
+        error: Option<models::Error>,
+    ) -> BulkUpdateConfigInstanceResult {
+        BulkUpdateConfigInstanceResult {
+            id,
+            success,
+            config_instance: config_instance.map(Box::new),
+            error: error.map(Box::new),
+        }
+    }
+}
@@ -0,0 +1,24 @@
+/*
+ * Miru API
+ *
+ * No description provided (generated by Openapi Generator https://github.com/openapitools/openapi-generator)
+ *
+ * The version of the OpenAPI document: 0.1.0
+ *
+ * Generated by: https://openapi-generator.tech
+ */
+
+use crate::models;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Default, Debug, PartialEq, Serialize, Deserialize)]
+pub struct BulkUpdateConfigInstancesRequest {
+    #[serde(rename = "updates")]
+    pub updates: Vec<models::BulkUpdateConfigInstance>,
+}
+
+impl BulkUpdateConfigInstancesRequest {
+    pub fn new(updates: Vec<models::BulkUpdateConfigInstance>) -> BulkUpdateConfigInstancesRequest {
+        BulkUpdateConfigInstancesRequest { updates }
+    }
+}
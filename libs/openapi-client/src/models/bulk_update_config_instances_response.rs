@@ -0,0 +1,26 @@
+/*
+ * Miru API
+ *
+ * No description provided (generated by Openapi Generator https://github.com/openapitools/openapi-generator)
+ *
+ * The version of the OpenAPI document: 0.1.0
+ *
+ * Generated by: https://openapi-generator.tech
+ */
+
+use crate::models;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Default, Debug, PartialEq, Serialize, Deserialize)]
+pub struct BulkUpdateConfigInstancesResponse {
+    #[serde(rename = "results")]
+    pub results: Vec<models::BulkUpdateConfigInstanceResult>,
+}
+
+impl BulkUpdateConfigInstancesResponse {
+    pub fn new(
+        results: Vec<models::BulkUpdateConfigInstanceResult>,
+    ) -> BulkUpdateConfigInstancesResponse {
+        BulkUpdateConfigInstancesResponse { results }
+    }
+}
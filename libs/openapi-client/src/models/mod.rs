@@ -12,6 +12,14 @@ pub mod base_device;
 pub use self::base_device::BaseDevice;
 pub mod billing_plan;
 pub use self::billing_plan::BillingPlan;
+pub mod bulk_update_config_instance;
+pub use self::bulk_update_config_instance::BulkUpdateConfigInstance;
+pub mod bulk_update_config_instance_result;
+pub use self::bulk_update_config_instance_result::BulkUpdateConfigInstanceResult;
+pub mod bulk_update_config_instances_request;
+pub use self::bulk_update_config_instances_request::BulkUpdateConfigInstancesRequest;
+pub mod bulk_update_config_instances_response;
+pub use self::bulk_update_config_instances_response::BulkUpdateConfigInstancesResponse;
 pub mod config_instance;
 pub use self::config_instance::ConfigInstance;
 pub mod config_instance_activity_status;
@@ -0,0 +1,39 @@
+/*
+ * Miru API
+ *
+ * No description provided (generated by Openapi Generator https://github.com/openapitools/openapi-generator)
+ *
+ * The version of the OpenAPI document: 0.1.0
+ *
+ * Generated by: https://openapi-generator.tech
+ */
+
+use serde::{Deserialize, Serialize};
+
+/// CircuitBreakerState : The state of the circuit breaker around the connection to the backend. - Closed: Requests to the backend are sent normally - Open: Requests to the backend fail fast since it is unreachable - Half Open: A single probe request is testing whether the backend is reachable again
+/// The state of the circuit breaker around the connection to the backend. - Closed: Requests to the backend are sent normally - Open: Requests to the backend fail fast since it is unreachable - Half Open: A single probe request is testing whether the backend is reachable again
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize, Deserialize)]
+pub enum CircuitBreakerState {
+    #[serde(rename = "closed")]
+    CIRCUIT_BREAKER_STATE_CLOSED,
+    #[serde(rename = "open")]
+    CIRCUIT_BREAKER_STATE_OPEN,
+    #[serde(rename = "half_open")]
+    CIRCUIT_BREAKER_STATE_HALF_OPEN,
+}
+
+impl std::fmt::Display for CircuitBreakerState {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::CIRCUIT_BREAKER_STATE_CLOSED => write!(f, "closed"),
+            Self::CIRCUIT_BREAKER_STATE_OPEN => write!(f, "open"),
+            Self::CIRCUIT_BREAKER_STATE_HALF_OPEN => write!(f, "half_open"),
+        }
+    }
+}
+
+impl Default for CircuitBreakerState {
+    fn default() -> CircuitBreakerState {
+        Self::CIRCUIT_BREAKER_STATE_CLOSED
+    }
+}
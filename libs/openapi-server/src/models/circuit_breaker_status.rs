@@ -0,0 +1,37 @@
+/*
+ * Miru API
+ *
+ * No description provided (generated by Openapi Generator https://github.com/openapitools/openapi-generator)
+ *
+ * The version of the OpenAPI document: 0.1.0
+ *
+ * Generated by: https://openapi-generator.tech
+ */
+
+use crate::models;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Default, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CircuitBreakerStatus {
+    #[serde(rename = "state")]
+    pub state: models::CircuitBreakerState,
+    /// The number of consecutive network failures when connecting to the backend
+    #[serde(rename = "consecutive_failures")]
+    pub consecutive_failures: i32,
+    /// The number of milliseconds until a probe request is sent to the backend (only present while the circuit is open)
+    #[serde(rename = "retry_in_ms", skip_serializing_if = "Option::is_none")]
+    pub retry_in_ms: Option<i64>,
+}
+
+impl CircuitBreakerStatus {
+    pub fn new(
+        state: models::CircuitBreakerState,
+        consecutive_failures: i32,
+    ) -> CircuitBreakerStatus {
+        CircuitBreakerStatus {
+            state,
+            consecutive_failures,
+            retry_in_ms: None,
+        }
+    }
+}
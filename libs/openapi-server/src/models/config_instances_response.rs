@@ -0,0 +1,25 @@
+/*
+ * Miru API
+ *
+ * No description provided (generated by Openapi Generator https://github.com/openapitools/openapi-generator)
+ *
+ * The version of the OpenAPI document: 0.1.0
+ *
+ * Generated by: https://openapi-generator.tech
+ */
+
+use crate::models;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Default, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ConfigInstancesResponse {
+    /// The config instances stored on the device which match the filters.
+    #[serde(rename = "config_instances")]
+    pub config_instances: Vec<models::LocalConfigInstance>,
+}
+
+impl ConfigInstancesResponse {
+    pub fn new(config_instances: Vec<models::LocalConfigInstance>) -> ConfigInstancesResponse {
+        ConfigInstancesResponse { config_instances }
+    }
+}
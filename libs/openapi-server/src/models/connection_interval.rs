@@ -0,0 +1,30 @@
+/*
+ * Miru API
+ *
+ * No description provided (generated by Openapi Generator https://github.com/openapitools/openapi-generator)
+ *
+ * The version of the OpenAPI document: 0.1.0
+ *
+ * Generated by: https://openapi-generator.tech
+ */
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Default, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ConnectionInterval {
+    /// Timestamp of when the connection was established.
+    #[serde(rename = "connected_at")]
+    pub connected_at: String,
+    /// Timestamp of when the connection was lost. Missing while the device is connected or if the disconnection wasn't recorded (e.g. the agent crashed).
+    #[serde(rename = "disconnected_at", skip_serializing_if = "Option::is_none")]
+    pub disconnected_at: Option<String>,
+}
+
+impl ConnectionInterval {
+    pub fn new(connected_at: String) -> ConnectionInterval {
+        ConnectionInterval {
+            connected_at,
+            disconnected_at: None,
+        }
+    }
+}
@@ -0,0 +1,43 @@
+/*
+ * Miru API
+ *
+ * No description provided (generated by Openapi Generator https://github.com/openapitools/openapi-generator)
+ *
+ * The version of the OpenAPI document: 0.1.0
+ *
+ * Generated by: https://openapi-generator.tech
+ */
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Default, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DeployError {
+    /// The error code
+    #[serde(rename = "code")]
+    pub code: String,
+    /// A human readable description of the error
+    #[serde(rename = "message")]
+    pub message: String,
+    /// Details of the error (e.g. the json pointers of the config values which failed schema validation)
+    #[serde(rename = "params", deserialize_with = "Option::deserialize")]
+    pub params: Option<serde_json::Value>,
+    /// The timestamp of when the error occurred
+    #[serde(rename = "occurred_at")]
+    pub occurred_at: String,
+}
+
+impl DeployError {
+    pub fn new(
+        code: String,
+        message: String,
+        params: Option<serde_json::Value>,
+        occurred_at: String,
+    ) -> DeployError {
+        DeployError {
+            code,
+            message,
+            params,
+            occurred_at,
+        }
+    }
+}
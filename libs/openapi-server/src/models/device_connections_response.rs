@@ -0,0 +1,25 @@
+/*
+ * Miru API
+ *
+ * No description provided (generated by Openapi Generator https://github.com/openapitools/openapi-generator)
+ *
+ * The version of the OpenAPI document: 0.1.0
+ *
+ * Generated by: https://openapi-generator.tech
+ */
+
+use crate::models;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Default, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DeviceConnectionsResponse {
+    /// The device's most recent connections, oldest first.
+    #[serde(rename = "connections")]
+    pub connections: Vec<models::ConnectionInterval>,
+}
+
+impl DeviceConnectionsResponse {
+    pub fn new(connections: Vec<models::ConnectionInterval>) -> DeviceConnectionsResponse {
+        DeviceConnectionsResponse { connections }
+    }
+}
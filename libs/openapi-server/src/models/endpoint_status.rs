@@ -0,0 +1,38 @@
+/*
+ * Miru API
+ *
+ * No description provided (generated by Openapi Generator https://github.com/openapitools/openapi-generator)
+ *
+ * The version of the OpenAPI document: 0.1.0
+ *
+ * Generated by: https://openapi-generator.tech
+ */
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Default, Debug, PartialEq, Serialize, Deserialize)]
+pub struct EndpointStatus {
+    /// The endpoint currently in use
+    #[serde(rename = "active")]
+    pub active: String,
+    /// The preferred endpoint, which is used unless it is unreachable
+    #[serde(rename = "primary")]
+    pub primary: String,
+    /// The endpoints in the order they are failed over to
+    #[serde(rename = "endpoints")]
+    pub endpoints: Vec<String>,
+    /// The number of milliseconds until the primary endpoint is tried again (only present while failed over)
+    #[serde(rename = "fail_back_in_ms", skip_serializing_if = "Option::is_none")]
+    pub fail_back_in_ms: Option<i64>,
+}
+
+impl EndpointStatus {
+    pub fn new(active: String, primary: String, endpoints: Vec<String>) -> EndpointStatus {
+        EndpointStatus {
+            active,
+            primary,
+            endpoints,
+            fail_back_in_ms: None,
+        }
+    }
+}
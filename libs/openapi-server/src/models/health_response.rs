@@ -8,6 +8,7 @@
  * Generated by: https://openapi-generator.tech
  */
 
+use crate::models;
 use serde::{Deserialize, Serialize};
 
 #[derive(Clone, Default, Debug, PartialEq, Serialize, Deserialize)]
@@ -15,10 +16,15 @@ pub struct HealthResponse {
     /// The status of the agent
     #[serde(rename = "status")]
     pub status: String,
+    #[serde(rename = "circuit_breaker")]
+    pub circuit_breaker: Box<models::CircuitBreakerStatus>,
 }
 
 impl HealthResponse {
-    pub fn new(status: String) -> HealthResponse {
-        HealthResponse { status }
+    pub fn new(status: String, circuit_breaker: models::CircuitBreakerStatus) -> HealthResponse {
+        HealthResponse {
+            status,
+            circuit_breaker: Box::new(circuit_breaker),
+        }
     }
 }
@@ -1,5 +1,9 @@
 pub mod base_config_instance;
 pub use self::base_config_instance::BaseConfigInstance;
+pub mod circuit_breaker_state;
+pub use self::circuit_breaker_state::CircuitBreakerState;
+pub mod circuit_breaker_status;
+pub use self::circuit_breaker_status::CircuitBreakerStatus;
 pub mod config_instance;
 pub use self::config_instance::ConfigInstance;
 pub mod config_instance_activity_status;
@@ -10,10 +14,20 @@ pub mod config_instance_status;
 pub use self::config_instance_status::ConfigInstanceStatus;
 pub mod config_instance_target_status;
 pub use self::config_instance_target_status::ConfigInstanceTargetStatus;
+pub mod config_instances_response;
+pub use self::config_instances_response::ConfigInstancesResponse;
+pub mod connection_interval;
+pub use self::connection_interval::ConnectionInterval;
+pub mod deploy_error;
+pub use self::deploy_error::DeployError;
 pub mod device;
 pub use self::device::Device;
+pub mod device_connections_response;
+pub use self::device_connections_response::DeviceConnectionsResponse;
 pub mod device_status;
 pub use self::device_status::DeviceStatus;
+pub mod endpoint_status;
+pub use self::endpoint_status::EndpointStatus;
 pub mod error;
 pub use self::error::Error;
 pub mod error_response;
@@ -24,11 +38,19 @@ pub mod hash_serialized_config_schema_format;
 pub use self::hash_serialized_config_schema_format::HashSerializedConfigSchemaFormat;
 pub mod health_response;
 pub use self::health_response::HealthResponse;
+pub mod local_config_instance;
+pub use self::local_config_instance::LocalConfigInstance;
 pub mod schema_digest_response;
 pub use self::schema_digest_response::SchemaDigestResponse;
+pub mod schema_violation;
+pub use self::schema_violation::SchemaViolation;
+pub mod status_response;
+pub use self::status_response::StatusResponse;
 pub mod sync_device_response;
 pub use self::sync_device_response::SyncDeviceResponse;
 pub mod sync_device_result;
 pub use self::sync_device_result::SyncDeviceResult;
+pub mod validate_config_response;
+pub use self::validate_config_response::ValidateConfigResponse;
 pub mod version_response;
 pub use self::version_response::VersionResponse;
@@ -0,0 +1,34 @@
+/*
+ * Miru API
+ *
+ * No description provided (generated by Openapi Generator https://github.com/openapitools/openapi-generator)
+ *
+ * The version of the OpenAPI document: 0.1.0
+ *
+ * Generated by: https://openapi-generator.tech
+ */
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Default, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SchemaViolation {
+    /// JSON pointer to the config value which failed validation ('' is the root)
+    #[serde(rename = "instance_path")]
+    pub instance_path: String,
+    /// JSON pointer to the schema keyword which failed
+    #[serde(rename = "schema_path")]
+    pub schema_path: String,
+    /// A human readable description of the violation
+    #[serde(rename = "message")]
+    pub message: String,
+}
+
+impl SchemaViolation {
+    pub fn new(instance_path: String, schema_path: String, message: String) -> SchemaViolation {
+        SchemaViolation {
+            instance_path,
+            schema_path,
+            message,
+        }
+    }
+}
@@ -0,0 +1,32 @@
+/*
+ * Miru API
+ *
+ * No description provided (generated by Openapi Generator https://github.com/openapitools/openapi-generator)
+ *
+ * The version of the OpenAPI document: 0.1.0
+ *
+ * Generated by: https://openapi-generator.tech
+ */
+
+use crate::models;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Default, Debug, PartialEq, Serialize, Deserialize)]
+pub struct StatusResponse {
+    #[serde(rename = "backend")]
+    pub backend: Box<models::EndpointStatus>,
+    #[serde(rename = "mqtt_broker")]
+    pub mqtt_broker: Box<models::EndpointStatus>,
+}
+
+impl StatusResponse {
+    pub fn new(
+        backend: models::EndpointStatus,
+        mqtt_broker: models::EndpointStatus,
+    ) -> StatusResponse {
+        StatusResponse {
+            backend: Box::new(backend),
+            mqtt_broker: Box::new(mqtt_broker),
+        }
+    }
+}
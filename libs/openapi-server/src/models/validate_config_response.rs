@@ -0,0 +1,39 @@
+/*
+ * Miru API
+ *
+ * No description provided (generated by Openapi Generator https://github.com/openapitools/openapi-generator)
+ *
+ * The version of the OpenAPI document: 0.1.0
+ *
+ * Generated by: https://openapi-generator.tech
+ */
+
+use crate::models;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Default, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ValidateConfigResponse {
+    /// Whether the config is valid against the config schema
+    #[serde(rename = "valid")]
+    pub valid: bool,
+    /// ID of the config schema which the config was validated against
+    #[serde(rename = "config_schema_id")]
+    pub config_schema_id: String,
+    /// The schema violations (empty if the config is valid)
+    #[serde(rename = "errors")]
+    pub errors: Vec<models::SchemaViolation>,
+}
+
+impl ValidateConfigResponse {
+    pub fn new(
+        valid: bool,
+        config_schema_id: String,
+        errors: Vec<models::SchemaViolation>,
+    ) -> ValidateConfigResponse {
+        ValidateConfigResponse {
+            valid,
+            config_schema_id,
+            errors,
+        }
+    }
+}